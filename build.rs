@@ -1,4 +1,16 @@
 fn main() {
   println!("cargo:rerun-if-changed=linker.ld");
   println!("cargo:rerun-if-changed=src/asm/entry.S");
+  println!("cargo:rerun-if-changed=src/asm/machinevec.S");
+  println!("cargo:rerun-if-changed=src/asm/kernelvec.S");
+  println!("cargo:rerun-if-changed=src/asm/switch.S");
+  println!("cargo:rerun-if-changed=src/asm/trampoline.S");
+
+  // A file system image can be embedded into the kernel binary, to initialize a RAM disk with.
+  println!("cargo::rustc-check-cfg=cfg(ramdisk_image)");
+  println!("cargo:rerun-if-env-changed=RAMDISK_IMAGE");
+  if let Ok(path) = std::env::var("RAMDISK_IMAGE") {
+    println!("cargo:rerun-if-changed={}", path);
+    println!("cargo:rustc-cfg=ramdisk_image");
+  }
 }
@@ -0,0 +1,748 @@
+#![allow(non_snake_case)]
+
+/*
+  Creates a disk image containing an ArnoFS File System (the kernel's native on disk File System),
+  populated with the given files and directory trees. The image is validated once it's written.
+
+    mkfs <image> [--size <blocks>] [--inodes <count>] [<path>...]
+    mkfs --check <image>
+
+  A file is copied into the root directory (by its file name). The contents of a directory are
+  copied into a directory with the same name, recursively.
+
+  The image can be attached as a virtio disk, or embedded into the kernel binary to initialize a
+  RAM disk with (see the ramdisk driver).
+
+  NOTE : The kernel's cargo config makes RISC-V the default target, so run this with
+  `cargo run --target <host target> -- ...`.
+*/
+
+// The on disk structures are shared with the kernel, so that the two can't drift apart.
+#[allow(dead_code, clippy::missing_safety_doc)]
+#[path = "../../../src/fs/arnofs/layout.rs"]
+mod layout;
+
+use {
+  layout::{
+    BlockPath, DirectoryEntry, DiskInode, FileType, OnDisk, SuperBlock, BITS_PER_BLOCK, BLOCK_SIZE,
+    DIRECTORY_ENTRY_SIZE, DIRECTORY_NAME_SIZE, FS_MAGIC, INDIRECT_BLOCKS_COUNT, INODES_PER_BLOCK,
+    LOG_AREA_SIZE, MAX_FILE_BLOCKS, ROOT_INODE_NUMBER, SUPERBLOCK_BLOCK_NUMBER,
+  },
+  std::{
+    collections::{HashMap, HashSet},
+    env, fs,
+    mem::size_of,
+    path::{Path, PathBuf},
+    process::exit,
+  },
+};
+
+const DEFAULT_SIZE: u32 = 4096;
+const DEFAULT_INODES_COUNT: u32 = 256;
+
+type Block = [u8; BLOCK_SIZE];
+
+// A disk image, held in memory.
+struct Image {
+  blocks: Vec<Block>,
+  superBlock: SuperBlock,
+
+  // Blocks and inodes are allocated sequentially.
+  nextBlockNumber: u32,
+  nextInodeNumber: u32,
+}
+
+impl Image {
+  fn new(size: u32, inodesCount: u32) -> Result<Self, String> {
+    let inodeBlocksCount = inodesCount.div_ceil(INODES_PER_BLOCK as u32);
+    let bitmapBlocksCount = size.div_ceil(BITS_PER_BLOCK as u32);
+
+    let logStart = SUPERBLOCK_BLOCK_NUMBER as u32 + 1;
+    let inodeStart = logStart + LOG_AREA_SIZE as u32;
+    let bitmapStart = inodeStart + inodeBlocksCount;
+    let dataStart = bitmapStart + bitmapBlocksCount;
+
+    if inodesCount <= ROOT_INODE_NUMBER || inodesCount > u16::MAX as u32 {
+      return Err(format!("Invalid number of inodes : {}", inodesCount));
+    }
+    if dataStart >= size {
+      return Err(format!(
+        "Image is too small : the metadata alone takes {} blocks",
+        dataStart
+      ));
+    }
+
+    let superBlock = SuperBlock {
+      magic: FS_MAGIC,
+      size,
+      dataBlocksCount: size - dataStart,
+      inodesCount,
+      logBlocksCount: LOG_AREA_SIZE as u32,
+      logStart,
+      inodeStart,
+      bitmapStart,
+    };
+
+    let mut image = Self {
+      blocks: vec![[0; BLOCK_SIZE]; size as usize],
+      superBlock,
+      nextBlockNumber: dataStart,
+      nextInodeNumber: ROOT_INODE_NUMBER,
+    };
+    superBlock.writeTo(&mut image.blocks[SUPERBLOCK_BLOCK_NUMBER]);
+
+    Ok(image)
+  }
+
+  fn fromBytes(bytes: &[u8]) -> Result<Self, String> {
+    if !bytes.len().is_multiple_of(BLOCK_SIZE)
+      || bytes.len() <= SUPERBLOCK_BLOCK_NUMBER * BLOCK_SIZE
+    {
+      return Err(format!(
+        "Image size ({} bytes) isn't a positive multiple of the block size",
+        bytes.len()
+      ));
+    }
+
+    let blocks: Vec<Block> = bytes.as_chunks::<BLOCK_SIZE>().0.to_vec();
+
+    let superBlock = SuperBlock::readFrom(&blocks[SUPERBLOCK_BLOCK_NUMBER]);
+    if superBlock.magic != FS_MAGIC {
+      return Err("Image doesn't contain a File System (bad magic)".into());
+    }
+
+    Ok(Self {
+      nextBlockNumber: superBlock.size,
+      nextInodeNumber: superBlock.inodesCount,
+
+      blocks,
+      superBlock,
+    })
+  }
+
+  fn toBytes(&self) -> Vec<u8> {
+    self.blocks.concat()
+  }
+
+  fn getDataStart(&self) -> u32 {
+    self.superBlock.size - self.superBlock.dataBlocksCount
+  }
+
+  fn allocateBlock(&mut self) -> Result<u32, String> {
+    if self.nextBlockNumber == self.superBlock.size {
+      return Err("Image ran out of data blocks (try a larger --size)".into());
+    }
+
+    self.nextBlockNumber += 1;
+    Ok(self.nextBlockNumber - 1)
+  }
+
+  fn readInode(&self, inodeNumber: u32) -> DiskInode {
+    let block = &self.blocks[self.superBlock.getInodeBlock(inodeNumber)];
+    let offset = (inodeNumber as usize % INODES_PER_BLOCK) * size_of::<DiskInode>();
+
+    DiskInode::readFrom(&block[offset..])
+  }
+
+  fn writeInode(&mut self, inodeNumber: u32, diskInode: &DiskInode) {
+    let block = &mut self.blocks[self.superBlock.getInodeBlock(inodeNumber)];
+    let offset = (inodeNumber as usize % INODES_PER_BLOCK) * size_of::<DiskInode>();
+
+    diskInode.writeTo(&mut block[offset..]);
+  }
+
+  fn allocateInode(&mut self, fileType: FileType) -> Result<u32, String> {
+    if self.nextInodeNumber == self.superBlock.inodesCount {
+      return Err("Image ran out of inodes (try a larger --inodes)".into());
+    }
+
+    let inodeNumber = self.nextInodeNumber;
+    self.nextInodeNumber += 1;
+
+    let diskInode = DiskInode {
+      fileType: fileType as u16,
+      ..DiskInode::new()
+    };
+    self.writeInode(inodeNumber, &diskInode);
+
+    Ok(inodeNumber)
+  }
+
+  fn readAddress(&self, indirectBlock: u32, index: usize) -> u32 {
+    u32::readFrom(&self.blocks[indirectBlock as usize][index * 4..])
+  }
+
+  // Returns the disk block number of the given block of the file (0, if it isn't allocated).
+  fn getBlockAddress(&self, diskInode: &DiskInode, blockIndex: usize) -> u32 {
+    let Some(blockPath) = BlockPath::new(blockIndex)
+    else {
+      return 0;
+    };
+
+    let mut address = diskInode.addresses[blockPath.slot];
+    for &index in blockPath.getIndices() {
+      if address == 0 {
+        return 0;
+      }
+      address = self.readAddress(address, index);
+    }
+    address
+  }
+
+  // Same as getBlockAddress( ), but allocates the block (along with the required indirect blocks)
+  // if it isn't allocated.
+  fn allocateBlockAddress(
+    &mut self,
+    diskInode: &mut DiskInode,
+    blockIndex: usize,
+  ) -> Result<u32, String> {
+    let blockPath = BlockPath::new(blockIndex).ok_or("File is too large")?;
+
+    let mut address = diskInode.addresses[blockPath.slot];
+    if address == 0 {
+      address = self.allocateBlock()?;
+      diskInode.addresses[blockPath.slot] = address;
+    }
+
+    for &index in blockPath.getIndices() {
+      let mut nextAddress = self.readAddress(address, index);
+      if nextAddress == 0 {
+        nextAddress = self.allocateBlock()?;
+        nextAddress.writeTo(&mut self.blocks[address as usize][index * 4..]);
+      }
+      address = nextAddress;
+    }
+
+    Ok(address)
+  }
+
+  fn readAt(&self, diskInode: &DiskInode, offset: usize, buffer: &mut [u8]) {
+    for (position, byte) in (offset..).zip(buffer.iter_mut()) {
+      *byte = match self.getBlockAddress(diskInode, position / BLOCK_SIZE) {
+        0 => 0,
+        address => self.blocks[address as usize][position % BLOCK_SIZE],
+      };
+    }
+  }
+
+  // Appends the given data to the file.
+  fn append(&mut self, inodeNumber: u32, data: &[u8]) -> Result<(), String> {
+    let mut diskInode = self.readInode(inodeNumber);
+    let offset = diskInode.size as usize;
+
+    if offset + data.len() > MAX_FILE_BLOCKS * BLOCK_SIZE {
+      return Err("File is too large".into());
+    }
+
+    let mut bytesWritten = 0;
+    while bytesWritten < data.len() {
+      let position = offset + bytesWritten;
+      let offsetInBlock = position % BLOCK_SIZE;
+      let chunkLength = (BLOCK_SIZE - offsetInBlock).min(data.len() - bytesWritten);
+
+      let address = self.allocateBlockAddress(&mut diskInode, position / BLOCK_SIZE)?;
+      self.blocks[address as usize][offsetInBlock..offsetInBlock + chunkLength]
+        .copy_from_slice(&data[bytesWritten..bytesWritten + chunkLength]);
+
+      bytesWritten += chunkLength;
+    }
+
+    diskInode.size = (offset + data.len()) as u32;
+    self.writeInode(inodeNumber, &diskInode);
+
+    Ok(())
+  }
+
+  // Returns the directory entries (including the unused ones).
+  fn getDirectoryEntries(&self, inodeNumber: u32) -> Vec<DirectoryEntry> {
+    let diskInode = self.readInode(inodeNumber);
+
+    (0..diskInode.size as usize)
+      .step_by(DIRECTORY_ENTRY_SIZE)
+      .map(|offset| {
+        let mut directoryEntry = [0; DIRECTORY_ENTRY_SIZE];
+        self.readAt(&diskInode, offset, &mut directoryEntry);
+
+        DirectoryEntry::readFrom(&directoryEntry)
+      })
+      .collect()
+  }
+
+  fn link(&mut self, directory: u32, name: &[u8], inodeNumber: u32) -> Result<(), String> {
+    let displayName = String::from_utf8_lossy(name).into_owned();
+
+    let directoryEntry = DirectoryEntry::new(inodeNumber as u16, name).ok_or(format!(
+      "Name {:?} is longer than {} bytes",
+      displayName, DIRECTORY_NAME_SIZE
+    ))?;
+
+    if self
+      .getDirectoryEntries(directory)
+      .iter()
+      .any(|directoryEntry| directoryEntry.inodeNumber != 0 && directoryEntry.getName() == name)
+    {
+      return Err(format!("Name {:?} is used more than once", displayName));
+    }
+
+    let mut encodedDirectoryEntry = [0; DIRECTORY_ENTRY_SIZE];
+    directoryEntry.writeTo(&mut encodedDirectoryEntry);
+    self.append(directory, &encodedDirectoryEntry)
+  }
+
+  fn changeLinksCount(&mut self, inodeNumber: u32, delta: i16) {
+    let mut diskInode = self.readInode(inodeNumber);
+    diskInode.linksCount = diskInode.linksCount.wrapping_add_signed(delta);
+    self.writeInode(inodeNumber, &diskInode);
+  }
+
+  /*
+    Creates a directory, with the "." and ".." entries. Returns its inode number.
+
+    NOTE : Like the kernel, "." isn't counted as a link. So a directory's links count is 1 (for its
+    entry in the parent, or for its own ".." entry in case of the root directory) + the number of
+    its subdirectories.
+  */
+  fn createDirectory(&mut self, parent: Option<u32>) -> Result<u32, String> {
+    let inodeNumber = self.allocateInode(FileType::Directory)?;
+    let parent = parent.unwrap_or(inodeNumber);
+
+    self.link(inodeNumber, b".", inodeNumber)?;
+    self.link(inodeNumber, b"..", parent)?;
+
+    self.changeLinksCount(inodeNumber, 1);
+    if parent != inodeNumber {
+      self.changeLinksCount(parent, 1);
+    }
+
+    Ok(inodeNumber)
+  }
+
+  fn createRootDirectory(&mut self) -> Result<(), String> {
+    let inodeNumber = self.createDirectory(None)?;
+    assert_eq!(inodeNumber, ROOT_INODE_NUMBER);
+
+    Ok(())
+  }
+
+  // Copies the given file or directory tree from the host, into the given directory.
+  fn addPath(&mut self, directory: u32, path: &Path) -> Result<(), String> {
+    let name = path
+      .file_name()
+      .ok_or(format!("{} doesn't have a file name", path.display()))?;
+    let name = name.as_encoded_bytes();
+
+    let metadata = fs::metadata(path)
+      .map_err(|error| format!("Failed reading {} : {}", path.display(), error))?;
+
+    if metadata.is_dir() {
+      let inodeNumber = self.createDirectory(Some(directory))?;
+      self.link(directory, name, inodeNumber)?;
+
+      // Sort the entries, so that the images are reproducible.
+      let mut entries = fs::read_dir(path)
+        .and_then(|entries| {
+          entries
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<PathBuf>, _>>()
+        })
+        .map_err(|error| format!("Failed reading {} : {}", path.display(), error))?;
+      entries.sort();
+
+      for entry in entries {
+        self.addPath(inodeNumber, &entry)?;
+      }
+    }
+    else if metadata.is_file() {
+      let contents =
+        fs::read(path).map_err(|error| format!("Failed reading {} : {}", path.display(), error))?;
+
+      let inodeNumber = self.allocateInode(FileType::File)?;
+      self.changeLinksCount(inodeNumber, 1);
+      self.append(inodeNumber, &contents)?;
+
+      self.link(directory, name, inodeNumber)?;
+    }
+    else {
+      println!(
+        "WARN : Skipping {} (not a file or directory)",
+        path.display()
+      );
+    }
+
+    Ok(())
+  }
+
+  // Marks all the blocks allocated so far (including the metadata blocks) in the free block bitmap.
+  fn writeBitmap(&mut self) {
+    for blockNumber in 0..self.nextBlockNumber as usize {
+      let bitmapBlock = self.superBlock.getBitmapBlock(blockNumber);
+      let bit = blockNumber % BITS_PER_BLOCK;
+
+      self.blocks[bitmapBlock][bit / 8] |= 1 << (bit % 8);
+    }
+  }
+
+  // Checks the consistency of the File System. Returns the problems found.
+  fn validate(&self) -> Vec<String> {
+    let mut validator = Validator {
+      image: self,
+      problems: Vec::new(),
+      usedBlocks: HashSet::new(),
+      references: HashMap::new(),
+    };
+    validator.validate();
+
+    validator.problems
+  }
+}
+
+struct Validator<'a> {
+  image: &'a Image,
+  problems: Vec<String>,
+
+  // Data blocks (including the indirect blocks) referred to by the reachable inodes.
+  usedBlocks: HashSet<u32>,
+
+  // Number of directory entries referring to each reachable inode (except the "." entries).
+  references: HashMap<u32, u16>,
+}
+
+impl Validator<'_> {
+  fn validate(&mut self) {
+    let superBlock = self.image.superBlock;
+
+    let inodeEnd = superBlock.inodeStart + superBlock.inodesCount.div_ceil(INODES_PER_BLOCK as u32);
+    let bitmapEnd = superBlock.bitmapStart + superBlock.size.div_ceil(BITS_PER_BLOCK as u32);
+
+    if superBlock.size as usize != self.image.blocks.len() {
+      self.problems.push(format!(
+        "Superblock size ({}) doesn't match the image size ({} blocks)",
+        superBlock.size,
+        self.image.blocks.len()
+      ));
+      return;
+    }
+    if superBlock.logStart <= SUPERBLOCK_BLOCK_NUMBER as u32
+      || superBlock.logStart + superBlock.logBlocksCount > superBlock.inodeStart
+      || inodeEnd > superBlock.bitmapStart
+      || bitmapEnd > self.image.getDataStart()
+      || superBlock.dataBlocksCount >= superBlock.size
+    {
+      self
+        .problems
+        .push(format!("Superblock regions overlap : {:?}", superBlock));
+      return;
+    }
+
+    if u32::readFrom(&self.image.blocks[superBlock.logStart as usize]) != 0 {
+      self
+        .problems
+        .push("Log contains a committed transaction, which hasn't been installed".into());
+    }
+
+    self.validateTree();
+    self.validateInodes();
+    self.validateBitmap();
+  }
+
+  // Walks the directory tree starting from the root directory.
+  fn validateTree(&mut self) {
+    let mut directories = vec![(ROOT_INODE_NUMBER, ROOT_INODE_NUMBER)];
+    let mut visited = HashSet::from([ROOT_INODE_NUMBER]);
+
+    match self.image.readInode(ROOT_INODE_NUMBER).getFileType() {
+      Some(FileType::Directory) => {}
+      _ => {
+        self.problems.push("Root inode isn't a directory".into());
+        return;
+      }
+    }
+    if !self.validateBlocks(ROOT_INODE_NUMBER) {
+      return;
+    }
+
+    while let Some((directory, parent)) = directories.pop() {
+      let mut hasDot = false;
+      let mut hasDotDot = false;
+
+      for directoryEntry in self.image.getDirectoryEntries(directory) {
+        let inodeNumber = directoryEntry.inodeNumber as u32;
+        if inodeNumber == 0 {
+          continue;
+        }
+
+        let name = String::from_utf8_lossy(directoryEntry.getName()).into_owned();
+        if inodeNumber >= self.image.superBlock.inodesCount {
+          self.problems.push(format!(
+            "Entry {:?} in directory {} refers to an invalid inode {}",
+            name, directory, inodeNumber
+          ));
+          continue;
+        }
+
+        match directoryEntry.getName() {
+          b"." => {
+            hasDot = true;
+            if inodeNumber != directory {
+              self.problems.push(format!(
+                "\".\" entry in directory {} refers to inode {}",
+                directory, inodeNumber
+              ));
+            }
+            continue;
+          }
+
+          b".." => {
+            hasDotDot = true;
+            if inodeNumber != parent {
+              self.problems.push(format!(
+                "\"..\" entry in directory {} refers to inode {}, instead of {}",
+                directory, inodeNumber, parent
+              ));
+            }
+            *self.references.entry(inodeNumber).or_default() += 1;
+            continue;
+          }
+
+          _ => {}
+        }
+        *self.references.entry(inodeNumber).or_default() += 1;
+
+        let diskInode = self.image.readInode(inodeNumber);
+        let Some(fileType) = diskInode.getFileType()
+        else {
+          self.problems.push(format!(
+            "Entry {:?} in directory {} refers to a free inode {}",
+            name, directory, inodeNumber
+          ));
+          continue;
+        };
+
+        if !visited.insert(inodeNumber) {
+          if fileType == FileType::Directory {
+            self.problems.push(format!(
+              "Directory {} is linked from more than one directory entry",
+              inodeNumber
+            ));
+          }
+          continue;
+        }
+
+        // The entries of a directory are read, only if its blocks are valid.
+        let areBlocksValid = self.validateBlocks(inodeNumber);
+        if fileType == FileType::Directory && areBlocksValid {
+          directories.push((inodeNumber, directory));
+        }
+      }
+
+      if !hasDot || !hasDotDot {
+        self.problems.push(format!(
+          "Directory {} is missing the \".\" or \"..\" entry",
+          directory
+        ));
+      }
+    }
+  }
+
+  // Records the blocks the given inode refers to, checking that they're valid data blocks which
+  // aren't referred to by any other inode. Returns whether they are, so that the contents of the
+  // inode can be read without indexing past the image.
+  fn validateBlocks(&mut self, inodeNumber: u32) -> bool {
+    let diskInode = self.image.readInode(inodeNumber);
+
+    if diskInode.size as usize > MAX_FILE_BLOCKS * BLOCK_SIZE {
+      self
+        .problems
+        .push(format!("Inode {} has an invalid size", inodeNumber));
+    }
+    if diskInode.getFileType() == Some(FileType::Directory)
+      && !(diskInode.size as usize).is_multiple_of(DIRECTORY_ENTRY_SIZE)
+    {
+      self
+        .problems
+        .push(format!("Directory {} has a partial entry", inodeNumber));
+    }
+
+    let mut areBlocksValid = true;
+    for (slot, &address) in diskInode.addresses.iter().enumerate() {
+      areBlocksValid &= self.validateBlockTree(inodeNumber, address, BlockPath::getSlotDepth(slot));
+    }
+    areBlocksValid
+  }
+
+  // NOTE : A block referred to more than once is reported as invalid, since it may be an indirect
+  // block for one of the references, and whatever it contains hasn't been checked.
+  fn validateBlockTree(&mut self, inodeNumber: u32, address: u32, depth: usize) -> bool {
+    if address == 0 {
+      return true;
+    }
+
+    if address < self.image.getDataStart() || address as usize >= self.image.blocks.len() {
+      self.problems.push(format!(
+        "Inode {} refers to block {}, which isn't a data block",
+        inodeNumber, address
+      ));
+      return false;
+    }
+    if !self.usedBlocks.insert(address) {
+      self.problems.push(format!(
+        "Block {} is referred to more than once (by inode {})",
+        address, inodeNumber
+      ));
+      return false;
+    }
+
+    let mut areBlocksValid = true;
+    if depth > 0 {
+      for index in 0..INDIRECT_BLOCKS_COUNT {
+        let nextAddress = self.image.readAddress(address, index);
+        areBlocksValid &= self.validateBlockTree(inodeNumber, nextAddress, depth - 1);
+      }
+    }
+    areBlocksValid
+  }
+
+  // Checks that the links counts match the directory entries, and that there are no allocated but
+  // unreachable inodes.
+  fn validateInodes(&mut self) {
+    for inodeNumber in ROOT_INODE_NUMBER..self.image.superBlock.inodesCount {
+      let diskInode = self.image.readInode(inodeNumber);
+      let references = self.references.get(&inodeNumber).copied().unwrap_or(0);
+
+      if diskInode.fileType == 0 {
+        continue;
+      }
+      if diskInode.getFileType().is_none() {
+        self.problems.push(format!(
+          "Inode {} has an invalid type {}",
+          inodeNumber, diskInode.fileType
+        ));
+        continue;
+      }
+
+      if references == 0 {
+        self.problems.push(format!(
+          "Inode {} is allocated, but unreachable",
+          inodeNumber
+        ));
+      }
+      else if diskInode.linksCount != references {
+        self.problems.push(format!(
+          "Inode {} has links count {}, but {} directory entries refer to it",
+          inodeNumber, diskInode.linksCount, references
+        ));
+      }
+    }
+  }
+
+  // Checks that exactly the metadata blocks and the used data blocks are marked allocated.
+  fn validateBitmap(&mut self) {
+    let superBlock = self.image.superBlock;
+
+    for blockNumber in 0..superBlock.size {
+      let bitmapBlock = &self.image.blocks[superBlock.getBitmapBlock(blockNumber as usize)];
+      let bit = blockNumber as usize % BITS_PER_BLOCK;
+
+      let isAllocated = bitmapBlock[bit / 8] & (1 << (bit % 8)) != 0;
+      let isUsed =
+        blockNumber < self.image.getDataStart() || self.usedBlocks.contains(&blockNumber);
+
+      if isAllocated != isUsed {
+        self.problems.push(format!(
+          "Block {} is {} in the bitmap, but is {}",
+          blockNumber,
+          if isAllocated { "allocated" } else { "free" },
+          if isUsed { "used" } else { "unused" }
+        ));
+      }
+    }
+  }
+}
+
+fn printUsage() -> ! {
+  eprintln!("Usage : mkfs <image> [--size <blocks>] [--inodes <count>] [<path>...]");
+  eprintln!("        mkfs --check <image>");
+  exit(2);
+}
+
+fn parseNumber(argument: Option<String>) -> u32 {
+  argument
+    .and_then(|argument| argument.parse().ok())
+    .unwrap_or_else(|| printUsage())
+}
+
+// Validates the given image, and reports the problems found (if any).
+fn check(image: &Image) -> Result<(), String> {
+  let problems = image.validate();
+  if problems.is_empty() {
+    return Ok(());
+  }
+
+  for problem in problems.iter() {
+    eprintln!("ERROR : {}", problem);
+  }
+  Err(format!("Image has {} problems", problems.len()))
+}
+
+fn run() -> Result<(), String> {
+  let mut arguments = env::args().skip(1);
+
+  let mut imagePath = None;
+  let mut shouldOnlyCheck = false;
+  let mut size = DEFAULT_SIZE;
+  let mut inodesCount = DEFAULT_INODES_COUNT;
+  let mut paths = Vec::new();
+
+  while let Some(argument) = arguments.next() {
+    match argument.as_str() {
+      "--check" => shouldOnlyCheck = true,
+      "--size" => size = parseNumber(arguments.next()),
+      "--inodes" => inodesCount = parseNumber(arguments.next()),
+      "--help" | "-h" => printUsage(),
+
+      _ if imagePath.is_none() => imagePath = Some(argument),
+      _ => paths.push(argument),
+    }
+  }
+  let imagePath = imagePath.unwrap_or_else(|| printUsage());
+
+  if shouldOnlyCheck {
+    let bytes =
+      fs::read(&imagePath).map_err(|error| format!("Failed reading {} : {}", imagePath, error))?;
+
+    check(&Image::fromBytes(&bytes)?)?;
+    println!("INFO : {} is consistent", imagePath);
+    return Ok(());
+  }
+
+  let mut image = Image::new(size, inodesCount)?;
+  image.createRootDirectory()?;
+  for path in paths.iter() {
+    image.addPath(ROOT_INODE_NUMBER, Path::new(path))?;
+  }
+  image.writeBitmap();
+
+  fs::write(&imagePath, image.toBytes())
+    .map_err(|error| format!("Failed writing {} : {}", imagePath, error))?;
+
+  // Validate the written image, as the kernel would see it.
+  let bytes =
+    fs::read(&imagePath).map_err(|error| format!("Failed reading {} : {}", imagePath, error))?;
+  check(&Image::fromBytes(&bytes)?)?;
+
+  println!(
+    "INFO : Wrote {} ({} blocks, {} inodes and {} data blocks used)",
+    imagePath,
+    size,
+    image.nextInodeNumber - ROOT_INODE_NUMBER,
+    image.nextBlockNumber - image.getDataStart()
+  );
+  Ok(())
+}
+
+fn main() {
+  if let Err(error) = run() {
+    eprintln!("ERROR : {}", error);
+    exit(1);
+  }
+}
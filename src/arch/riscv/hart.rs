@@ -0,0 +1,21 @@
+use {
+  super::{qemu::MAX_CORES, registers::misa::Misa},
+  array_macro::array,
+  core::sync::atomic::{AtomicUsize, Ordering},
+};
+
+// The misa register of each hart (0, if the hart hasn't booted). It's recorded while the hart
+// boots, since only M-mode can read it.
+static HART_ISAS: [AtomicUsize; MAX_CORES] = array![_ => AtomicUsize::new(0); MAX_CORES];
+
+// Records the information about the current hart, which only M-mode can read.
+// NOTE : Should be invoked once on every hart, from start( ).
+pub unsafe fn recordHartInfo(hartID: usize) {
+  HART_ISAS[hartID].store(Misa.read(), Ordering::Relaxed);
+}
+
+// Returns the misa register of the given hart.
+#[inline]
+pub fn getISA(hartID: usize) -> usize {
+  HART_ISAS[hartID].load(Ordering::Relaxed)
+}
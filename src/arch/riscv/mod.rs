@@ -1,3 +1,4 @@
+pub mod hart;
 pub mod modes;
 pub mod qemu;
 pub mod registers;
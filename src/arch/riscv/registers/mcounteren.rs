@@ -0,0 +1,19 @@
+use core::arch::asm;
+
+#[allow(non_camel_case_types)]
+enum BitMasks {
+  TIME_ENABLE = 1 << 1,
+}
+
+// The mcounteren (Machine Counter Enable) register controls whether the hardware performance
+// monitoring counters (like the time CSR) can be read from the next less privileged mode.
+// REFER : section 3.1.11 in privileged ISA manual.
+pub struct Mcounteren;
+
+impl Mcounteren {
+  // Lets S-mode read the time CSR. Required by the Sstc extension as well.
+  #[inline]
+  pub unsafe fn enableTimeCounter(&self) {
+    asm!("csrs mcounteren, {}", in(reg)BitMasks::TIME_ENABLE as usize);
+  }
+}
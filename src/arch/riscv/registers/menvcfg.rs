@@ -0,0 +1,19 @@
+use core::arch::asm;
+
+const SUPERVISOR_TIMECMP_ENABLE: usize = 1 << 63;
+
+// The menvcfg (Machine Environment Configuration) register controls certain characteristics of
+// the execution environment for modes less privileged than M-mode.
+//
+// Setting the STCE bit enables the Sstc extension, which gives S-mode its own stimecmp register.
+// So S-mode can program timer interrupts by itself, without going through M-mode.
+// NOTE : The CSR is referred to by its number (0x30A), since not every assembler knows its name.
+// REFER : section 3.1.18 in privileged ISA manual.
+pub struct Menvcfg;
+
+impl Menvcfg {
+  #[inline]
+  pub unsafe fn enableSupervisorTimecmp(&self) {
+    asm!("csrs 0x30A, {}", in(reg)SUPERVISOR_TIMECMP_ENABLE);
+  }
+}
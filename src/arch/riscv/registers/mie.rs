@@ -0,0 +1,24 @@
+use core::arch::asm;
+
+#[allow(non_camel_case_types)]
+enum BitMasks {
+  MACHINE_SOFTWARE_INTERRUPTS_ENABLE = 1 << 3,
+}
+
+// The mie (Machine Interrupt Enable) register contains bits using which we can enable / disable
+// interrupts in M-mode.
+//
+// NOTE : While the hart is running in a less privileged mode, M-mode interrupts are always
+// globally enabled, regardless of the MIE bit in mstatus. So setting the bit here is enough.
+// REFER : section 3.1.9 in privileged ISA manual.
+pub struct Mie;
+
+impl Mie {
+  #[inline]
+  pub unsafe fn enableSoftwareInterrupts(&self) {
+    asm!(
+      "csrs mie, {}",
+      in(reg)BitMasks::MACHINE_SOFTWARE_INTERRUPTS_ENABLE as usize
+    );
+  }
+}
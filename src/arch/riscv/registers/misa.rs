@@ -0,0 +1,36 @@
+use core::arch::asm;
+
+// The misa register reports the ISA supported by the hart : its base width (in the 2 most
+// significant bits) and the standard extensions (bit i is set if extension 'A' + i is supported).
+// REFER : section 3.1.1 in privileged ISA manual.
+pub struct Misa;
+
+impl Misa {
+  #[inline]
+  pub unsafe fn read(&self) -> usize {
+    let misa: usize;
+    asm!("csrr {}, misa", out(reg)misa);
+    misa
+  }
+}
+
+// The single letter extensions, in the canonical order they're listed in an ISA string.
+const CANONICAL_EXTENSIONS_ORDER: &[u8] = b"iemafdqlcbkjtpvh";
+
+// Returns the ISA string (like rv64imafdc) described by the given misa value.
+pub fn getISAString(misa: usize) -> alloc::string::String {
+  let baseWidth = match misa >> (usize::BITS - 2) {
+    1 => 32,
+    3 => 128,
+    _ => 64,
+  };
+
+  let mut isaString = alloc::format!("rv{}", baseWidth);
+  isaString.extend(
+    CANONICAL_EXTENSIONS_ORDER
+      .iter()
+      .filter(|&&extension| misa & (1 << (extension - b'a')) != 0)
+      .map(|&extension| char::from(extension)),
+  );
+  isaString
+}
@@ -1,10 +1,23 @@
+pub mod mcounteren;
 pub mod medeleg;
+pub mod menvcfg;
 pub mod mepc;
 pub mod mhartid;
 pub mod mideleg;
+pub mod mie;
+pub mod misa;
+pub mod mscratch;
 pub mod mstatus;
+pub mod mtvec;
 pub mod pmp;
 pub mod satp;
+pub mod scause;
+pub mod sepc;
 pub mod sie;
+pub mod sip;
 pub mod sstatus;
+pub mod stimecmp;
+pub mod stval;
+pub mod stvec;
+pub mod time;
 pub mod tp;
@@ -0,0 +1,14 @@
+use core::arch::asm;
+
+// The mscratch (Machine Scratch) register is dedicated for use by M-mode. Typically, it is used to
+// hold a pointer to a machine-mode hart-local context space, which is swapped with a user register
+// upon entry to an M-mode trap handler.
+// REFER : section 3.1.13 in privileged ISA manual.
+pub struct Mscratch;
+
+impl Mscratch {
+  #[inline]
+  pub unsafe fn write(&self, value: usize) {
+    asm!("csrw mscratch, {}", in(reg)value);
+  }
+}
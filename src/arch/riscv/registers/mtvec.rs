@@ -0,0 +1,13 @@
+use core::arch::asm;
+
+// The mtvec (Machine Trap Vector Base Address) register holds the address of the trap handler,
+// which the hart jumps to whenever a trap is taken into M-mode.
+// REFER : section 3.1.7 in privileged ISA manual.
+pub struct Mtvec;
+
+impl Mtvec {
+  #[inline]
+  pub unsafe fn set(&self, trapHandlerAddress: usize) {
+    asm!("csrw mtvec, {}", in(reg)trapHandlerAddress);
+  }
+}
@@ -18,7 +18,27 @@ use core::arch::asm;
 */
 pub struct Satp;
 
+#[allow(non_camel_case_types)]
+enum BitMasks {
+  SATP_MODE_SV39 = 8 << 60,
+  SATP_ASID = 0xffff << 44,
+}
+
+const ASID_SHIFT: usize = 44;
+
 impl Satp {
+  #[inline]
+  pub unsafe fn read(&self) -> usize {
+    let bits: usize;
+    asm!("csrr {}, satp", out(reg)bits);
+    bits
+  }
+
+  #[inline]
+  pub unsafe fn write(&self, bits: usize) {
+    asm!("csrw satp, {}", in(reg)bits);
+  }
+
   // Disales Virtual Address Translation (VAT), by setting the MODE to BARE.
   // Supervisor virtual addresses will then be equal to supervisor physical addresses, and there
   // will be no additional memory protection beyond the physical memory protection scheme described
@@ -28,4 +48,43 @@ impl Satp {
   pub unsafe fn disableVirtualAddressTranslation(&self) {
     asm!("csrw satp, {}", in(reg)0);
   }
+
+  // Enables Virtual Address Translation (VAT) using the Sv39 scheme, with the Page Table at the
+  // given Physical Address (PA) as the root, and the translations tagged with the given ASID.
+  // NOTE : Doesn't flush the TLB.
+  #[inline]
+  pub unsafe fn enableVirtualAddressTranslation(&self, rootPageTablePA: usize, asid: usize) {
+    self.write(Self::getSv39Bits(rootPageTablePA, asid));
+  }
+
+  // Returns the value of satp, which enables Virtual Address Translation (VAT) using the Sv39
+  // scheme, with the Page Table at the given Physical Address (PA) as the root, and the
+  // translations tagged with the given ASID.
+  #[inline]
+  pub const fn getSv39Bits(rootPageTablePA: usize, asid: usize) -> usize {
+    BitMasks::SATP_MODE_SV39 as usize
+      | ((asid << ASID_SHIFT) & BitMasks::SATP_ASID as usize)
+      | (rootPageTablePA >> 12)
+  }
+
+  /*
+    Returns the number of ASID bits implemented by the current hart (ASIDLEN). It can be anything
+    from 0 (ASIDs not supported) to 16 (maximum for Sv39).
+
+    The unimplemented ASID bits are hardwired to 0. So, we write 1s to all the ASID bits and count
+    how many of them stick.
+
+    NOTE : The ASID bits are only meaningful when Virtual Address Translation (VAT) is enabled. So,
+    this must be invoked after that.
+  */
+  pub unsafe fn probeASIDBits(&self) -> usize {
+    let original = self.read();
+
+    self.write(original | BitMasks::SATP_ASID as usize);
+    let asidBits = (self.read() & BitMasks::SATP_ASID as usize).count_ones() as usize;
+
+    self.write(original);
+
+    asidBits
+  }
 }
@@ -0,0 +1,53 @@
+use core::arch::asm;
+
+// When a trap is taken into S-mode, the scause (Supervisor Cause) register is written with a code
+// indicating the event that caused the trap.
+// The Interrupt bit (the most significant bit) is set if the trap was caused by an interrupt. The
+// remaining bits contain the Exception Code.
+// REFER : section 10.1.8 and table 22 in privileged ISA manual.
+pub struct Scause;
+
+const INTERRUPT_BIT: usize = 1 << (usize::BITS - 1);
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum TrapCause {
+  SupervisorSoftwareInterrupt,
+  SupervisorTimerInterrupt,
+  SupervisorExternalInterrupt,
+
+  EnvironmentCallFromUMode,
+
+  InstructionPageFault,
+  LoadPageFault,
+  StorePageFault,
+
+  Unknown(usize),
+}
+
+impl Scause {
+  #[inline]
+  pub unsafe fn read(&self) -> usize {
+    let bits: usize;
+    asm!("csrr {}, scause", out(reg)bits);
+    bits
+  }
+
+  // Reads and decodes the cause of the trap which is currently being handled.
+  pub unsafe fn readTrapCause(&self) -> TrapCause {
+    let bits = self.read();
+
+    match (bits & INTERRUPT_BIT != 0, bits & !INTERRUPT_BIT) {
+      (true, 1) => TrapCause::SupervisorSoftwareInterrupt,
+      (true, 5) => TrapCause::SupervisorTimerInterrupt,
+      (true, 9) => TrapCause::SupervisorExternalInterrupt,
+
+      (false, 8) => TrapCause::EnvironmentCallFromUMode,
+
+      (false, 12) => TrapCause::InstructionPageFault,
+      (false, 13) => TrapCause::LoadPageFault,
+      (false, 15) => TrapCause::StorePageFault,
+
+      _ => TrapCause::Unknown(bits),
+    }
+  }
+}
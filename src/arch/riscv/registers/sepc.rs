@@ -0,0 +1,21 @@
+use core::arch::asm;
+
+// When a trap is taken into S-mode, the sepc (Supervisor Exception Program Counter) register is
+// written with the virtual address of the instruction that was interrupted or that encountered the
+// exception. The SRET instruction jumps back to the address stored in it.
+// REFER : section 10.1.7 in privileged ISA manual.
+pub struct Sepc;
+
+impl Sepc {
+  #[inline]
+  pub unsafe fn read(&self) -> usize {
+    let address: usize;
+    asm!("csrr {}, sepc", out(reg)address);
+    address
+  }
+
+  #[inline]
+  pub unsafe fn write(&self, address: usize) {
+    asm!("csrw sepc, {}", in(reg)address);
+  }
+}
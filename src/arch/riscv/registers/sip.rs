@@ -0,0 +1,24 @@
+use core::arch::asm;
+
+#[allow(non_camel_case_types)]
+enum BitMasks {
+  SUPERVISOR_SOFTWARE_INTERRUPT_PENDING = 1 << 1,
+}
+
+// The sip (Supervisor Interrupt Pending) register contains information on pending interrupts. Bit
+// i is set when interrupt i is pending.
+//
+// The SSIP bit is writable by S-mode, so the supervisor software interrupt handler can acknowledge
+// a software interrupt by clearing it.
+// REFER : section 10.1.3 in privileged ISA manual.
+pub struct Sip;
+
+impl Sip {
+  #[inline]
+  pub unsafe fn clearSoftwareInterruptPending(&self) {
+    asm!(
+      "csrc sip, {}",
+      in(reg)BitMasks::SUPERVISOR_SOFTWARE_INTERRUPT_PENDING as usize
+    );
+  }
+}
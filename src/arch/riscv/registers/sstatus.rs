@@ -2,7 +2,9 @@ use core::arch::asm;
 
 #[allow(non_camel_case_types)]
 enum BitMasks {
-  SSTATUS_SIE_CLEARER = 0 << 1,
+  SSTATUS_SIE = 1 << 1,
+  SSTATUS_SPIE = 1 << 5,
+  SSTATUS_SPP = 1 << 8,
 }
 
 // The sstatus (Supervisor Status) register, keeps track of the processor’s current operating state.
@@ -15,18 +17,49 @@ pub struct Sstatus;
 // interrupts are enabled. The supervisor can disable individual interrupt sources using the sie
 // CSR.
 impl Sstatus {
-  // Returns whether all interrupts are disable or not.
   #[inline]
-  pub unsafe fn areInterruptsEnabled(&self) -> bool {
-    let mut bits: usize;
+  pub unsafe fn read(&self) -> usize {
+    let bits: usize;
     asm!("csrr {}, sstatus", out(reg)bits);
+    bits
+  }
 
-    (bits & BitMasks::SSTATUS_SIE_CLEARER as usize) != 1
+  #[inline]
+  pub unsafe fn write(&self, bits: usize) {
+    asm!("csrw sstatus, {}", in(reg)bits);
+  }
+
+  // Returns whether all interrupts are disable or not.
+  #[inline]
+  pub unsafe fn areInterruptsEnabled(&self) -> bool {
+    (self.read() & BitMasks::SSTATUS_SIE as usize) != 0
   }
 
   // Disable all interrupts by clearing the SIE bits.
   #[inline]
   pub unsafe fn disableInterrupts(&self) {
-    asm!("csrc sstatus, {}", in(reg)BitMasks::SSTATUS_SIE_CLEARER as usize);
+    asm!("csrc sstatus, {}", in(reg)BitMasks::SSTATUS_SIE as usize);
+  }
+
+  // Enable all interrupts by setting the SIE bits.
+  #[inline]
+  pub unsafe fn enableInterrupts(&self) {
+    asm!("csrs sstatus, {}", in(reg)BitMasks::SSTATUS_SIE as usize);
+  }
+
+  // The SPP bit indicates the privilege level at which the hart was executing before entering
+  // S-mode. When a trap is taken, SPP is set to 0 if the trap originated from U-mode, or 1
+  // otherwise.
+  #[inline]
+  pub unsafe fn wasPreviousModeSMode(&self) -> bool {
+    (self.read() & BitMasks::SSTATUS_SPP as usize) != 0
+  }
+
+  // Makes the next SRET instruction return to U-mode (by clearing the SPP bit), with interrupts
+  // enabled (by setting the SPIE bit, which SRET copies into the SIE bit).
+  #[inline]
+  pub unsafe fn prepareReturnToUMode(&self) {
+    asm!("csrc sstatus, {}", in(reg)BitMasks::SSTATUS_SPP as usize);
+    asm!("csrs sstatus, {}", in(reg)BitMasks::SSTATUS_SPIE as usize);
   }
 }
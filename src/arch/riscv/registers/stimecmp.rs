@@ -0,0 +1,15 @@
+use core::arch::asm;
+
+// The stimecmp (Supervisor Timer Compare) register is provided by the Sstc extension. A supervisor
+// timer interrupt becomes pending, whenever the time CSR contains a value greater than or equal to
+// stimecmp. Writing to stimecmp is how the pending interrupt gets cleared.
+// NOTE : The CSR is referred to by its number (0x14D), since not every assembler knows its name.
+// REFER : chapter 16 (Sstc extension) in privileged ISA manual.
+pub struct Stimecmp;
+
+impl Stimecmp {
+  #[inline]
+  pub unsafe fn write(&self, value: usize) {
+    asm!("csrw 0x14D, {}", in(reg)value);
+  }
+}
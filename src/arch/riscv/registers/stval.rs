@@ -0,0 +1,15 @@
+use core::arch::asm;
+
+// When a trap is taken into S-mode, the stval (Supervisor Trap Value) register is written with
+// exception-specific information, like the faulting virtual address for page faults.
+// REFER : section 10.1.9 in privileged ISA manual.
+pub struct Stval;
+
+impl Stval {
+  #[inline]
+  pub unsafe fn read(&self) -> usize {
+    let value: usize;
+    asm!("csrr {}, stval", out(reg)value);
+    value
+  }
+}
@@ -0,0 +1,15 @@
+use core::arch::asm;
+
+// The stvec (Supervisor Trap Vector Base Address) register holds the address of the trap handler,
+// which the hart jumps to whenever a trap is taken into S-mode.
+// The 2 low bits encode the MODE. We always use the Direct mode (MODE = 0), where all traps set
+// the pc to the BASE address.
+// REFER : section 10.1.2 in privileged ISA manual.
+pub struct Stvec;
+
+impl Stvec {
+  #[inline]
+  pub unsafe fn set(&self, trapHandlerAddress: usize) {
+    asm!("csrw stvec, {}", in(reg)trapHandlerAddress);
+  }
+}
@@ -0,0 +1,18 @@
+use core::arch::asm;
+
+// The time CSR is a read-only shadow of the memory mapped mtime register (in the CLINT), which
+// counts the number of ticks of a constant frequency real-time clock, since boot.
+// In QEMU's virt machine, the clock runs at 10 MHz.
+// REFER : section 8.1 (Zicntr extension) in unprivileged ISA manual.
+pub struct Time;
+
+pub const TIME_FREQUENCY: usize = 10_000_000; // (ticks per second)
+
+impl Time {
+  #[inline]
+  pub fn read(&self) -> usize {
+    let ticks: usize;
+    unsafe { asm!("csrr {}, time", out(reg)ticks) };
+    ticks
+  }
+}
@@ -15,7 +15,7 @@ impl Tp {
   #[inline]
   pub unsafe fn read(&self) -> usize {
     let hartID: usize;
-    asm!("mv tp, {}", out(reg)hartID);
+    asm!("mv {}, tp", out(reg)hartID);
     hartID
   }
 }
@@ -0,0 +1,33 @@
+use core::ptr::write_volatile;
+
+/*
+  The CLINT (Core Local Interruptor) is a memory mapped device, which generates the software and
+  timer interrupts for each hart.
+
+  For each hart, it has a 32 bit msip (Machine Software Interrupt Pending) register. Writing 1 to
+  the msip register of a hart raises a machine-mode software interrupt on that hart, and writing 0
+  clears it. This is how a hart interrupts another hart (Inter-Processor Interrupt / IPI).
+
+  NOTE : The interrupts generated by the CLINT are always taken in M-mode (they can't be delegated
+  using mideleg). The M-mode trap handler (defined in ../asm/machinevec.S) forwards them to S-mode
+  by setting the SSIP bit in the mip register.
+
+  REFER : https://github.com/qemu/qemu/blob/master/hw/riscv/virt.c#L70 for the memory mapped
+  address.
+*/
+pub struct CLINTDriver;
+
+pub const CLINT_BASE_REGISTER: usize = 0x0200_0000;
+
+impl CLINTDriver {
+  // Returns the memory mapped msip register of the given hart.
+  #[inline]
+  fn getMsipRegister(hartID: usize) -> *mut u32 {
+    (CLINT_BASE_REGISTER + 4 * hartID) as *mut u32
+  }
+
+  // Raises a software interrupt in the given hart.
+  pub unsafe fn raiseSoftwareInterrupt(&self, hartID: usize) {
+    write_volatile(Self::getMsipRegister(hartID), 1);
+  }
+}
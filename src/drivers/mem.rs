@@ -0,0 +1,113 @@
+use crate::{
+  arch::riscv::registers::time::Time,
+  fs::{
+    device::{self, CharDevice},
+    vfs::{DeviceNumber, FsError},
+  },
+  locks::spinlock::SpinLock,
+};
+
+/*
+  Character devices which aren't backed by any hardware (drivers/char/mem.c in Linux).
+
+    /dev/null     Reads return end of input, and writes are discarded.
+    /dev/zero     Reads return zeroes, and writes are discarded.
+    /dev/random   Reads return pseudo random bytes, and writes are discarded.
+*/
+
+// Device numbers, same as in Linux.
+pub const NULL_DEVICE_NUMBER: DeviceNumber = DeviceNumber::new(1, 3);
+pub const ZERO_DEVICE_NUMBER: DeviceNumber = DeviceNumber::new(1, 5);
+pub const RANDOM_DEVICE_NUMBER: DeviceNumber = DeviceNumber::new(1, 8);
+
+// Registers the null, zero and random character devices.
+// NOTE : Should only be invoked while the kernel is initializing.
+pub fn init() {
+  let devices: [(&[u8], DeviceNumber, &'static dyn CharDevice); 3] = [
+    (b"null", NULL_DEVICE_NUMBER, &NullDevice),
+    (b"zero", ZERO_DEVICE_NUMBER, &ZeroDevice),
+    (b"random", RANDOM_DEVICE_NUMBER, &RANDOM_DEVICE),
+  ];
+
+  for (name, deviceNumber, charDevice) in devices {
+    device::registerCharDevice(name, deviceNumber, charDevice)
+      .expect("Failed registering a memory character device");
+  }
+}
+
+struct NullDevice;
+
+impl CharDevice for NullDevice {
+  fn read(&self, _buffer: &mut [u8]) -> Result<usize, FsError> {
+    Ok(0)
+  }
+
+  fn write(&self, buffer: &[u8]) -> Result<usize, FsError> {
+    Ok(buffer.len())
+  }
+}
+
+struct ZeroDevice;
+
+impl CharDevice for ZeroDevice {
+  fn read(&self, buffer: &mut [u8]) -> Result<usize, FsError> {
+    buffer.fill(0);
+    Ok(buffer.len())
+  }
+
+  fn write(&self, buffer: &[u8]) -> Result<usize, FsError> {
+    Ok(buffer.len())
+  }
+}
+
+/*
+  Generates pseudo random bytes using xorshift64*, seeded with the time CSR when it's first read.
+
+  REFER : https://en.wikipedia.org/wiki/Xorshift#xorshift*.
+
+  NOTE : The output is predictable, so it mustn't be used for anything security sensitive.
+*/
+struct RandomDevice {
+  // 0 till the generator gets seeded (the state of xorshift is never 0).
+  state: SpinLock<u64>,
+}
+
+static RANDOM_DEVICE: RandomDevice = RandomDevice {
+  state: SpinLock::new(0),
+};
+
+impl RandomDevice {
+  fn getNext(state: &mut u64) -> u64 {
+    if *state == 0 {
+      *state = (Time.read() as u64) | 1;
+    }
+
+    *state ^= *state >> 12;
+    *state ^= *state << 25;
+    *state ^= *state >> 27;
+
+    state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+  }
+}
+
+impl CharDevice for RandomDevice {
+  fn read(&self, buffer: &mut [u8]) -> Result<usize, FsError> {
+    let mut state = self.state.acquire();
+
+    for chunk in buffer.chunks_mut(8) {
+      let randomBytes = Self::getNext(&mut state).to_le_bytes();
+      chunk.copy_from_slice(&randomBytes[..chunk.len()]);
+    }
+
+    Ok(buffer.len())
+  }
+
+  fn write(&self, buffer: &[u8]) -> Result<usize, FsError> {
+    Ok(buffer.len())
+  }
+}
+
+// Fills the given buffer with pseudo random bytes (the same ones /dev/random gives).
+pub fn getRandomBytes(buffer: &mut [u8]) {
+  let _ = RANDOM_DEVICE.read(buffer);
+}
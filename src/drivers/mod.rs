@@ -1,4 +1,8 @@
 #[macro_use]
 pub mod uart;
 
+pub mod clint;
+pub mod mem;
 pub mod plic;
+pub mod ramdisk;
+pub mod virtio;
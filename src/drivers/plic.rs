@@ -1 +1,81 @@
-struct PLICDriver;
+use core::ptr::{read_volatile, write_volatile};
+
+/*
+  The PLIC (Platform-Level Interrupt Controller) routes the interrupts raised by external devices
+  (like the UART or the virtio devices), to the harts.
+
+  Each interrupt source (identified by an IRQ number) has a priority. Each (hart, privilege mode)
+  pair is a separate PLIC context, which has its own set of enabled interrupt sources and a
+  priority threshold. An interrupt is forwarded to a context, only if it's enabled for the context
+  and its priority is greater than the context's threshold.
+
+  On getting a supervisor external interrupt, the hart claims the interrupt from the PLIC (which
+  returns the IRQ number of the highest priority pending interrupt), handles it and then tells the
+  PLIC that it has completed handling the interrupt.
+
+  REFER : https://github.com/riscv/riscv-plic-spec/blob/master/riscv-plic.adoc and
+  https://github.com/qemu/qemu/blob/master/include/hw/riscv/virt.h for the memory mapped
+  addresses and IRQ numbers.
+*/
+pub struct PLICDriver;
+
+pub const PLIC_BASE_REGISTER: usize = 0x0c00_0000;
+
+// In QEMU's virt machine, the virtio-mmio devices use IRQs 1 to 8 and the UART uses IRQ 10.
+pub const VIRTIO_IRQS: core::ops::RangeInclusive<u32> = 1..=8;
+pub const UART_IRQ: u32 = 10;
+
+const PRIORITIES_OFFSET: usize = 0;
+const ENABLES_OFFSET: usize = 0x2000;
+const CONTEXTS_OFFSET: usize = 0x20_0000;
+
+// In QEMU's virt machine, PLIC context (2 * hartID) belongs to the M-mode and (2 * hartID + 1) to
+// the S-mode of the hart.
+#[inline]
+fn getSModeContext(hartID: usize) -> usize {
+  2 * hartID + 1
+}
+
+impl PLICDriver {
+  // Sets the priority of the given interrupt source. Priority 0 means never interrupt.
+  pub unsafe fn setPriority(&self, irq: u32, priority: u32) {
+    write_volatile(
+      (PLIC_BASE_REGISTER + PRIORITIES_OFFSET + 4 * irq as usize) as *mut u32,
+      priority,
+    );
+  }
+
+  // Enables the given interrupt source, for the S-mode of the given hart.
+  pub unsafe fn enableForHart(&self, hartID: usize, irq: u32) {
+    let enableRegister = (PLIC_BASE_REGISTER
+      + ENABLES_OFFSET
+      + 0x80 * getSModeContext(hartID)
+      + 4 * (irq as usize / 32)) as *mut u32;
+
+    write_volatile(
+      enableRegister,
+      read_volatile(enableRegister) | (1 << (irq % 32)),
+    );
+  }
+
+  // Sets the priority threshold, for the S-mode of the given hart.
+  pub unsafe fn setThreshold(&self, hartID: usize, threshold: u32) {
+    write_volatile(Self::getContextRegister(hartID, 0), threshold);
+  }
+
+  // Returns the IRQ number of the highest priority pending interrupt, for the S-mode of the given
+  // hart. 0 means there's none.
+  pub unsafe fn claim(&self, hartID: usize) -> u32 {
+    read_volatile(Self::getContextRegister(hartID, 4))
+  }
+
+  // Tells the PLIC that the S-mode of the given hart has finished handling the given interrupt.
+  pub unsafe fn complete(&self, hartID: usize, irq: u32) {
+    write_volatile(Self::getContextRegister(hartID, 4), irq);
+  }
+
+  #[inline]
+  fn getContextRegister(hartID: usize, offset: usize) -> *mut u32 {
+    (PLIC_BASE_REGISTER + CONTEXTS_OFFSET + 0x1000 * getSModeContext(hartID) + offset) as *mut u32
+  }
+}
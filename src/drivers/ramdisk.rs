@@ -0,0 +1,119 @@
+use {
+  crate::{
+    fs::disk::{self, BlockDevice, BlockDeviceError, BlockOperation, BlockRequest},
+    locks::spinlock::SpinLock,
+  },
+  alloc::{boxed::Box, vec::Vec},
+  core::slice,
+};
+
+/*
+  A block device backed by memory. Its contents are lost once the system shuts down.
+
+  Useful for testing the file system without any real device : the RAM disk can be initialized
+  from a file system image embedded into the kernel binary. Build the kernel with the RAMDISK_IMAGE
+  environment variable pointing to the image, to embed it. Images can be created using the mkfs tool
+  (in primitives/mkfs).
+*/
+pub struct RamDisk {
+  blockSize: usize,
+  data: SpinLock<Vec<u8>>,
+}
+
+// Block size used by RAM disks.
+pub const RAMDISK_BLOCK_SIZE: usize = 512;
+
+// The file system image embedded into the kernel binary (if any).
+#[cfg(ramdisk_image)]
+static RAMDISK_IMAGE: &[u8] = include_bytes!(env!("RAMDISK_IMAGE"));
+
+impl RamDisk {
+  // Creates a zero filled RAM disk, with the given number of blocks.
+  pub fn new(blocksCount: usize) -> Self {
+    Self {
+      blockSize: RAMDISK_BLOCK_SIZE,
+      data: SpinLock::new(alloc::vec![0; blocksCount * RAMDISK_BLOCK_SIZE]),
+    }
+  }
+
+  // Creates a RAM disk, initialized with a copy of the given image.
+  // The image size gets rounded up to a multiple of the block size.
+  pub fn fromImage(image: &[u8]) -> Self {
+    let mut data = alloc::vec![0; image.len().div_ceil(RAMDISK_BLOCK_SIZE) * RAMDISK_BLOCK_SIZE];
+    data[..image.len()].copy_from_slice(image);
+
+    Self {
+      blockSize: RAMDISK_BLOCK_SIZE,
+      data: SpinLock::new(data),
+    }
+  }
+
+  // Carries out the given request right away. Returns its result.
+  fn process(&self, request: &BlockRequest) -> Result<(), BlockDeviceError> {
+    let mut data = self.data.acquire();
+
+    let start = request.blockNumber * self.blockSize;
+    let end = start + request.length;
+    if end > data.len() {
+      return Err(BlockDeviceError::OutOfRange);
+    }
+
+    match request.operation {
+      BlockOperation::Read => {
+        let buffer = unsafe { slice::from_raw_parts_mut(request.buffer, request.length) };
+        buffer.copy_from_slice(&data[start..end]);
+      }
+
+      BlockOperation::Write => {
+        let buffer = unsafe { slice::from_raw_parts(request.buffer, request.length) };
+        data[start..end].copy_from_slice(buffer);
+      }
+
+      // Memory is as stable as the RAM disk gets.
+      BlockOperation::Flush => {}
+    }
+
+    Ok(())
+  }
+}
+
+impl BlockDevice for RamDisk {
+  fn getBlockSize(&self) -> usize {
+    self.blockSize
+  }
+
+  fn getBlocksCount(&self) -> usize {
+    self.data.acquire().len() / self.blockSize
+  }
+
+  // Requests get completed right away.
+  unsafe fn submit(&self, request: &BlockRequest) {
+    assert!(
+      request.length % self.blockSize == 0,
+      "RAM disk : buffer length isn't a multiple of the block size"
+    );
+
+    request.complete(self.process(request));
+  }
+}
+
+// Attaches a RAM disk initialized from the embedded file system image (if any).
+// Returns the disk number.
+// NOTE : Should only be invoked once, while the kernel is initializing.
+pub fn init() -> Option<usize> {
+  #[cfg(ramdisk_image)]
+  {
+    let ramDisk: &'static RamDisk = Box::leak(Box::new(RamDisk::fromImage(RAMDISK_IMAGE)));
+    disk::attach(ramDisk)
+  }
+
+  #[cfg(not(ramdisk_image))]
+  None
+}
+
+// Creates a zero filled RAM disk with the given number of blocks, and attaches it. Returns the disk
+// number.
+pub fn attachEmpty(blocksCount: usize) -> Option<usize> {
+  let ramDisk: &'static RamDisk = Box::leak(Box::new(RamDisk::new(blocksCount)));
+  disk::attach(ramDisk)
+}
@@ -1,6 +1,15 @@
-use core::{
-  fmt::{self, Write},
-  ptr::write_volatile,
+use {
+  crate::{
+    fs::{
+      device::{self, CharDevice},
+      vfs::{DeviceNumber, FsError, PollEvents},
+    },
+    locks::{spinlock::SpinLock, waitqueue::WaitQueue},
+  },
+  core::{
+    fmt::{self, Write},
+    ptr::{read_volatile, write_volatile},
+  },
 };
 
 /*
@@ -26,9 +35,17 @@ struct UARTDriver;
   space. This allows us to interact with the UART controller using standard memory read-write
   operations. The base register is mapped to the 0x1000_0000 memory address.
 */
-const UART_BASE_REGISTER: usize = 0x1000_0000;
+pub const UART_BASE_REGISTER: usize = 0x1000_0000;
 
 const TRANSMIT_HOLDING_REGISTER: usize = UART_BASE_REGISTER;
+const RECEIVE_HOLDING_REGISTER: usize = UART_BASE_REGISTER;
+const INTERRUPT_ENABLE_REGISTER: usize = UART_BASE_REGISTER + 1;
+const FIFO_CONTROL_REGISTER: usize = UART_BASE_REGISTER + 2;
+const LINE_STATUS_REGISTER: usize = UART_BASE_REGISTER + 5;
+
+const IER_RECEIVED_DATA_AVAILABLE: u8 = 1 << 0;
+const FCR_ENABLE_FIFOS: u8 = 1 << 0;
+const LSR_DATA_READY: u8 = 1 << 0;
 
 impl Write for UARTDriver {
   fn write_str(&mut self, s: &str) -> core::fmt::Result {
@@ -41,6 +58,153 @@ impl Write for UARTDriver {
   }
 }
 
+/*
+  Bytes received by the UART are queued in an input buffer by the interrupt handler, till a
+  process reads them. Once the input buffer is full, further bytes get dropped.
+*/
+const INPUT_BUFFER_SIZE: usize = 256;
+
+struct InputBuffer {
+  bytes: [u8; INPUT_BUFFER_SIZE],
+
+  // Free running indices : the unread bytes are the ones in [readIndex, writeIndex).
+  readIndex: usize,
+  writeIndex: usize,
+}
+
+impl InputBuffer {
+  const fn new() -> Self {
+    Self {
+      bytes: [0; INPUT_BUFFER_SIZE],
+      readIndex: 0,
+      writeIndex: 0,
+    }
+  }
+
+  #[inline]
+  fn getLength(&self) -> usize {
+    self.writeIndex - self.readIndex
+  }
+
+  fn push(&mut self, byte: u8) {
+    if self.getLength() < INPUT_BUFFER_SIZE {
+      self.bytes[self.writeIndex % INPUT_BUFFER_SIZE] = byte;
+      self.writeIndex += 1;
+    }
+  }
+
+  fn pop(&mut self) -> Option<u8> {
+    if self.getLength() == 0 {
+      return None;
+    }
+
+    let byte = self.bytes[self.readIndex % INPUT_BUFFER_SIZE];
+    self.readIndex += 1;
+    Some(byte)
+  }
+}
+
+static INPUT_BUFFER: SpinLock<InputBuffer> = SpinLock::new(InputBuffer::new());
+
+// Processes waiting for the input buffer to become non empty.
+static INPUT_WAIT_QUEUE: WaitQueue = WaitQueue::new();
+
+// Starts receiving input, and registers the UART as the ttyS0 and console character devices.
+// NOTE : Should only be invoked while the kernel is initializing.
+pub fn init() {
+  unsafe {
+    write_volatile(FIFO_CONTROL_REGISTER as *mut u8, FCR_ENABLE_FIFOS);
+    write_volatile(
+      INTERRUPT_ENABLE_REGISTER as *mut u8,
+      IER_RECEIVED_DATA_AVAILABLE,
+    );
+  }
+
+  for (name, deviceNumber) in [
+    (&b"ttyS0"[..], SERIAL_DEVICE_NUMBER),
+    (&b"console"[..], CONSOLE_DEVICE_NUMBER),
+  ] {
+    device::registerCharDevice(name, deviceNumber, &SERIAL)
+      .expect("Failed registering the UART as a character device");
+  }
+}
+
+// Moves the received bytes from the UART into the input buffer, and wakes up the processes waiting
+// for input.
+// NOTE : Invoked when the UART raises an interrupt.
+pub fn handleInterrupt() {
+  let mut inputBuffer = INPUT_BUFFER.acquire();
+
+  while unsafe { read_volatile(LINE_STATUS_REGISTER as *const u8) } & LSR_DATA_READY != 0 {
+    let byte = unsafe { read_volatile(RECEIVE_HOLDING_REGISTER as *const u8) };
+    inputBuffer.push(byte);
+  }
+
+  drop(inputBuffer);
+  INPUT_WAIT_QUEUE.notifyAll();
+}
+
+// Device numbers, same as in Linux.
+pub const SERIAL_DEVICE_NUMBER: DeviceNumber = DeviceNumber::new(4, 64);
+pub const CONSOLE_DEVICE_NUMBER: DeviceNumber = DeviceNumber::new(5, 1);
+
+// ioctl( ) request returning the number of received bytes, which haven't been read yet.
+pub const FIONREAD: usize = 0x541b;
+
+// The UART, as a character device. The console is the same device, since the UART is the only
+// terminal we have.
+struct SerialDevice;
+
+static SERIAL: SerialDevice = SerialDevice;
+
+impl CharDevice for SerialDevice {
+  // Blocks till there's some input, and then returns as much of it as fits in the buffer.
+  fn read(&self, buffer: &mut [u8]) -> Result<usize, FsError> {
+    if buffer.is_empty() {
+      return Ok(0);
+    }
+
+    let inputBuffer = INPUT_BUFFER.acquire();
+    let mut inputBuffer =
+      INPUT_WAIT_QUEUE.waitUntil(inputBuffer, |inputBuffer| inputBuffer.getLength() > 0);
+
+    let mut bytesRead = 0;
+    while bytesRead < buffer.len() {
+      let Some(byte) = inputBuffer.pop()
+      else {
+        break;
+      };
+
+      buffer[bytesRead] = byte;
+      bytesRead += 1;
+    }
+
+    Ok(bytesRead)
+  }
+
+  fn write(&self, buffer: &[u8]) -> Result<usize, FsError> {
+    for byte in buffer {
+      unsafe { write_volatile(TRANSMIT_HOLDING_REGISTER as *mut u8, *byte) };
+    }
+
+    Ok(buffer.len())
+  }
+
+  fn ioctl(&self, request: usize, _argument: usize) -> Result<usize, FsError> {
+    match request {
+      FIONREAD => Ok(INPUT_BUFFER.acquire().getLength()),
+      _ => Err(FsError::NotSupported),
+    }
+  }
+
+  fn poll(&self) -> PollEvents {
+    match INPUT_BUFFER.acquire().getLength() {
+      0 => PollEvents::WRITABLE,
+      _ => PollEvents::READABLE | PollEvents::WRITABLE,
+    }
+  }
+}
+
 pub fn print(args: fmt::Arguments) {
   UARTDriver.write_fmt(args).unwrap();
 }
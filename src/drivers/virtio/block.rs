@@ -0,0 +1,444 @@
+use {
+  super::{
+    queue::{DescriptorFlags, VirtQueue, QUEUE_SIZE},
+    DeviceStatus, Register, VirtioMMIO, VIRTIO_F_VERSION_1, VIRTIO_MMIO_SLOTS_COUNT,
+  },
+  crate::{
+    fs::disk::{self, BlockDevice, BlockDeviceError, BlockOperation, BlockRequest},
+    locks::{
+      spinlock::{SpinLock, SpinLockGuard},
+      waitqueue::WaitQueue,
+    },
+    process::process::getCurrentProcess,
+  },
+  alloc::vec::Vec,
+  array_macro::array,
+  core::{
+    hint::spin_loop,
+    mem::{self, size_of},
+    ptr::addr_of,
+  },
+};
+
+/*
+  Driver for the virtio block device (virtio-blk).
+
+  Each request is made up of a chain of descriptors :
+
+    (1) the request header (type and starting sector), read by the device.
+
+    (2) the data buffers, which the device reads from (write request) or writes to (read request).
+        Requests for contiguous sectors get merged into a single request, with a descriptor per
+        data buffer.
+
+    (3) a single status byte, written by the device once it has finished processing the request.
+
+  Requests are asynchronous. The device raises an interrupt (through the PLIC), after putting a
+  finished request in the used ring. The interrupt handler then completes the corresponding
+  BlockRequests, waking up their submitters.
+
+  REFER : section 5.2 in the virtio specification.
+*/
+
+// The device always addresses data in units of 512 byte sectors. So that's its block size.
+pub const SECTOR_SIZE: usize = 512;
+
+// Device specific feature bits, we care about.
+const VIRTIO_BLK_F_RO: usize = 5;
+const VIRTIO_BLK_F_FLUSH: usize = 9;
+
+// Maximum number of BlockRequests, which can be merged into a single request.
+const MAX_MERGED_REQUESTS: usize = 8;
+
+// Number of requests which can be in flight, such that the virtqueue never runs out of descriptors.
+const QUEUE_DEPTH: usize = QUEUE_SIZE / (MAX_MERGED_REQUESTS + 2);
+
+// Offset of the capacity (number of sectors) field in the device configuration space.
+const CONFIG_CAPACITY_OFFSET: usize = 0;
+
+#[allow(non_camel_case_types)]
+#[derive(Copy, Clone)]
+enum RequestType {
+  IN = 0,
+  OUT = 1,
+  FLUSH = 4,
+}
+
+const REQUEST_STATUS_OK: u8 = 0;
+const REQUEST_STATUS_UNSUPPORTED: u8 = 2;
+// Set by us, before handing over the request to the device.
+const REQUEST_STATUS_PENDING: u8 = 0xff;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct RequestHeader {
+  requestType: u32,
+  reserved: u32,
+  sector: u64,
+}
+
+struct VirtioBlockState {
+  mmio: Option<VirtioMMIO>,
+
+  // Size of the device, in sectors.
+  capacity: u64,
+
+  isReadOnly: bool,
+  supportsFlush: bool,
+
+  queue: VirtQueue,
+
+  // The following are indexed by the head descriptor of the request.
+  requestHeaders: [RequestHeader; QUEUE_SIZE],
+  requestStatuses: [u8; QUEUE_SIZE],
+  // BlockRequests handed over to the device, merged into the request.
+  inFlightRequests: [Vec<*const BlockRequest>; QUEUE_SIZE],
+}
+
+impl VirtioBlockState {
+  const fn new() -> Self {
+    Self {
+      mmio: None,
+
+      capacity: 0,
+
+      isReadOnly: false,
+      supportsFlush: false,
+
+      queue: VirtQueue::new(),
+
+      requestHeaders: [RequestHeader {
+        requestType: 0,
+        reserved: 0,
+        sector: 0,
+      }; QUEUE_SIZE],
+      requestStatuses: [0; QUEUE_SIZE],
+      inFlightRequests: [const { Vec::new() }; QUEUE_SIZE],
+    }
+  }
+
+  // Frees the descriptors of the requests which the device has finished processing. Returns the
+  // corresponding BlockRequests along with their results, to be completed.
+  // NOTE : The BlockRequests must be completed after releasing the SpinLock guarding the state,
+  // since completing a BlockRequest may submit another one.
+  fn takeFinishedRequests(&mut self) -> FinishedRequests {
+    let mut finishedRequests = Vec::new();
+
+    while let Some((head, _)) = self.queue.popUsed() {
+      let requests = mem::take(&mut self.inFlightRequests[head as usize]);
+
+      let result = match self.requestStatuses[head as usize] {
+        REQUEST_STATUS_OK => Ok(()),
+        REQUEST_STATUS_UNSUPPORTED => Err(BlockDeviceError::Unsupported),
+        _ => Err(BlockDeviceError::IO),
+      };
+
+      self.queue.freeDescriptorChain(head);
+
+      finishedRequests.extend(requests.into_iter().map(|request| (request, result)));
+    }
+
+    FinishedRequests(finishedRequests)
+  }
+}
+
+// BlockRequests which the device has finished processing.
+struct FinishedRequests(Vec<(*const BlockRequest, Result<(), BlockDeviceError>)>);
+
+impl FinishedRequests {
+  #[inline]
+  fn isEmpty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  fn complete(self) {
+    for (request, result) in self.0 {
+      // The request stays alive till it's completed.
+      unsafe { (*request).complete(result) };
+    }
+  }
+}
+
+// The BlockRequests are only accessed while holding the SpinLock guarding the state.
+unsafe impl Send for VirtioBlockState {}
+
+pub struct VirtioBlockDevice {
+  state: SpinLock<VirtioBlockState>,
+
+  // Processes waiting for free descriptors.
+  waitQueue: WaitQueue,
+}
+
+impl VirtioBlockDevice {
+  const fn new() -> Self {
+    Self {
+      state: SpinLock::new(VirtioBlockState::new()),
+      waitQueue: WaitQueue::new(),
+    }
+  }
+
+  pub fn isReadOnly(&self) -> bool {
+    self.state.acquire().isReadOnly
+  }
+
+  /*
+    Waits till the given condition becomes true.
+
+    A process sleeps on the WaitQueue, and gets woken up by the interrupt handler. But while the
+    kernel is initializing, there's no process to put to sleep (and interrupts are disabled). So
+    the CPU core keeps polling the used ring instead.
+  */
+  fn waitUntil<'a>(
+    &'a self,
+    mut state: SpinLockGuard<'a, VirtioBlockState>,
+    mut condition: impl FnMut(&mut VirtioBlockState) -> bool,
+  ) -> SpinLockGuard<'a, VirtioBlockState> {
+    if getCurrentProcess().is_some() {
+      return self.waitQueue.waitUntil(state, condition);
+    }
+
+    while !condition(&mut state) {
+      let finishedRequests = state.takeFinishedRequests();
+      drop(state);
+
+      finishedRequests.complete();
+      spin_loop();
+
+      state = self.state.acquire();
+    }
+    state
+  }
+
+  // Handles an interrupt raised by the device.
+  fn handleInterrupt(&self) {
+    let mut state = self.state.acquire();
+
+    let Some(mmio) = state.mmio
+    else {
+      return;
+    };
+
+    // Acknowledge the interrupt.
+    mmio.write(
+      Register::INTERRUPT_ACK,
+      mmio.read(Register::INTERRUPT_STATUS) & 0b11,
+    );
+
+    let finishedRequests = state.takeFinishedRequests();
+    drop(state);
+
+    self.completeFinishedRequests(finishedRequests);
+  }
+
+  fn completeFinishedRequests(&self, finishedRequests: FinishedRequests) {
+    if finishedRequests.isEmpty() {
+      return;
+    }
+
+    // Descriptors have been freed.
+    self.waitQueue.notifyAll();
+
+    finishedRequests.complete();
+  }
+}
+
+impl BlockDevice for VirtioBlockDevice {
+  fn getBlockSize(&self) -> usize {
+    SECTOR_SIZE
+  }
+
+  fn getBlocksCount(&self) -> usize {
+    self.state.acquire().capacity as usize
+  }
+
+  fn getQueueDepth(&self) -> usize {
+    QUEUE_DEPTH
+  }
+
+  fn getMaxMergedRequestsCount(&self) -> usize {
+    MAX_MERGED_REQUESTS
+  }
+
+  unsafe fn submit(&self, request: &BlockRequest) {
+    unsafe { self.submitMerged(&[request]) };
+  }
+
+  unsafe fn submitMerged(&self, requests: &[&BlockRequest]) {
+    assert!(
+      (1..=MAX_MERGED_REQUESTS).contains(&requests.len()),
+      "virtio-blk : invalid number of merged requests"
+    );
+    assert!(
+      requests
+        .iter()
+        .all(|request| request.length % SECTOR_SIZE == 0),
+      "virtio-blk : buffer length isn't a multiple of the sector size"
+    );
+
+    let mut state = self.state.acquire();
+    let mmio = state.mmio.expect("virtio-blk : device isn't initialized");
+
+    let requestType = match requests[0].operation {
+      BlockOperation::Read => RequestType::IN,
+      BlockOperation::Write => RequestType::OUT,
+      BlockOperation::Flush => RequestType::FLUSH,
+    };
+
+    let sector = requests[0].blockNumber as u64;
+    let sectorsCount = requests
+      .iter()
+      .map(|request| (request.length / SECTOR_SIZE) as u64)
+      .sum::<u64>();
+
+    let rejection = match requestType {
+      RequestType::OUT if state.isReadOnly => Some(Err(BlockDeviceError::Unsupported)),
+
+      // Nothing to flush, if the device doesn't cache writes.
+      RequestType::FLUSH if !state.supportsFlush => Some(Ok(())),
+
+      RequestType::IN | RequestType::OUT if sector + sectorsCount > state.capacity => {
+        Some(Err(BlockDeviceError::OutOfRange))
+      }
+
+      _ => None,
+    };
+    if let Some(result) = rejection {
+      drop(state);
+      for request in requests {
+        request.complete(result);
+      }
+      return;
+    }
+
+    // A flush request doesn't have data buffers.
+    let dataBuffers: Vec<&BlockRequest> = requests
+      .iter()
+      .copied()
+      .filter(|request| request.length > 0)
+      .collect();
+
+    let requiredDescriptorsCount = dataBuffers.len() + 2;
+    state = self.waitUntil(state, |state| {
+      state.queue.getFreeDescriptorsCount() >= requiredDescriptorsCount
+    });
+
+    let descriptors: Vec<u16> = (0..requiredDescriptorsCount)
+      .map(|_| state.queue.allocateDescriptor().unwrap())
+      .collect();
+    let head = descriptors[0];
+
+    // The request header and the status byte live in the state, so that they stay put till the
+    // device has finished processing the request.
+    state.requestHeaders[head as usize] = RequestHeader {
+      requestType: requestType as u32,
+      reserved: 0,
+      sector,
+    };
+    state.requestStatuses[head as usize] = REQUEST_STATUS_PENDING;
+
+    let headerAddress = addr_of!(state.requestHeaders[head as usize]) as u64;
+    let statusAddress = addr_of!(state.requestStatuses[head as usize]) as u64;
+
+    state
+      .queue
+      .setDescriptor(head, headerAddress, size_of::<RequestHeader>() as u32, 0);
+
+    // For a read request, the device writes to the data buffers.
+    let dataFlags = match requestType {
+      RequestType::IN => DescriptorFlags::WRITE as u16,
+      _ => 0,
+    };
+    for (&descriptor, request) in descriptors[1..].iter().zip(dataBuffers) {
+      state.queue.setDescriptor(
+        descriptor,
+        request.buffer as u64,
+        request.length as u32,
+        dataFlags,
+      );
+    }
+
+    state.queue.setDescriptor(
+      descriptors[requiredDescriptorsCount - 1],
+      statusAddress,
+      1,
+      DescriptorFlags::WRITE as u16,
+    );
+
+    state.queue.chainDescriptors(&descriptors);
+
+    state.inFlightRequests[head as usize] = requests
+      .iter()
+      .map(|&request| request as *const BlockRequest)
+      .collect();
+    state.queue.submit(head);
+
+    // Notify the device, that there's a new request in virtqueue 0.
+    mmio.write(Register::QUEUE_NOTIFY, 0);
+  }
+
+  fn poll(&self) {
+    let finishedRequests = self.state.acquire().takeFinishedRequests();
+    self.completeFinishedRequests(finishedRequests);
+  }
+}
+
+// One per virtio-mmio slot.
+static VIRTIO_BLOCK_DEVICES: [VirtioBlockDevice; VIRTIO_MMIO_SLOTS_COUNT] =
+  array![_ => VirtioBlockDevice::new(); VIRTIO_MMIO_SLOTS_COUNT];
+
+// Initializes the virtio block device in the given virtio-mmio slot, and attaches it as a disk.
+pub fn init(slot: usize, mmio: VirtioMMIO) {
+  let device = &VIRTIO_BLOCK_DEVICES[slot];
+  let mut state = device.state.acquire();
+
+  let Some(acceptedFeatures) = mmio.negotiateFeatures(|feature| {
+    matches!(
+      feature,
+      VIRTIO_F_VERSION_1 | VIRTIO_BLK_F_RO | VIRTIO_BLK_F_FLUSH
+    )
+  })
+  else {
+    println!(
+      "WARN : virtio-blk device in slot {} rejected our features",
+      slot
+    );
+    return;
+  };
+
+  if !state.queue.init(mmio, 0) {
+    mmio.setStatusBits(DeviceStatus::FAILED as u32);
+    println!(
+      "WARN : virtio-blk device in slot {} doesn't support our virtqueue",
+      slot
+    );
+    return;
+  }
+
+  state.mmio = Some(mmio);
+  state.capacity = mmio.readConfig::<u64>(CONFIG_CAPACITY_OFFSET);
+  state.isReadOnly = acceptedFeatures & (1 << VIRTIO_BLK_F_RO) != 0;
+  state.supportsFlush = acceptedFeatures & (1 << VIRTIO_BLK_F_FLUSH) != 0;
+
+  // The device is live now.
+  mmio.setStatusBits(DeviceStatus::DRIVER_OK as u32);
+
+  println!(
+    "INFO : virtio-blk device in slot {} has {} sectors",
+    slot, state.capacity
+  );
+  drop(state);
+
+  match disk::attach(device) {
+    Some(diskNumber) => {
+      println!("INFO : Attached as disk {}", diskNumber);
+    }
+    None => {
+      println!("WARN : No free disk number to attach the virtio-blk device to");
+    }
+  }
+}
+
+// Handles an interrupt raised by the virtio block device in the given virtio-mmio slot.
+pub fn handleInterrupt(slot: usize) {
+  VIRTIO_BLOCK_DEVICES[slot].handleInterrupt();
+}
@@ -0,0 +1,210 @@
+pub mod block;
+pub mod queue;
+
+use {
+  crate::drivers::plic::VIRTIO_IRQS,
+  core::ptr::{read_volatile, write_volatile},
+};
+
+/*
+  Virtio is a standard interface for virtual devices (like disks and network cards). QEMU's virt
+  machine exposes upto 8 virtio devices over the MMIO (memory mapped I/O) transport : each virtio
+  device slot gets a 4 KB region of memory mapped registers, starting at 0x1000_1000.
+
+  NOTE : We only support the modern (version 2) MMIO interface. QEMU must be run with
+  -global virtio-mmio.force-legacy=false.
+
+  REFER : https://docs.oasis-open.org/virtio/virtio/v1.2/virtio-v1.2.html (section 4.2).
+*/
+
+pub const VIRTIO_MMIO_BASE_REGISTER: usize = 0x1000_1000;
+pub const VIRTIO_MMIO_SLOT_SIZE: usize = 0x1000;
+pub const VIRTIO_MMIO_SLOTS_COUNT: usize = 8;
+
+const VIRTIO_MAGIC_VALUE: u32 = 0x7472_6976; // "virt" in little endian.
+const VIRTIO_MMIO_VERSION: u32 = 2;
+
+// Offsets of the MMIO registers (from the base register of the device slot).
+#[allow(non_camel_case_types)]
+#[derive(Copy, Clone)]
+pub enum Register {
+  MAGIC_VALUE = 0x000,
+  VERSION = 0x004,
+  DEVICE_ID = 0x008,
+  DEVICE_FEATURES = 0x010,
+  DEVICE_FEATURES_SEL = 0x014,
+  DRIVER_FEATURES = 0x020,
+  DRIVER_FEATURES_SEL = 0x024,
+  QUEUE_SEL = 0x030,
+  QUEUE_NUM_MAX = 0x034,
+  QUEUE_NUM = 0x038,
+  QUEUE_READY = 0x044,
+  QUEUE_NOTIFY = 0x050,
+  INTERRUPT_STATUS = 0x060,
+  INTERRUPT_ACK = 0x064,
+  STATUS = 0x070,
+  QUEUE_DESC_LOW = 0x080,
+  QUEUE_DESC_HIGH = 0x084,
+  QUEUE_DRIVER_LOW = 0x090,
+  QUEUE_DRIVER_HIGH = 0x094,
+  QUEUE_DEVICE_LOW = 0x0a0,
+  QUEUE_DEVICE_HIGH = 0x0a4,
+
+  // The device specific configuration space starts here.
+  CONFIG = 0x100,
+}
+
+// Bits of the device status register, set by the driver while initializing the device.
+#[allow(non_camel_case_types)]
+pub enum DeviceStatus {
+  ACKNOWLEDGE = 1,
+  DRIVER = 2,
+  DRIVER_OK = 4,
+  FEATURES_OK = 8,
+  FAILED = 128,
+}
+
+// Device IDs.
+pub const VIRTIO_DEVICE_ID_BLOCK: u32 = 2;
+
+// Device independent feature bits, we care about.
+pub const VIRTIO_F_ANY_LAYOUT: usize = 27;
+pub const VIRTIO_F_RING_INDIRECT_DESC: usize = 28;
+pub const VIRTIO_F_RING_EVENT_IDX: usize = 29;
+pub const VIRTIO_F_VERSION_1: usize = 32;
+
+// The memory mapped registers of a virtio device slot.
+#[derive(Copy, Clone)]
+pub struct VirtioMMIO {
+  baseRegister: usize,
+}
+
+impl VirtioMMIO {
+  pub const fn new(slot: usize) -> Self {
+    Self {
+      baseRegister: VIRTIO_MMIO_BASE_REGISTER + slot * VIRTIO_MMIO_SLOT_SIZE,
+    }
+  }
+
+  #[inline]
+  pub fn read(&self, register: Register) -> u32 {
+    unsafe { read_volatile((self.baseRegister + register as usize) as *const u32) }
+  }
+
+  #[inline]
+  pub fn write(&self, register: Register, value: u32) {
+    unsafe { write_volatile((self.baseRegister + register as usize) as *mut u32, value) };
+  }
+
+  // Reads from the device specific configuration space, at the given offset.
+  #[inline]
+  pub fn readConfig<T: Copy>(&self, offset: usize) -> T {
+    unsafe { read_volatile((self.baseRegister + Register::CONFIG as usize + offset) as *const T) }
+  }
+
+  // Returns the ID of the device in the slot, if it's a (version 2) virtio device. An ID of 0 means
+  // the slot is empty.
+  pub fn probe(&self) -> Option<u32> {
+    if self.read(Register::MAGIC_VALUE) != VIRTIO_MAGIC_VALUE
+      || self.read(Register::VERSION) != VIRTIO_MMIO_VERSION
+    {
+      return None;
+    }
+
+    match self.read(Register::DEVICE_ID) {
+      0 => None,
+      deviceID => Some(deviceID),
+    }
+  }
+
+  #[inline]
+  pub fn setStatusBits(&self, bits: u32) {
+    self.write(Register::STATUS, self.read(Register::STATUS) | bits);
+  }
+
+  /*
+    Resets the device and negotiates features with it :
+
+      (1) reset the device, then set the ACKNOWLEDGE and DRIVER status bits.
+
+      (2) read the features offered by the device, and accept those for which the given filter
+          returns true.
+
+      (3) set the FEATURES_OK status bit, and re-read it to ensure that the device accepted our
+          subset of features.
+
+    Returns the accepted features. Or None, if the device rejected them.
+  */
+  pub fn negotiateFeatures(&self, isFeatureAccepted: impl Fn(usize) -> bool) -> Option<u64> {
+    self.write(Register::STATUS, 0);
+    self.setStatusBits(DeviceStatus::ACKNOWLEDGE as u32);
+    self.setStatusBits(DeviceStatus::DRIVER as u32);
+
+    let mut acceptedFeatures: u64 = 0;
+
+    // Features are exposed as 2 pages of 32 bits each.
+    for page in 0..2 {
+      self.write(Register::DEVICE_FEATURES_SEL, page);
+      let offeredFeatures = self.read(Register::DEVICE_FEATURES);
+
+      let mut acceptedFeaturesPage: u32 = 0;
+      for bit in 0..32 {
+        if offeredFeatures & (1 << bit) != 0 && isFeatureAccepted(page as usize * 32 + bit) {
+          acceptedFeaturesPage |= 1 << bit;
+        }
+      }
+
+      self.write(Register::DRIVER_FEATURES_SEL, page);
+      self.write(Register::DRIVER_FEATURES, acceptedFeaturesPage);
+
+      acceptedFeatures |= (acceptedFeaturesPage as u64) << (page * 32);
+    }
+
+    self.setStatusBits(DeviceStatus::FEATURES_OK as u32);
+    if self.read(Register::STATUS) & DeviceStatus::FEATURES_OK as u32 == 0 {
+      self.setStatusBits(DeviceStatus::FAILED as u32);
+      return None;
+    }
+
+    Some(acceptedFeatures)
+  }
+}
+
+// Returns the IRQ number used by the device in the given virtio-mmio slot.
+#[inline]
+pub fn getIRQ(slot: usize) -> u32 {
+  VIRTIO_IRQS.start() + slot as u32
+}
+
+// Returns the virtio-mmio slot, of the device using the given IRQ number.
+#[inline]
+pub fn getSlot(irq: u32) -> usize {
+  (irq - VIRTIO_IRQS.start()) as usize
+}
+
+// Discovers the virtio devices attached to the virtio-mmio slots, and initializes the ones we have
+// drivers for.
+// NOTE : Should only be invoked once, while the kernel is initializing.
+pub fn init() {
+  for slot in 0..VIRTIO_MMIO_SLOTS_COUNT {
+    let mmio = VirtioMMIO::new(slot);
+
+    match mmio.probe() {
+      Some(VIRTIO_DEVICE_ID_BLOCK) => block::init(slot, mmio),
+
+      Some(deviceID) => {
+        println!(
+          "WARN : Ignoring unsupported virtio device (ID = {}) in slot {}",
+          deviceID, slot
+        );
+      }
+
+      None => {}
+    }
+  }
+}
+
+// Handles an interrupt raised by the virtio device using the given IRQ number.
+pub fn handleInterrupt(irq: u32) {
+  block::handleInterrupt(getSlot(irq));
+}
@@ -0,0 +1,249 @@
+use {
+  super::{Register, VirtioMMIO},
+  core::{
+    ptr::{addr_of, read_volatile},
+    sync::atomic::{fence, Ordering},
+  },
+};
+
+/*
+  A split virtqueue, through which the driver sends requests to the device. It consists of 3
+  parts, each of which is shared with the device :
+
+    (1) descriptor table : each descriptor points to a buffer (in physical memory). A request is
+        made up of a chain of descriptors, linked using the next field.
+
+    (2) available ring : the driver puts the head of a descriptor chain here, to hand the request
+        over to the device.
+
+    (3) used ring : the device puts the head of a descriptor chain here, once it has finished
+        processing the request.
+
+  NOTE : The kernel Page Table identity maps the DRAM (REFER : ../../memory/page_table/kernel.rs).
+  So the addresses we hand over to the device are the physical addresses.
+
+  REFER : section 2.7 in the virtio specification.
+*/
+
+// Number of descriptors in a virtqueue.
+pub const QUEUE_SIZE: usize = 64;
+
+#[allow(non_camel_case_types)]
+pub enum DescriptorFlags {
+  // The buffer continues via the next field.
+  NEXT = 1,
+
+  // The buffer is write-only for the device (otherwise it's read-only).
+  WRITE = 2,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct Descriptor {
+  address: u64,
+  length: u32,
+  flags: u16,
+  next: u16,
+}
+
+impl Descriptor {
+  const fn new() -> Self {
+    Self {
+      address: 0,
+      length: 0,
+      flags: 0,
+      next: 0,
+    }
+  }
+}
+
+#[repr(C, align(16))]
+struct DescriptorTable([Descriptor; QUEUE_SIZE]);
+
+#[repr(C, align(2))]
+struct AvailableRing {
+  flags: u16,
+  // Where the driver would put the next descriptor chain head (modulo QUEUE_SIZE).
+  index: u16,
+  ring: [u16; QUEUE_SIZE],
+  usedEvent: u16,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct UsedElement {
+  // Head of the descriptor chain.
+  id: u32,
+  // Number of bytes written by the device into the buffers.
+  length: u32,
+}
+
+#[repr(C, align(4))]
+struct UsedRing {
+  flags: u16,
+  // Where the device would put the next descriptor chain head (modulo QUEUE_SIZE).
+  index: u16,
+  ring: [UsedElement; QUEUE_SIZE],
+  availableEvent: u16,
+}
+
+pub struct VirtQueue {
+  descriptorTable: DescriptorTable,
+  availableRing: AvailableRing,
+  usedRing: UsedRing,
+
+  isDescriptorFree: [bool; QUEUE_SIZE],
+
+  // Index into the used ring, till which we've processed the finished requests.
+  lastSeenUsedIndex: u16,
+}
+
+impl VirtQueue {
+  pub const fn new() -> Self {
+    Self {
+      descriptorTable: DescriptorTable([Descriptor::new(); QUEUE_SIZE]),
+      availableRing: AvailableRing {
+        flags: 0,
+        index: 0,
+        ring: [0; QUEUE_SIZE],
+        usedEvent: 0,
+      },
+      usedRing: UsedRing {
+        flags: 0,
+        index: 0,
+        ring: [UsedElement { id: 0, length: 0 }; QUEUE_SIZE],
+        availableEvent: 0,
+      },
+
+      isDescriptorFree: [true; QUEUE_SIZE],
+
+      lastSeenUsedIndex: 0,
+    }
+  }
+
+  // Hands over the given virtqueue to the device.
+  // Returns false, if the device doesn't support a virtqueue with that index or that big.
+  // NOTE : The VirtQueue must not be moved afterwards.
+  pub fn init(&mut self, mmio: VirtioMMIO, queueIndex: u32) -> bool {
+    mmio.write(Register::QUEUE_SEL, queueIndex);
+
+    if mmio.read(Register::QUEUE_READY) != 0
+      || (mmio.read(Register::QUEUE_NUM_MAX) as usize) < QUEUE_SIZE
+    {
+      return false;
+    }
+    mmio.write(Register::QUEUE_NUM, QUEUE_SIZE as u32);
+
+    let descriptorTableAddress = addr_of!(self.descriptorTable) as u64;
+    let availableRingAddress = addr_of!(self.availableRing) as u64;
+    let usedRingAddress = addr_of!(self.usedRing) as u64;
+
+    mmio.write(Register::QUEUE_DESC_LOW, descriptorTableAddress as u32);
+    mmio.write(
+      Register::QUEUE_DESC_HIGH,
+      (descriptorTableAddress >> 32) as u32,
+    );
+    mmio.write(Register::QUEUE_DRIVER_LOW, availableRingAddress as u32);
+    mmio.write(
+      Register::QUEUE_DRIVER_HIGH,
+      (availableRingAddress >> 32) as u32,
+    );
+    mmio.write(Register::QUEUE_DEVICE_LOW, usedRingAddress as u32);
+    mmio.write(Register::QUEUE_DEVICE_HIGH, (usedRingAddress >> 32) as u32);
+
+    mmio.write(Register::QUEUE_READY, 1);
+
+    true
+  }
+
+  pub fn getFreeDescriptorsCount(&self) -> usize {
+    self
+      .isDescriptorFree
+      .iter()
+      .filter(|isFree| **isFree)
+      .count()
+  }
+
+  // Allocates a descriptor. Returns its index, or None if all descriptors are in use.
+  pub fn allocateDescriptor(&mut self) -> Option<u16> {
+    let index = self.isDescriptorFree.iter().position(|isFree| *isFree)?;
+    self.isDescriptorFree[index] = false;
+
+    Some(index as u16)
+  }
+
+  // Points the given (allocated) descriptor to the given buffer.
+  pub fn setDescriptor(&mut self, index: u16, address: u64, length: u32, flags: u16) {
+    self.descriptorTable.0[index as usize] = Descriptor {
+      address,
+      length,
+      flags,
+      next: 0,
+    };
+  }
+
+  // Links the given descriptors into a chain, in the given order. Returns the head.
+  pub fn chainDescriptors(&mut self, descriptors: &[u16]) -> u16 {
+    for pair in descriptors.windows(2) {
+      let descriptor = &mut self.descriptorTable.0[pair[0] as usize];
+
+      descriptor.flags |= DescriptorFlags::NEXT as u16;
+      descriptor.next = pair[1];
+    }
+
+    descriptors[0]
+  }
+
+  // Frees the descriptor chain, starting from the given head.
+  pub fn freeDescriptorChain(&mut self, head: u16) {
+    let mut index = head as usize;
+
+    loop {
+      assert!(
+        !self.isDescriptorFree[index],
+        "virtqueue : freeing a free descriptor"
+      );
+      self.isDescriptorFree[index] = true;
+
+      let descriptor = self.descriptorTable.0[index];
+      if descriptor.flags & DescriptorFlags::NEXT as u16 == 0 {
+        break;
+      }
+      index = descriptor.next as usize;
+    }
+  }
+
+  // Hands over the descriptor chain (starting from the given head) to the device.
+  // NOTE : The device still needs to be notified.
+  pub fn submit(&mut self, head: u16) {
+    let ringIndex = self.availableRing.index as usize % QUEUE_SIZE;
+    self.availableRing.ring[ringIndex] = head;
+
+    // The device must see the descriptor chain, before it sees the incremented index.
+    fence(Ordering::SeqCst);
+    self.availableRing.index = self.availableRing.index.wrapping_add(1);
+    fence(Ordering::SeqCst);
+  }
+
+  // Returns the head of a descriptor chain the device has finished processing (along with the
+  // number of bytes it wrote), if any.
+  pub fn popUsed(&mut self) -> Option<(u16, u32)> {
+    // The used ring is written by the device.
+    let usedIndex = unsafe { read_volatile(addr_of!(self.usedRing.index)) };
+    if self.lastSeenUsedIndex == usedIndex {
+      return None;
+    }
+
+    // Read the used element, only after seeing the incremented index.
+    fence(Ordering::SeqCst);
+
+    let usedElement = unsafe {
+      read_volatile(addr_of!(
+        self.usedRing.ring[self.lastSeenUsedIndex as usize % QUEUE_SIZE]
+      ))
+    };
+    self.lastSeenUsedIndex = self.lastSeenUsedIndex.wrapping_add(1);
+
+    Some((usedElement.id as u16, usedElement.length))
+  }
+}
@@ -0,0 +1,306 @@
+use {
+  super::{
+    inode::{Inode, ICACHE},
+    layout::{
+      DiskInode, FileType, OnDisk, SuperBlock, BITS_PER_BLOCK, FS_MAGIC, INODES_PER_BLOCK,
+      ROOT_INODE_NUMBER, SUPERBLOCK_BLOCK_NUMBER,
+    },
+  },
+  crate::{
+    fs::{
+      bcache::{BCacheNode, BCACHE},
+      disk,
+      log::{Log, LogOperation},
+      vfs::FsError,
+      BLOCK_SIZE, FS_OP_MAX_BLOCK_WRITES, MAX_DISKS,
+    },
+    locks::{
+      sleeplock::{SleepLock, SleepLockGuard},
+      spinlock::SpinLock,
+    },
+  },
+  alloc::vec::Vec,
+  array_macro::array,
+  core::mem::{size_of, ManuallyDrop},
+};
+
+/*
+  An on disk inode File System, mounted from a disk.
+
+  All modifications of the File System must be done inside a Transaction, so that they're crash
+  consistent (see the log module). Only reads can be done outside one.
+*/
+pub struct FileSystem {
+  diskNumber: usize,
+
+  // None, till the File System is mounted.
+  superBlock: SpinLock<Option<SuperBlock>>,
+
+  log: Log,
+
+  // Serializes modifications of the directory tree (like creating or unlinking files). This way,
+  // no operation needs to lock 2 inodes at once.
+  namespaceLock: SleepLock<()>,
+
+  // Inodes which aren't linked from any directory anymore, and whose last reference has been
+  // dropped. They're freed by the next Transaction which ends.
+  orphans: SpinLock<Vec<u32>>,
+}
+
+impl FileSystem {
+  const fn new(diskNumber: usize) -> Self {
+    Self {
+      diskNumber,
+
+      superBlock: SpinLock::new(None),
+
+      log: Log::new(),
+
+      namespaceLock: SleepLock::new(()),
+
+      orphans: SpinLock::new(Vec::new()),
+    }
+  }
+
+  #[inline]
+  pub fn getDiskNumber(&self) -> usize {
+    self.diskNumber
+  }
+
+  pub fn isMounted(&self) -> bool {
+    self.superBlock.acquire().is_some()
+  }
+
+  pub fn getSuperBlock(&self) -> SuperBlock {
+    self
+      .superBlock
+      .acquire()
+      .expect("File System isn't mounted")
+  }
+
+  pub fn getRootInode(&self) -> Inode {
+    ICACHE.get(self.diskNumber, ROOT_INODE_NUMBER)
+  }
+
+  // Begins a File System operation. It ends when the returned Transaction is dropped.
+  pub fn beginTransaction(&self) -> Transaction<'_> {
+    Transaction {
+      fileSystem: self,
+      logOperation: ManuallyDrop::new(self.log.beginOp()),
+      isFreeingOrphan: false,
+    }
+  }
+
+  #[track_caller]
+  pub fn lockNamespace(&self) -> SleepLockGuard<'_, ()> {
+    self.namespaceLock.acquire()
+  }
+
+  // Reads the superblock, and replays the log.
+  fn mount(&self) -> Result<(), FsError> {
+    let superBlock = SuperBlock::readFrom(&*BCACHE.read(self.diskNumber, SUPERBLOCK_BLOCK_NUMBER));
+
+    // The log area must have space for at least one File System operation, along with the log
+    // header.
+    if superBlock.magic != FS_MAGIC
+      || superBlock.size as usize > disk::getDisk(self.diskNumber).getBlocksCount()
+      || (superBlock.logBlocksCount as usize) < FS_OP_MAX_BLOCK_WRITES + 1
+    {
+      return Err(FsError::InvalidSuperBlock);
+    }
+
+    *self.superBlock.acquire() = Some(superBlock);
+    self.log.init(
+      self.diskNumber,
+      superBlock.logStart as usize,
+      superBlock.logBlocksCount as usize,
+    );
+
+    Ok(())
+  }
+
+  // Allocates a zeroed data block. Returns its block number.
+  pub fn allocateBlock(&self, transaction: &Transaction) -> Result<usize, FsError> {
+    let superBlock = self.getSuperBlock();
+    let size = superBlock.size as usize;
+
+    for bitmapBlockStart in (0..size).step_by(BITS_PER_BLOCK) {
+      let mut bitmapBlock =
+        BCACHE.read(self.diskNumber, superBlock.getBitmapBlock(bitmapBlockStart));
+
+      let Some(bit) = (0..BITS_PER_BLOCK.min(size - bitmapBlockStart))
+        .find(|bit| bitmapBlock[bit / 8] & (1 << (bit % 8)) == 0)
+      else {
+        continue;
+      };
+
+      bitmapBlock[bit / 8] |= 1 << (bit % 8);
+      transaction.write(&bitmapBlock);
+      drop(bitmapBlock);
+
+      let blockNumber = bitmapBlockStart + bit;
+
+      let mut block = BCACHE.read(self.diskNumber, blockNumber);
+      block.fill(0);
+      transaction.write(&block);
+
+      return Ok(blockNumber);
+    }
+
+    Err(FsError::NoSpace)
+  }
+
+  pub fn freeBlock(&self, transaction: &Transaction, blockNumber: usize) {
+    let superBlock = self.getSuperBlock();
+    let mut bitmapBlock = BCACHE.read(self.diskNumber, superBlock.getBitmapBlock(blockNumber));
+
+    let bit = blockNumber % BITS_PER_BLOCK;
+    assert!(
+      bitmapBlock[bit / 8] & (1 << (bit % 8)) != 0,
+      "Freeing a free block"
+    );
+
+    bitmapBlock[bit / 8] &= !(1 << (bit % 8));
+    transaction.write(&bitmapBlock);
+  }
+
+  // Allocates an inode of the given type. Returns a reference to it (unlocked).
+  pub fn allocateInode(
+    &self,
+    transaction: &Transaction,
+    fileType: FileType,
+    major: u16,
+    minor: u16,
+  ) -> Result<Inode, FsError> {
+    let superBlock = self.getSuperBlock();
+
+    for inodeNumber in ROOT_INODE_NUMBER..superBlock.inodesCount {
+      let mut inodeBlock = BCACHE.read(self.diskNumber, superBlock.getInodeBlock(inodeNumber));
+
+      let offset = (inodeNumber as usize % INODES_PER_BLOCK) * size_of::<DiskInode>();
+      if DiskInode::readFrom(&inodeBlock[offset..])
+        .getFileType()
+        .is_some()
+      {
+        continue;
+      }
+
+      let diskInode = DiskInode {
+        fileType: fileType as u16,
+        major,
+        minor,
+        ..DiskInode::new()
+      };
+      diskInode.writeTo(&mut inodeBlock[offset..]);
+      transaction.write(&inodeBlock);
+      drop(inodeBlock);
+
+      return Ok(ICACHE.get(self.diskNumber, inodeNumber));
+    }
+
+    Err(FsError::NoInodes)
+  }
+
+  // Records that the given inode is unlinked and unreferenced. It gets freed by the next
+  // Transaction which ends.
+  pub(super) fn addOrphan(&self, inodeNumber: u32) {
+    self.orphans.acquire().push(inodeNumber);
+  }
+
+  // Frees each orphan in a File System operation of its own, since freeing an inode (along with
+  // its data blocks) can use up the whole log space reserved for an operation.
+  fn freeOrphans(&self) {
+    loop {
+      let Some(inodeNumber) = self.orphans.acquire().pop()
+      else {
+        return;
+      };
+
+      let mut transaction = self.beginTransaction();
+      transaction.isFreeingOrphan = true;
+
+      ICACHE
+        .get(self.diskNumber, inodeNumber)
+        .lock()
+        .free(&transaction);
+    }
+  }
+}
+
+// A File System operation. All the blocks it modifies are committed to the disk atomically.
+pub struct Transaction<'a> {
+  fileSystem: &'a FileSystem,
+  logOperation: ManuallyDrop<LogOperation<'a>>,
+
+  // Set for the Transactions freeOrphans( ) begins, so that they don't free the orphans (queued
+  // meanwhile) themselves.
+  isFreeingOrphan: bool,
+}
+
+impl Transaction<'_> {
+  #[inline]
+  pub fn getFileSystem(&self) -> &FileSystem {
+    self.fileSystem
+  }
+
+  // Records that the given cached block has been modified by this operation.
+  #[inline]
+  pub fn write(&self, bcacheNode: &BCacheNode) {
+    self.logOperation.write(bcacheNode);
+  }
+}
+
+impl Drop for Transaction<'_> {
+  fn drop(&mut self) {
+    unsafe { ManuallyDrop::drop(&mut self.logOperation) };
+
+    if !self.isFreeingOrphan {
+      self.fileSystem.freeOrphans();
+    }
+  }
+}
+
+static FILE_SYSTEMS: [FileSystem; MAX_DISKS] =
+  array![diskNumber => FileSystem::new(diskNumber); MAX_DISKS];
+
+// Mounts the File System in the given disk. Mounting an already mounted File System is a no-op.
+pub fn mount(diskNumber: usize) -> Result<&'static FileSystem, FsError> {
+  let fileSystem = &FILE_SYSTEMS[diskNumber];
+
+  if !fileSystem.isMounted() {
+    fileSystem.mount()?;
+  }
+  Ok(fileSystem)
+}
+
+/*
+  Unmounts the File System in the given disk, writing back its cached blocks.
+
+  NOTE : Nothing may be referring to its inodes anymore. Since Transactions commit once they end,
+  the log is empty by then.
+*/
+pub fn unmount(diskNumber: usize) {
+  let fileSystem = getFileSystem(diskNumber);
+
+  // Frees the orphaned inodes (if any), since their last references may have been dropped outside
+  // a Transaction.
+  drop(fileSystem.beginTransaction());
+
+  BCACHE.sync(diskNumber);
+  *fileSystem.superBlock.acquire() = None;
+}
+
+// Returns the File System in the given disk, which must have been mounted.
+pub fn getFileSystem(diskNumber: usize) -> &'static FileSystem {
+  let fileSystem = &FILE_SYSTEMS[diskNumber];
+
+  assert!(
+    fileSystem.isMounted(),
+    "File System in disk {} isn't mounted",
+    diskNumber
+  );
+  fileSystem
+}
+
+// Inodes must not straddle block boundaries.
+const _: () = assert!(BLOCK_SIZE % size_of::<DiskInode>() == 0);
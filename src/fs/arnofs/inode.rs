@@ -0,0 +1,548 @@
+use {
+  super::{
+    filesystem::{self, FileSystem, Transaction},
+    layout::{
+      BlockPath, DirectoryEntry, DiskInode, FileType, OnDisk, DIRECTORY_ENTRY_SIZE,
+      DIRECT_BLOCKS_COUNT, INODES_PER_BLOCK, MAX_FILE_BLOCKS,
+    },
+  },
+  crate::{
+    fs::{bcache::BCACHE, vfs::FsError, BLOCK_SIZE, FS_OP_MAX_BLOCK_WRITES},
+    locks::{
+      sleeplock::{SleepLock, SleepLockGuard},
+      spinlock::SpinLock,
+    },
+  },
+  alloc::vec::Vec,
+  array_macro::array,
+  core::mem::size_of,
+};
+
+// Maximum number of inodes, which can be in use at any moment of time.
+// NOTE : The VFS keeps the inodes of the cached Dentries (and their ancestors) in use. So this must
+// be well above DENTRY_CACHE_SIZE.
+pub const INODE_CACHE_SIZE: usize = 200;
+
+/*
+  Maximum number of bytes, which can be written to a file in a single Transaction.
+
+  Writing a block may require allocating it along with an indirect block and a double indirect
+  block, each of which modifies a bitmap block. And the inode gets modified too. So larger writes
+  must be split across multiple Transactions.
+*/
+pub const MAX_WRITE_SIZE_PER_TRANSACTION: usize = ((FS_OP_MAX_BLOCK_WRITES - 4) / 2) * BLOCK_SIZE;
+
+struct InodeCacheEntry {
+  diskNumber: usize,
+  inodeNumber: u32,
+
+  // Number of Inode references. The entry can be reused, once it drops to 0.
+  refCount: usize,
+}
+
+struct InodeData {
+  // Disk and inode numbers of the inode, whose disk inode has been read from the disk (if any).
+  // The cache entry may have been reused for another inode since then.
+  identity: Option<(usize, u32)>,
+
+  diskInode: DiskInode,
+}
+
+/*
+  The inode cache holds the inodes which are in use, so that :
+
+    (1) there's a single in memory copy of each inode, which processes synchronize on (using its
+        SleepLock).
+
+    (2) an inode which is unlinked while it's still in use (say, an open file), only gets freed
+        once its last reference is dropped.
+
+  An Inode is a reference to a cached inode. Locking it (Inode::lock( )) reads the disk inode (if
+  required), and gives access to its contents.
+*/
+pub struct InodeCache {
+  entries: SpinLock<[InodeCacheEntry; INODE_CACHE_SIZE]>,
+  inodes: [SleepLock<InodeData>; INODE_CACHE_SIZE],
+}
+
+impl InodeCache {
+  const fn new() -> Self {
+    Self {
+      entries: SpinLock::new(
+        [const {
+          InodeCacheEntry {
+            diskNumber: 0,
+            inodeNumber: 0,
+            refCount: 0,
+          }
+        }; INODE_CACHE_SIZE],
+      ),
+      inodes: array![_ => SleepLock::new(InodeData {
+        identity: None,
+        diskInode: DiskInode::new(),
+      }); INODE_CACHE_SIZE],
+    }
+  }
+
+  // Returns a reference to the given inode (unlocked), without reading it from the disk.
+  pub fn get(&self, diskNumber: usize, inodeNumber: u32) -> Inode {
+    let mut entries = self.entries.acquire();
+
+    let index = match entries.iter().position(|entry| {
+      entry.refCount > 0 && entry.diskNumber == diskNumber && entry.inodeNumber == inodeNumber
+    }) {
+      Some(index) => index,
+
+      None => {
+        let index = entries
+          .iter()
+          .position(|entry| entry.refCount == 0)
+          .expect("Inode cache is full");
+
+        let entry = &mut entries[index];
+        entry.diskNumber = diskNumber;
+        entry.inodeNumber = inodeNumber;
+
+        index
+      }
+    };
+    entries[index].refCount += 1;
+
+    Inode {
+      index,
+      diskNumber,
+      inodeNumber,
+    }
+  }
+}
+
+pub static ICACHE: InodeCache = InodeCache::new();
+
+// A reference to a cached inode. The reference is dropped (iput), when the Inode is dropped.
+pub struct Inode {
+  index: usize, // Index of the inode in the inode cache.
+
+  diskNumber: usize,
+  inodeNumber: u32,
+}
+
+impl Inode {
+  #[inline]
+  pub fn getDiskNumber(&self) -> usize {
+    self.diskNumber
+  }
+
+  #[inline]
+  pub fn getInodeNumber(&self) -> u32 {
+    self.inodeNumber
+  }
+
+  #[inline]
+  pub fn getFileSystem(&self) -> &'static FileSystem {
+    filesystem::getFileSystem(self.diskNumber)
+  }
+
+  // Locks the inode, reading it from the disk if required.
+  #[track_caller]
+  pub fn lock(&self) -> InodeGuard<'_> {
+    let mut data = ICACHE.inodes[self.index].acquire();
+
+    if data.identity != Some((self.diskNumber, self.inodeNumber)) {
+      let superBlock = self.getFileSystem().getSuperBlock();
+      let inodeBlock = BCACHE.read(self.diskNumber, superBlock.getInodeBlock(self.inodeNumber));
+
+      let offset = (self.inodeNumber as usize % INODES_PER_BLOCK) * size_of::<DiskInode>();
+      data.diskInode = DiskInode::readFrom(&inodeBlock[offset..]);
+      data.identity = Some((self.diskNumber, self.inodeNumber));
+    }
+
+    InodeGuard { inode: self, data }
+  }
+}
+
+// Duplicates the reference (idup).
+impl Clone for Inode {
+  fn clone(&self) -> Self {
+    ICACHE.entries.acquire()[self.index].refCount += 1;
+
+    Self {
+      index: self.index,
+      diskNumber: self.diskNumber,
+      inodeNumber: self.inodeNumber,
+    }
+  }
+}
+
+impl Drop for Inode {
+  fn drop(&mut self) {
+    let mut entries = ICACHE.entries.acquire();
+    if entries[self.index].refCount > 1 {
+      entries[self.index].refCount -= 1;
+      return;
+    }
+    drop(entries);
+
+    // This is the last reference. If the inode isn't linked from any directory, then nobody can
+    // get another reference to it. So it's freed.
+    // NOTE : The entry can't be reused meanwhile, since its refCount is still 1.
+    let data = ICACHE.inodes[self.index].acquire();
+    if data.identity == Some((self.diskNumber, self.inodeNumber))
+      && data.diskInode.getFileType().is_some()
+      && data.diskInode.linksCount == 0
+    {
+      self.getFileSystem().addOrphan(self.inodeNumber);
+    }
+    drop(data);
+
+    ICACHE.entries.acquire()[self.index].refCount -= 1;
+  }
+}
+
+// A locked inode. The inode gets unlocked when the InodeGuard is dropped.
+pub struct InodeGuard<'a> {
+  inode: &'a Inode,
+  data: SleepLockGuard<'static, InodeData>,
+}
+
+impl InodeGuard<'_> {
+  #[inline]
+  pub fn getInode(&self) -> &Inode {
+    self.inode
+  }
+
+  #[inline]
+  pub fn getFileType(&self) -> FileType {
+    self.data.diskInode.getFileType().expect("Inode is free")
+  }
+
+  #[inline]
+  pub fn getSize(&self) -> usize {
+    self.data.diskInode.size as usize
+  }
+
+  #[inline]
+  pub fn getLinksCount(&self) -> u16 {
+    self.data.diskInode.linksCount
+  }
+
+  #[inline]
+  pub fn setLinksCount(&mut self, linksCount: u16) {
+    self.data.diskInode.linksCount = linksCount;
+  }
+
+  // Returns the major and minor device numbers (only meaningful for a device).
+  #[inline]
+  pub fn getDeviceNumbers(&self) -> (u16, u16) {
+    (self.data.diskInode.major, self.data.diskInode.minor)
+  }
+
+  // Writes the in memory copy of the inode to the disk (iupdate). Must be invoked after modifying
+  // the inode.
+  pub fn update(&self, transaction: &Transaction) {
+    let superBlock = self.inode.getFileSystem().getSuperBlock();
+    let mut inodeBlock = BCACHE.read(
+      self.inode.diskNumber,
+      superBlock.getInodeBlock(self.inode.inodeNumber),
+    );
+
+    let offset = (self.inode.inodeNumber as usize % INODES_PER_BLOCK) * size_of::<DiskInode>();
+    self.data.diskInode.writeTo(&mut inodeBlock[offset..]);
+    transaction.write(&inodeBlock);
+  }
+
+  // Frees the inode, along with its data blocks.
+  pub(super) fn free(&mut self, transaction: &Transaction) {
+    self.truncate(transaction);
+
+    self.data.diskInode.fileType = 0;
+    self.update(transaction);
+
+    self.data.identity = None;
+  }
+
+  // Returns the address stored at the given index of the given indirect block.
+  fn readAddress(&self, indirectBlock: usize, index: usize) -> usize {
+    let indirectBlock = BCACHE.read(self.inode.diskNumber, indirectBlock);
+    u32::readFrom(&indirectBlock[index * 4..]) as usize
+  }
+
+  // Returns the disk block number of the given block of the file, or None if it isn't allocated
+  // (bmap).
+  pub fn getBlockAddress(&self, blockIndex: usize) -> Option<usize> {
+    let blockPath = BlockPath::new(blockIndex)?;
+
+    let mut address = self.data.diskInode.addresses[blockPath.slot] as usize;
+    for &index in blockPath.getIndices() {
+      if address == 0 {
+        return None;
+      }
+      address = self.readAddress(address, index);
+    }
+
+    (address != 0).then_some(address)
+  }
+
+  // Same as getBlockAddress( ), but allocates the block (along with the required indirect blocks)
+  // if it isn't allocated.
+  // NOTE : The inode must be updated afterwards.
+  fn allocateBlockAddress(
+    &mut self,
+    transaction: &Transaction,
+    blockIndex: usize,
+  ) -> Result<usize, FsError> {
+    let fileSystem = transaction.getFileSystem();
+    let blockPath = BlockPath::new(blockIndex).ok_or(FsError::FileTooLarge)?;
+
+    let mut address = self.data.diskInode.addresses[blockPath.slot] as usize;
+    if address == 0 {
+      address = fileSystem.allocateBlock(transaction)?;
+      self.data.diskInode.addresses[blockPath.slot] = address as u32;
+    }
+
+    for &index in blockPath.getIndices() {
+      let nextAddress = self.readAddress(address, index);
+      if nextAddress != 0 {
+        address = nextAddress;
+        continue;
+      }
+
+      // The indirect block isn't kept locked while allocating, since that reads a bitmap block.
+      // It can't change meanwhile, since the inode is locked.
+      let allocatedAddress = fileSystem.allocateBlock(transaction)?;
+
+      let mut indirectBlock = BCACHE.read(self.inode.diskNumber, address);
+      (allocatedAddress as u32).writeTo(&mut indirectBlock[index * 4..]);
+      transaction.write(&indirectBlock);
+
+      address = allocatedAddress;
+    }
+
+    Ok(address)
+  }
+
+  // Returns the addresses stored in the given indirect block.
+  fn readAddresses(&self, indirectBlock: usize) -> Vec<usize> {
+    let indirectBlock = BCACHE.read(self.inode.diskNumber, indirectBlock);
+
+    indirectBlock
+      .as_chunks::<4>()
+      .0
+      .iter()
+      .map(|address| u32::readFrom(address) as usize)
+      .collect()
+  }
+
+  // Frees the given block, along with the blocks it refers to. The depth is the number of levels
+  // of indirection.
+  fn freeBlockTree(&self, transaction: &Transaction, address: usize, depth: usize) {
+    if address == 0 {
+      return;
+    }
+
+    if depth > 0 {
+      for address in self.readAddresses(address) {
+        self.freeBlockTree(transaction, address, depth - 1);
+      }
+    }
+
+    transaction.getFileSystem().freeBlock(transaction, address);
+  }
+
+  // Discards the contents of the file (itrunc).
+  pub fn truncate(&mut self, transaction: &Transaction) {
+    for slot in 0..DIRECT_BLOCKS_COUNT + 2 {
+      self.freeBlockTree(
+        transaction,
+        self.data.diskInode.addresses[slot] as usize,
+        BlockPath::getSlotDepth(slot),
+      );
+
+      self.data.diskInode.addresses[slot] = 0;
+    }
+
+    self.data.diskInode.size = 0;
+    self.update(transaction);
+  }
+
+  // Reads from the file starting at the given offset, into the given buffer (readi). Returns the
+  // number of bytes read.
+  pub fn readAt(&self, offset: usize, buffer: &mut [u8]) -> usize {
+    let size = self.getSize();
+    if offset >= size {
+      return 0;
+    }
+    let length = buffer.len().min(size - offset);
+
+    let mut bytesRead = 0;
+    while bytesRead < length {
+      let position = offset + bytesRead;
+      let offsetInBlock = position % BLOCK_SIZE;
+      let chunkLength = (BLOCK_SIZE - offsetInBlock).min(length - bytesRead);
+      let chunk = &mut buffer[bytesRead..bytesRead + chunkLength];
+
+      match self.getBlockAddress(position / BLOCK_SIZE) {
+        Some(address) => {
+          let block = BCACHE.read(self.inode.diskNumber, address);
+          chunk.copy_from_slice(&block[offsetInBlock..offsetInBlock + chunkLength]);
+        }
+
+        // A hole in the file.
+        None => chunk.fill(0),
+      }
+
+      bytesRead += chunkLength;
+    }
+
+    bytesRead
+  }
+
+  /*
+    Writes the given buffer to the file starting at the given offset, growing the file if required
+    (writei). Returns the number of bytes written, which may be less than requested if the disk
+    runs out of space.
+
+    NOTE : At most MAX_WRITE_SIZE_PER_TRANSACTION bytes should be written in a single Transaction.
+  */
+  pub fn writeAt(
+    &mut self,
+    transaction: &Transaction,
+    offset: usize,
+    buffer: &[u8],
+  ) -> Result<usize, FsError> {
+    if offset > self.getSize() {
+      return Err(FsError::InvalidArgument);
+    }
+    if offset + buffer.len() > MAX_FILE_BLOCKS * BLOCK_SIZE {
+      return Err(FsError::FileTooLarge);
+    }
+
+    let mut bytesWritten = 0;
+    let mut result = Ok(());
+
+    while bytesWritten < buffer.len() {
+      let position = offset + bytesWritten;
+      let offsetInBlock = position % BLOCK_SIZE;
+      let chunkLength = (BLOCK_SIZE - offsetInBlock).min(buffer.len() - bytesWritten);
+
+      let address = match self.allocateBlockAddress(transaction, position / BLOCK_SIZE) {
+        Ok(address) => address,
+        Err(error) => {
+          result = Err(error);
+          break;
+        }
+      };
+
+      let mut block = BCACHE.read(self.inode.diskNumber, address);
+      block[offsetInBlock..offsetInBlock + chunkLength]
+        .copy_from_slice(&buffer[bytesWritten..bytesWritten + chunkLength]);
+      transaction.write(&block);
+
+      bytesWritten += chunkLength;
+    }
+
+    let newSize = offset + bytesWritten;
+    if newSize > self.getSize() {
+      self.data.diskInode.size = newSize as u32;
+    }
+
+    // The block addresses in the inode may have changed, even if the size hasn't.
+    self.update(transaction);
+
+    match (bytesWritten, result) {
+      (0, Err(error)) => Err(error),
+      _ => Ok(bytesWritten),
+    }
+  }
+
+  // Returns the directory entries starting at the given offset, along with their offsets
+  // (including the unused ones).
+  fn getDirectoryEntries(
+    &self,
+    start: usize,
+  ) -> impl Iterator<Item = (usize, DirectoryEntry)> + '_ {
+    (start.next_multiple_of(DIRECTORY_ENTRY_SIZE)..self.getSize())
+      .step_by(DIRECTORY_ENTRY_SIZE)
+      .map(move |offset| {
+        let mut directoryEntry = [0; DIRECTORY_ENTRY_SIZE];
+        assert_eq!(
+          self.readAt(offset, &mut directoryEntry),
+          DIRECTORY_ENTRY_SIZE,
+          "Directory has a partial entry"
+        );
+
+        (offset, DirectoryEntry::readFrom(&directoryEntry))
+      })
+  }
+
+  // Returns the first used directory entry at or after the given offset, along with its offset.
+  pub fn getNextDirectoryEntry(&self, offset: usize) -> Option<(usize, DirectoryEntry)> {
+    self
+      .getDirectoryEntries(offset)
+      .find(|(_, directoryEntry)| directoryEntry.inodeNumber != 0)
+  }
+
+  // Returns the directory entry with the given name, along with its offset.
+  pub fn findEntry(&self, name: &[u8]) -> Option<(usize, DirectoryEntry)> {
+    assert!(
+      self.getFileType() == FileType::Directory,
+      "Looking up a name in a non directory inode"
+    );
+
+    self.getDirectoryEntries(0).find(|(_, directoryEntry)| {
+      directoryEntry.inodeNumber != 0 && directoryEntry.getName() == name
+    })
+  }
+
+  // Looks up the given name in the directory (dirlookup). Returns a reference to the corresponding
+  // inode, along with the offset of the directory entry.
+  pub fn lookup(&self, name: &[u8]) -> Option<(Inode, usize)> {
+    self.findEntry(name).map(|(offset, directoryEntry)| {
+      (
+        ICACHE.get(self.inode.diskNumber, directoryEntry.inodeNumber as u32),
+        offset,
+      )
+    })
+  }
+
+  // Adds a directory entry with the given name and inode number, to the directory (dirlink).
+  pub fn link(
+    &mut self,
+    transaction: &Transaction,
+    name: &[u8],
+    inodeNumber: u32,
+  ) -> Result<(), FsError> {
+    if self.findEntry(name).is_some() {
+      return Err(FsError::AlreadyExists);
+    }
+    let directoryEntry =
+      DirectoryEntry::new(inodeNumber as u16, name).ok_or(FsError::NameTooLong)?;
+
+    // Reuse an unused directory entry, or append one.
+    let offset = self
+      .getDirectoryEntries(0)
+      .find(|(_, directoryEntry)| directoryEntry.inodeNumber == 0)
+      .map_or(self.getSize(), |(offset, _)| offset);
+
+    let mut encodedDirectoryEntry = [0; DIRECTORY_ENTRY_SIZE];
+    directoryEntry.writeTo(&mut encodedDirectoryEntry);
+
+    match self.writeAt(transaction, offset, &encodedDirectoryEntry)? {
+      DIRECTORY_ENTRY_SIZE => Ok(()),
+      _ => Err(FsError::NoSpace),
+    }
+  }
+
+  // Clears the directory entry at the given offset.
+  pub fn unlinkAt(&mut self, transaction: &Transaction, offset: usize) -> Result<(), FsError> {
+    self
+      .writeAt(transaction, offset, &[0; DIRECTORY_ENTRY_SIZE])
+      .map(|_| ())
+  }
+
+  // Returns whether the directory contains nothing other than "." and "..".
+  pub fn isDirectoryEmpty(&self) -> bool {
+    self.getDirectoryEntries(0).all(|(_, directoryEntry)| {
+      directoryEntry.inodeNumber == 0 || matches!(directoryEntry.getName(), b"." | b"..")
+    })
+  }
+}
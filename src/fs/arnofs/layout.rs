@@ -0,0 +1,288 @@
+use core::{
+  mem::size_of,
+  ptr::{read_unaligned, write_unaligned},
+};
+
+/*
+  On disk layout of the File System :
+
+    [ boot block | superblock | log area | inode blocks | free block bitmap | data blocks ]
+
+  The superblock describes where each region starts. The block numbers and sizes are stored as
+  little endian u32s. The free block bitmap tracks all the blocks, and the ones before the data
+  blocks are marked allocated when the File System is created.
+
+  NOTE : This module only describes the on disk format (and doesn't depend on the rest of the
+  kernel), so that host side tools (like mkfs) can share it.
+*/
+
+// A Disk is considered as a numbered sequence of Blocks.
+// All reads and writes done by the kernel against the disk, are done in units of block.
+//
+// On the other hand, all reads and writes done by the disk itself, are done in a unit called
+// sector. And usually, the sector size < block size.
+//
+// In UNIX systems for example, block size = 4096 and sector size = 512.
+pub const BLOCK_SIZE: usize = 1024;
+
+// Maximum number of blocks a File System (FS) operation can write to.
+pub const FS_OP_MAX_BLOCK_WRITES: usize = 10;
+
+// Maximum number of blocks a transaction can write to (REFER : ../log.rs).
+pub const LOG_SIZE: usize = FS_OP_MAX_BLOCK_WRITES * 3;
+
+// Number of blocks in the log area : the log header block, followed by the log blocks.
+pub const LOG_AREA_SIZE: usize = 1 + LOG_SIZE;
+
+// Identifies a disk containing our File System.
+pub const FS_MAGIC: u32 = 0x4172_6e6f;
+
+pub const SUPERBLOCK_BLOCK_NUMBER: usize = 1;
+
+// Inode number of the root directory. Inode number 0 is never used, so that it can mark an unused
+// directory entry.
+pub const ROOT_INODE_NUMBER: u32 = 1;
+
+// Number of block addresses stored directly in an inode.
+pub const DIRECT_BLOCKS_COUNT: usize = 11;
+
+// Number of block addresses stored in an indirect block.
+pub const INDIRECT_BLOCKS_COUNT: usize = BLOCK_SIZE / size_of::<u32>();
+
+// Maximum size of a file, in blocks.
+pub const MAX_FILE_BLOCKS: usize =
+  DIRECT_BLOCKS_COUNT + INDIRECT_BLOCKS_COUNT + INDIRECT_BLOCKS_COUNT * INDIRECT_BLOCKS_COUNT;
+
+pub const INODES_PER_BLOCK: usize = BLOCK_SIZE / size_of::<DiskInode>();
+
+// Number of blocks, whose allocation status a single bitmap block tracks.
+pub const BITS_PER_BLOCK: usize = BLOCK_SIZE * 8;
+
+// Maximum length of a directory entry name.
+pub const DIRECTORY_NAME_SIZE: usize = 14;
+
+pub const DIRECTORY_ENTRY_SIZE: usize = size_of::<DirectoryEntry>();
+
+/*
+  Structures which are stored on the disk as they're laid out in memory.
+
+  # Safety
+
+  The implementor must be #[repr(C)], must not have any padding, and must be valid for any bit
+  pattern. Both the kernel and the host tools run on little endian machines, so the in memory
+  layout matches the on disk one.
+*/
+pub unsafe trait OnDisk: Copy {
+  fn readFrom(bytes: &[u8]) -> Self {
+    assert!(bytes.len() >= size_of::<Self>());
+    unsafe { read_unaligned(bytes.as_ptr() as *const Self) }
+  }
+
+  fn writeTo(&self, bytes: &mut [u8]) {
+    assert!(bytes.len() >= size_of::<Self>());
+    unsafe { write_unaligned(bytes.as_mut_ptr() as *mut Self, *self) };
+  }
+}
+
+// Block addresses in the indirect blocks.
+unsafe impl OnDisk for u32 {}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct SuperBlock {
+  pub magic: u32,
+
+  // Total number of blocks in the File System.
+  pub size: u32,
+
+  pub dataBlocksCount: u32,
+  pub inodesCount: u32,
+  pub logBlocksCount: u32,
+
+  // Block numbers, where each region starts.
+  pub logStart: u32,
+  pub inodeStart: u32,
+  pub bitmapStart: u32,
+}
+
+unsafe impl OnDisk for SuperBlock {}
+
+impl SuperBlock {
+  // Returns the block containing the given inode.
+  #[inline]
+  pub fn getInodeBlock(&self, inodeNumber: u32) -> usize {
+    self.inodeStart as usize + inodeNumber as usize / INODES_PER_BLOCK
+  }
+
+  // Returns the bitmap block, tracking the allocation status of the given block.
+  #[inline]
+  pub fn getBitmapBlock(&self, blockNumber: usize) -> usize {
+    self.bitmapStart as usize + blockNumber / BITS_PER_BLOCK
+  }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum FileType {
+  Directory = 1,
+  File = 2,
+  Device = 3,
+}
+
+impl FileType {
+  pub fn fromRaw(fileType: u16) -> Option<Self> {
+    match fileType {
+      1 => Some(FileType::Directory),
+      2 => Some(FileType::File),
+      3 => Some(FileType::Device),
+      _ => None,
+    }
+  }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct DiskInode {
+  // 0 for a free inode.
+  pub fileType: u16,
+
+  // Device numbers (only for a device).
+  pub major: u16,
+  pub minor: u16,
+
+  // Number of directory entries referring to the inode.
+  pub linksCount: u16,
+
+  // Size of the file, in bytes.
+  pub size: u32,
+
+  // Addresses of the direct blocks, followed by the address of the indirect block and the address
+  // of the double indirect block. 0 means not allocated.
+  pub addresses: [u32; DIRECT_BLOCKS_COUNT + 2],
+}
+
+unsafe impl OnDisk for DiskInode {}
+
+impl DiskInode {
+  pub const fn new() -> Self {
+    Self {
+      fileType: 0,
+
+      major: 0,
+      minor: 0,
+
+      linksCount: 0,
+
+      size: 0,
+
+      addresses: [0; DIRECT_BLOCKS_COUNT + 2],
+    }
+  }
+
+  // Returns None for a free inode.
+  #[inline]
+  pub fn getFileType(&self) -> Option<FileType> {
+    FileType::fromRaw(self.fileType)
+  }
+}
+
+/*
+  Path to the address of a block of a file : the slot in the inode's addresses, followed by the
+  indices into the indirect blocks to be followed (if any). For example, with 11 direct blocks and
+  256 addresses per indirect block :
+
+    block 3   -> addresses[3]
+    block 20  -> addresses[11] (indirect block), index 9
+    block 300 -> addresses[12] (double indirect block), index 0, index 33
+*/
+pub struct BlockPath {
+  pub slot: usize,
+
+  indices: [usize; 2],
+  depth: usize, // Number of levels of indirection.
+}
+
+impl BlockPath {
+  // Returns None if the block is beyond the maximum file size.
+  pub fn new(blockIndex: usize) -> Option<Self> {
+    if blockIndex < DIRECT_BLOCKS_COUNT {
+      return Some(Self {
+        slot: blockIndex,
+        indices: [0; 2],
+        depth: 0,
+      });
+    }
+
+    let blockIndex = blockIndex - DIRECT_BLOCKS_COUNT;
+    if blockIndex < INDIRECT_BLOCKS_COUNT {
+      return Some(Self {
+        slot: DIRECT_BLOCKS_COUNT,
+        indices: [blockIndex, 0],
+        depth: 1,
+      });
+    }
+
+    let blockIndex = blockIndex - INDIRECT_BLOCKS_COUNT;
+    if blockIndex < INDIRECT_BLOCKS_COUNT * INDIRECT_BLOCKS_COUNT {
+      return Some(Self {
+        slot: DIRECT_BLOCKS_COUNT + 1,
+        indices: [
+          blockIndex / INDIRECT_BLOCKS_COUNT,
+          blockIndex % INDIRECT_BLOCKS_COUNT,
+        ],
+        depth: 2,
+      });
+    }
+
+    None
+  }
+
+  #[inline]
+  pub fn getIndices(&self) -> &[usize] {
+    &self.indices[..self.depth]
+  }
+
+  // Returns the number of levels of indirection, of the block tree rooted at the given slot.
+  #[inline]
+  pub fn getSlotDepth(slot: usize) -> usize {
+    slot.saturating_sub(DIRECT_BLOCKS_COUNT - 1)
+  }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct DirectoryEntry {
+  // 0 for an unused directory entry.
+  pub inodeNumber: u16,
+
+  // NUL padded (not NUL terminated, if the name is DIRECTORY_NAME_SIZE bytes long).
+  pub name: [u8; DIRECTORY_NAME_SIZE],
+}
+
+unsafe impl OnDisk for DirectoryEntry {}
+
+impl DirectoryEntry {
+  // Returns None if the name is too long.
+  pub fn new(inodeNumber: u16, name: &[u8]) -> Option<Self> {
+    if name.len() > DIRECTORY_NAME_SIZE {
+      return None;
+    }
+
+    let mut directoryEntry = Self {
+      inodeNumber,
+      name: [0; DIRECTORY_NAME_SIZE],
+    };
+    directoryEntry.name[..name.len()].copy_from_slice(name);
+
+    Some(directoryEntry)
+  }
+
+  pub fn getName(&self) -> &[u8] {
+    let length = self
+      .name
+      .iter()
+      .position(|&byte| byte == 0)
+      .unwrap_or(DIRECTORY_NAME_SIZE);
+
+    &self.name[..length]
+  }
+}
@@ -0,0 +1,297 @@
+pub mod filesystem;
+pub mod inode;
+pub mod layout;
+pub mod namespace;
+
+use {
+  self::{
+    inode::{Inode, MAX_WRITE_SIZE_PER_TRANSACTION},
+    layout::{FileType, DIRECTORY_ENTRY_SIZE},
+  },
+  crate::fs::{
+    bcache::BCACHE,
+    vfs::{self, mount::FileSystemType, DeviceNumber, DirectoryEntry, FsError, Stat},
+    BLOCK_SIZE,
+  },
+  alloc::sync::Arc,
+  core::any::Any,
+};
+
+/*
+  ArnoFS is the kernel's native on disk File System : an inode File System along the lines of xv6's
+  one, made crash consistent using the log. Disk images containing it are built by the mkfs tool.
+
+  This module plugs it into the VFS. Each VFS inode wraps a reference to a cached inode, and each
+  modifying operation is done in its own Transaction (writes are split across multiple ones).
+*/
+
+pub static FILE_SYSTEM_TYPE: FileSystemType = FileSystemType {
+  name: "arnofs",
+  isDiskBased: true,
+  mount,
+};
+
+fn mount(diskNumber: Option<usize>, _options: &[u8]) -> Result<Arc<dyn vfs::FileSystem>, FsError> {
+  let diskNumber = diskNumber.ok_or(FsError::InvalidArgument)?;
+  filesystem::mount(diskNumber)?;
+
+  Ok(Arc::new(ArnoFileSystem {
+    diskNumber,
+    fileSystemID: vfs::allocateFileSystemID(),
+  }))
+}
+
+struct ArnoFileSystem {
+  diskNumber: usize,
+  fileSystemID: usize,
+}
+
+impl vfs::FileSystem for ArnoFileSystem {
+  fn getTypeName(&self) -> &'static str {
+    FILE_SYSTEM_TYPE.name
+  }
+
+  fn getRootInode(&self) -> Result<Arc<dyn vfs::Inode>, FsError> {
+    let inode = filesystem::getFileSystem(self.diskNumber).getRootInode();
+    Ok(ArnoInode::new(inode, self.fileSystemID))
+  }
+
+  fn sync(&self) -> Result<(), FsError> {
+    BCACHE.sync(self.diskNumber);
+    Ok(())
+  }
+
+  fn unmount(&self) -> Result<(), FsError> {
+    filesystem::unmount(self.diskNumber);
+    Ok(())
+  }
+}
+
+struct ArnoInode {
+  inode: Inode,
+  fileSystemID: usize,
+}
+
+impl ArnoInode {
+  fn new(inode: Inode, fileSystemID: usize) -> Arc<dyn vfs::Inode> {
+    Arc::new(Self {
+      inode,
+      fileSystemID,
+    })
+  }
+
+  // Returns the given inode, if it's an inode of the same File System.
+  fn downcast<'a>(&self, inode: &'a dyn vfs::Inode) -> Result<&'a Self, FsError> {
+    match inode.asAny().downcast_ref::<Self>() {
+      Some(inode) if inode.fileSystemID == self.fileSystemID => Ok(inode),
+      _ => Err(FsError::CrossDevice),
+    }
+  }
+
+  // Writes the given buffer to the file starting at the given offset (which mustn't be past the
+  // end of the file), splitting it across Transactions. Returns the number of bytes written.
+  fn write(&self, offset: usize, buffer: &[u8]) -> Result<usize, FsError> {
+    let fileSystem = self.inode.getFileSystem();
+
+    let mut bytesWritten = 0;
+    while bytesWritten < buffer.len() {
+      let chunkLength = (buffer.len() - bytesWritten).min(MAX_WRITE_SIZE_PER_TRANSACTION);
+      let chunk = &buffer[bytesWritten..bytesWritten + chunkLength];
+
+      let transaction = fileSystem.beginTransaction();
+      let result = self
+        .inode
+        .lock()
+        .writeAt(&transaction, offset + bytesWritten, chunk);
+      drop(transaction);
+
+      match result {
+        Ok(chunkBytesWritten) => {
+          bytesWritten += chunkBytesWritten;
+
+          // The disk has run out of space.
+          if chunkBytesWritten < chunkLength {
+            break;
+          }
+        }
+
+        Err(error) if bytesWritten == 0 => return Err(error),
+        Err(_) => break,
+      }
+    }
+
+    Ok(bytesWritten)
+  }
+
+  // Grows the file to the given size, zero filling it.
+  fn growTo(&self, size: usize) -> Result<(), FsError> {
+    const ZEROES: [u8; BLOCK_SIZE] = [0; BLOCK_SIZE];
+
+    let mut currentSize = self.inode.lock().getSize();
+    while currentSize < size {
+      match self.write(currentSize, &ZEROES[..(size - currentSize).min(BLOCK_SIZE)])? {
+        0 => return Err(FsError::NoSpace),
+        bytesWritten => currentSize += bytesWritten,
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl vfs::Inode for ArnoInode {
+  fn asAny(&self) -> &dyn Any {
+    self
+  }
+
+  fn getStat(&self) -> Result<Stat, FsError> {
+    let inodeGuard = self.inode.lock();
+
+    let (fileType, deviceNumber) = match inodeGuard.getFileType() {
+      FileType::Directory => (vfs::FileType::Directory, DeviceNumber::default()),
+      FileType::File => (vfs::FileType::Regular, DeviceNumber::default()),
+      FileType::Device => {
+        let (major, minor) = inodeGuard.getDeviceNumbers();
+        (
+          vfs::FileType::CharDevice,
+          DeviceNumber::new(major as u32, minor as u32),
+        )
+      }
+    };
+
+    Ok(Stat {
+      fileSystemID: self.fileSystemID,
+      inodeNumber: self.inode.getInodeNumber() as u64,
+
+      fileType,
+      linksCount: inodeGuard.getLinksCount() as u32,
+
+      size: inodeGuard.getSize() as u64,
+
+      deviceNumber,
+    })
+  }
+
+  fn readAt(&self, offset: u64, buffer: &mut [u8]) -> Result<usize, FsError> {
+    let offset = usize::try_from(offset).map_err(|_| FsError::InvalidArgument)?;
+    Ok(self.inode.lock().readAt(offset, buffer))
+  }
+
+  fn writeAt(&self, offset: u64, buffer: &[u8]) -> Result<usize, FsError> {
+    let offset = usize::try_from(offset).map_err(|_| FsError::FileTooLarge)?;
+
+    // ArnoFS files can't have holes past their end. So the gap (if any) is zero filled.
+    self.growTo(offset)?;
+
+    self.write(offset, buffer)
+  }
+
+  // NOTE : Only discarding the whole contents of a file, or growing it, is supported.
+  fn truncate(&self, size: u64) -> Result<(), FsError> {
+    let size = usize::try_from(size).map_err(|_| FsError::FileTooLarge)?;
+    let currentSize = self.inode.lock().getSize();
+
+    match size {
+      0 => {
+        let transaction = self.inode.getFileSystem().beginTransaction();
+        self.inode.lock().truncate(&transaction);
+        Ok(())
+      }
+
+      _ if size >= currentSize => self.growTo(size),
+      _ => Err(FsError::NotSupported),
+    }
+  }
+
+  fn lookup(&self, name: &[u8]) -> Result<Arc<dyn vfs::Inode>, FsError> {
+    let inodeGuard = self.inode.lock();
+    if inodeGuard.getFileType() != FileType::Directory {
+      return Err(FsError::NotADirectory);
+    }
+
+    let (inode, _) = inodeGuard.lookup(name).ok_or(FsError::NotFound)?;
+    drop(inodeGuard);
+
+    Ok(Self::new(inode, self.fileSystemID))
+  }
+
+  fn create(
+    &self,
+    name: &[u8],
+    fileType: vfs::FileType,
+    deviceNumber: DeviceNumber,
+  ) -> Result<Arc<dyn vfs::Inode>, FsError> {
+    let fileType = match fileType {
+      vfs::FileType::Regular => FileType::File,
+      vfs::FileType::Directory => FileType::Directory,
+      vfs::FileType::CharDevice => FileType::Device,
+      _ => return Err(FsError::NotSupported),
+    };
+
+    let (Ok(major), Ok(minor)) = (
+      u16::try_from(deviceNumber.major),
+      u16::try_from(deviceNumber.minor),
+    )
+    else {
+      return Err(FsError::InvalidArgument);
+    };
+
+    let transaction = self.inode.getFileSystem().beginTransaction();
+    let inode = namespace::create(&transaction, &self.inode, name, fileType, major, minor)?;
+    drop(transaction);
+
+    Ok(Self::new(inode, self.fileSystemID))
+  }
+
+  fn link(&self, name: &[u8], inode: &dyn vfs::Inode) -> Result<(), FsError> {
+    let inode = self.downcast(inode)?;
+
+    let transaction = self.inode.getFileSystem().beginTransaction();
+    namespace::link(&transaction, &self.inode, name, &inode.inode)
+  }
+
+  fn unlink(&self, name: &[u8]) -> Result<(), FsError> {
+    let transaction = self.inode.getFileSystem().beginTransaction();
+    namespace::unlink(&transaction, &self.inode, name)
+  }
+
+  fn rename(
+    &self,
+    oldName: &[u8],
+    newDirectory: &dyn vfs::Inode,
+    newName: &[u8],
+  ) -> Result<(), FsError> {
+    let newDirectory = self.downcast(newDirectory)?;
+
+    let transaction = self.inode.getFileSystem().beginTransaction();
+    namespace::rename(
+      &transaction,
+      &self.inode,
+      oldName,
+      &newDirectory.inode,
+      newName,
+    )
+  }
+
+  // The position is the offset of the directory entry, in the directory.
+  fn readDirectory(&self, position: usize) -> Result<Option<(DirectoryEntry, usize)>, FsError> {
+    let inodeGuard = self.inode.lock();
+    if inodeGuard.getFileType() != FileType::Directory {
+      return Err(FsError::NotADirectory);
+    }
+
+    Ok(
+      inodeGuard
+        .getNextDirectoryEntry(position)
+        .map(|(offset, directoryEntry)| {
+          (
+            DirectoryEntry {
+              inodeNumber: directoryEntry.inodeNumber as u64,
+              name: directoryEntry.getName().to_vec(),
+            },
+            offset + DIRECTORY_ENTRY_SIZE,
+          )
+        }),
+    )
+  }
+}
@@ -0,0 +1,234 @@
+use {
+  super::{
+    filesystem::Transaction,
+    inode::Inode,
+    layout::{FileType, DIRECTORY_NAME_SIZE},
+  },
+  crate::fs::vfs::FsError,
+};
+
+/*
+  Operations on the directory tree. Each of them is done inside the given Transaction, and while
+  holding the File System's namespace lock.
+
+  NOTE : Directories get the "." and ".." entries. The "." entry isn't counted as a link, so that
+  a directory's links count is 1 (for its entry in the parent) + the number of its subdirectories.
+*/
+
+// Creates a file (or directory or device) with the given name in the given directory. Returns its
+// inode (unlocked).
+pub fn create(
+  transaction: &Transaction,
+  parent: &Inode,
+  name: &[u8],
+  fileType: FileType,
+  major: u16,
+  minor: u16,
+) -> Result<Inode, FsError> {
+  let fileSystem = transaction.getFileSystem();
+  let _namespaceGuard = fileSystem.lockNamespace();
+
+  if matches!(name, b"" | b"." | b"..") {
+    return Err(FsError::AlreadyExists);
+  }
+  if name.len() > DIRECTORY_NAME_SIZE {
+    return Err(FsError::NameTooLong);
+  }
+
+  {
+    let parentGuard = parent.lock();
+
+    if parentGuard.getFileType() != FileType::Directory {
+      return Err(FsError::NotADirectory);
+    }
+    // A removed directory can't get new entries.
+    if parentGuard.getLinksCount() == 0 {
+      return Err(FsError::NotFound);
+    }
+    if parentGuard.findEntry(name).is_some() {
+      return Err(FsError::AlreadyExists);
+    }
+  }
+
+  let inode = fileSystem.allocateInode(transaction, fileType, major, minor)?;
+
+  let result = (|| {
+    {
+      let mut inodeGuard = inode.lock();
+
+      inodeGuard.setLinksCount(1);
+      inodeGuard.update(transaction);
+
+      if fileType == FileType::Directory {
+        inodeGuard.link(transaction, b".", inode.getInodeNumber())?;
+        inodeGuard.link(transaction, b"..", parent.getInodeNumber())?;
+      }
+    }
+
+    let mut parentGuard = parent.lock();
+    parentGuard.link(transaction, name, inode.getInodeNumber())?;
+
+    // The ".." entry of the directory.
+    if fileType == FileType::Directory {
+      let linksCount = parentGuard.getLinksCount();
+      parentGuard.setLinksCount(linksCount + 1);
+      parentGuard.update(transaction);
+    }
+
+    Ok(())
+  })();
+
+  if let Err(error) = result {
+    // The inode gets freed once it's dropped.
+    let mut inodeGuard = inode.lock();
+    inodeGuard.setLinksCount(0);
+    inodeGuard.update(transaction);
+
+    return Err(error);
+  }
+
+  Ok(inode)
+}
+
+// Adds an entry with the given name in the given directory, to the given (non directory) inode.
+pub fn link(
+  transaction: &Transaction,
+  parent: &Inode,
+  name: &[u8],
+  inode: &Inode,
+) -> Result<(), FsError> {
+  let _namespaceGuard = transaction.getFileSystem().lockNamespace();
+
+  {
+    let mut inodeGuard = inode.lock();
+
+    if inodeGuard.getFileType() == FileType::Directory {
+      return Err(FsError::IsADirectory);
+    }
+
+    let linksCount = inodeGuard.getLinksCount();
+    inodeGuard.setLinksCount(linksCount + 1);
+    inodeGuard.update(transaction);
+  }
+
+  let result = parent
+    .lock()
+    .link(transaction, name, inode.getInodeNumber());
+
+  if result.is_err() {
+    let mut inodeGuard = inode.lock();
+
+    let linksCount = inodeGuard.getLinksCount();
+    inodeGuard.setLinksCount(linksCount - 1);
+    inodeGuard.update(transaction);
+  }
+  result
+}
+
+// Removes the entry with the given name from the given directory. The inode gets freed, once it
+// isn't linked from any directory and its last reference is dropped.
+pub fn unlink(transaction: &Transaction, parent: &Inode, name: &[u8]) -> Result<(), FsError> {
+  let _namespaceGuard = transaction.getFileSystem().lockNamespace();
+  removeEntry(transaction, parent, name)
+}
+
+fn removeEntry(transaction: &Transaction, parent: &Inode, name: &[u8]) -> Result<(), FsError> {
+  if matches!(name, b"" | b"." | b"..") {
+    return Err(FsError::InvalidArgument);
+  }
+
+  let (inode, offset) = parent.lock().lookup(name).ok_or(FsError::NotFound)?;
+
+  let isDirectory = {
+    let inodeGuard = inode.lock();
+    assert!(
+      inodeGuard.getLinksCount() > 0,
+      "Unlinking an inode with no links"
+    );
+
+    let isDirectory = inodeGuard.getFileType() == FileType::Directory;
+    if isDirectory && !inodeGuard.isDirectoryEmpty() {
+      return Err(FsError::DirectoryNotEmpty);
+    }
+
+    isDirectory
+  };
+
+  {
+    let mut parentGuard = parent.lock();
+    parentGuard.unlinkAt(transaction, offset)?;
+
+    // The ".." entry of the directory.
+    if isDirectory {
+      let linksCount = parentGuard.getLinksCount();
+      parentGuard.setLinksCount(linksCount - 1);
+      parentGuard.update(transaction);
+    }
+  }
+
+  let mut inodeGuard = inode.lock();
+  let linksCount = inodeGuard.getLinksCount();
+  inodeGuard.setLinksCount(linksCount - 1);
+  inodeGuard.update(transaction);
+
+  Ok(())
+}
+
+// Moves the entry with the given old name in the given old directory, to the given new name in the
+// given new directory. An existing entry with the new name is replaced.
+pub fn rename(
+  transaction: &Transaction,
+  oldParent: &Inode,
+  oldName: &[u8],
+  newParent: &Inode,
+  newName: &[u8],
+) -> Result<(), FsError> {
+  let _namespaceGuard = transaction.getFileSystem().lockNamespace();
+
+  if matches!(newName, b"" | b"." | b"..") {
+    return Err(FsError::InvalidArgument);
+  }
+  if newName.len() > DIRECTORY_NAME_SIZE {
+    return Err(FsError::NameTooLong);
+  }
+
+  let (inode, oldOffset) = oldParent.lock().lookup(oldName).ok_or(FsError::NotFound)?;
+  let isDirectory = inode.lock().getFileType() == FileType::Directory;
+
+  let replacedEntry = newParent.lock().findEntry(newName);
+  if let Some((_, replacedEntry)) = replacedEntry {
+    if replacedEntry.inodeNumber as u32 == inode.getInodeNumber() {
+      return Ok(());
+    }
+
+    removeEntry(transaction, newParent, newName)?;
+  }
+
+  newParent
+    .lock()
+    .link(transaction, newName, inode.getInodeNumber())?;
+  oldParent.lock().unlinkAt(transaction, oldOffset)?;
+
+  // Point the ".." entry of the moved directory to the new parent.
+  if isDirectory && oldParent.getInodeNumber() != newParent.getInodeNumber() {
+    {
+      let mut inodeGuard = inode.lock();
+
+      let (offset, _) = inodeGuard
+        .findEntry(b"..")
+        .expect("Directory doesn't have a \"..\" entry");
+      inodeGuard.unlinkAt(transaction, offset)?;
+      inodeGuard.link(transaction, b"..", newParent.getInodeNumber())?;
+    }
+
+    for (parent, delta) in [(oldParent, -1), (newParent, 1)] {
+      let mut parentGuard = parent.lock();
+
+      let linksCount = parentGuard.getLinksCount();
+      parentGuard.setLinksCount(linksCount.wrapping_add_signed(delta));
+      parentGuard.update(transaction);
+    }
+  }
+
+  Ok(())
+}
@@ -1,278 +1,890 @@
 use {
-  super::{BLOCK_SIZE, FS_OP_MAX_BLOCK_WRITES},
-  crate::locks::{
-    sleeplock::{SleepLock, SleepLockGuard},
-    spinlock::SpinLock,
+  super::{
+    disk::{self, BlockOperation, BlockRequest},
+    BLOCK_SIZE, FS_OP_MAX_BLOCK_WRITES, MAX_DISKS,
   },
-  array_macro::array,
+  crate::{
+    locks::{
+      sleeplock::{SleepLock, SleepLockGuard},
+      spinlock::SpinLock,
+      waitqueue::WaitQueue,
+    },
+    memory::allocator::GLOBAL_ALLOCATOR,
+  },
+  alloc::vec::Vec,
   core::{
-    ptr,
-    sync::atomic::{AtomicBool, Ordering},
+    cell::UnsafeCell,
+    mem::ManuallyDrop,
+    ops::{Deref, DerefMut, Range},
+    ptr::addr_of,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
   },
 };
 
-// Maximum number of blocks we can store at any moment of time, in the Buffer Cache (LRUCache).
-pub const BCACHE_SIZE: usize = FS_OP_MAX_BLOCK_WRITES * 3;
+// Bounds on the number of blocks we can store at any moment of time, in the Buffer Cache.
+// NOTE : A File System operation must be able to keep all the blocks it writes to cached.
+pub const MIN_BCACHE_SIZE: usize = FS_OP_MAX_BLOCK_WRITES * 3;
+pub const MAX_BCACHE_SIZE: usize = 4096;
 
-struct LRUCacheNode {
-  // Index of this LRUCacheNode in the LRUCache.nodes array.
-  index: usize,
+// The Buffer Cache gets (1 / BCACHE_MEMORY_FRACTION)th of the memory available at boot.
+const BCACHE_MEMORY_FRACTION: usize = 64;
 
-  previous: *mut LRUCacheNode,
-  next: *mut LRUCacheNode,
+// Maximum number of blocks read ahead, once sequential reads of a disk are detected.
+const READ_AHEAD_WINDOW: usize = 8;
 
-  // If the refCount is not 0, that means this Buffer Cache (LRUCache) node is being used.
+// Number of buckets in the hash table. A prime, so that the blocks spread evenly.
+const BCACHE_BUCKETS_COUNT: usize = 61;
+
+// Bookkeeping information of a cached block, stored in the bucket the cached block belongs to.
+struct BufferMetadata {
+  // Index of the cached block in the BCache.buffers array.
+  index: usize,
+
+  // If the refCount is not 0, that means this cached block is being used (or is pinned).
   refCount: usize,
 
   // Corresponding disk and block numbers.
   diskNumber: usize,
   blockNumber: usize,
-}
 
-impl LRUCacheNode {
-  pub const fn new() -> Self {
-    Self {
-      index: 0,
+  // Whether the cached block has been modified, but not yet written back to the disk.
+  isDirty: bool,
 
-      previous: ptr::null_mut(),
-      next: ptr::null_mut(),
+  // Whether a request (write-back or read-ahead) for the cached block has been submitted to the
+  // disk, but nobody has waited for it to finish yet.
+  isRequestInFlight: bool,
 
-      refCount: 0,
+  // Value of the BCache.lruClock, when the cached block was last released. The unused cached block
+  // with the smallest value is the least recently used (LRU) one.
+  lastUsedAt: usize,
+}
 
-      diskNumber: 0,
-      blockNumber: 0,
-    }
+impl BufferMetadata {
+  // Whether the cached block must be written back to the disk (or its in flight request must be
+  // waited for), before it can be recycled.
+  #[inline]
+  fn hasPendingIO(&self) -> bool {
+    self.isDirty || self.isRequestInFlight
   }
 }
 
-// This LRUCache is wrapped around by BCache and must be first initialized using BCache.init( ).
-// NOTE : Implemented using a Doubly Linked List (not a Circular Linked List).
-struct LRUCache {
-  head: *mut LRUCacheNode,
-  tail: *mut LRUCacheNode,
-
-  nodes: [LRUCacheNode; BCACHE_SIZE],
+// A bucket of the hash table, holding the cached blocks whose (disk number, block number)
+// combinations hash to it.
+struct Bucket {
+  buffers: Vec<BufferMetadata>,
 }
 
-impl LRUCache {
+impl Bucket {
   const fn new() -> Self {
     Self {
-      head: ptr::null_mut(),
-      tail: ptr::null_mut(),
-
-      nodes: array![_ => LRUCacheNode::new(); BCACHE_SIZE],
+      buffers: Vec::new(),
     }
   }
 
-  // Looks for an LRUCache node corresponding to the given disk and block numbers combination.
-  // If found, returns the index of the node in the LRUCache.nodes array and the current number of
-  // users of this node (refCount).
-  fn get(&self, diskNumber: usize, blockNumber: usize) -> Option<(usize, *mut usize)> {
-    let mut nodeRawPointer = self.head;
-    while !nodeRawPointer.is_null() {
-      let node = unsafe { &mut *nodeRawPointer };
+  fn find(&mut self, diskNumber: usize, blockNumber: usize) -> Option<&mut BufferMetadata> {
+    self
+      .buffers
+      .iter_mut()
+      .find(|buffer| buffer.diskNumber == diskNumber && buffer.blockNumber == blockNumber)
+  }
+
+  fn findByIndex(&mut self, index: usize) -> &mut BufferMetadata {
+    self
+      .buffers
+      .iter_mut()
+      .find(|buffer| buffer.index == index)
+      .expect("Cached block not found in its bucket")
+  }
+}
 
-      if (node.diskNumber == diskNumber) && (node.blockNumber == blockNumber) {
-        node.refCount += 1;
-        return Some((node.index, &mut node.refCount));
-      }
+#[repr(C, align(8))]
+struct BlockData([u8; BLOCK_SIZE]);
 
-      nodeRawPointer = node.next;
-    }
-    None
+impl BlockData {
+  pub const fn new() -> Self {
+    Self([0; BLOCK_SIZE])
   }
+}
 
-  // Recycle an unused LRUCache node (a node whose refCount = 0), with the given disk and block
-  // numbers combintaion .
-  //
-  // If a recyclable BCache node is found, then returns its index in the LRUCache.nodes array and
-  // the current number of places it's being used in (refCount - which will always be 1).
-  //
-  // NOTE : In this LRU cache, we're storing the least recently used (LRU) nodes towards the head.
-  //				So, we will start searching from the tail towards the head.
-  fn recycle(&self, diskNumber: usize, blockNumber: usize) -> Option<(usize, *mut usize)> {
-    let mut nodeRawPointer = self.tail;
-    while !nodeRawPointer.is_null() {
-      let node = unsafe { &mut *nodeRawPointer };
+struct BlockBuffer {
+  blockData: BlockData,
 
-      if node.refCount == 0 {
-        node.diskNumber = diskNumber;
-        node.blockNumber = blockNumber;
+  // The request (if any) which has been submitted to the disk, but not yet waited for. The block
+  // data must not be accessed while it's in flight.
+  inFlightRequest: Option<BlockRequest>,
+}
 
-        node.refCount += 1;
+struct BlockDataGuard {
+  // Indicates whether the corresponding BlockData contains garbage or actual data from the disk.
+  isValid: AtomicBool,
 
-        return Some((node.index, &mut node.refCount));
-      }
+  blockBuffer: SleepLock<BlockBuffer>,
+}
 
-      nodeRawPointer = node.previous;
+impl BlockDataGuard {
+  pub const fn new() -> Self {
+    Self {
+      isValid: AtomicBool::new(false),
+      blockBuffer: SleepLock::new(BlockBuffer {
+        blockData: BlockData::new(),
+        inFlightRequest: None,
+      }),
     }
-    None
   }
+}
 
-  // Decreases the refCount of the givnen node.
-  // If the final refCount is 0, then that means the node has become the least recently used (LRU)
-  // one and gets moved to the head.
-  fn decreaseRefCount(&mut self, index: usize) {
-    let node = &mut self.nodes[index];
+// A cached block, locked for exclusive use by the holder. Dereferences to the block data.
+// The cached block gets released (brelse), when the BCacheNode is dropped.
+pub struct BCacheNode<'a> {
+  bcache: &'a BCache,
 
-    node.refCount -= 1;
+  index: usize, // Index of the cached block in the BCache.buffers array.
 
-    // The node has become the least recently used (LRU) one and needs to be moved to the head.
-    if node.refCount == 0 && !ptr::eq(node, self.head) {
-      // If this node is currently the tail, then set node.previous as the tail.
-      if ptr::eq(node, self.tail) {
-        self.tail = node.previous;
-      }
+  diskNumber: usize,
+  blockNumber: usize,
 
-      // Detach the node, from its previous and next.
-      unsafe {
-        node.previous.as_mut().unwrap().next = node.next;
-        node.next.as_mut().unwrap().previous = node.previous;
-      }
+  blockBuffer: ManuallyDrop<SleepLockGuard<'a, BlockBuffer>>,
+}
 
-      // Put the node in the head.
+impl BCacheNode<'_> {
+  #[inline]
+  pub fn getDiskNumber(&self) -> usize {
+    self.diskNumber
+  }
 
-      node.previous = ptr::null_mut();
-      node.next = self.head;
+  #[inline]
+  pub fn getBlockNumber(&self) -> usize {
+    self.blockNumber
+  }
 
-      unsafe { self.head.as_mut().unwrap().previous = node };
+  // Marks the cached block as modified. It gets written back to the disk later (when it's about to
+  // be recycled, or the disk is synced).
+  pub fn markDirty(&mut self) {
+    self
+      .bcache
+      .updateMetadata(self, |bufferMetadata| bufferMetadata.isDirty = true);
+  }
+}
 
-      self.head = node;
-    }
+impl Deref for BCacheNode<'_> {
+  type Target = [u8; BLOCK_SIZE];
+
+  fn deref(&self) -> &Self::Target {
+    &self.blockBuffer.blockData.0
   }
 }
 
-unsafe impl Send for LRUCache {}
+impl DerefMut for BCacheNode<'_> {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    &mut self.blockBuffer.blockData.0
+  }
+}
 
-#[repr(C, align(8))]
-struct BlockData([u8; BLOCK_SIZE]);
+// Release the cached block when the BCacheNode is dropped.
+impl Drop for BCacheNode<'_> {
+  fn drop(&mut self) {
+    // Release the SleepLock before decreasing the refCount.
+    unsafe { ManuallyDrop::drop(&mut self.blockBuffer) };
 
-impl BlockData {
-  pub const fn new() -> Self {
-    Self([0; BLOCK_SIZE])
+    self
+      .bcache
+      .release(self.diskNumber, self.blockNumber, self.index);
   }
 }
 
-struct BlockDataGuard {
-  // Indicates whether the corresponding BlockData contains garbage or actual data from the disk.
-  isValid: AtomicBool,
+// A snapshot of the BCache statistics.
+#[derive(Debug, Copy, Clone)]
+pub struct BCacheStatistics {
+  pub buffersCount: usize,
 
-  blockData: SleepLock<BlockData>,
+  pub hits: usize,
+  pub misses: usize,
+
+  // Number of times a cached block got recycled, to cache some other block.
+  pub evictions: usize,
+
+  pub writeBacks: usize,
+  pub readAheads: usize,
 }
 
-impl BlockDataGuard {
-  pub const fn new() -> Self {
+// Tracks the reads of a disk, to detect sequential access.
+struct ReadAheadState {
+  lastReadBlockNumber: usize,
+
+  // The block right after the last block which has been read ahead.
+  readAheadEnd: usize,
+}
+
+impl ReadAheadState {
+  const fn new() -> Self {
     Self {
-      isValid: AtomicBool::new(false),
-      blockData: SleepLock::new(BlockData::new()),
+      lastReadBlockNumber: usize::MAX,
+      readAheadEnd: 0,
     }
   }
+
+  /*
+    Records a read of the given block. Returns the blocks which should be read ahead.
+
+    Once the reads are found to be sequential, the next READ_AHEAD_WINDOW blocks are read ahead.
+    The window is moved forward once the reader is halfway through it, so that the read-ahead
+    stays ahead of the reader.
+  */
+  fn recordRead(&mut self, blockNumber: usize, blocksCount: usize) -> Range<usize> {
+    let isSequential = blockNumber == self.lastReadBlockNumber.wrapping_add(1);
+    self.lastReadBlockNumber = blockNumber;
+
+    if !isSequential {
+      self.readAheadEnd = blockNumber + 1;
+      return 0..0;
+    }
+
+    if self.readAheadEnd > blockNumber + READ_AHEAD_WINDOW / 2 {
+      return 0..0;
+    }
+
+    let start = self.readAheadEnd.max(blockNumber + 1);
+    let end = (blockNumber + 1 + READ_AHEAD_WINDOW).min(blocksCount);
+
+    self.readAheadEnd = end.max(start);
+    start..end
+  }
 }
 
-pub struct BCacheNode<'a> {
-  index: usize, // = LRUCacheNode.index.
+// How get( ) behaves, when the block isn't cached.
+#[derive(PartialEq, Copy, Clone)]
+enum LookupMode {
+  // Recycle an unused cached block, waiting for one if required.
+  Demand,
+
+  // Recycle an unused cached block, only if that doesn't require waiting. The block is skipped if
+  // it's already cached.
+  ReadAhead,
+}
 
-  refCount: *mut usize,
+enum Victim {
+  // An unused cached block, which can be recycled right away.
+  Clean(usize, usize),
 
-  diskNumber: usize,
-  blockNumber: usize,
+  // An unused cached block, which must be written back (or have its in flight request waited for)
+  // before it can be recycled.
+  Dirty(usize, usize),
 
-  blockData: SleepLockGuard<'a, BlockData>,
+  // All the cached blocks are being used.
+  None,
 }
 
-// NOTE : The BCache (specifically BCache.BlockDataGuards) is guarded using a SleepLock. So only
-// 1 process can use the BCache at a time.
+/*
+  The Buffer Cache (BCache) caches blocks of the disks in memory. It provides the bread / bwrite /
+  brelse semantics :
+
+    (1) read( ) returns a locked cached block, reading it from the disk if it isn't already cached.
+
+    (2) write( ) writes a cached block to the disk right away. Alternatively, a cached block can be
+        marked dirty, and it gets written back later (write-back caching).
+
+    (3) the cached block is released by dropping it.
+
+  Each cached block is guarded by a SleepLock. So only 1 process can use a cached block at a time.
+
+  Sequential reads of a disk are detected, and the blocks which are likely to be read next get read
+  ahead asynchronously. The disk is plugged meanwhile, so the I/O scheduler can merge those reads.
+
+  The cached blocks are spread across the buckets of a hash table (keyed by the disk and block
+  numbers), each guarded by its own SpinLock. So, lookups of different blocks rarely contend.
+  Recycling a cached block moves it from one bucket to another. That's serialized using the
+  eviction SpinLock, so that the same block never gets cached twice.
+*/
 pub struct BCache {
-  lruCache: SpinLock<LRUCache>,
-  blockDataGuards: [BlockDataGuard; BCACHE_SIZE],
+  // Set once by init( ), and only read afterwards.
+  buffers: UnsafeCell<Vec<BlockDataGuard>>,
+  buckets: UnsafeCell<Vec<SpinLock<Bucket>>>,
+
+  evictionLock: SpinLock<()>,
+
+  // Ticks on every release of a cached block. Used to determine the least recently used (LRU)
+  // cached block.
+  lruClock: AtomicUsize,
+
+  // Processes waiting for a cached block to become unused, so that it can be recycled.
+  waitQueue: WaitQueue,
+
+  readAheadStates: SpinLock<[ReadAheadState; MAX_DISKS]>,
+
+  hits: AtomicUsize,
+  misses: AtomicUsize,
+  evictions: AtomicUsize,
+  writeBacks: AtomicUsize,
+  readAheads: AtomicUsize,
 }
 
+unsafe impl Sync for BCache {}
+
 impl BCache {
   pub const fn new() -> Self {
     Self {
-      lruCache: SpinLock::new(LRUCache::new()),
-      blockDataGuards: array![_ => BlockDataGuard::new(); BCACHE_SIZE],
+      buffers: UnsafeCell::new(Vec::new()),
+      buckets: UnsafeCell::new(Vec::new()),
+
+      evictionLock: SpinLock::new(()),
+
+      lruClock: AtomicUsize::new(0),
+
+      waitQueue: WaitQueue::new(),
+
+      readAheadStates: SpinLock::new([const { ReadAheadState::new() }; MAX_DISKS]),
+
+      hits: AtomicUsize::new(0),
+      misses: AtomicUsize::new(0),
+      evictions: AtomicUsize::new(0),
+      writeBacks: AtomicUsize::new(0),
+      readAheads: AtomicUsize::new(0),
     }
   }
 
-  // Initializes the BCache.
+  // Initializes the BCache, sizing it based on the memory available at boot.
   // NOTE : Should only be called once when the Kernel is intializing.
   pub fn init(&self) {
-    let mut lruCacheGuard = self.lruCache.acquire();
+    let availableMemorySize = unsafe { (*addr_of!(GLOBAL_ALLOCATOR)).getAvailableMemorySize() };
+    let buffersCount = (availableMemorySize / BCACHE_MEMORY_FRACTION / BLOCK_SIZE)
+      .clamp(MIN_BCACHE_SIZE, MAX_BCACHE_SIZE);
+
+    let buffers = unsafe { &mut *self.buffers.get() };
+    let buckets = unsafe { &mut *self.buckets.get() };
 
-    // Initialize the head and tail nodes of the LRU Cache.
-    lruCacheGuard.head = &mut lruCacheGuard.nodes[0];
-    lruCacheGuard.tail = &mut lruCacheGuard.nodes[BCACHE_SIZE - 1];
+    assert!(buffers.is_empty(), "BCache is already initialized");
 
-    // Establish links between the LRU cache nodes.
+    buffers.extend((0..buffersCount).map(|_| BlockDataGuard::new()));
+    buckets.extend((0..BCACHE_BUCKETS_COUNT).map(|_| SpinLock::new(Bucket::new())));
 
-    lruCacheGuard.nodes[0].previous = ptr::null_mut();
-    lruCacheGuard.nodes[0].next = &mut lruCacheGuard.nodes[1];
+    // Initially, all the cached blocks are unused and contain garbage. We put them in the buckets
+    // round robin, with block numbers no real block would have.
+    for index in 0..buffersCount {
+      buckets[index % BCACHE_BUCKETS_COUNT]
+        .acquire()
+        .buffers
+        .push(BufferMetadata {
+          index,
 
-    lruCacheGuard.nodes[BCACHE_SIZE - 1].previous = &mut lruCacheGuard.nodes[BCACHE_SIZE - 2];
-    lruCacheGuard.nodes[BCACHE_SIZE - 1].next = ptr::null_mut();
+          refCount: 0,
 
-    for i in 1..=(BCACHE_SIZE - 2) {
-      lruCacheGuard.nodes[i].previous = &mut lruCacheGuard.nodes[i - 1];
-      lruCacheGuard.nodes[i].next = &mut lruCacheGuard.nodes[i + 1];
+          diskNumber: usize::MAX,
+          blockNumber: index,
+
+          isDirty: false,
+          isRequestInFlight: false,
+
+          lastUsedAt: 0,
+        });
     }
 
-    // Set index field for each node in the LRUCache.nodes array.
-    lruCacheGuard
-      .nodes
-      .iter_mut()
-      .enumerate()
-      .for_each(|(i, node)| node.index = i);
+    println!("INFO : Buffer cache has {} blocks", buffersCount);
+  }
+
+  #[inline]
+  fn getBuffers(&self) -> &[BlockDataGuard] {
+    unsafe { &*self.buffers.get() }
+  }
+
+  #[inline]
+  fn getBuckets(&self) -> &[SpinLock<Bucket>] {
+    unsafe { &*self.buckets.get() }
+  }
+
+  #[inline]
+  fn getBucket(&self, diskNumber: usize, blockNumber: usize) -> &SpinLock<Bucket> {
+    let hash = diskNumber.wrapping_mul(31).wrapping_add(blockNumber);
+    &self.getBuckets()[hash % BCACHE_BUCKETS_COUNT]
   }
 
-  // Read the given block in the given disk, through this BCache.
+  // Runs the given function on the bookkeeping information of the given cached block.
+  fn updateMetadata<R>(
+    &self,
+    bcacheNode: &BCacheNode,
+    function: impl FnOnce(&mut BufferMetadata) -> R,
+  ) -> R {
+    let mut bucket = self
+      .getBucket(bcacheNode.diskNumber, bcacheNode.blockNumber)
+      .acquire();
+    function(bucket.findByIndex(bcacheNode.index))
+  }
+
+  // Read the given block in the given disk, through this BCache (bread).
+  // If all the cached blocks are being used, then sleeps till one gets released.
   pub fn read(&self, diskNumber: usize, blockNumber: usize) -> BCacheNode<'_> {
-    let lruCacheGuard = self.lruCache.acquire();
+    let index = self
+      .get(diskNumber, blockNumber, LookupMode::Demand)
+      .unwrap();
+
+    // NOTE : The read-ahead is done before locking the cached block, since waiting for the
+    // SleepLock while the disk is plugged can deadlock.
+    let disk = disk::getDisk(diskNumber);
+    let readAheadBlocks =
+      self.readAheadStates.acquire()[diskNumber].recordRead(blockNumber, disk.getBlocksCount());
+    self.readAhead(diskNumber, readAheadBlocks);
+
+    let mut bcacheNode = self.lock(index, diskNumber, blockNumber);
+
+    if !self.isValid(index) {
+      self.submitRead(&mut bcacheNode);
+      self.waitForRequest(&mut bcacheNode);
+
+      assert!(self.isValid(index), "Failed reading block from disk");
+    }
+
+    bcacheNode
+  }
+
+  // Submits reads of the given blocks of the given disk, which aren't already cached. Doesn't wait
+  // for them.
+  fn readAhead(&self, diskNumber: usize, blockNumbers: Range<usize>) {
+    if blockNumbers.is_empty() {
+      return;
+    }
+
+    let _diskPlug = disk::getDisk(diskNumber).plug();
+
+    for blockNumber in blockNumbers {
+      let Some(index) = self.get(diskNumber, blockNumber, LookupMode::ReadAhead)
+      else {
+        continue;
+      };
+
+      // The cached block has just been recycled. So the SleepLock is free, and there's no request
+      // in flight.
+      let mut bcacheNode = self.lock(index, diskNumber, blockNumber);
+      self.submitRead(&mut bcacheNode);
 
-    match lruCacheGuard.get(diskNumber, blockNumber) {
-      // A cached block correspnding to the given disk and block number combination has been found.
-      Some((lruCacheNodeIndex, lruCacheNodeRefCount)) => BCacheNode {
-        index: lruCacheNodeIndex,
+      self.readAheads.fetch_add(1, Ordering::Relaxed);
+    }
+  }
 
-        refCount: lruCacheNodeRefCount,
+  #[inline]
+  fn isValid(&self, index: usize) -> bool {
+    self.getBuffers()[index].isValid.load(Ordering::Acquire)
+  }
 
-        diskNumber,
-        blockNumber,
+  // Looks up the cached block corresponding to the given disk and block numbers combination,
+  // recycling an unused cached block if there's none. Increments its refCount and returns its
+  // index.
+  // Returns None only in LookupMode::ReadAhead.
+  fn get(&self, diskNumber: usize, blockNumber: usize, lookupMode: LookupMode) -> Option<usize> {
+    let bucket = self.getBucket(diskNumber, blockNumber);
 
-        blockData: self.blockDataGuards[lruCacheNodeIndex].blockData.acquire(),
-      },
+    // Returns the index of the cached block, if the block is already cached.
+    let find = || {
+      let mut bucket = bucket.acquire();
+      let bufferMetadata = bucket.find(diskNumber, blockNumber)?;
 
-      // The block data corresponding to the given disk and block number combination isn't cached.
-      // So, we'll first find the least recently used (LRU) unused cached block (refCount = 0) and
-      // use that to cache the block data.
-      None => {
-        let (lruCacheNodeIndex, lruCacheNodeRefCount) = lruCacheGuard
-          .recycle(diskNumber, blockNumber)
-          .expect("No least recently used (LRU) unused cached block found to recycle");
+      if lookupMode == LookupMode::ReadAhead {
+        return Some(None);
+      }
 
-        // TODO : write the block data to the cache.
+      bufferMetadata.refCount += 1;
+      self.hits.fetch_add(1, Ordering::Relaxed);
 
-        self.blockDataGuards[lruCacheNodeIndex]
-          .isValid
-          .store(true, Ordering::Relaxed);
+      Some(Some(bufferMetadata.index))
+    };
 
-        BCacheNode {
-          index: lruCacheNodeIndex,
+    if let Some(index) = find() {
+      return index;
+    }
+
+    loop {
+      let evictionGuard = self.evictionLock.acquire();
 
-          refCount: lruCacheNodeRefCount,
+      // Some other process may have cached the block, while we were acquiring the eviction lock.
+      if let Some(index) = find() {
+        return index;
+      }
 
-          diskNumber,
-          blockNumber,
+      match self.findVictim() {
+        Victim::Clean(victimBucketIndex, index) => {
+          // The victim may have been looked up, since we found it.
+          let mut bufferMetadata = {
+            let mut victimBucket = self.getBuckets()[victimBucketIndex].acquire();
+
+            let Some(position) = victimBucket.buffers.iter().position(|bufferMetadata| {
+              bufferMetadata.index == index
+                && bufferMetadata.refCount == 0
+                && !bufferMetadata.hasPendingIO()
+            })
+            else {
+              continue;
+            };
+            victimBucket.buffers.swap_remove(position)
+          };
+
+          bufferMetadata.diskNumber = diskNumber;
+          bufferMetadata.blockNumber = blockNumber;
+          bufferMetadata.refCount = 1;
+
+          // The recycled cached block contains data of some other block.
+          self.getBuffers()[index]
+            .isValid
+            .store(false, Ordering::Release);
+
+          bucket.acquire().buffers.push(bufferMetadata);
+          drop(evictionGuard);
+
+          if lookupMode == LookupMode::Demand {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+          }
+          self.evictions.fetch_add(1, Ordering::Relaxed);
+          return Some(index);
+        }
 
-          blockData: self.blockDataGuards[lruCacheNodeIndex].blockData.acquire(),
+        // Read-ahead is only opportunistic.
+        _ if lookupMode == LookupMode::ReadAhead => return None,
+
+        // The SpinLocks must be released before acquiring the SleepLock (and doing disk I/O),
+        // since we may go to sleep.
+        Victim::Dirty(victimBucketIndex, index) => {
+          let victimIdentity = {
+            let mut victimBucket = self.getBuckets()[victimBucketIndex].acquire();
+            let bufferMetadata = victimBucket.findByIndex(index);
+
+            (bufferMetadata.refCount == 0).then(|| {
+              // So that the victim doesn't get recycled, while we're writing it back.
+              bufferMetadata.refCount += 1;
+              (bufferMetadata.diskNumber, bufferMetadata.blockNumber)
+            })
+          };
+          drop(evictionGuard);
+
+          if let Some((victimDiskNumber, victimBlockNumber)) = victimIdentity {
+            let mut victim = self.lock(index, victimDiskNumber, victimBlockNumber);
+            self.writeBack(&mut victim);
+            self.releaseInternalReference(victim);
+          }
         }
+
+        // A wakeup can get lost, if a cached block gets released while we're scanning the buckets.
+        // So we don't sleep for more than a tick.
+        Victim::None => drop(self.waitQueue.waitTimeout(evictionGuard, 1)),
       }
     }
   }
 
-  // Decrease the refCount (number of users) for the given cached block.
-  pub fn decreaseRefCount(&mut self, index: usize) {
-    self.lruCache.acquire().decreaseRefCount(index);
+  // Finds the least recently used (LRU) unused cached block, preferring the ones which don't need
+  // to be written back. Returns the bucket it's in, along with its index.
+  // NOTE : Must be invoked while holding the eviction lock.
+  fn findVictim(&self) -> Victim {
+    let mut cleanVictim: Option<(usize, usize, usize)> = None;
+    let mut dirtyVictim: Option<(usize, usize, usize)> = None;
+
+    for (bucketIndex, bucket) in self.getBuckets().iter().enumerate() {
+      let bucket = bucket.acquire();
+
+      for bufferMetadata in bucket.buffers.iter() {
+        if bufferMetadata.refCount != 0 {
+          continue;
+        }
+
+        let victim = match bufferMetadata.hasPendingIO() {
+          true => &mut dirtyVictim,
+          false => &mut cleanVictim,
+        };
+        if victim.map_or(true, |(.., lastUsedAt)| {
+          bufferMetadata.lastUsedAt < lastUsedAt
+        }) {
+          *victim = Some((bucketIndex, bufferMetadata.index, bufferMetadata.lastUsedAt));
+        }
+      }
+    }
+
+    match (cleanVictim, dirtyVictim) {
+      (Some((bucketIndex, index, _)), _) => Victim::Clean(bucketIndex, index),
+      (None, Some((bucketIndex, index, _))) => Victim::Dirty(bucketIndex, index),
+      (None, None) => Victim::None,
+    }
+  }
+
+  // Writes the given cached block to the disk right away (bwrite).
+  pub fn write(&self, bcacheNode: &mut BCacheNode) {
+    bcacheNode.markDirty();
+    self.writeBack(bcacheNode);
+  }
+
+  /*
+    Reads bytes from the given disk, starting at the given byte offset. Used by the File Systems
+    whose on disk structures needn't be aligned to blocks (like FAT32 and ext2).
+
+    The following byte level helpers go through the cached blocks, so writes are written back
+    lazily.
+  */
+  pub fn readBytes(&self, diskNumber: usize, offset: u64, buffer: &mut [u8]) {
+    let mut bytesRead = 0;
+
+    while bytesRead < buffer.len() {
+      let position = offset + bytesRead as u64;
+      let offsetInBlock = (position % BLOCK_SIZE as u64) as usize;
+      let chunkLength = (BLOCK_SIZE - offsetInBlock).min(buffer.len() - bytesRead);
+
+      let bcacheNode = self.read(diskNumber, (position / BLOCK_SIZE as u64) as usize);
+      buffer[bytesRead..bytesRead + chunkLength]
+        .copy_from_slice(&bcacheNode[offsetInBlock..offsetInBlock + chunkLength]);
+
+      bytesRead += chunkLength;
+    }
+  }
+
+  // Writes the given bytes to the given disk, starting at the given byte offset.
+  pub fn writeBytes(&self, diskNumber: usize, offset: u64, buffer: &[u8]) {
+    let mut bytesWritten = 0;
+
+    while bytesWritten < buffer.len() {
+      let position = offset + bytesWritten as u64;
+      let offsetInBlock = (position % BLOCK_SIZE as u64) as usize;
+      let chunkLength = (BLOCK_SIZE - offsetInBlock).min(buffer.len() - bytesWritten);
+
+      let mut bcacheNode = self.read(diskNumber, (position / BLOCK_SIZE as u64) as usize);
+      bcacheNode[offsetInBlock..offsetInBlock + chunkLength]
+        .copy_from_slice(&buffer[bytesWritten..bytesWritten + chunkLength]);
+      bcacheNode.markDirty();
+
+      bytesWritten += chunkLength;
+    }
+  }
+
+  // Zero fills the given byte range of the given disk.
+  pub fn zeroBytes(&self, diskNumber: usize, offset: u64, length: usize) {
+    const ZEROES: [u8; BLOCK_SIZE] = [0; BLOCK_SIZE];
+
+    let mut bytesZeroed = 0;
+    while bytesZeroed < length {
+      let chunkLength = (length - bytesZeroed).min(BLOCK_SIZE);
+      self.writeBytes(diskNumber, offset + bytesZeroed as u64, &ZEROES[..chunkLength]);
+
+      bytesZeroed += chunkLength;
+    }
+  }
+
+  // Decreases the refCount of the given cached block, marking it as the most recently used one.
+  // Wakes up the processes waiting for a cached block to recycle, if it has become unused.
+  fn release(&self, diskNumber: usize, blockNumber: usize, index: usize) {
+    self.decreaseRefCount(diskNumber, blockNumber, index, true);
+  }
+
+  // Releases a reference to the given cached block, which the BCache took itself (to write it back
+  // while evicting / syncing). That isn't a use of the cached block. So unlike dropping the
+  // BCacheNode, it doesn't make the cached block the most recently used one.
+  fn releaseInternalReference(&self, bcacheNode: BCacheNode) {
+    let mut bcacheNode = ManuallyDrop::new(bcacheNode);
+
+    // Release the SleepLock before decreasing the refCount.
+    unsafe { ManuallyDrop::drop(&mut bcacheNode.blockBuffer) };
+
+    self.decreaseRefCount(
+      bcacheNode.diskNumber,
+      bcacheNode.blockNumber,
+      bcacheNode.index,
+      false,
+    );
+  }
+
+  fn decreaseRefCount(
+    &self,
+    diskNumber: usize,
+    blockNumber: usize,
+    index: usize,
+    shouldMarkUsed: bool,
+  ) {
+    let hasBecomeUnused = {
+      let mut bucket = self.getBucket(diskNumber, blockNumber).acquire();
+      let bufferMetadata = bucket.findByIndex(index);
+
+      bufferMetadata.refCount -= 1;
+      if shouldMarkUsed {
+        bufferMetadata.lastUsedAt = self.lruClock.fetch_add(1, Ordering::Relaxed);
+      }
+
+      bufferMetadata.refCount == 0
+    };
+
+    if hasBecomeUnused {
+      self.waitQueue.notifyAll();
+    }
+  }
+
+  // Pins the given cached block, so that it doesn't get recycled even after being released. Used
+  // by the log, to keep the blocks of a transaction cached till they're installed.
+  pub fn pin(&self, bcacheNode: &BCacheNode) {
+    self.updateMetadata(bcacheNode, |bufferMetadata| bufferMetadata.refCount += 1);
+  }
+
+  pub fn unpin(&self, bcacheNode: &BCacheNode) {
+    self.release(
+      bcacheNode.diskNumber,
+      bcacheNode.blockNumber,
+      bcacheNode.index,
+    );
+  }
+
+  // Submits a write-back of the given cached block (if it's dirty) to the disk, and releases it
+  // without waiting for the write-back to finish. Whoever locks the cached block next, waits for
+  // it.
+  pub fn startWriteBack(&self, mut bcacheNode: BCacheNode) {
+    self.submitWriteBack(&mut bcacheNode);
+  }
+
+  /*
+    Writes back all the dirty cached blocks of the given disk, and then flushes the disk.
+
+    The write-backs are submitted asynchronously first, so that the disk can process them together.
+    Then we wait for each of them.
+  */
+  pub fn sync(&self, diskNumber: usize) {
+    for pass in [Self::submitWriteBack, Self::waitForRequest] {
+      for bucket in self.getBuckets() {
+        let blocksToWriteBack: Vec<(usize, usize)> = bucket
+          .acquire()
+          .buffers
+          .iter_mut()
+          .filter(|bufferMetadata| {
+            bufferMetadata.diskNumber == diskNumber && bufferMetadata.hasPendingIO()
+          })
+          .map(|bufferMetadata| {
+            // So that the cached block doesn't get recycled, while we're waiting for its SleepLock.
+            bufferMetadata.refCount += 1;
+            (bufferMetadata.index, bufferMetadata.blockNumber)
+          })
+          .collect();
+
+        for (index, blockNumber) in blocksToWriteBack {
+          let mut bcacheNode = self.lock(index, diskNumber, blockNumber);
+          pass(self, &mut bcacheNode);
+          self.releaseInternalReference(bcacheNode);
+        }
+      }
+    }
+
+    disk::getDisk(diskNumber)
+      .flush()
+      .expect("Failed flushing disk");
+  }
+
+  // Returns a snapshot of the statistics.
+  pub fn getStatistics(&self) -> BCacheStatistics {
+    BCacheStatistics {
+      buffersCount: self.getBuffers().len(),
+
+      hits: self.hits.load(Ordering::Relaxed),
+      misses: self.misses.load(Ordering::Relaxed),
+
+      evictions: self.evictions.load(Ordering::Relaxed),
+
+      writeBacks: self.writeBacks.load(Ordering::Relaxed),
+      readAheads: self.readAheads.load(Ordering::Relaxed),
+    }
+  }
+
+  pub fn dumpStatistics(&self) {
+    let statistics = self.getStatistics();
+
+    println!(
+      "BCache : blocks = {}, hits = {}, misses = {}, evictions = {}, write-backs = {}, read-aheads = {}",
+      statistics.buffersCount,
+      statistics.hits,
+      statistics.misses,
+      statistics.evictions,
+      statistics.writeBacks,
+      statistics.readAheads
+    );
+  }
+
+  // Locks the cached block at the given index, whose refCount has already been incremented.
+  // Waits for any in flight request of the cached block to finish.
+  fn lock(&self, index: usize, diskNumber: usize, blockNumber: usize) -> BCacheNode<'_> {
+    let blockBuffer = self.getBuffers()[index].blockBuffer.acquire();
+
+    let mut bcacheNode = BCacheNode {
+      bcache: self,
+
+      index,
+
+      diskNumber,
+      blockNumber,
+
+      blockBuffer: ManuallyDrop::new(blockBuffer),
+    };
+    self.waitForRequest(&mut bcacheNode);
+
+    bcacheNode
+  }
+
+  // Writes back the given cached block (if it's dirty), and waits for it.
+  fn writeBack(&self, bcacheNode: &mut BCacheNode) {
+    self.submitWriteBack(bcacheNode);
+    self.waitForRequest(bcacheNode);
+  }
+
+  // Submits a write-back of the given cached block to the disk, if it's dirty.
+  fn submitWriteBack(&self, bcacheNode: &mut BCacheNode) {
+    let isDirty = self.updateMetadata(bcacheNode, |bufferMetadata| {
+      let isDirty = bufferMetadata.isDirty;
+      bufferMetadata.isDirty = false;
+      isDirty
+    });
+    if !isDirty {
+      return;
+    }
+
+    self.writeBacks.fetch_add(1, Ordering::Relaxed);
+    self.submitRequest(bcacheNode, BlockOperation::Write);
+  }
+
+  // Submits a read of the given cached block from the disk.
+  fn submitRead(&self, bcacheNode: &mut BCacheNode) {
+    self.submitRequest(bcacheNode, BlockOperation::Read);
+  }
+
+  fn submitRequest(&self, bcacheNode: &mut BCacheNode, operation: BlockOperation) {
+    self.updateMetadata(bcacheNode, |bufferMetadata| {
+      bufferMetadata.isRequestInFlight = true
+    });
+
+    let disk = disk::getDisk(bcacheNode.diskNumber);
+
+    let blockBuffer = &mut **bcacheNode.blockBuffer;
+    let request = blockBuffer.inFlightRequest.insert(BlockRequest::new(
+      operation,
+      disk.toDeviceBlockNumber(bcacheNode.blockNumber),
+      blockBuffer.blockData.0.as_mut_ptr(),
+      BLOCK_SIZE,
+    ));
+
+    // The BlockRequest lives in the cached block, which isn't recycled till the request is waited
+    // for.
+    unsafe { disk.submit(request) };
+  }
+
+  // Waits for the in flight request (if any) of the given cached block to finish.
+  // If it's a read which succeeded, then the cached block becomes valid.
+  fn waitForRequest(&self, bcacheNode: &mut BCacheNode) {
+    let Some(request) = bcacheNode.blockBuffer.inFlightRequest.as_ref()
+    else {
+      return;
+    };
+
+    let result = disk::getDisk(bcacheNode.diskNumber).waitFor(request);
+    match request.operation {
+      BlockOperation::Read => {
+        // A failed read-ahead is retried when the block is actually read.
+        if result.is_ok() {
+          self.getBuffers()[bcacheNode.index]
+            .isValid
+            .store(true, Ordering::Release);
+        }
+      }
+
+      _ => result.expect("Failed writing back block to disk"),
+    }
+
+    bcacheNode.blockBuffer.inFlightRequest = None;
+    self.updateMetadata(bcacheNode, |bufferMetadata| {
+      bufferMetadata.isRequestInFlight = false
+    });
   }
 }
 
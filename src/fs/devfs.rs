@@ -0,0 +1,175 @@
+use {
+  super::{
+    device,
+    vfs::{
+      self, mount::FileSystemType, DeviceNumber, DirectoryEntry, FileType, FsError, Inode, Stat,
+    },
+  },
+  alloc::{sync::Arc, vec::Vec},
+  core::any::Any,
+};
+
+/*
+  devfs is a pseudo File System (mounted on /dev), having a device file for each registered
+  character device (like console, null, zero, random and ttyS0) and each attached disk (vda, vdb
+  and so on). Its contents are generated from the device registries whenever they're looked up, so
+  nothing is stored.
+
+  Opening a device file is handled by the VFS (see the device module), so devfs just provides the
+  device numbers.
+*/
+
+pub static FILE_SYSTEM_TYPE: FileSystemType = FileSystemType {
+  name: "devfs",
+  isDiskBased: false,
+  mount,
+};
+
+fn mount(_diskNumber: Option<usize>, options: &[u8]) -> Result<Arc<dyn vfs::FileSystem>, FsError> {
+  if !options.is_empty() {
+    return Err(FsError::InvalidArgument);
+  }
+
+  let info = Arc::new(DevFsInfo {
+    fileSystemID: vfs::allocateFileSystemID(),
+  });
+
+  Ok(Arc::new(DevFs {
+    root: DevInode::new(&info, Node::Root),
+  }))
+}
+
+struct DevFsInfo {
+  fileSystemID: usize,
+}
+
+struct DevFs {
+  root: Arc<DevInode>,
+}
+
+impl vfs::FileSystem for DevFs {
+  fn getTypeName(&self) -> &'static str {
+    FILE_SYSTEM_TYPE.name
+  }
+
+  fn getRootInode(&self) -> Result<Arc<dyn Inode>, FsError> {
+    Ok(self.root.clone())
+  }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum Node {
+  Root,
+  Device(FileType, DeviceNumber),
+}
+
+impl Node {
+  // A device file gets its inode number from its device number, which never collides with the
+  // root directory's (since major numbers start from 1).
+  fn getInodeNumber(&self) -> u64 {
+    match *self {
+      Self::Root => 1,
+      Self::Device(fileType, deviceNumber) => {
+        ((deviceNumber.major as u64) << 33)
+          | ((deviceNumber.minor as u64) << 1)
+          | (fileType == FileType::BlockDevice) as u64
+      }
+    }
+  }
+
+  fn getFileType(&self) -> FileType {
+    match *self {
+      Self::Root => FileType::Directory,
+      Self::Device(fileType, _) => fileType,
+    }
+  }
+}
+
+// Returns the entries in the root directory, other than "." and "..".
+fn getEntries() -> Vec<(Vec<u8>, Node)> {
+  device::getCharDevices()
+    .into_iter()
+    .map(|(name, deviceNumber)| {
+      (
+        name.to_vec(),
+        Node::Device(FileType::CharDevice, deviceNumber),
+      )
+    })
+    .chain(
+      device::getBlockDevices()
+        .into_iter()
+        .map(|(name, deviceNumber)| (name, Node::Device(FileType::BlockDevice, deviceNumber))),
+    )
+    .collect()
+}
+
+struct DevInode {
+  info: Arc<DevFsInfo>,
+  node: Node,
+}
+
+impl DevInode {
+  fn new(info: &Arc<DevFsInfo>, node: Node) -> Arc<Self> {
+    Arc::new(Self {
+      info: info.clone(),
+      node,
+    })
+  }
+}
+
+impl Inode for DevInode {
+  fn asAny(&self) -> &dyn Any {
+    self
+  }
+
+  fn getStat(&self) -> Result<Stat, FsError> {
+    let (linksCount, deviceNumber) = match self.node {
+      Node::Root => (2, DeviceNumber::default()),
+      Node::Device(_, deviceNumber) => (1, deviceNumber),
+    };
+
+    Ok(Stat {
+      fileSystemID: self.info.fileSystemID,
+      inodeNumber: self.node.getInodeNumber(),
+
+      fileType: self.node.getFileType(),
+      linksCount,
+
+      size: 0,
+
+      deviceNumber,
+    })
+  }
+
+  fn lookup(&self, name: &[u8]) -> Result<Arc<dyn Inode>, FsError> {
+    let node = getEntries()
+      .into_iter()
+      .find(|(entryName, _)| entryName == name)
+      .map(|(_, node)| node)
+      .ok_or(FsError::NotFound)?;
+
+    Ok(DevInode::new(&self.info, node))
+  }
+
+  fn readDirectory(&self, position: usize) -> Result<Option<(DirectoryEntry, usize)>, FsError> {
+    if self.node != Node::Root {
+      return Err(FsError::NotADirectory);
+    }
+
+    let directoryEntry = match position {
+      0 => Some((b".".to_vec(), Node::Root)),
+      1 => Some((b"..".to_vec(), Node::Root)),
+      _ => getEntries().into_iter().nth(position - 2),
+    };
+
+    Ok(directoryEntry.map(|(name, node)| {
+      (
+        DirectoryEntry {
+          inodeNumber: node.getInodeNumber(),
+          name,
+        },
+        position + 1,
+      )
+    }))
+  }
+}
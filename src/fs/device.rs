@@ -0,0 +1,254 @@
+use {
+  super::{
+    bcache::BCACHE,
+    disk,
+    vfs::{lookup::ResolvedPath, mount, DeviceNumber, File, FileType, FsError, PollEvents, Stat},
+    BLOCK_SIZE,
+  },
+  crate::locks::spinlock::SpinLock,
+  alloc::{collections::BTreeMap, sync::Arc, vec::Vec},
+};
+
+/*
+  Device files let processes use devices, the same way as regular files. A device file (created
+  using mknod, or provided by devfs) only stores a device number. Opening it gives a File which
+  forwards to the device, that the device number identifies.
+
+    (1) Character devices (like the UART or /dev/null) transfer a stream of bytes. Their drivers
+        implement the CharDevice trait, and register them with a (unique) device number.
+
+    (2) Block devices are the attached disks. Their contents are accessed through the buffer cache,
+        like the disk based File Systems do.
+
+  REFER : chapter 3 (Char Drivers) of Linux Device Drivers by Jonathan Corbet et al.
+*/
+
+pub trait CharDevice: Sync {
+  // Returns the number of bytes read, which (unless the buffer is empty) is 0 only at the end of
+  // the input. May block, till there's some input.
+  fn read(&self, buffer: &mut [u8]) -> Result<usize, FsError>;
+
+  // Returns the number of bytes written.
+  fn write(&self, buffer: &[u8]) -> Result<usize, FsError>;
+
+  // Performs a device specific operation. Returns a request specific value.
+  fn ioctl(&self, _request: usize, _argument: usize) -> Result<usize, FsError> {
+    Err(FsError::NotSupported)
+  }
+
+  // Returns whether a read / write would block right now.
+  fn poll(&self) -> PollEvents {
+    PollEvents::READABLE | PollEvents::WRITABLE
+  }
+}
+
+struct RegisteredCharDevice {
+  name: &'static [u8],
+  device: &'static dyn CharDevice,
+}
+
+static CHAR_DEVICES: SpinLock<BTreeMap<DeviceNumber, RegisteredCharDevice>> =
+  SpinLock::new(BTreeMap::new());
+
+// Registers the given character device, with the given (devfs) name and device number.
+pub fn registerCharDevice(
+  name: &'static [u8],
+  deviceNumber: DeviceNumber,
+  device: &'static dyn CharDevice,
+) -> Result<(), FsError> {
+  let mut charDevices = CHAR_DEVICES.acquire();
+
+  if charDevices.contains_key(&deviceNumber)
+    || charDevices
+      .values()
+      .any(|registeredCharDevice| registeredCharDevice.name == name)
+  {
+    return Err(FsError::AlreadyExists);
+  }
+
+  charDevices.insert(deviceNumber, RegisteredCharDevice { name, device });
+  Ok(())
+}
+
+// Returns the character device with the given device number (if registered).
+pub fn getCharDevice(deviceNumber: DeviceNumber) -> Option<&'static dyn CharDevice> {
+  CHAR_DEVICES
+    .acquire()
+    .get(&deviceNumber)
+    .map(|registeredCharDevice| registeredCharDevice.device)
+}
+
+// Returns the names and device numbers of the registered character devices.
+pub fn getCharDevices() -> Vec<(&'static [u8], DeviceNumber)> {
+  CHAR_DEVICES
+    .acquire()
+    .iter()
+    .map(|(deviceNumber, registeredCharDevice)| (registeredCharDevice.name, *deviceNumber))
+    .collect()
+}
+
+// Block devices share a major number (the one Linux commonly assigns to virtio disks), and the
+// minor number is the disk number.
+pub const BLOCK_DEVICE_MAJOR: u32 = 254;
+
+// Returns the names (vda, vdb and so on) and device numbers of the attached disks.
+pub fn getBlockDevices() -> Vec<(Vec<u8>, DeviceNumber)> {
+  (0..disk::getAttachedDisksCount())
+    .map(|diskNumber| {
+      (
+        mount::getDiskName(diskNumber),
+        DeviceNumber::new(BLOCK_DEVICE_MAJOR, diskNumber as u32),
+      )
+    })
+    .collect()
+}
+
+// Returns the disk number of the attached disk, with the given device number.
+fn getDiskNumber(deviceNumber: DeviceNumber) -> Option<usize> {
+  let diskNumber = deviceNumber.minor as usize;
+
+  match deviceNumber.major == BLOCK_DEVICE_MAJOR && diskNumber < disk::getAttachedDisksCount() {
+    true => Some(diskNumber),
+    false => None,
+  }
+}
+
+// Opens the given device file.
+// NOTE : The device file may refer to a device which doesn't exist, in which case this fails with
+// NotFound (ENXIO in Linux).
+pub fn open(path: ResolvedPath) -> Result<Arc<dyn File>, FsError> {
+  let stat = path.getInode().getStat()?;
+
+  match stat.fileType {
+    FileType::CharDevice => {
+      let device = getCharDevice(stat.deviceNumber).ok_or(FsError::NotFound)?;
+      Ok(Arc::new(CharDeviceFile { device, path }))
+    }
+
+    FileType::BlockDevice => {
+      let diskNumber = getDiskNumber(stat.deviceNumber).ok_or(FsError::NotFound)?;
+      Ok(Arc::new(BlockDeviceFile { diskNumber, path }))
+    }
+
+    _ => Err(FsError::InvalidArgument),
+  }
+}
+
+// An opened character device file.
+struct CharDeviceFile {
+  device: &'static dyn CharDevice,
+
+  // Keeps the mounted File System (having the device file) busy, while the File is open.
+  path: ResolvedPath,
+}
+
+impl File for CharDeviceFile {
+  fn read(&self, _offset: u64, buffer: &mut [u8]) -> Result<usize, FsError> {
+    self.device.read(buffer)
+  }
+
+  fn write(&self, _offset: u64, buffer: &[u8]) -> Result<usize, FsError> {
+    self.device.write(buffer)
+  }
+
+  // NOTE : Another reader may consume the input in between polling and reading, in which case the
+  // read still blocks.
+  fn readNonBlocking(&self, _offset: u64, buffer: &mut [u8]) -> Result<usize, FsError> {
+    match self.device.poll().contains(PollEvents::READABLE) {
+      true => self.device.read(buffer),
+      false => Err(FsError::WouldBlock),
+    }
+  }
+
+  fn writeNonBlocking(&self, _offset: u64, buffer: &[u8]) -> Result<usize, FsError> {
+    match self.device.poll().contains(PollEvents::WRITABLE) {
+      true => self.device.write(buffer),
+      false => Err(FsError::WouldBlock),
+    }
+  }
+
+  fn getStat(&self) -> Result<Stat, FsError> {
+    self.path.getInode().getStat()
+  }
+
+  fn isSeekable(&self) -> bool {
+    false
+  }
+
+  fn ioctl(&self, request: usize, argument: usize) -> Result<usize, FsError> {
+    self.device.ioctl(request, argument)
+  }
+
+  fn poll(&self) -> PollEvents {
+    self.device.poll()
+  }
+
+  fn getPath(&self) -> Option<&ResolvedPath> {
+    Some(&self.path)
+  }
+}
+
+/*
+  An opened block device file, through which the whole disk can be read and written.
+
+  NOTE : Writing to a disk which has a mounted File System, corrupts it.
+*/
+struct BlockDeviceFile {
+  diskNumber: usize,
+
+  // Keeps the mounted File System (having the device file) busy, while the File is open.
+  path: ResolvedPath,
+}
+
+impl BlockDeviceFile {
+  // Returns the size of the disk, in bytes.
+  fn getSize(&self) -> u64 {
+    (disk::getDisk(self.diskNumber).getBlocksCount() * BLOCK_SIZE) as u64
+  }
+
+  // Returns the length of the transfer, starting at the given offset, which fits in the disk.
+  fn clampLength(&self, offset: u64, length: usize) -> usize {
+    let size = self.getSize();
+
+    match offset < size {
+      true => (length as u64).min(size - offset) as usize,
+      false => 0,
+    }
+  }
+}
+
+impl File for BlockDeviceFile {
+  fn read(&self, offset: u64, buffer: &mut [u8]) -> Result<usize, FsError> {
+    let length = self.clampLength(offset, buffer.len());
+
+    BCACHE.readBytes(self.diskNumber, offset, &mut buffer[..length]);
+    Ok(length)
+  }
+
+  fn write(&self, offset: u64, buffer: &[u8]) -> Result<usize, FsError> {
+    let length = self.clampLength(offset, buffer.len());
+    if length == 0 && !buffer.is_empty() {
+      return Err(FsError::NoSpace);
+    }
+
+    BCACHE.writeBytes(self.diskNumber, offset, &buffer[..length]);
+    Ok(length)
+  }
+
+  // The size reported is the size of the disk.
+  fn getStat(&self) -> Result<Stat, FsError> {
+    Ok(Stat {
+      size: self.getSize(),
+      ..self.path.getInode().getStat()?
+    })
+  }
+
+  fn sync(&self) -> Result<(), FsError> {
+    BCACHE.sync(self.diskNumber);
+    Ok(())
+  }
+
+  fn getPath(&self) -> Option<&ResolvedPath> {
+    Some(&self.path)
+  }
+}
@@ -1,21 +1,417 @@
-use array_macro::array;
+use {
+  super::{iosched::IOScheduler, BLOCK_SIZE, MAX_DISKS},
+  crate::{
+    locks::{completion::Completion, spinlock::SpinLock},
+    process::process::getCurrentProcess,
+  },
+  array_macro::array,
+  core::{
+    hint::spin_loop,
+    ptr::{addr_of, addr_of_mut},
+  },
+};
 
-use super::MAX_DISKS;
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum BlockDeviceError {
+  // The device failed to process the request.
+  IO,
 
-pub struct Disk {}
+  // The device doesn't support the request (like writing to a read-only device).
+  Unsupported,
+
+  // The request goes beyond the end of the device.
+  OutOfRange,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum BlockOperation {
+  Read,
+  Write,
+
+  // Waits till the data written so far reaches stable storage.
+  Flush,
+}
+
+// A function which gets invoked (with the request and the given argument), once the request gets
+// completed. Used by the I/O scheduler, to find out when it can dispatch more requests.
+pub struct CompletionHook {
+  pub function: fn(&BlockRequest, usize),
+  pub argument: usize,
+}
+
+/*
+  An asynchronous request to a BlockDevice. The submitter hands it over to the device using
+  BlockDevice::submit( ), and later waits for it using BlockDevice::waitFor( ). The device
+  completes it (usually from an interrupt handler), once it has finished processing it.
+*/
+pub struct BlockRequest {
+  pub operation: BlockOperation,
+
+  // Starting block (in units of the device's block size).
+  pub blockNumber: usize,
+
+  // The data buffer, whose length must be a multiple of the device's block size. The device writes
+  // to it for a read request, and reads from it for a write request.
+  pub buffer: *mut u8,
+  pub length: usize,
+
+  result: SpinLock<Option<Result<(), BlockDeviceError>>>,
+  completionHook: SpinLock<Option<CompletionHook>>,
+  completion: Completion,
+}
+
+impl BlockRequest {
+  #[track_caller]
+  pub const fn new(
+    operation: BlockOperation,
+    blockNumber: usize,
+    buffer: *mut u8,
+    length: usize,
+  ) -> Self {
+    Self {
+      operation,
+      blockNumber,
+      buffer,
+      length,
+
+      result: SpinLock::new(None),
+      completionHook: SpinLock::new(None),
+      completion: Completion::new(),
+    }
+  }
+
+  // Invoked by the device, once it has finished processing the request.
+  // NOTE : The submitter may free the request as soon as this returns.
+  pub fn complete(&self, result: Result<(), BlockDeviceError>) {
+    *self.result.acquire() = Some(result);
+
+    // The hook runs before the submitter gets woken up, since the submitter may free the request.
+    let completionHook = self.completionHook.acquire().take();
+    if let Some(completionHook) = completionHook {
+      (completionHook.function)(self, completionHook.argument);
+    }
+
+    self.completion.completeAll();
+  }
+
+  // Sets the hook, which gets invoked once the request gets completed.
+  // NOTE : Must be set before the request is handed over to the device.
+  pub fn setCompletionHook(&self, completionHook: CompletionHook) {
+    *self.completionHook.acquire() = Some(completionHook);
+  }
+
+  pub fn isCompleted(&self) -> bool {
+    self.completion.isDone()
+  }
+
+  // Returns the result of the request, if it has been completed.
+  pub fn getResult(&self) -> Option<Result<(), BlockDeviceError>> {
+    *self.result.acquire()
+  }
+}
+
+/*
+  A device which stores data as a numbered sequence of fixed size blocks (like a virtio block
+  device or a RAM disk). Its block size need not match the file system's block size.
+
+  Requests are asynchronous : submit( ) only hands the request over to the device. The synchronous
+  read( ) / write( ) / flush( ) are built on top of it.
+*/
+pub trait BlockDevice: Sync {
+  // Size of a block of the device, in bytes.
+  fn getBlockSize(&self) -> usize;
+
+  // Number of blocks in the device.
+  fn getBlocksCount(&self) -> usize;
+
+  // Number of (possibly merged) requests, which can be handed over to the device at once without
+  // submit( ) having to block.
+  fn getQueueDepth(&self) -> usize {
+    1
+  }
+
+  // Maximum number of requests, which can be merged into a single request by submitMerged( ).
+  fn getMaxMergedRequestsCount(&self) -> usize {
+    1
+  }
+
+  /*
+    Hands over the given request to the device.
+
+    # Safety
+
+    The request (and its buffer) must stay alive and must not be moved, till the request gets
+    completed.
+  */
+  unsafe fn submit(&self, request: &BlockRequest);
+
+  /*
+    Hands over the given read / write requests to the device, as a single request. The requests
+    must be of the same kind, and for contiguous blocks (in the given order).
+
+    # Safety
+
+    Same as submit( ).
+  */
+  unsafe fn submitMerged(&self, requests: &[&BlockRequest]) {
+    for request in requests {
+      unsafe { self.submit(request) };
+    }
+  }
+
+  // Processes the finished requests, without waiting for an interrupt. Used while there's no
+  // process to put to sleep.
+  fn poll(&self) {}
+
+  // Waits till the given (submitted) request gets completed, and returns its result.
+  fn waitFor(&self, request: &BlockRequest) -> Result<(), BlockDeviceError> {
+    match getCurrentProcess() {
+      Some(_) => request.completion.waitForCompletion(),
+
+      None => {
+        while !request.isCompleted() {
+          self.poll();
+          spin_loop();
+        }
+      }
+    }
+
+    request
+      .getResult()
+      .expect("Completed BlockRequest doesn't have a result")
+  }
+
+  // Submits a request and waits for it.
+  fn execute(
+    &self,
+    operation: BlockOperation,
+    blockNumber: usize,
+    buffer: *mut u8,
+    length: usize,
+  ) -> Result<(), BlockDeviceError> {
+    let request = BlockRequest::new(operation, blockNumber, buffer, length);
+
+    unsafe { self.submit(&request) };
+    self.waitFor(&request)
+  }
+
+  fn read(&self, blockNumber: usize, buffer: &mut [u8]) -> Result<(), BlockDeviceError> {
+    self.execute(
+      BlockOperation::Read,
+      blockNumber,
+      buffer.as_mut_ptr(),
+      buffer.len(),
+    )
+  }
+
+  fn write(&self, blockNumber: usize, buffer: &[u8]) -> Result<(), BlockDeviceError> {
+    self.execute(
+      BlockOperation::Write,
+      blockNumber,
+      buffer.as_ptr() as *mut u8,
+      buffer.len(),
+    )
+  }
+
+  fn flush(&self) -> Result<(), BlockDeviceError> {
+    self.execute(BlockOperation::Flush, 0, core::ptr::null_mut(), 0)
+  }
+}
+
+/*
+  A disk, as seen by the file system : a numbered sequence of BLOCK_SIZE sized blocks, backed by a
+  BlockDevice.
+
+  All reads and writes done by the kernel against the disk are done in units of BLOCK_SIZE. These
+  get translated to the block size of the underlying device.
+
+  Requests go through the disk's I/O scheduler, which reorders and merges them before handing them
+  over to the device.
+*/
+pub struct Disk {
+  diskNumber: usize,
+  device: Option<&'static dyn BlockDevice>,
+  scheduler: IOScheduler,
+}
 
 impl Disk {
-	pub const fn new() -> Self {
-		Self {}
-	}
+  pub const fn new() -> Self {
+    Self {
+      diskNumber: 0,
+      device: None,
+      scheduler: IOScheduler::new(),
+    }
+  }
+
+  #[inline]
+  pub fn getDiskNumber(&self) -> usize {
+    self.diskNumber
+  }
+
+  #[inline]
+  pub fn getDevice(&self) -> &'static dyn BlockDevice {
+    self.device.expect("Disk isn't attached to any device")
+  }
+
+  #[inline]
+  pub fn getScheduler(&self) -> &IOScheduler {
+    &self.scheduler
+  }
+
+  // Number of device blocks in a file system block.
+  #[inline]
+  fn getDeviceBlocksPerBlock(&self) -> usize {
+    BLOCK_SIZE / self.getDevice().getBlockSize()
+  }
+
+  // Returns the number of (BLOCK_SIZE sized) blocks in the disk.
+  pub fn getBlocksCount(&self) -> usize {
+    self.getDevice().getBlocksCount() / self.getDeviceBlocksPerBlock()
+  }
+
+  // Returns the device block number, corresponding to the given file system block number.
+  #[inline]
+  pub fn toDeviceBlockNumber(&self, blockNumber: usize) -> usize {
+    blockNumber * self.getDeviceBlocksPerBlock()
+  }
+
+  /*
+    Hands over the given request (whose block number is a device block number) to the I/O
+    scheduler.
+
+    # Safety
+
+    The request (and its buffer) must stay alive and must not be moved, till the request gets
+    completed.
+  */
+  pub unsafe fn submit(&self, request: &BlockRequest) {
+    unsafe { self.scheduler.submit(self, request) };
+  }
+
+  // Waits till the given (submitted) request gets completed, and returns its result.
+  // NOTE : The invoker must not be holding a plug of the disk.
+  pub fn waitFor(&self, request: &BlockRequest) -> Result<(), BlockDeviceError> {
+    self.getDevice().waitFor(request)
+  }
+
+  // Plugs the disk : the requests submitted till the returned plug is dropped, are only queued up.
+  // This gives the I/O scheduler a chance to merge them.
+  pub fn plug(&self) -> DiskPlug<'_> {
+    self.scheduler.plug();
+    DiskPlug { disk: self }
+  }
+
+  // Submits a request and waits for it.
+  fn execute(
+    &self,
+    operation: BlockOperation,
+    blockNumber: usize,
+    buffer: *mut u8,
+    length: usize,
+  ) -> Result<(), BlockDeviceError> {
+    let request = BlockRequest::new(operation, blockNumber, buffer, length);
+
+    unsafe { self.submit(&request) };
+    self.waitFor(&request)
+  }
+
+  // Reads the given block from the disk, into the given buffer.
+  pub fn readBlock(
+    &self,
+    blockNumber: usize,
+    buffer: &mut [u8; BLOCK_SIZE],
+  ) -> Result<(), BlockDeviceError> {
+    self.execute(
+      BlockOperation::Read,
+      self.toDeviceBlockNumber(blockNumber),
+      buffer.as_mut_ptr(),
+      BLOCK_SIZE,
+    )
+  }
+
+  // Writes the given buffer to the given block in the disk.
+  pub fn writeBlock(
+    &self,
+    blockNumber: usize,
+    buffer: &[u8; BLOCK_SIZE],
+  ) -> Result<(), BlockDeviceError> {
+    self.execute(
+      BlockOperation::Write,
+      self.toDeviceBlockNumber(blockNumber),
+      buffer.as_ptr() as *mut u8,
+      BLOCK_SIZE,
+    )
+  }
+
+  pub fn flush(&self) -> Result<(), BlockDeviceError> {
+    self.execute(BlockOperation::Flush, 0, core::ptr::null_mut(), 0)
+  }
+}
+
+// Unplugs the disk when dropped, dispatching the queued up requests.
+pub struct DiskPlug<'a> {
+  disk: &'a Disk,
 }
 
-pub struct Disks([Disk; MAX_DISKS]);
+impl Drop for DiskPlug<'_> {
+  fn drop(&mut self) {
+    self.disk.scheduler.unplug(self.disk);
+  }
+}
+
+// The disks attached to the system, addressed by their disk numbers.
+pub struct Disks {
+  disks: [Disk; MAX_DISKS],
+  attachedDisksCount: usize,
+}
 
 impl Disks {
-	pub const fn new( ) -> Self {
-		Self(array![_ => Disk::new(); MAX_DISKS])
-	}
+  pub const fn new() -> Self {
+    Self {
+      disks: array![_ => Disk::new(); MAX_DISKS],
+      attachedDisksCount: 0,
+    }
+  }
 }
 
-pub static mut DISKS = Disks::new();
+// NOTE : Disks only get attached while the kernel is initializing. Afterwards, DISKS is only read.
+pub static mut DISKS: Disks = Disks::new();
+
+// Attaches the given device as the next disk. Returns the disk number, or None if all the disk
+// numbers are taken.
+// NOTE : Should only be invoked while the kernel is initializing.
+pub fn attach(device: &'static dyn BlockDevice) -> Option<usize> {
+  assert!(
+    BLOCK_SIZE % device.getBlockSize() == 0,
+    "Block size of the device doesn't divide the file system block size"
+  );
+
+  let disks = unsafe { &mut *addr_of_mut!(DISKS) };
+
+  let diskNumber = disks.attachedDisksCount;
+  if diskNumber == MAX_DISKS {
+    return None;
+  }
+
+  disks.disks[diskNumber].diskNumber = diskNumber;
+  disks.disks[diskNumber].device = Some(device);
+  disks.attachedDisksCount += 1;
+
+  Some(diskNumber)
+}
+
+// Returns the disk with the given disk number.
+pub fn getAttachedDisksCount() -> usize {
+  unsafe { (*addr_of!(DISKS)).attachedDisksCount }
+}
+
+pub fn getDisk(diskNumber: usize) -> &'static Disk {
+  let disks = unsafe { &*addr_of!(DISKS) };
+
+  assert!(
+    diskNumber < disks.attachedDisksCount,
+    "Disk {} isn't attached",
+    diskNumber
+  );
+  &disks.disks[diskNumber]
+}
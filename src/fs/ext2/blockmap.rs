@@ -0,0 +1,240 @@
+use {
+  super::{
+    filesystem::FileSystem,
+    layout::{DiskInode, DIRECT_BLOCKS_COUNT, SINGLY_INDIRECT_BLOCK_INDEX},
+  },
+  crate::fs::vfs::FsError,
+};
+
+/*
+  Maps the blocks of a file to the blocks of the File System, through the block pointers of its
+  inode :
+
+    (1) The first 12 blocks are referred to by the direct block pointers.
+
+    (2) The next ones by the singly, the doubly and the triply indirect block pointers. Each refers
+        to a tree of indirect blocks (of depth 1, 2 and 3), whose leaves refer to the blocks of the
+        file.
+
+  A block pointer is 0, if the block hasn't been allocated. So files can be sparse : a hole reads
+  as zeroes.
+*/
+
+// The block pointers leading to a block of the file : the index of the block pointer in the
+// inode, followed by the indices of the block pointers in the indirect blocks.
+struct BlockPath {
+  pointerIndex: usize,
+
+  indices: [usize; 3],
+  depth: usize,
+}
+
+fn getBlockPath(fileSystem: &FileSystem, fileBlock: u64) -> Result<BlockPath, FsError> {
+  let pointersPerBlock = (fileSystem.getBlockSize() / 4) as u64;
+
+  if fileBlock < DIRECT_BLOCKS_COUNT as u64 {
+    return Ok(BlockPath {
+      pointerIndex: fileBlock as usize,
+
+      indices: [0; 3],
+      depth: 0,
+    });
+  }
+
+  let mut firstFileBlock = DIRECT_BLOCKS_COUNT as u64;
+  for depth in 1..=3 {
+    let blocksCount = pointersPerBlock.pow(depth as u32);
+
+    if fileBlock < firstFileBlock + blocksCount {
+      let mut offset = fileBlock - firstFileBlock;
+
+      let mut indices = [0; 3];
+      for index in (0..depth).rev() {
+        indices[index] = (offset % pointersPerBlock) as usize;
+        offset /= pointersPerBlock;
+      }
+
+      return Ok(BlockPath {
+        pointerIndex: SINGLY_INDIRECT_BLOCK_INDEX + depth - 1,
+
+        indices,
+        depth,
+      });
+    }
+
+    firstFileBlock += blocksCount;
+  }
+
+  Err(FsError::FileTooLarge)
+}
+
+fn checkBlock(fileSystem: &FileSystem, block: u32) -> Result<u32, FsError> {
+  match block == 0 || fileSystem.isValidBlock(block) {
+    true => Ok(block),
+    false => {
+      println!(
+        "WARN : Invalid block pointer {} in disk {}",
+        block,
+        fileSystem.getDiskNumber()
+      );
+      Err(FsError::IOError)
+    }
+  }
+}
+
+// Returns the block of the File System, holding the given block of the file. Returns None for a
+// hole.
+pub fn getBlock(
+  fileSystem: &FileSystem,
+  diskInode: &DiskInode,
+  fileBlock: u64,
+) -> Result<Option<u32>, FsError> {
+  let path = getBlockPath(fileSystem, fileBlock)?;
+
+  let mut block = checkBlock(fileSystem, diskInode.blocks[path.pointerIndex])?;
+  for &index in path.indices[..path.depth].iter() {
+    if block == 0 {
+      break;
+    }
+    block = checkBlock(fileSystem, fileSystem.readBlockPointer(block, index))?;
+  }
+
+  Ok((block != 0).then_some(block))
+}
+
+/*
+  Returns the block of the File System, holding the given block of the file. The block (and the
+  indirect blocks leading to it) are allocated (zeroed) if required, preferably in the given block
+  group.
+
+  NOTE : The inode is modified, but not written.
+*/
+pub fn getOrAllocateBlock(
+  fileSystem: &FileSystem,
+  diskInode: &mut DiskInode,
+  fileBlock: u64,
+  goalGroup: u32,
+) -> Result<u32, FsError> {
+  let path = getBlockPath(fileSystem, fileBlock)?;
+  let sectorsPerBlock = (fileSystem.getBlockSize() / 512) as u32;
+
+  let mut block = checkBlock(fileSystem, diskInode.blocks[path.pointerIndex])?;
+  if block == 0 {
+    block = fileSystem.allocateBlock(goalGroup)?;
+    diskInode.blocks[path.pointerIndex] = block;
+    diskInode.sectorsCount += sectorsPerBlock;
+  }
+
+  for &index in path.indices[..path.depth].iter() {
+    let parentBlock = block;
+
+    block = checkBlock(fileSystem, fileSystem.readBlockPointer(parentBlock, index))?;
+    if block == 0 {
+      block = fileSystem.allocateBlock(goalGroup)?;
+      fileSystem.writeBlockPointer(parentBlock, index, block);
+      diskInode.sectorsCount += sectorsPerBlock;
+    }
+  }
+
+  Ok(block)
+}
+
+/*
+  Frees the blocks of the file starting from the given block, along with the indirect blocks which
+  no longer refer to any block.
+
+  NOTE : The inode is modified, but not written.
+*/
+pub fn freeBlocks(fileSystem: &FileSystem, diskInode: &mut DiskInode, firstFreedBlock: u64) {
+  let pointersPerBlock = (fileSystem.getBlockSize() / 4) as u64;
+
+  let mut freedBlocksCount = 0;
+  let mut firstFileBlock = 0;
+
+  for (pointerIndex, depth) in
+    (0..DIRECT_BLOCKS_COUNT).map(|index| (index, 0)).chain((1..=3).map(|depth: u32| {
+      (SINGLY_INDIRECT_BLOCK_INDEX + depth as usize - 1, depth)
+    }))
+  {
+    let block = diskInode.blocks[pointerIndex];
+    if block != 0
+      && freeSubtree(
+        fileSystem,
+        block,
+        depth,
+        firstFileBlock,
+        firstFreedBlock,
+        &mut freedBlocksCount,
+      )
+    {
+      diskInode.blocks[pointerIndex] = 0;
+    }
+
+    firstFileBlock += pointersPerBlock.pow(depth);
+  }
+
+  let sectorsPerBlock = (fileSystem.getBlockSize() / 512) as u32;
+  diskInode.sectorsCount = diskInode
+    .sectorsCount
+    .saturating_sub(freedBlocksCount * sectorsPerBlock);
+}
+
+/*
+  Frees the blocks of the file starting from the given block, in the tree of indirect blocks of
+  the given depth rooted at the given block (which covers the blocks of the file starting from the
+  given first block). A tree of depth 0 is a single block of the file.
+
+  Returns whether the whole tree has been freed, in which case the pointer to it must be cleared.
+*/
+fn freeSubtree(
+  fileSystem: &FileSystem,
+  block: u32,
+  depth: u32,
+  firstFileBlock: u64,
+  firstFreedBlock: u64,
+  freedBlocksCount: &mut u32,
+) -> bool {
+  let pointersPerBlock = (fileSystem.getBlockSize() / 4) as u64;
+  let blocksCount = pointersPerBlock.pow(depth);
+
+  // The whole tree is retained.
+  if firstFileBlock + blocksCount <= firstFreedBlock {
+    return false;
+  }
+  // A corrupted block pointer is leaked.
+  if !fileSystem.isValidBlock(block) {
+    return false;
+  }
+
+  let mut isEmpty = true;
+  if depth > 0 {
+    let childBlocksCount = blocksCount / pointersPerBlock;
+
+    for index in 0..pointersPerBlock as usize {
+      let childBlock = fileSystem.readBlockPointer(block, index);
+      if childBlock == 0 {
+        continue;
+      }
+
+      let isChildFreed = freeSubtree(
+        fileSystem,
+        childBlock,
+        depth - 1,
+        firstFileBlock + index as u64 * childBlocksCount,
+        firstFreedBlock,
+        freedBlocksCount,
+      );
+      match isChildFreed {
+        true if firstFileBlock < firstFreedBlock => fileSystem.writeBlockPointer(block, index, 0),
+        true => {}
+        false => isEmpty = false,
+      }
+    }
+  }
+
+  if isEmpty {
+    fileSystem.freeBlock(block);
+    *freedBlocksCount += 1;
+  }
+  isEmpty
+}
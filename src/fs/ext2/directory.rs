@@ -0,0 +1,417 @@
+use {
+  super::{
+    blockmap,
+    filesystem::FileSystem,
+    layout::{
+      DirectoryEntryHeader, DiskInode, DIRECTORY_ENTRY_HEADER_SIZE, INODE_FLAG_INDEX,
+      MAX_NAME_LENGTH,
+    },
+  },
+  crate::fs::vfs::FsError,
+  alloc::vec::Vec,
+};
+
+/*
+  A directory is made up of variable length entries, which never span blocks. The record length of
+  an entry extends it till the next entry (or the end of the block), so an entry can have unused
+  space after its name. An entry whose inode number is 0 is unused.
+
+  Entries are found by scanning the directory linearly. The hashed index (htree) some directories
+  have is ignored : it's only an optimization, and the index flag is cleared once the directory is
+  modified (so that ext3 / ext4 don't use the stale index).
+*/
+
+// An entry in a directory.
+pub struct DirectoryRecord {
+  pub inodeNumber: u32,
+  pub name: Vec<u8>,
+
+  // Offset of the entry, in the directory.
+  pub offset: u64,
+  pub recordLength: u16,
+
+  // Offset of the previous entry in the same block (None, if this is the first entry in the
+  // block).
+  pub previousOffset: Option<u64>,
+}
+
+// Reads the given block of the given directory.
+fn readBlock(
+  fileSystem: &FileSystem,
+  directory: &DiskInode,
+  fileBlock: u64,
+) -> Result<Vec<u8>, FsError> {
+  // Directories can't have holes.
+  let Some(block) = blockmap::getBlock(fileSystem, directory, fileBlock)?
+  else {
+    println!(
+      "WARN : Directory with a hole in disk {}",
+      fileSystem.getDiskNumber()
+    );
+    return Err(FsError::IOError);
+  };
+
+  let mut bytes = alloc::vec![0; fileSystem.getBlockSize()];
+  fileSystem.readBytes(fileSystem.getBlockOffset(block), &mut bytes);
+  Ok(bytes)
+}
+
+// Parses the entries (including the unused ones) in the given block of a directory.
+fn parseBlock(
+  fileSystem: &FileSystem,
+  bytes: &[u8],
+  fileBlock: u64,
+) -> Result<Vec<DirectoryRecord>, FsError> {
+  let blockOffset = fileBlock * bytes.len() as u64;
+
+  let mut records = Vec::new();
+  let mut offsetInBlock = 0;
+  let mut previousOffset = None;
+
+  while offsetInBlock < bytes.len() {
+    let header = DirectoryEntryHeader::decode(&bytes[offsetInBlock..]);
+
+    let recordLength = header.recordLength as usize;
+    let isValid = recordLength >= DIRECTORY_ENTRY_HEADER_SIZE
+      && recordLength % 4 == 0
+      && offsetInBlock + recordLength <= bytes.len()
+      && DIRECTORY_ENTRY_HEADER_SIZE + header.nameLength as usize <= recordLength;
+    if !isValid {
+      println!(
+        "WARN : Corrupted directory entry in disk {}",
+        fileSystem.getDiskNumber()
+      );
+      return Err(FsError::IOError);
+    }
+
+    let nameStart = offsetInBlock + DIRECTORY_ENTRY_HEADER_SIZE;
+    let offset = blockOffset + offsetInBlock as u64;
+
+    records.push(DirectoryRecord {
+      inodeNumber: header.inodeNumber,
+      name: bytes[nameStart..nameStart + header.nameLength as usize].to_vec(),
+
+      offset,
+      recordLength: header.recordLength,
+
+      previousOffset,
+    });
+
+    previousOffset = Some(offset);
+    offsetInBlock += recordLength;
+  }
+
+  Ok(records)
+}
+
+#[inline]
+fn getBlocksCount(fileSystem: &FileSystem, directory: &DiskInode) -> u64 {
+  directory.getSize() / fileSystem.getBlockSize() as u64
+}
+
+// Returns the first used entry, at or after the given offset in the directory.
+pub fn getNextRecord(
+  fileSystem: &FileSystem,
+  directory: &DiskInode,
+  offset: u64,
+) -> Result<Option<DirectoryRecord>, FsError> {
+  let blockSize = fileSystem.getBlockSize() as u64;
+
+  for fileBlock in offset / blockSize..getBlocksCount(fileSystem, directory) {
+    let bytes = readBlock(fileSystem, directory, fileBlock)?;
+
+    let record = parseBlock(fileSystem, &bytes, fileBlock)?
+      .into_iter()
+      .find(|record| record.inodeNumber != 0 && record.offset >= offset);
+    if record.is_some() {
+      return Ok(record);
+    }
+  }
+
+  Ok(None)
+}
+
+pub fn findRecord(
+  fileSystem: &FileSystem,
+  directory: &DiskInode,
+  name: &[u8],
+) -> Result<Option<DirectoryRecord>, FsError> {
+  for fileBlock in 0..getBlocksCount(fileSystem, directory) {
+    let bytes = readBlock(fileSystem, directory, fileBlock)?;
+
+    let record = parseBlock(fileSystem, &bytes, fileBlock)?
+      .into_iter()
+      .find(|record| record.inodeNumber != 0 && record.name == name);
+    if record.is_some() {
+      return Ok(record);
+    }
+  }
+
+  Ok(None)
+}
+
+// Returns the offset of the given offset in the directory, in the File System.
+fn getVolumeOffset(
+  fileSystem: &FileSystem,
+  directory: &DiskInode,
+  offset: u64,
+) -> Result<u64, FsError> {
+  let blockSize = fileSystem.getBlockSize() as u64;
+
+  match blockmap::getBlock(fileSystem, directory, offset / blockSize)? {
+    Some(block) => Ok(fileSystem.getBlockOffset(block) + offset % blockSize),
+    None => Err(FsError::IOError),
+  }
+}
+
+fn writeEntry(
+  fileSystem: &FileSystem,
+  directory: &DiskInode,
+  offset: u64,
+  header: &DirectoryEntryHeader,
+  name: &[u8],
+) -> Result<(), FsError> {
+  let volumeOffset = getVolumeOffset(fileSystem, directory, offset)?;
+
+  fileSystem.writeBytes(volumeOffset, &header.encode());
+  fileSystem.writeBytes(volumeOffset + DIRECTORY_ENTRY_HEADER_SIZE as u64, name);
+
+  Ok(())
+}
+
+fn writeHeader(
+  fileSystem: &FileSystem,
+  directory: &DiskInode,
+  offset: u64,
+  header: &DirectoryEntryHeader,
+) -> Result<(), FsError> {
+  let volumeOffset = getVolumeOffset(fileSystem, directory, offset)?;
+  fileSystem.writeBytes(volumeOffset, &header.encode());
+
+  Ok(())
+}
+
+/*
+  Adds an entry with the given name to the directory, referring to the given inode. The entry is
+  put into the unused space of an existing entry if possible, otherwise a block is appended to the
+  directory (preferably in the given block group).
+
+  NOTE : The directory inode is modified, but not written.
+*/
+pub fn addRecord(
+  fileSystem: &FileSystem,
+  directory: &mut DiskInode,
+  goalGroup: u32,
+  name: &[u8],
+  inodeNumber: u32,
+  fileType: u8,
+) -> Result<(), FsError> {
+  if name.len() > MAX_NAME_LENGTH {
+    return Err(FsError::NameTooLong);
+  }
+  let requiredLength = DirectoryEntryHeader::getMinRecordLength(name.len());
+
+  let mut freeSpace = None;
+  for fileBlock in 0..getBlocksCount(fileSystem, directory) {
+    let bytes = readBlock(fileSystem, directory, fileBlock)?;
+
+    for record in parseBlock(fileSystem, &bytes, fileBlock)? {
+      if record.inodeNumber != 0 && record.name == name {
+        return Err(FsError::AlreadyExists);
+      }
+
+      let usedLength = match record.inodeNumber {
+        0 => 0,
+        _ => DirectoryEntryHeader::getMinRecordLength(record.name.len()),
+      };
+      if freeSpace.is_none() && record.recordLength as usize - usedLength >= requiredLength {
+        freeSpace = Some((record, usedLength));
+      }
+    }
+  }
+
+  directory.flags &= !INODE_FLAG_INDEX;
+
+  let mut header = DirectoryEntryHeader {
+    inodeNumber,
+    recordLength: 0,
+    nameLength: name.len() as u8,
+    fileType,
+  };
+
+  match freeSpace {
+    // Reuse an unused entry.
+    Some((record, 0)) => {
+      header.recordLength = record.recordLength;
+      writeEntry(fileSystem, directory, record.offset, &header, name)
+    }
+
+    // Split the unused space off an entry.
+    Some((record, usedLength)) => {
+      let mut shrunkHeader = DirectoryEntryHeader {
+        inodeNumber: record.inodeNumber,
+        recordLength: usedLength as u16,
+        nameLength: record.name.len() as u8,
+        fileType: 0,
+      };
+      let mut bytes = [0; DIRECTORY_ENTRY_HEADER_SIZE];
+      fileSystem.readBytes(
+        getVolumeOffset(fileSystem, directory, record.offset)?,
+        &mut bytes,
+      );
+      shrunkHeader.fileType = DirectoryEntryHeader::decode(&bytes).fileType;
+
+      header.recordLength = record.recordLength - usedLength as u16;
+      writeEntry(
+        fileSystem,
+        directory,
+        record.offset + usedLength as u64,
+        &header,
+        name,
+      )?;
+      writeHeader(fileSystem, directory, record.offset, &shrunkHeader)
+    }
+
+    // Append a block, holding only the entry.
+    None => {
+      let blockSize = fileSystem.getBlockSize();
+      let offset = directory.getSize();
+
+      blockmap::getOrAllocateBlock(
+        fileSystem,
+        directory,
+        offset / blockSize as u64,
+        goalGroup,
+      )?;
+      directory.setSize(offset + blockSize as u64);
+
+      header.recordLength = blockSize as u16;
+      writeEntry(fileSystem, directory, offset, &header, name)
+    }
+  }
+}
+
+/*
+  Removes the given entry from the directory, by merging it into the previous entry in the block.
+  The first entry in a block is marked unused instead.
+
+  NOTE : The directory inode is modified, but not written.
+*/
+pub fn removeRecord(
+  fileSystem: &FileSystem,
+  directory: &mut DiskInode,
+  record: &DirectoryRecord,
+) -> Result<(), FsError> {
+  directory.flags &= !INODE_FLAG_INDEX;
+
+  let (offset, header) = match record.previousOffset {
+    Some(previousOffset) => {
+      let mut bytes = [0; DIRECTORY_ENTRY_HEADER_SIZE];
+      fileSystem.readBytes(
+        getVolumeOffset(fileSystem, directory, previousOffset)?,
+        &mut bytes,
+      );
+
+      let mut header = DirectoryEntryHeader::decode(&bytes);
+      header.recordLength += record.recordLength;
+      (previousOffset, header)
+    }
+
+    None => (
+      record.offset,
+      DirectoryEntryHeader {
+        inodeNumber: 0,
+        recordLength: record.recordLength,
+        nameLength: 0,
+        fileType: 0,
+      },
+    ),
+  };
+
+  writeHeader(fileSystem, directory, offset, &header)
+}
+
+// Points the given entry to the given inode (used while renaming over an existing name).
+pub fn replaceRecord(
+  fileSystem: &FileSystem,
+  directory: &DiskInode,
+  record: &DirectoryRecord,
+  inodeNumber: u32,
+  fileType: u8,
+) -> Result<(), FsError> {
+  let header = DirectoryEntryHeader {
+    inodeNumber,
+    recordLength: record.recordLength,
+    nameLength: record.name.len() as u8,
+    fileType,
+  };
+
+  writeHeader(fileSystem, directory, record.offset, &header)
+}
+
+/*
+  Writes the "." and ".." entries of a new directory, into its first block (which is allocated
+  preferably in the given block group).
+
+  NOTE : The directory inode is modified, but not written.
+*/
+pub fn initialize(
+  fileSystem: &FileSystem,
+  directory: &mut DiskInode,
+  goalGroup: u32,
+  inodeNumber: u32,
+  parentInodeNumber: u32,
+) -> Result<(), FsError> {
+  let blockSize = fileSystem.getBlockSize();
+  let fileType = fileSystem.getDirectoryEntryType(directory.mode);
+
+  blockmap::getOrAllocateBlock(fileSystem, directory, 0, goalGroup)?;
+  directory.setSize(blockSize as u64);
+
+  let dotRecordLength = DirectoryEntryHeader::getMinRecordLength(1);
+  let dotHeader = DirectoryEntryHeader {
+    inodeNumber,
+    recordLength: dotRecordLength as u16,
+    nameLength: 1,
+    fileType,
+  };
+  writeEntry(fileSystem, directory, 0, &dotHeader, b".")?;
+
+  let dotDotHeader = DirectoryEntryHeader {
+    inodeNumber: parentInodeNumber,
+    recordLength: (blockSize - dotRecordLength) as u16,
+    nameLength: 2,
+    fileType,
+  };
+  writeEntry(
+    fileSystem,
+    directory,
+    dotRecordLength as u64,
+    &dotDotHeader,
+    b"..",
+  )
+}
+
+// Points the ".." entry of the given directory to the given parent directory.
+pub fn setParent(
+  fileSystem: &FileSystem,
+  directory: &DiskInode,
+  parentInodeNumber: u32,
+) -> Result<(), FsError> {
+  let bytes = readBlock(fileSystem, directory, 0)?;
+
+  let Some(record) = parseBlock(fileSystem, &bytes, 0)?
+    .into_iter()
+    .find(|record| record.name == b"..")
+  else {
+    return Err(FsError::IOError);
+  };
+
+  replaceRecord(
+    fileSystem,
+    directory,
+    &record,
+    parentInodeNumber,
+    fileSystem.getDirectoryEntryType(directory.mode),
+  )
+}
@@ -0,0 +1,406 @@
+use {
+  super::layout::{
+    getDirectoryEntryType, readU32, DiskInode, GroupDescriptor, SuperBlock,
+    DIRECTORY_ENTRY_TYPE_UNKNOWN, FEATURE_INCOMPAT_FILETYPE, FEATURE_RO_COMPAT_LARGE_FILE,
+    GROUP_DESCRIPTOR_SIZE, INODE_SIZE, STATE_VALID, SUPERBLOCK_OFFSET, SUPERBLOCK_SIZE,
+  },
+  crate::{
+    fs::{bcache::BCACHE, disk, vfs::FsError, BLOCK_SIZE},
+    locks::sleeplock::SleepLock,
+  },
+  alloc::vec::Vec,
+};
+
+/*
+  A mounted ext2 File System. The File System starts at the beginning of the disk (partitioned
+  disks aren't supported), and is accessed through the buffer cache. Since the ext2 block size can
+  be larger than the buffer cache's, the File System is addressed in bytes.
+
+  NOTE : ext2 isn't crash consistent. The File System is marked as not cleanly unmounted while it's
+  mounted read-write, so that e2fsck checks it if the kernel crashes.
+*/
+pub struct FileSystem {
+  diskNumber: usize,
+
+  // The superblock, as read while mounting. Only its geometry is used : the counts are kept by the
+  // allocator.
+  superBlock: SuperBlock,
+  isReadOnly: bool,
+
+  allocator: SleepLock<Allocator>,
+}
+
+// Serializes block and inode allocation, and keeps the free counts.
+struct Allocator {
+  superBlock: SuperBlock,
+  groups: Vec<GroupDescriptor>,
+}
+
+impl FileSystem {
+  // Reads the superblock and the group descriptors of the File System in the given disk.
+  pub fn mount(diskNumber: usize, isReadOnly: bool) -> Result<Self, FsError> {
+    let mut superBlockBytes = [0; SUPERBLOCK_SIZE];
+    BCACHE.readBytes(diskNumber, SUPERBLOCK_OFFSET, &mut superBlockBytes);
+
+    let mut superBlock = SuperBlock::parse(&superBlockBytes)?;
+    let size = superBlock.blocksCount as u64 * superBlock.getBlockSize() as u64;
+    if size > (disk::getDisk(diskNumber).getBlocksCount() * BLOCK_SIZE) as u64 {
+      return Err(FsError::InvalidSuperBlock);
+    }
+
+    let groups = (0..superBlock.getGroupsCount())
+      .map(|group| {
+        let mut bytes = [0; GROUP_DESCRIPTOR_SIZE];
+        BCACHE.readBytes(
+          diskNumber,
+          superBlock.getGroupDescriptorsOffset() + (group as usize * GROUP_DESCRIPTOR_SIZE) as u64,
+          &mut bytes,
+        );
+        GroupDescriptor::decode(&bytes)
+      })
+      .collect();
+
+    let isReadOnly = isReadOnly || superBlock.requiresReadOnly();
+    if superBlock.state != STATE_VALID {
+      println!(
+        "WARN : The ext2 File System in disk {} wasn't cleanly unmounted, it should be checked using e2fsck",
+        diskNumber
+      );
+    }
+
+    // While it's mounted read-write, the File System is marked as not cleanly unmounted.
+    if !isReadOnly {
+      superBlock.state &= !STATE_VALID;
+    }
+
+    let fileSystem = Self {
+      diskNumber,
+
+      superBlock,
+      isReadOnly,
+
+      allocator: SleepLock::new(Allocator {
+        superBlock,
+        groups,
+      }),
+    };
+
+    if !isReadOnly {
+      fileSystem.writeSuperBlock(&superBlock);
+    }
+
+    Ok(fileSystem)
+  }
+
+  #[inline]
+  pub fn getDiskNumber(&self) -> usize {
+    self.diskNumber
+  }
+
+  #[inline]
+  pub fn getSuperBlock(&self) -> &SuperBlock {
+    &self.superBlock
+  }
+
+  #[inline]
+  pub fn getBlockSize(&self) -> usize {
+    self.superBlock.getBlockSize()
+  }
+
+  #[inline]
+  pub fn isReadOnly(&self) -> bool {
+    self.isReadOnly
+  }
+
+  #[inline]
+  pub fn readBytes(&self, offset: u64, buffer: &mut [u8]) {
+    BCACHE.readBytes(self.diskNumber, offset, buffer);
+  }
+
+  #[inline]
+  pub fn writeBytes(&self, offset: u64, buffer: &[u8]) {
+    BCACHE.writeBytes(self.diskNumber, offset, buffer);
+  }
+
+  #[inline]
+  pub fn zeroBytes(&self, offset: u64, length: usize) {
+    BCACHE.zeroBytes(self.diskNumber, offset, length);
+  }
+
+  #[inline]
+  pub fn getBlockOffset(&self, block: u32) -> u64 {
+    block as u64 * self.getBlockSize() as u64
+  }
+
+  #[inline]
+  pub fn isValidBlock(&self, block: u32) -> bool {
+    (self.superBlock.firstDataBlock..self.superBlock.blocksCount).contains(&block)
+  }
+
+  // Returns the type to store in a directory entry, for an inode with the given mode.
+  pub fn getDirectoryEntryType(&self, mode: u16) -> u8 {
+    match self.superBlock.hasFeatureIncompat(FEATURE_INCOMPAT_FILETYPE) {
+      true => getDirectoryEntryType(mode),
+      false => DIRECTORY_ENTRY_TYPE_UNKNOWN,
+    }
+  }
+
+  #[inline]
+  pub fn getGroupOfInode(&self, inodeNumber: u32) -> u32 {
+    (inodeNumber - 1) / self.superBlock.inodesPerGroup
+  }
+
+  // Returns the maximum size of a regular file. Files can grow past 2 GB (by enabling the
+  // large_file feature), unless the File System is of revision 0.
+  pub fn getMaxFileSize(&self) -> u64 {
+    self
+      .superBlock
+      .getMaxFileSize(self.superBlock.revisionLevel > 0)
+  }
+
+  // Enables the large_file feature (if it isn't already), once a regular file grows past 2 GB.
+  pub fn enableLargeFiles(&self) {
+    let mut allocator = self.allocator.acquire();
+
+    if !allocator
+      .superBlock
+      .hasFeatureRoCompat(FEATURE_RO_COMPAT_LARGE_FILE)
+    {
+      allocator.superBlock.featureRoCompat |= FEATURE_RO_COMPAT_LARGE_FILE;
+      self.writeSuperBlock(&allocator.superBlock);
+    }
+  }
+
+  fn getInodeOffset(&self, inodeNumber: u32) -> Result<u64, FsError> {
+    if !(1..=self.superBlock.inodesCount).contains(&inodeNumber) {
+      println!(
+        "WARN : Invalid inode number {} in disk {}",
+        inodeNumber, self.diskNumber
+      );
+      return Err(FsError::IOError);
+    }
+
+    let group = self.getGroupOfInode(inodeNumber);
+    let index = (inodeNumber - 1) % self.superBlock.inodesPerGroup;
+    let inodeTable = self.allocator.acquire().groups[group as usize].inodeTable;
+
+    Ok(self.getBlockOffset(inodeTable) + index as u64 * self.superBlock.inodeSize as u64)
+  }
+
+  pub fn readInode(&self, inodeNumber: u32) -> Result<DiskInode, FsError> {
+    let mut bytes = [0; INODE_SIZE];
+    self.readBytes(self.getInodeOffset(inodeNumber)?, &mut bytes);
+
+    Ok(DiskInode::decode(&bytes))
+  }
+
+  // The fields the driver doesn't know about are preserved.
+  pub fn writeInode(&self, inodeNumber: u32, diskInode: &DiskInode) -> Result<(), FsError> {
+    let offset = self.getInodeOffset(inodeNumber)?;
+
+    let mut bytes = [0; INODE_SIZE];
+    self.readBytes(offset, &mut bytes);
+    diskInode.encode(&mut bytes);
+    self.writeBytes(offset, &bytes);
+
+    Ok(())
+  }
+
+  // Writes a newly allocated inode, clearing whatever a previously freed inode left behind.
+  pub fn initializeInode(&self, inodeNumber: u32, diskInode: &DiskInode) -> Result<(), FsError> {
+    let offset = self.getInodeOffset(inodeNumber)?;
+    self.zeroBytes(offset, self.superBlock.inodeSize as usize);
+
+    self.writeInode(inodeNumber, diskInode)
+  }
+
+  // Finds a clear bit (from the given start) among the given number of bits in the given bitmap
+  // block, and sets it. Returns the index of the bit.
+  fn setFirstClearBit(&self, bitmapBlock: u32, start: u32, bitsCount: u32) -> Option<u32> {
+    let offset = self.getBlockOffset(bitmapBlock);
+
+    let mut bitmap = alloc::vec![0; bitsCount.div_ceil(8) as usize];
+    self.readBytes(offset, &mut bitmap);
+
+    let index = (start..bitsCount).find(|&index| bitmap[index as usize / 8] & (1 << (index % 8)) == 0)?;
+
+    let byteIndex = index as usize / 8;
+    self.writeBytes(
+      offset + byteIndex as u64,
+      &[bitmap[byteIndex] | (1 << (index % 8))],
+    );
+    Some(index)
+  }
+
+  // Clears the given bit in the given bitmap block. Returns whether it was set.
+  fn clearBit(&self, bitmapBlock: u32, index: u32) -> bool {
+    let offset = self.getBlockOffset(bitmapBlock) + (index / 8) as u64;
+
+    let mut byte = [0];
+    self.readBytes(offset, &mut byte);
+    self.writeBytes(offset, &[byte[0] & !(1 << (index % 8))]);
+
+    byte[0] & (1 << (index % 8)) != 0
+  }
+
+  /*
+    Allocates a zeroed block, preferably in the given block group (so that the blocks of a file are
+    close to its inode). Returns the block number.
+  */
+  pub fn allocateBlock(&self, goalGroup: u32) -> Result<u32, FsError> {
+    let block = {
+      let mut allocator = self.allocator.acquire();
+      let groupsCount = self.superBlock.getGroupsCount();
+
+      (0..groupsCount)
+        .map(|index| (goalGroup + index) % groupsCount)
+        .find_map(|group| {
+          let descriptor = &mut allocator.groups[group as usize];
+          if descriptor.freeBlocksCount == 0 {
+            return None;
+          }
+
+          let index = self.setFirstClearBit(
+            descriptor.blockBitmap,
+            0,
+            self.superBlock.getGroupBlocksCount(group),
+          )?;
+          descriptor.freeBlocksCount -= 1;
+          self.writeGroupDescriptor(group, descriptor);
+
+          allocator.superBlock.freeBlocksCount -= 1;
+          Some(self.superBlock.firstDataBlock + group * self.superBlock.blocksPerGroup + index)
+        })
+        .ok_or(FsError::NoSpace)?
+    };
+
+    self.zeroBytes(self.getBlockOffset(block), self.getBlockSize());
+    Ok(block)
+  }
+
+  pub fn freeBlock(&self, block: u32) {
+    let mut allocator = self.allocator.acquire();
+
+    let group = (block - self.superBlock.firstDataBlock) / self.superBlock.blocksPerGroup;
+    let index = (block - self.superBlock.firstDataBlock) % self.superBlock.blocksPerGroup;
+
+    let descriptor = &mut allocator.groups[group as usize];
+    if !self.clearBit(descriptor.blockBitmap, index) {
+      println!(
+        "WARN : Freeing free block {} in disk {}",
+        block, self.diskNumber
+      );
+      return;
+    }
+    descriptor.freeBlocksCount += 1;
+    self.writeGroupDescriptor(group, descriptor);
+
+    allocator.superBlock.freeBlocksCount += 1;
+  }
+
+  // Allocates an inode, preferably in the given block group. Returns the inode number.
+  pub fn allocateInode(&self, goalGroup: u32, isDirectory: bool) -> Result<u32, FsError> {
+    let mut allocator = self.allocator.acquire();
+    let groupsCount = self.superBlock.getGroupsCount();
+    let inodesPerGroup = self.superBlock.inodesPerGroup;
+
+    (0..groupsCount)
+      .map(|index| (goalGroup + index) % groupsCount)
+      .find_map(|group| {
+        let descriptor = &mut allocator.groups[group as usize];
+        if descriptor.freeInodesCount == 0 {
+          return None;
+        }
+
+        // The inodes before the first usable one are reserved.
+        let start = (self.superBlock.firstInode - 1).saturating_sub(group * inodesPerGroup);
+        let index = self.setFirstClearBit(descriptor.inodeBitmap, start, inodesPerGroup)?;
+
+        descriptor.freeInodesCount -= 1;
+        if isDirectory {
+          descriptor.usedDirectoriesCount += 1;
+        }
+        self.writeGroupDescriptor(group, descriptor);
+
+        allocator.superBlock.freeInodesCount -= 1;
+        Some(group * inodesPerGroup + index + 1)
+      })
+      .ok_or(FsError::NoInodes)
+  }
+
+  pub fn freeInode(&self, inodeNumber: u32, isDirectory: bool) {
+    let mut allocator = self.allocator.acquire();
+
+    let group = self.getGroupOfInode(inodeNumber);
+    let index = (inodeNumber - 1) % self.superBlock.inodesPerGroup;
+
+    let descriptor = &mut allocator.groups[group as usize];
+    if !self.clearBit(descriptor.inodeBitmap, index) {
+      println!(
+        "WARN : Freeing free inode {} in disk {}",
+        inodeNumber, self.diskNumber
+      );
+      return;
+    }
+    descriptor.freeInodesCount += 1;
+    if isDirectory {
+      descriptor.usedDirectoriesCount -= 1;
+    }
+    self.writeGroupDescriptor(group, descriptor);
+
+    allocator.superBlock.freeInodesCount += 1;
+  }
+
+  // Reads the block pointer with the given index, in the given indirect block.
+  pub fn readBlockPointer(&self, block: u32, index: usize) -> u32 {
+    let mut bytes = [0; 4];
+    self.readBytes(self.getBlockOffset(block) + index as u64 * 4, &mut bytes);
+
+    readU32(&bytes, 0)
+  }
+
+  pub fn writeBlockPointer(&self, block: u32, index: usize, value: u32) {
+    self.writeBytes(
+      self.getBlockOffset(block) + index as u64 * 4,
+      &value.to_le_bytes(),
+    );
+  }
+
+  // NOTE : The backups of the group descriptors aren't updated (like Linux does), since e2fsck
+  // recomputes the counts anyway.
+  fn writeGroupDescriptor(&self, group: u32, descriptor: &GroupDescriptor) {
+    let (offset, encoded) = descriptor.encodeCounts();
+
+    self.writeBytes(
+      self.superBlock.getGroupDescriptorsOffset()
+        + (group as usize * GROUP_DESCRIPTOR_SIZE + offset) as u64,
+      &encoded,
+    );
+  }
+
+  fn writeSuperBlock(&self, superBlock: &SuperBlock) {
+    let mut bytes = [0; SUPERBLOCK_SIZE];
+    self.readBytes(SUPERBLOCK_OFFSET, &mut bytes);
+    superBlock.encode(&mut bytes);
+    self.writeBytes(SUPERBLOCK_OFFSET, &bytes);
+  }
+
+  // Writes the free counts to the superblock, and then writes back all the modified blocks.
+  pub fn sync(&self) {
+    if !self.isReadOnly {
+      self.writeSuperBlock(&self.allocator.acquire().superBlock);
+    }
+
+    BCACHE.sync(self.diskNumber);
+  }
+
+  // Marks the File System as cleanly unmounted, and syncs it.
+  pub fn unmount(&self) {
+    if !self.isReadOnly {
+      let mut allocator = self.allocator.acquire();
+      allocator.superBlock.state |= STATE_VALID;
+    }
+
+    self.sync();
+  }
+}
@@ -0,0 +1,553 @@
+use {crate::fs::vfs::FsError, alloc::vec::Vec};
+
+/*
+  On disk structures of ext2 :
+
+    [ boot block | block group 0 | block group 1 | ... ]
+
+  Each block group is laid out as :
+
+    [ superblock (backup) | group descriptors (backup) | block bitmap | inode bitmap | inode table |
+      data blocks ]
+
+  The superblock always starts at byte offset 1024. With the sparse_super feature, only some of the
+  block groups hold backups of the superblock and the group descriptors.
+
+  An inode refers to the data blocks of the file using 12 direct block numbers, followed by a
+  singly, a doubly and a triply indirect block number. A directory is a file made up of variable
+  length entries, which never span blocks.
+
+  All the fields are little endian.
+
+  REFER : The Second Extended File System by Dave Poirier (https://www.nongnu.org/ext2-doc).
+*/
+
+pub const SUPERBLOCK_OFFSET: u64 = 1024;
+pub const SUPERBLOCK_SIZE: usize = 1024;
+
+const EXT2_MAGIC: u16 = 0xef53;
+
+// Set in the state field of the superblock, once the File System is cleanly unmounted.
+pub const STATE_VALID: u16 = 1;
+
+pub const ROOT_INODE_NUMBER: u32 = 2;
+
+// Incompatible features : the File System can't be mounted, if any unknown one is enabled.
+pub const FEATURE_INCOMPAT_FILETYPE: u32 = 0x0002;
+const SUPPORTED_INCOMPAT_FEATURES: u32 = FEATURE_INCOMPAT_FILETYPE;
+
+// Read only compatible features : the File System can only be mounted read only, if any unknown
+// one is enabled.
+pub const FEATURE_RO_COMPAT_SPARSE_SUPER: u32 = 0x0001;
+pub const FEATURE_RO_COMPAT_LARGE_FILE: u32 = 0x0002;
+const SUPPORTED_RO_COMPAT_FEATURES: u32 =
+  FEATURE_RO_COMPAT_SPARSE_SUPER | FEATURE_RO_COMPAT_LARGE_FILE;
+
+pub const GROUP_DESCRIPTOR_SIZE: usize = 32;
+
+// The part of an inode, which is common across revisions. Revision 1 inodes can be larger.
+pub const INODE_SIZE: usize = 128;
+
+pub const DIRECT_BLOCKS_COUNT: usize = 12;
+pub const SINGLY_INDIRECT_BLOCK_INDEX: usize = 12;
+pub const BLOCK_POINTERS_COUNT: usize = 15;
+
+// The target of a fast symbolic link is stored in the block pointers, instead of a data block.
+pub const MAX_FAST_SYMLINK_LENGTH: usize = BLOCK_POINTERS_COUNT * 4 - 1;
+
+// File types and permissions in the mode of an inode.
+pub const MODE_TYPE_MASK: u16 = 0xf000;
+pub const MODE_SOCKET: u16 = 0xc000;
+pub const MODE_SYMLINK: u16 = 0xa000;
+pub const MODE_REGULAR: u16 = 0x8000;
+pub const MODE_BLOCK_DEVICE: u16 = 0x6000;
+pub const MODE_DIRECTORY: u16 = 0x4000;
+pub const MODE_CHAR_DEVICE: u16 = 0x2000;
+pub const MODE_FIFO: u16 = 0x1000;
+
+// Permissions given to the inodes the kernel creates (since it doesn't have users yet).
+pub const DEFAULT_DIRECTORY_PERMISSIONS: u16 = 0o755;
+pub const DEFAULT_FILE_PERMISSIONS: u16 = 0o644;
+pub const DEFAULT_SYMLINK_PERMISSIONS: u16 = 0o777;
+
+// Set on a directory, which has a hashed index (htree) of its entries.
+pub const INODE_FLAG_INDEX: u32 = 0x1000;
+
+// File types stored in directory entries (with the filetype feature).
+pub const DIRECTORY_ENTRY_TYPE_UNKNOWN: u8 = 0;
+pub const DIRECTORY_ENTRY_TYPE_REGULAR: u8 = 1;
+pub const DIRECTORY_ENTRY_TYPE_DIRECTORY: u8 = 2;
+pub const DIRECTORY_ENTRY_TYPE_CHAR_DEVICE: u8 = 3;
+pub const DIRECTORY_ENTRY_TYPE_BLOCK_DEVICE: u8 = 4;
+pub const DIRECTORY_ENTRY_TYPE_FIFO: u8 = 5;
+pub const DIRECTORY_ENTRY_TYPE_SOCKET: u8 = 6;
+pub const DIRECTORY_ENTRY_TYPE_SYMLINK: u8 = 7;
+
+pub const DIRECTORY_ENTRY_HEADER_SIZE: usize = 8;
+pub const MAX_NAME_LENGTH: usize = 255;
+
+#[inline]
+pub fn readU16(bytes: &[u8], offset: usize) -> u16 {
+  u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+#[inline]
+pub fn readU32(bytes: &[u8], offset: usize) -> u32 {
+  u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[inline]
+pub fn writeU16(bytes: &mut [u8], offset: usize, value: u16) {
+  bytes[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+#[inline]
+pub fn writeU32(bytes: &mut [u8], offset: usize, value: u32) {
+  bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+// The fields of the superblock, which the driver uses.
+#[derive(Debug, Copy, Clone)]
+pub struct SuperBlock {
+  pub inodesCount: u32,
+  pub blocksCount: u32,
+  pub freeBlocksCount: u32,
+  pub freeInodesCount: u32,
+  pub firstDataBlock: u32,
+
+  // The block size is 1024 << logBlockSize.
+  pub logBlockSize: u32,
+
+  pub blocksPerGroup: u32,
+  pub inodesPerGroup: u32,
+
+  pub state: u16,
+
+  pub revisionLevel: u32,
+  // The first inode usable for files (the ones before it are reserved).
+  pub firstInode: u32,
+  pub inodeSize: u16,
+
+  pub featureCompat: u32,
+  pub featureIncompat: u32,
+  pub featureRoCompat: u32,
+}
+
+impl SuperBlock {
+  const FREE_BLOCKS_COUNT_OFFSET: usize = 12;
+  const FREE_INODES_COUNT_OFFSET: usize = 16;
+  const STATE_OFFSET: usize = 58;
+  const FEATURE_RO_COMPAT_OFFSET: usize = 100;
+
+  // Decodes and validates the superblock. Fails with NotSupported, if the File System uses
+  // incompatible features the driver doesn't know about.
+  pub fn parse(bytes: &[u8; SUPERBLOCK_SIZE]) -> Result<Self, FsError> {
+    if readU16(bytes, 56) != EXT2_MAGIC {
+      return Err(FsError::InvalidSuperBlock);
+    }
+
+    let revisionLevel = readU32(bytes, 76);
+    let (firstInode, inodeSize, featureCompat, featureIncompat, featureRoCompat) =
+      match revisionLevel {
+        0 => (11, INODE_SIZE as u16, 0, 0, 0),
+        _ => (
+          readU32(bytes, 84),
+          readU16(bytes, 88),
+          readU32(bytes, 92),
+          readU32(bytes, 96),
+          readU32(bytes, Self::FEATURE_RO_COMPAT_OFFSET),
+        ),
+      };
+
+    let superBlock = Self {
+      inodesCount: readU32(bytes, 0),
+      blocksCount: readU32(bytes, 4),
+      freeBlocksCount: readU32(bytes, Self::FREE_BLOCKS_COUNT_OFFSET),
+      freeInodesCount: readU32(bytes, Self::FREE_INODES_COUNT_OFFSET),
+      firstDataBlock: readU32(bytes, 20),
+
+      logBlockSize: readU32(bytes, 24),
+
+      blocksPerGroup: readU32(bytes, 32),
+      inodesPerGroup: readU32(bytes, 40),
+
+      state: readU16(bytes, Self::STATE_OFFSET),
+
+      revisionLevel,
+      firstInode,
+      inodeSize,
+
+      featureCompat,
+      featureIncompat,
+      featureRoCompat,
+    };
+
+    let isValid = superBlock.logBlockSize <= 2
+      && superBlock.blocksPerGroup > 0
+      && superBlock.blocksPerGroup as usize <= superBlock.getBlockSize() * 8
+      && superBlock.inodesPerGroup > 0
+      && superBlock.inodesPerGroup as usize <= superBlock.getBlockSize() * 8
+      && superBlock.firstDataBlock < superBlock.blocksCount
+      && superBlock.inodesCount >= superBlock.firstInode
+      && (superBlock.inodeSize as usize).is_power_of_two()
+      && (INODE_SIZE..=superBlock.getBlockSize()).contains(&(superBlock.inodeSize as usize));
+    if !isValid {
+      return Err(FsError::InvalidSuperBlock);
+    }
+
+    match superBlock.featureIncompat & !SUPPORTED_INCOMPAT_FEATURES {
+      0 => Ok(superBlock),
+      _ => Err(FsError::NotSupported),
+    }
+  }
+
+  // Writes the fields the driver modifies (the free counts and the state), to the given
+  // superblock.
+  pub fn encode(&self, bytes: &mut [u8; SUPERBLOCK_SIZE]) {
+    writeU32(bytes, Self::FREE_BLOCKS_COUNT_OFFSET, self.freeBlocksCount);
+    writeU32(bytes, Self::FREE_INODES_COUNT_OFFSET, self.freeInodesCount);
+    writeU16(bytes, Self::STATE_OFFSET, self.state);
+
+    if self.revisionLevel > 0 {
+      writeU32(bytes, Self::FEATURE_RO_COMPAT_OFFSET, self.featureRoCompat);
+    }
+  }
+
+  #[inline]
+  pub fn getBlockSize(&self) -> usize {
+    1024 << self.logBlockSize
+  }
+
+  #[inline]
+  pub fn getGroupsCount(&self) -> u32 {
+    (self.blocksCount - self.firstDataBlock).div_ceil(self.blocksPerGroup)
+  }
+
+  // Whether the File System can only be mounted read only, since it uses read only compatible
+  // features the driver doesn't know about.
+  #[inline]
+  pub fn requiresReadOnly(&self) -> bool {
+    self.featureRoCompat & !SUPPORTED_RO_COMPAT_FEATURES != 0
+  }
+
+  #[inline]
+  pub fn hasFeatureIncompat(&self, feature: u32) -> bool {
+    self.featureIncompat & feature != 0
+  }
+
+  #[inline]
+  pub fn hasFeatureRoCompat(&self, feature: u32) -> bool {
+    self.featureRoCompat & feature != 0
+  }
+
+  // The group descriptors are stored in the block(s), following the superblock.
+  #[inline]
+  pub fn getGroupDescriptorsOffset(&self) -> u64 {
+    (self.firstDataBlock as u64 + 1) * self.getBlockSize() as u64
+  }
+
+  // Number of data blocks in the given block group (the last one may be smaller).
+  pub fn getGroupBlocksCount(&self, group: u32) -> u32 {
+    let firstBlock = self.firstDataBlock + group * self.blocksPerGroup;
+    (self.blocksCount - firstBlock).min(self.blocksPerGroup)
+  }
+
+  /*
+    Returns the maximum size of a file, which is limited by the number of blocks the block pointers
+    can refer to, and by the number of 512 byte sectors (which the inode stores in 32 bits).
+    Without the large_file feature, the size of a regular file must fit in 31 bits.
+  */
+  pub fn getMaxFileSize(&self, isLargeFileEnabled: bool) -> u64 {
+    let blockSize = self.getBlockSize() as u64;
+    let pointersPerBlock = blockSize / 4;
+
+    let blocksCount = DIRECT_BLOCKS_COUNT as u64
+      + pointersPerBlock
+      + pointersPerBlock.pow(2)
+      + pointersPerBlock.pow(3);
+    let maxSize = (blocksCount * blockSize).min((u32::MAX as u64) * 512);
+
+    match isLargeFileEnabled {
+      true => maxSize,
+      false => maxSize.min(i32::MAX as u64),
+    }
+  }
+}
+
+// Describes a block group.
+#[derive(Debug, Copy, Clone)]
+pub struct GroupDescriptor {
+  pub blockBitmap: u32,
+  pub inodeBitmap: u32,
+  pub inodeTable: u32,
+
+  pub freeBlocksCount: u16,
+  pub freeInodesCount: u16,
+  pub usedDirectoriesCount: u16,
+}
+
+impl GroupDescriptor {
+  pub fn decode(bytes: &[u8; GROUP_DESCRIPTOR_SIZE]) -> Self {
+    Self {
+      blockBitmap: readU32(bytes, 0),
+      inodeBitmap: readU32(bytes, 4),
+      inodeTable: readU32(bytes, 8),
+
+      freeBlocksCount: readU16(bytes, 12),
+      freeInodesCount: readU16(bytes, 14),
+      usedDirectoriesCount: readU16(bytes, 16),
+    }
+  }
+
+  // Only the counts are written : the rest of the group descriptor never changes.
+  pub fn encodeCounts(&self) -> (usize, [u8; 6]) {
+    let mut encoded = [0; 6];
+    writeU16(&mut encoded, 0, self.freeBlocksCount);
+    writeU16(&mut encoded, 2, self.freeInodesCount);
+    writeU16(&mut encoded, 4, self.usedDirectoriesCount);
+
+    (12, encoded)
+  }
+}
+
+// The fields of an inode, which the driver uses. The rest are preserved, when it's written.
+#[derive(Debug, Copy, Clone)]
+pub struct DiskInode {
+  pub mode: u16,
+
+  pub size: u32,
+  // The upper 32 bits of the size of a regular file (with the large_file feature).
+  pub sizeHigh: u32,
+
+  pub deletionTime: u32,
+  pub linksCount: u16,
+
+  // Number of 512 byte sectors allocated to the file (including the indirect blocks).
+  pub sectorsCount: u32,
+  pub flags: u32,
+
+  pub blocks: [u32; BLOCK_POINTERS_COUNT],
+
+  // The block holding the extended attributes of the file (if any).
+  pub fileAclBlock: u32,
+}
+
+impl DiskInode {
+  const MODE_OFFSET: usize = 0;
+  const SIZE_OFFSET: usize = 4;
+  const DELETION_TIME_OFFSET: usize = 20;
+  const LINKS_COUNT_OFFSET: usize = 26;
+  const SECTORS_COUNT_OFFSET: usize = 28;
+  const FLAGS_OFFSET: usize = 32;
+  const BLOCKS_OFFSET: usize = 40;
+  const FILE_ACL_OFFSET: usize = 104;
+  const SIZE_HIGH_OFFSET: usize = 108;
+
+  pub fn new(mode: u16) -> Self {
+    Self {
+      mode,
+
+      size: 0,
+      sizeHigh: 0,
+
+      deletionTime: 0,
+      linksCount: 1,
+
+      sectorsCount: 0,
+      flags: 0,
+
+      blocks: [0; BLOCK_POINTERS_COUNT],
+
+      fileAclBlock: 0,
+    }
+  }
+
+  pub fn decode(bytes: &[u8; INODE_SIZE]) -> Self {
+    let mut blocks = [0; BLOCK_POINTERS_COUNT];
+    for (index, block) in blocks.iter_mut().enumerate() {
+      *block = readU32(bytes, Self::BLOCKS_OFFSET + index * 4);
+    }
+
+    Self {
+      mode: readU16(bytes, Self::MODE_OFFSET),
+
+      size: readU32(bytes, Self::SIZE_OFFSET),
+      sizeHigh: readU32(bytes, Self::SIZE_HIGH_OFFSET),
+
+      deletionTime: readU32(bytes, Self::DELETION_TIME_OFFSET),
+      linksCount: readU16(bytes, Self::LINKS_COUNT_OFFSET),
+
+      sectorsCount: readU32(bytes, Self::SECTORS_COUNT_OFFSET),
+      flags: readU32(bytes, Self::FLAGS_OFFSET),
+
+      blocks,
+
+      fileAclBlock: readU32(bytes, Self::FILE_ACL_OFFSET),
+    }
+  }
+
+  // Writes the fields to the given (previous contents of the) inode.
+  pub fn encode(&self, bytes: &mut [u8; INODE_SIZE]) {
+    writeU16(bytes, Self::MODE_OFFSET, self.mode);
+
+    writeU32(bytes, Self::SIZE_OFFSET, self.size);
+    writeU32(bytes, Self::SIZE_HIGH_OFFSET, self.sizeHigh);
+
+    writeU32(bytes, Self::DELETION_TIME_OFFSET, self.deletionTime);
+    writeU16(bytes, Self::LINKS_COUNT_OFFSET, self.linksCount);
+
+    writeU32(bytes, Self::SECTORS_COUNT_OFFSET, self.sectorsCount);
+    writeU32(bytes, Self::FLAGS_OFFSET, self.flags);
+
+    for (index, &block) in self.blocks.iter().enumerate() {
+      writeU32(bytes, Self::BLOCKS_OFFSET + index * 4, block);
+    }
+
+    writeU32(bytes, Self::FILE_ACL_OFFSET, self.fileAclBlock);
+  }
+
+  #[inline]
+  pub fn getFileType(&self) -> u16 {
+    self.mode & MODE_TYPE_MASK
+  }
+
+  #[inline]
+  pub fn isDirectory(&self) -> bool {
+    self.getFileType() == MODE_DIRECTORY
+  }
+
+  // The upper 32 bits of the size are only used by regular files (for directories, that field is
+  // the directory ACL).
+  pub fn getSize(&self) -> u64 {
+    match self.getFileType() {
+      MODE_REGULAR => (self.sizeHigh as u64) << 32 | self.size as u64,
+      _ => self.size as u64,
+    }
+  }
+
+  pub fn setSize(&mut self, size: u64) {
+    self.size = size as u32;
+    if self.getFileType() == MODE_REGULAR {
+      self.sizeHigh = (size >> 32) as u32;
+    }
+  }
+
+  // Whether the inode is a symbolic link, whose target is stored in the block pointers.
+  pub fn isFastSymlink(&self) -> bool {
+    let extendedAttributeSectorsCount = match self.fileAclBlock {
+      0 => 0,
+      _ => 1,
+    };
+
+    self.getFileType() == MODE_SYMLINK && self.sectorsCount <= extendedAttributeSectorsCount
+  }
+
+  // Whether the block pointers refer to data blocks. Those of fast symbolic links and device files
+  // hold other data.
+  pub fn hasDataBlocks(&self) -> bool {
+    match self.getFileType() {
+      MODE_REGULAR | MODE_DIRECTORY => true,
+      MODE_SYMLINK => !self.isFastSymlink(),
+      _ => false,
+    }
+  }
+
+  pub fn getFastSymlinkTarget(&self) -> Vec<u8> {
+    let mut target: Vec<u8> = self.blocks.iter().flat_map(|block| block.to_le_bytes()).collect();
+    target.truncate(self.size as usize);
+    target
+  }
+
+  pub fn setFastSymlinkTarget(&mut self, target: &[u8]) {
+    let mut bytes = [0; BLOCK_POINTERS_COUNT * 4];
+    bytes[..target.len()].copy_from_slice(target);
+
+    for (index, block) in self.blocks.iter_mut().enumerate() {
+      *block = readU32(&bytes, index * 4);
+    }
+    self.size = target.len() as u32;
+  }
+
+  /*
+    Returns the major and minor numbers of a device file. Small numbers are stored in the first
+    block pointer (the old encoding), and large ones in the second block pointer (the new
+    encoding, which Linux uses).
+  */
+  pub fn getDeviceNumbers(&self) -> (u32, u32) {
+    match self.blocks[0] {
+      0 => {
+        let encoded = self.blocks[1];
+        ((encoded & 0xfff00) >> 8, (encoded & 0xff) | ((encoded >> 12) & 0xfff00))
+      }
+      encoded => ((encoded >> 8) & 0xff, encoded & 0xff),
+    }
+  }
+
+  pub fn setDeviceNumbers(&mut self, major: u32, minor: u32) {
+    if major < 256 && minor < 256 {
+      self.blocks[0] = major << 8 | minor;
+      self.blocks[1] = 0;
+    }
+    else {
+      self.blocks[0] = 0;
+      self.blocks[1] = (minor & 0xff) | (major << 8) | ((minor & !0xff) << 12);
+    }
+  }
+}
+
+// The header of a directory entry, which is followed by the name (not NUL terminated).
+#[derive(Debug, Copy, Clone)]
+pub struct DirectoryEntryHeader {
+  // 0, if the entry is unused.
+  pub inodeNumber: u32,
+
+  // Length of the entry, including the padding after the name (up to the next entry).
+  pub recordLength: u16,
+  pub nameLength: u8,
+
+  // DIRECTORY_ENTRY_TYPE_UNKNOWN, if the filetype feature isn't enabled.
+  pub fileType: u8,
+}
+
+impl DirectoryEntryHeader {
+  pub fn decode(bytes: &[u8]) -> Self {
+    Self {
+      inodeNumber: readU32(bytes, 0),
+      recordLength: readU16(bytes, 4),
+      nameLength: bytes[6],
+      fileType: bytes[7],
+    }
+  }
+
+  pub fn encode(&self) -> [u8; DIRECTORY_ENTRY_HEADER_SIZE] {
+    let mut bytes = [0; DIRECTORY_ENTRY_HEADER_SIZE];
+
+    writeU32(&mut bytes, 0, self.inodeNumber);
+    writeU16(&mut bytes, 4, self.recordLength);
+    bytes[6] = self.nameLength;
+    bytes[7] = self.fileType;
+
+    bytes
+  }
+
+  // The length of an entry with a name of the given length, without any padding after it. Entries
+  // are 4 byte aligned.
+  #[inline]
+  pub fn getMinRecordLength(nameLength: usize) -> usize {
+    (DIRECTORY_ENTRY_HEADER_SIZE + nameLength).next_multiple_of(4)
+  }
+}
+
+// Returns the file type stored in directory entries, for the given mode.
+pub fn getDirectoryEntryType(mode: u16) -> u8 {
+  match mode & MODE_TYPE_MASK {
+    MODE_REGULAR => DIRECTORY_ENTRY_TYPE_REGULAR,
+    MODE_DIRECTORY => DIRECTORY_ENTRY_TYPE_DIRECTORY,
+    MODE_CHAR_DEVICE => DIRECTORY_ENTRY_TYPE_CHAR_DEVICE,
+    MODE_BLOCK_DEVICE => DIRECTORY_ENTRY_TYPE_BLOCK_DEVICE,
+    MODE_FIFO => DIRECTORY_ENTRY_TYPE_FIFO,
+    MODE_SOCKET => DIRECTORY_ENTRY_TYPE_SOCKET,
+    MODE_SYMLINK => DIRECTORY_ENTRY_TYPE_SYMLINK,
+    _ => DIRECTORY_ENTRY_TYPE_UNKNOWN,
+  }
+}
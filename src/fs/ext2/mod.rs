@@ -0,0 +1,788 @@
+pub mod blockmap;
+pub mod directory;
+pub mod filesystem;
+pub mod layout;
+
+use {
+  self::{
+    filesystem::FileSystem,
+    layout::{
+      DiskInode, DEFAULT_DIRECTORY_PERMISSIONS, DEFAULT_FILE_PERMISSIONS,
+      DEFAULT_SYMLINK_PERMISSIONS, MAX_FAST_SYMLINK_LENGTH, MODE_BLOCK_DEVICE, MODE_CHAR_DEVICE,
+      MODE_DIRECTORY, MODE_FIFO, MODE_REGULAR, MODE_SYMLINK, ROOT_INODE_NUMBER,
+    },
+  },
+  crate::{
+    fs::vfs::{self, mount::FileSystemType, DeviceNumber, DirectoryEntry, FileType, FsError, Stat},
+    locks::sleeplock::{SleepLock, SleepLockGuard},
+  },
+  alloc::{
+    collections::BTreeMap,
+    sync::{Arc, Weak},
+    vec::Vec,
+  },
+  core::{any::Any, ops::Range},
+};
+
+/*
+  An ext2 driver, so that the root File System can be built on the host using standard tools
+  (like mke2fs -d). Regular files, directories, symbolic links, device files and FIFOs are
+  supported. Sockets can't be looked up, since the VFS has no such file type.
+
+  The File System can be mounted read-write, unless it uses read only compatible features which
+  aren't supported. The mount options are "ro" and "rw" (the default).
+
+  The VFS inodes are cached (by inode number), so that a file opened multiple times is backed by a
+  single inode. An inode which has been unlinked from all the directories is freed, once it's
+  dropped.
+
+  NOTE : Operations never lock two inodes at once.
+*/
+
+pub static FILE_SYSTEM_TYPE: FileSystemType = FileSystemType {
+  name: "ext2",
+  isDiskBased: true,
+  mount,
+};
+
+fn mount(diskNumber: Option<usize>, options: &[u8]) -> Result<Arc<dyn vfs::FileSystem>, FsError> {
+  let diskNumber = diskNumber.ok_or(FsError::InvalidArgument)?;
+
+  let mut isReadOnly = false;
+  for option in options.split(|&byte| byte == b',') {
+    match option {
+      b"" => {}
+      b"ro" => isReadOnly = true,
+      b"rw" => isReadOnly = false,
+      _ => return Err(FsError::InvalidArgument),
+    }
+  }
+
+  let info = Arc::new(Ext2FsInfo {
+    fileSystem: FileSystem::mount(diskNumber, isReadOnly)?,
+    fileSystemID: vfs::allocateFileSystemID(),
+
+    inodes: SleepLock::new(BTreeMap::new()),
+  });
+
+  let root = Ext2Inode::get(&info, ROOT_INODE_NUMBER)?;
+  if !root.isDirectory() {
+    println!(
+      "WARN : The root inode in disk {} isn't a directory",
+      diskNumber
+    );
+    return Err(FsError::InvalidSuperBlock);
+  }
+
+  Ok(Arc::new(Ext2Fs { info, root }))
+}
+
+// Shared by the File System instance and its inodes.
+struct Ext2FsInfo {
+  fileSystem: FileSystem,
+  fileSystemID: usize,
+
+  // The cached inodes, by inode number.
+  inodes: SleepLock<BTreeMap<u32, Weak<Ext2Inode>>>,
+}
+
+struct Ext2Fs {
+  info: Arc<Ext2FsInfo>,
+  root: Arc<Ext2Inode>,
+}
+
+impl vfs::FileSystem for Ext2Fs {
+  fn getTypeName(&self) -> &'static str {
+    FILE_SYSTEM_TYPE.name
+  }
+
+  fn getRootInode(&self) -> Result<Arc<dyn vfs::Inode>, FsError> {
+    Ok(self.root.clone())
+  }
+
+  fn sync(&self) -> Result<(), FsError> {
+    self.info.fileSystem.sync();
+    Ok(())
+  }
+
+  fn unmount(&self) -> Result<(), FsError> {
+    self.info.fileSystem.unmount();
+    Ok(())
+  }
+}
+
+/*
+  An inode of an ext2 File System. Its state is a copy of the on disk inode, which is written
+  through whenever it's modified (so that the on disk inode is up to date, if the inode gets cached
+  again).
+*/
+struct Ext2Inode {
+  info: Arc<Ext2FsInfo>,
+
+  inodeNumber: u32,
+  // The file type and permissions (which never change).
+  mode: u16,
+
+  state: SleepLock<DiskInode>,
+}
+
+impl Ext2Inode {
+  // Returns the (cached) inode with the given inode number.
+  fn get(info: &Arc<Ext2FsInfo>, inodeNumber: u32) -> Result<Arc<Self>, FsError> {
+    let mut inodes = info.inodes.acquire();
+    if let Some(inode) = inodes.get(&inodeNumber).and_then(Weak::upgrade) {
+      return Ok(inode);
+    }
+
+    // The inode is read while the cache is locked, so that it's up to date (even if an inode for
+    // it has just been dropped).
+    let diskInode = info.fileSystem.readInode(inodeNumber)?;
+
+    let inode = Arc::new(Self {
+      info: info.clone(),
+
+      inodeNumber,
+      mode: diskInode.mode,
+
+      state: SleepLock::new(diskInode),
+    });
+    inodes.insert(inodeNumber, Arc::downgrade(&inode));
+
+    Ok(inode)
+  }
+
+  #[inline]
+  fn getFileSystem(&self) -> &FileSystem {
+    &self.info.fileSystem
+  }
+
+  fn getFileType(&self) -> Result<FileType, FsError> {
+    match self.mode & layout::MODE_TYPE_MASK {
+      MODE_REGULAR => Ok(FileType::Regular),
+      MODE_DIRECTORY => Ok(FileType::Directory),
+      MODE_SYMLINK => Ok(FileType::Symlink),
+      MODE_CHAR_DEVICE => Ok(FileType::CharDevice),
+      MODE_BLOCK_DEVICE => Ok(FileType::BlockDevice),
+      MODE_FIFO => Ok(FileType::Fifo),
+      _ => Err(FsError::NotSupported),
+    }
+  }
+
+  #[inline]
+  fn isDirectory(&self) -> bool {
+    self.mode & layout::MODE_TYPE_MASK == MODE_DIRECTORY
+  }
+
+  #[inline]
+  fn getGroup(&self) -> u32 {
+    self.getFileSystem().getGroupOfInode(self.inodeNumber)
+  }
+
+  // Returns the given inode, if it's an inode of the same File System.
+  fn downcast<'a>(&self, inode: &'a dyn vfs::Inode) -> Result<&'a Self, FsError> {
+    match inode.asAny().downcast_ref::<Self>() {
+      Some(inode) if Arc::ptr_eq(&inode.info, &self.info) => Ok(inode),
+      _ => Err(FsError::CrossDevice),
+    }
+  }
+
+  fn checkWritable(&self) -> Result<(), FsError> {
+    match self.getFileSystem().isReadOnly() {
+      true => Err(FsError::ReadOnly),
+      false => Ok(()),
+    }
+  }
+
+  fn lockDirectory(&self) -> Result<SleepLockGuard<'_, DiskInode>, FsError> {
+    match self.isDirectory() {
+      true => Ok(self.state.acquire()),
+      false => Err(FsError::NotADirectory),
+    }
+  }
+
+  fn writeInode(&self, diskInode: &DiskInode) -> Result<(), FsError> {
+    self.getFileSystem().writeInode(self.inodeNumber, diskInode)
+  }
+
+  // Reads the contents of the file starting at the given offset (which is within the file), till
+  // the end of the given buffer or the file.
+  fn readContents(
+    &self,
+    diskInode: &DiskInode,
+    offset: u64,
+    buffer: &mut [u8],
+  ) -> Result<usize, FsError> {
+    let fileSystem = self.getFileSystem();
+    let length = buffer.len().min((diskInode.getSize() - offset) as usize);
+
+    for (fileBlock, offsetInBlock, range) in mapRange(fileSystem, offset, length) {
+      match blockmap::getBlock(fileSystem, diskInode, fileBlock)? {
+        Some(block) => fileSystem.readBytes(
+          fileSystem.getBlockOffset(block) + offsetInBlock as u64,
+          &mut buffer[range],
+        ),
+
+        // A hole reads as zeroes.
+        None => buffer[range].fill(0),
+      }
+    }
+
+    Ok(length)
+  }
+
+  /*
+    Creates an inode with the given mode, and adds it to this directory with the given name. The
+    given function initializes the inode (given its inode number and the block group to allocate
+    its blocks from), before it's written.
+
+    If anything fails midway, then the inode and its blocks are freed.
+  */
+  fn createChild(
+    &self,
+    name: &[u8],
+    mode: u16,
+    initialize: impl FnOnce(&mut DiskInode, u32, u32) -> Result<(), FsError>,
+  ) -> Result<Arc<dyn vfs::Inode>, FsError> {
+    self.checkWritable()?;
+    let fileSystem = self.getFileSystem();
+
+    let mut state = self.lockDirectory()?;
+
+    // A removed directory can't get new entries.
+    if state.linksCount == 0 {
+      return Err(FsError::NotFound);
+    }
+    if directory::findRecord(fileSystem, &state, name)?.is_some() {
+      return Err(FsError::AlreadyExists);
+    }
+
+    let isDirectory = mode & layout::MODE_TYPE_MASK == MODE_DIRECTORY;
+    let group = self.getGroup();
+    let inodeNumber = fileSystem.allocateInode(group, isDirectory)?;
+
+    let mut diskInode = DiskInode::new(mode);
+    let result = initialize(&mut diskInode, inodeNumber, group)
+      .and_then(|()| fileSystem.initializeInode(inodeNumber, &diskInode))
+      .and_then(|()| {
+        directory::addRecord(
+          fileSystem,
+          &mut state,
+          group,
+          name,
+          inodeNumber,
+          fileSystem.getDirectoryEntryType(mode),
+        )
+      });
+
+    if let Err(error) = result {
+      if diskInode.hasDataBlocks() {
+        blockmap::freeBlocks(fileSystem, &mut diskInode, 0);
+      }
+      fileSystem.freeInode(inodeNumber, isDirectory);
+
+      // The directory may have been modified, before adding the entry failed.
+      self.writeInode(&state)?;
+      return Err(error);
+    }
+
+    // The ".." entry of the new directory.
+    if isDirectory {
+      state.linksCount += 1;
+    }
+    self.writeInode(&state)?;
+
+    Ok(Self::get(&self.info, inodeNumber)?)
+  }
+
+  // Drops a link to this inode, which has been removed from a directory.
+  fn dropLink(&self) -> Result<(), FsError> {
+    let mut state = self.state.acquire();
+
+    state.linksCount = match self.isDirectory() {
+      // Along with the link from its "." entry.
+      true => 0,
+      false => state.linksCount.saturating_sub(1),
+    };
+    self.writeInode(&state)
+  }
+}
+
+impl Drop for Ext2Inode {
+  fn drop(&mut self) {
+    {
+      let mut inodes = self.info.inodes.acquire();
+
+      // The inode number may already belong to a new inode (if this one was dropped midway a
+      // lookup).
+      if inodes
+        .get(&self.inodeNumber)
+        .is_some_and(|inode| inode.strong_count() == 0)
+      {
+        inodes.remove(&self.inodeNumber);
+      }
+    }
+
+    let mut state = self.state.acquire();
+    if state.linksCount > 0 || self.getFileSystem().isReadOnly() {
+      return;
+    }
+
+    // The inode has been unlinked from all the directories, so it's freed.
+    let fileSystem = &self.info.fileSystem;
+    if state.hasDataBlocks() {
+      blockmap::freeBlocks(fileSystem, &mut state, 0);
+    }
+
+    // NOTE : There's no wall clock, so the deletion time only marks the inode as deleted (for
+    // e2fsck).
+    state.deletionTime = 1;
+    if fileSystem.writeInode(self.inodeNumber, &state).is_ok() {
+      fileSystem.freeInode(self.inodeNumber, self.isDirectory());
+    }
+  }
+}
+
+// Splits the given byte range of a file into the parts in each of its blocks. Yields the block of
+// the file, the offset in that block, and the range relative to the start of the given byte
+// range.
+fn mapRange(
+  fileSystem: &FileSystem,
+  offset: u64,
+  length: usize,
+) -> impl Iterator<Item = (u64, usize, Range<usize>)> {
+  let blockSize = fileSystem.getBlockSize();
+  let mut bytesMapped = 0;
+
+  core::iter::from_fn(move || {
+    if bytesMapped >= length {
+      return None;
+    }
+
+    let position = offset + bytesMapped as u64;
+    let offsetInBlock = (position % blockSize as u64) as usize;
+    let chunkLength = (blockSize - offsetInBlock).min(length - bytesMapped);
+
+    let chunk = (
+      position / blockSize as u64,
+      offsetInBlock,
+      bytesMapped..bytesMapped + chunkLength,
+    );
+
+    bytesMapped += chunkLength;
+    Some(chunk)
+  })
+}
+
+// Regular files larger than 2 GB require the large_file feature.
+const MAX_SMALL_FILE_SIZE: u64 = i32::MAX as u64;
+
+impl vfs::Inode for Ext2Inode {
+  fn asAny(&self) -> &dyn Any {
+    self
+  }
+
+  fn getStat(&self) -> Result<Stat, FsError> {
+    let state = self.state.acquire();
+
+    let fileType = self.getFileType()?;
+    let deviceNumber = match fileType {
+      FileType::CharDevice | FileType::BlockDevice => {
+        let (major, minor) = state.getDeviceNumbers();
+        DeviceNumber::new(major, minor)
+      }
+      _ => DeviceNumber::default(),
+    };
+
+    Ok(Stat {
+      fileSystemID: self.info.fileSystemID,
+      inodeNumber: self.inodeNumber as u64,
+
+      fileType,
+      linksCount: state.linksCount as u32,
+
+      size: state.getSize(),
+
+      deviceNumber,
+    })
+  }
+
+  fn readAt(&self, offset: u64, buffer: &mut [u8]) -> Result<usize, FsError> {
+    match self.getFileType()? {
+      FileType::Regular => {}
+      FileType::Directory => return Err(FsError::IsADirectory),
+      _ => return Err(FsError::NotSupported),
+    }
+
+    let state = self.state.acquire();
+    if offset >= state.getSize() {
+      return Ok(0);
+    }
+
+    self.readContents(&state, offset, buffer)
+  }
+
+  // Returns fewer bytes written than requested, if the File System runs out of space midway.
+  fn writeAt(&self, offset: u64, buffer: &[u8]) -> Result<usize, FsError> {
+    match self.getFileType()? {
+      FileType::Regular => {}
+      FileType::Directory => return Err(FsError::IsADirectory),
+      _ => return Err(FsError::NotSupported),
+    }
+    self.checkWritable()?;
+    let fileSystem = self.getFileSystem();
+
+    offset
+      .checked_add(buffer.len() as u64)
+      .filter(|&end| end <= fileSystem.getMaxFileSize())
+      .ok_or(FsError::FileTooLarge)?;
+    if buffer.is_empty() {
+      return Ok(0);
+    }
+
+    let mut state = self.state.acquire();
+
+    let mut bytesWritten = 0;
+    let mut result = Ok(());
+    for (fileBlock, offsetInBlock, range) in mapRange(fileSystem, offset, buffer.len()) {
+      match blockmap::getOrAllocateBlock(fileSystem, &mut state, fileBlock, self.getGroup()) {
+        Ok(block) => {
+          bytesWritten = range.end;
+          fileSystem.writeBytes(
+            fileSystem.getBlockOffset(block) + offsetInBlock as u64,
+            &buffer[range],
+          );
+        }
+
+        Err(error) => {
+          result = Err(error);
+          break;
+        }
+      }
+    }
+
+    let writtenEnd = offset + bytesWritten as u64;
+    if writtenEnd > state.getSize() {
+      if writtenEnd > MAX_SMALL_FILE_SIZE {
+        fileSystem.enableLargeFiles();
+      }
+      state.setSize(writtenEnd);
+    }
+    self.writeInode(&state)?;
+
+    match bytesWritten {
+      0 => Err(result.err().unwrap_or(FsError::NoSpace)),
+      _ => Ok(bytesWritten),
+    }
+  }
+
+  fn truncate(&self, newSize: u64) -> Result<(), FsError> {
+    match self.getFileType()? {
+      FileType::Regular => {}
+      FileType::Directory => return Err(FsError::IsADirectory),
+      _ => return Err(FsError::NotSupported),
+    }
+    self.checkWritable()?;
+    let fileSystem = self.getFileSystem();
+    let blockSize = fileSystem.getBlockSize() as u64;
+
+    if newSize > fileSystem.getMaxFileSize() {
+      return Err(FsError::FileTooLarge);
+    }
+
+    let mut state = self.state.acquire();
+
+    if newSize < state.getSize() {
+      blockmap::freeBlocks(fileSystem, &mut state, newSize.div_ceil(blockSize));
+
+      // Zero the rest of the last block, so that it reads as zeroes if the file grows again.
+      let offsetInBlock = newSize % blockSize;
+      if offsetInBlock != 0 {
+        if let Some(block) = blockmap::getBlock(fileSystem, &state, newSize / blockSize)? {
+          fileSystem.zeroBytes(
+            fileSystem.getBlockOffset(block) + offsetInBlock,
+            (blockSize - offsetInBlock) as usize,
+          );
+        }
+      }
+    }
+    // Growing the file leaves a hole past its previous end.
+    else if newSize > MAX_SMALL_FILE_SIZE {
+      fileSystem.enableLargeFiles();
+    }
+
+    state.setSize(newSize);
+    self.writeInode(&state)
+  }
+
+  fn lookup(&self, name: &[u8]) -> Result<Arc<dyn vfs::Inode>, FsError> {
+    let fileSystem = self.getFileSystem();
+
+    let inode = {
+      let state = self.lockDirectory()?;
+      let record = directory::findRecord(fileSystem, &state, name)?.ok_or(FsError::NotFound)?;
+
+      Self::get(&self.info, record.inodeNumber)?
+    };
+
+    // Checked once the directory is unlocked, since dropping the inode (on failure) locks it.
+    inode.getFileType()?;
+
+    Ok(inode)
+  }
+
+  fn create(
+    &self,
+    name: &[u8],
+    fileType: FileType,
+    deviceNumber: DeviceNumber,
+  ) -> Result<Arc<dyn vfs::Inode>, FsError> {
+    let mode = match fileType {
+      FileType::Regular => MODE_REGULAR | DEFAULT_FILE_PERMISSIONS,
+      FileType::Directory => MODE_DIRECTORY | DEFAULT_DIRECTORY_PERMISSIONS,
+      FileType::CharDevice => MODE_CHAR_DEVICE | DEFAULT_FILE_PERMISSIONS,
+      FileType::BlockDevice => MODE_BLOCK_DEVICE | DEFAULT_FILE_PERMISSIONS,
+      FileType::Fifo => MODE_FIFO | DEFAULT_FILE_PERMISSIONS,
+      FileType::Symlink => return Err(FsError::NotSupported),
+    };
+    let fileSystem = self.getFileSystem();
+
+    self.createChild(name, mode, |diskInode, inodeNumber, group| {
+      match fileType {
+        FileType::Directory => {
+          // Linked from its parent, and from its "." entry.
+          diskInode.linksCount = 2;
+          directory::initialize(fileSystem, diskInode, group, inodeNumber, self.inodeNumber)?;
+        }
+        FileType::CharDevice | FileType::BlockDevice => {
+          diskInode.setDeviceNumbers(deviceNumber.major, deviceNumber.minor);
+        }
+        _ => {}
+      }
+      Ok(())
+    })
+  }
+
+  // Targets which fit in the block pointers of the inode are stored there (a fast symbolic link).
+  // Longer ones are stored in a data block.
+  fn symlink(&self, name: &[u8], target: &[u8]) -> Result<Arc<dyn vfs::Inode>, FsError> {
+    let fileSystem = self.getFileSystem();
+    if target.is_empty() {
+      return Err(FsError::NotFound);
+    }
+    if target.len() >= fileSystem.getBlockSize() {
+      return Err(FsError::NameTooLong);
+    }
+
+    let mode = MODE_SYMLINK | DEFAULT_SYMLINK_PERMISSIONS;
+    self.createChild(name, mode, |diskInode, _, group| {
+      if target.len() <= MAX_FAST_SYMLINK_LENGTH {
+        diskInode.setFastSymlinkTarget(target);
+        return Ok(());
+      }
+
+      let block = blockmap::getOrAllocateBlock(fileSystem, diskInode, 0, group)?;
+      fileSystem.writeBytes(fileSystem.getBlockOffset(block), target);
+      diskInode.setSize(target.len() as u64);
+
+      Ok(())
+    })
+  }
+
+  fn link(&self, name: &[u8], inode: &dyn vfs::Inode) -> Result<(), FsError> {
+    let inode = self.downcast(inode)?;
+    if inode.isDirectory() {
+      return Err(FsError::IsADirectory);
+    }
+    self.checkWritable()?;
+    let fileSystem = self.getFileSystem();
+
+    {
+      let mut state = self.lockDirectory()?;
+
+      if state.linksCount == 0 {
+        return Err(FsError::NotFound);
+      }
+
+      let result = directory::addRecord(
+        fileSystem,
+        &mut state,
+        self.getGroup(),
+        name,
+        inode.inodeNumber,
+        fileSystem.getDirectoryEntryType(inode.mode),
+      );
+      self.writeInode(&state)?;
+      result?;
+    }
+
+    let mut state = inode.state.acquire();
+    state.linksCount += 1;
+    inode.writeInode(&state)
+  }
+
+  fn unlink(&self, name: &[u8]) -> Result<(), FsError> {
+    self.checkWritable()?;
+    let fileSystem = self.getFileSystem();
+
+    // On failure, the removed inode is dropped only after the directory is unlocked, since dropping
+    // it locks it.
+    let (removedInode, result) = {
+      let mut state = self.lockDirectory()?;
+
+      let record = directory::findRecord(fileSystem, &state, name)?.ok_or(FsError::NotFound)?;
+      let removedInode = Self::get(&self.info, record.inodeNumber)?;
+
+      let result = directory::removeRecord(fileSystem, &mut state, &record).and_then(|()| {
+        // The ".." entry of the removed directory.
+        if removedInode.isDirectory() {
+          state.linksCount -= 1;
+        }
+        self.writeInode(&state)
+      });
+
+      (removedInode, result)
+    };
+    result?;
+
+    // The inode is freed once it's dropped (if nothing else refers to it).
+    removedInode.dropLink()
+  }
+
+  /*
+    The new entry is added (or the replaced entry is pointed to the moved inode) before the old
+    entry is removed, so that the inode stays reachable if anything fails midway.
+  */
+  fn rename(
+    &self,
+    oldName: &[u8],
+    newDirectory: &dyn vfs::Inode,
+    newName: &[u8],
+  ) -> Result<(), FsError> {
+    let newDirectory = self.downcast(newDirectory)?;
+    if !newDirectory.isDirectory() {
+      return Err(FsError::NotADirectory);
+    }
+    self.checkWritable()?;
+    let fileSystem = self.getFileSystem();
+    let isSameDirectory = self.inodeNumber == newDirectory.inodeNumber;
+
+    let movedInode = {
+      let state = self.lockDirectory()?;
+
+      let record = directory::findRecord(fileSystem, &state, oldName)?.ok_or(FsError::NotFound)?;
+      Self::get(&self.info, record.inodeNumber)?
+    };
+    let fileType = fileSystem.getDirectoryEntryType(movedInode.mode);
+
+    // Declared before the directory guard, so that it's dropped after it (dropping the last
+    // reference to an inode locks it).
+    let mut replacedInode: Option<Arc<Self>> = None;
+    {
+      let mut state = newDirectory.state.acquire();
+
+      if state.linksCount == 0 {
+        return Err(FsError::NotFound);
+      }
+
+      match directory::findRecord(fileSystem, &state, newName)? {
+        Some(record) => {
+          // Both the names are links to the same inode.
+          if record.inodeNumber == movedInode.inodeNumber {
+            return Ok(());
+          }
+
+          let inode = replacedInode.insert(Self::get(&self.info, record.inodeNumber)?);
+          directory::replaceRecord(
+            fileSystem,
+            &state,
+            &record,
+            movedInode.inodeNumber,
+            fileType,
+          )?;
+
+          // The ".." entry of the replaced directory.
+          if inode.isDirectory() {
+            state.linksCount -= 1;
+          }
+        }
+
+        None => {
+          let result = directory::addRecord(
+            fileSystem,
+            &mut state,
+            newDirectory.getGroup(),
+            newName,
+            movedInode.inodeNumber,
+            fileType,
+          );
+          if result.is_err() {
+            newDirectory.writeInode(&state)?;
+          }
+          result?;
+        }
+      }
+
+      // The ".." entry of the moved directory.
+      if movedInode.isDirectory() && !isSameDirectory {
+        state.linksCount += 1;
+      }
+      newDirectory.writeInode(&state)?;
+    }
+
+    {
+      let mut state = self.state.acquire();
+
+      // The old entry is looked up again, since adding the new entry may have moved it around.
+      let record = directory::findRecord(fileSystem, &state, oldName)?.ok_or(FsError::NotFound)?;
+      directory::removeRecord(fileSystem, &mut state, &record)?;
+
+      if movedInode.isDirectory() && !isSameDirectory {
+        state.linksCount -= 1;
+      }
+      self.writeInode(&state)?;
+    }
+
+    if movedInode.isDirectory() && !isSameDirectory {
+      let state = movedInode.state.acquire();
+      directory::setParent(fileSystem, &state, newDirectory.inodeNumber)?;
+    }
+
+    match replacedInode {
+      Some(replacedInode) => replacedInode.dropLink(),
+      None => Ok(()),
+    }
+  }
+
+  // The position is the offset of the entry in the directory.
+  fn readDirectory(&self, position: usize) -> Result<Option<(DirectoryEntry, usize)>, FsError> {
+    let fileSystem = self.getFileSystem();
+    let state = self.lockDirectory()?;
+
+    Ok(
+      directory::getNextRecord(fileSystem, &state, position as u64)?.map(|record| {
+        (
+          DirectoryEntry {
+            inodeNumber: record.inodeNumber as u64,
+            name: record.name,
+          },
+          (record.offset + record.recordLength as u64) as usize,
+        )
+      }),
+    )
+  }
+
+  fn readLink(&self) -> Result<Vec<u8>, FsError> {
+    if self.getFileType() != Ok(FileType::Symlink) {
+      return Err(FsError::InvalidArgument);
+    }
+
+    let state = self.state.acquire();
+    if state.isFastSymlink() {
+      return Ok(state.getFastSymlinkTarget());
+    }
+
+    let mut target = alloc::vec![0; state.getSize() as usize];
+    self.readContents(&state, 0, &mut target)?;
+    Ok(target)
+  }
+}
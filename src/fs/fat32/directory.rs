@@ -0,0 +1,312 @@
+use {
+  super::{
+    filesystem::FileSystem,
+    layout::{
+      decodeLongName, encodeLongName, generateShortName, toShortName, LongEntry, ShortEntry,
+      ATTRIBUTE_DIRECTORY, ATTRIBUTE_LONG_NAME, ATTRIBUTE_VOLUME_ID, DELETED_ENTRY_MARKER,
+      DIRECTORY_ENTRY_SIZE, END_OF_DIRECTORY_MARKER, LAST_LONG_ENTRY_FLAG,
+      LONG_NAME_CHARACTERS_PER_ENTRY,
+    },
+  },
+  crate::fs::vfs::FsError,
+  alloc::vec::Vec,
+};
+
+/*
+  A directory is stored in a cluster chain, like a regular file, as an array of 32 byte slots. Each
+  file takes up a run of slots : the long name entries (if any) followed by the short entry.
+
+  The root directory has no "." and ".." entries. Other directories start with them.
+*/
+
+// A directory can't have more than 65536 slots (so that the slot index fits in 16 bits).
+const MAX_SLOTS_COUNT: usize = 65536;
+
+// Maximum numeric tail tried, while generating a unique short name.
+const MAX_TAIL_NUMBER: u32 = 999_999;
+
+// A file in a directory.
+pub struct DirectoryRecord {
+  // The long name if there's one, otherwise the short name.
+  pub name: Vec<u8>,
+  pub shortEntry: ShortEntry,
+
+  // Offset of the short entry, in the volume.
+  pub entryOffset: u64,
+
+  // Offsets of all the slots taken up by the file (in the volume), including the short entry.
+  pub slotOffsets: Vec<u64>,
+
+  // Index of the short entry, in the directory.
+  pub index: usize,
+}
+
+// Returns the offset (in the volume) of the slot with the given index, in the directory stored in
+// the given clusters.
+fn getSlotOffset(fileSystem: &FileSystem, clusters: &[u32], index: usize) -> u64 {
+  let slotsPerCluster = fileSystem.getClusterSize() / DIRECTORY_ENTRY_SIZE;
+
+  fileSystem
+    .getBpb()
+    .getClusterOffset(clusters[index / slotsPerCluster])
+    + ((index % slotsPerCluster) * DIRECTORY_ENTRY_SIZE) as u64
+}
+
+// Reads all the slots of the directory stored in the given clusters.
+fn readSlots(fileSystem: &FileSystem, clusters: &[u32]) -> Vec<[u8; DIRECTORY_ENTRY_SIZE]> {
+  let clusterSize = fileSystem.getClusterSize();
+
+  let mut contents = alloc::vec![0; clusters.len() * clusterSize];
+  for (index, &cluster) in clusters.iter().enumerate() {
+    fileSystem.readBytes(
+      fileSystem.getBpb().getClusterOffset(cluster),
+      &mut contents[index * clusterSize..(index + 1) * clusterSize],
+    );
+  }
+
+  contents.as_chunks::<DIRECTORY_ENTRY_SIZE>().0.to_vec()
+}
+
+// A long name being assembled, from the long name entries preceding a short entry.
+struct PendingLongName {
+  checksum: u8,
+
+  // Order of the next expected long name entry (0, once all of them have been seen).
+  nextOrder: u8,
+
+  characters: Vec<u16>,
+  slotIndices: Vec<usize>,
+}
+
+/*
+  Parses the given slots into records, till the end of the directory. Volume labels and the "."
+  and ".." entries are skipped.
+
+  Long name entries which don't match the short entry following them (since their checksum
+  differs, or some of them are missing) are ignored, and the short name is used instead.
+*/
+fn parseRecords(
+  fileSystem: &FileSystem,
+  clusters: &[u32],
+  slots: &[[u8; DIRECTORY_ENTRY_SIZE]],
+) -> Vec<DirectoryRecord> {
+  let mut records = Vec::new();
+  let mut pendingLongName: Option<PendingLongName> = None;
+
+  for (index, slot) in slots.iter().enumerate() {
+    match slot[0] {
+      END_OF_DIRECTORY_MARKER => break,
+      DELETED_ENTRY_MARKER => {
+        pendingLongName = None;
+        continue;
+      }
+      _ => {}
+    }
+
+    if slot[11] & 0x3f == ATTRIBUTE_LONG_NAME {
+      let longEntry = LongEntry::decode(slot);
+      let order = longEntry.order & !LAST_LONG_ENTRY_FLAG;
+
+      // The entries are stored in reverse order, starting with the last one.
+      if longEntry.order & LAST_LONG_ENTRY_FLAG != 0 {
+        pendingLongName = (1..=20).contains(&order).then(|| PendingLongName {
+          checksum: longEntry.checksum,
+          nextOrder: order,
+          characters: alloc::vec![0; order as usize * LONG_NAME_CHARACTERS_PER_ENTRY],
+          slotIndices: Vec::new(),
+        });
+      }
+
+      pendingLongName = pendingLongName.filter(|pending| {
+        pending.nextOrder == order && order > 0 && pending.checksum == longEntry.checksum
+      });
+      if let Some(pending) = &mut pendingLongName {
+        let start = (order as usize - 1) * LONG_NAME_CHARACTERS_PER_ENTRY;
+        pending.characters[start..start + LONG_NAME_CHARACTERS_PER_ENTRY]
+          .copy_from_slice(&longEntry.characters);
+
+        pending.nextOrder -= 1;
+        pending.slotIndices.push(index);
+      }
+      continue;
+    }
+
+    let pending = pendingLongName.take();
+
+    let shortEntry = ShortEntry::decode(slot);
+    if shortEntry.attributes & ATTRIBUTE_VOLUME_ID != 0 || shortEntry.isDotEntry() {
+      continue;
+    }
+
+    let (name, mut slotIndices) = match pending {
+      Some(pending) if pending.nextOrder == 0 && pending.checksum == shortEntry.getChecksum() => {
+        (decodeLongName(&pending.characters), pending.slotIndices)
+      }
+      _ => (shortEntry.getName(), Vec::new()),
+    };
+    slotIndices.push(index);
+
+    records.push(DirectoryRecord {
+      name,
+      shortEntry,
+
+      entryOffset: getSlotOffset(fileSystem, clusters, index),
+      slotOffsets: slotIndices
+        .iter()
+        .map(|&slotIndex| getSlotOffset(fileSystem, clusters, slotIndex))
+        .collect(),
+
+      index,
+    });
+  }
+
+  records
+}
+
+// Returns the files in the directory stored in the given clusters.
+pub fn getRecords(fileSystem: &FileSystem, clusters: &[u32]) -> Vec<DirectoryRecord> {
+  parseRecords(fileSystem, clusters, &readSlots(fileSystem, clusters))
+}
+
+// Returns the file with the given name (which is matched ignoring the ASCII case, like Windows
+// does).
+pub fn findRecord(
+  fileSystem: &FileSystem,
+  clusters: &[u32],
+  name: &[u8],
+) -> Option<DirectoryRecord> {
+  getRecords(fileSystem, clusters)
+    .into_iter()
+    .find(|record| record.name.eq_ignore_ascii_case(name))
+}
+
+/*
+  Adds a file with the given name to the directory stored in the given clusters, using the given
+  short entry (whose name gets filled in). The directory is grown, if it has no run of free slots
+  long enough. Returns the offset of the added short entry, in the volume.
+
+  If the name isn't a valid 8.3 name, then it's stored in long name entries, along with a unique
+  short name generated from it.
+*/
+pub fn addRecord(
+  fileSystem: &FileSystem,
+  clusters: &mut Vec<u32>,
+  name: &[u8],
+  mut shortEntry: ShortEntry,
+) -> Result<u64, FsError> {
+  let longName = encodeLongName(name)?;
+
+  let slots = readSlots(fileSystem, clusters);
+  let records = parseRecords(fileSystem, clusters, &slots);
+
+  if records
+    .iter()
+    .any(|record| record.name.eq_ignore_ascii_case(name))
+  {
+    return Err(FsError::AlreadyExists);
+  }
+  let isShortNameTaken = |shortName: &[u8; 11]| {
+    records
+      .iter()
+      .any(|record| record.shortEntry.name == *shortName)
+  };
+
+  let longEntries = match toShortName(name) {
+    Some((shortName, caseFlags)) if !isShortNameTaken(&shortName) => {
+      shortEntry.name = shortName;
+      shortEntry.caseFlags = caseFlags;
+      Vec::new()
+    }
+
+    _ => {
+      shortEntry.name = (1..=MAX_TAIL_NUMBER)
+        .map(|tailNumber| generateShortName(name, tailNumber))
+        .find(|shortName| !isShortNameTaken(shortName))
+        .ok_or(FsError::NoSpace)?;
+      shortEntry.caseFlags = 0;
+      LongEntry::fromName(&longName, shortEntry.getChecksum())
+    }
+  };
+  let slotsCount = longEntries.len() + 1;
+
+  // Find the first run of free slots, which is long enough. All the slots after the end of the
+  // directory are free.
+  let endIndex = slots
+    .iter()
+    .position(|slot| slot[0] == END_OF_DIRECTORY_MARKER)
+    .unwrap_or(slots.len());
+  let isFree = |index: usize| index >= endIndex || slots[index][0] == DELETED_ENTRY_MARKER;
+
+  let mut startIndex = 0;
+  let mut runLength = 0;
+  for index in 0..slots.len() {
+    if runLength == slotsCount {
+      break;
+    }
+
+    match isFree(index) {
+      true => runLength += 1,
+      false => {
+        startIndex = index + 1;
+        runLength = 0;
+      }
+    }
+  }
+
+  // Grow the directory, so that the run (continuing past its end) fits.
+  let slotsPerCluster = fileSystem.getClusterSize() / DIRECTORY_ENTRY_SIZE;
+  let requiredSlotsCount = startIndex + slotsCount;
+  if requiredSlotsCount > MAX_SLOTS_COUNT {
+    return Err(FsError::NoSpace);
+  }
+  while clusters.len() * slotsPerCluster < requiredSlotsCount {
+    let cluster = fileSystem.allocateCluster(clusters.last().copied())?;
+    clusters.push(cluster);
+  }
+
+  let encodedSlots = longEntries
+    .iter()
+    .map(LongEntry::encode)
+    .chain(core::iter::once(shortEntry.encode()));
+  for (index, encodedSlot) in (startIndex..).zip(encodedSlots) {
+    fileSystem.writeBytes(getSlotOffset(fileSystem, clusters, index), &encodedSlot);
+  }
+
+  // If the run went past the end of the directory, then the slot following it becomes the end of
+  // the directory (the slots after the end needn't be zeroed).
+  if requiredSlotsCount > endIndex && requiredSlotsCount < slots.len() {
+    fileSystem.writeBytes(
+      getSlotOffset(fileSystem, clusters, requiredSlotsCount),
+      &[END_OF_DIRECTORY_MARKER],
+    );
+  }
+
+  Ok(getSlotOffset(fileSystem, clusters, requiredSlotsCount - 1))
+}
+
+// Removes the given file from its directory, by marking its slots as deleted.
+pub fn removeRecord(fileSystem: &FileSystem, record: &DirectoryRecord) {
+  for &slotOffset in record.slotOffsets.iter() {
+    fileSystem.writeBytes(slotOffset, &[DELETED_ENTRY_MARKER]);
+  }
+}
+
+// Writes the "." and ".." entries of a directory stored in the given (zeroed) cluster, whose
+// parent directory starts at the given cluster (0, if it's the root directory).
+pub fn initialize(fileSystem: &FileSystem, cluster: u32, parentCluster: u32) {
+  let offset = fileSystem.getBpb().getClusterOffset(cluster);
+
+  let dotEntry = ShortEntry::new(*b".          ", 0, ATTRIBUTE_DIRECTORY, cluster);
+  fileSystem.writeBytes(offset, &dotEntry.encode());
+
+  setParentCluster(fileSystem, cluster, parentCluster);
+}
+
+// Points the ".." entry of the directory stored starting at the given cluster, to the given parent
+// directory (0, if it's the root directory).
+pub fn setParentCluster(fileSystem: &FileSystem, cluster: u32, parentCluster: u32) {
+  let offset = fileSystem.getBpb().getClusterOffset(cluster) + DIRECTORY_ENTRY_SIZE as u64;
+
+  let dotDotEntry = ShortEntry::new(*b"..         ", 0, ATTRIBUTE_DIRECTORY, parentCluster);
+  fileSystem.writeBytes(offset, &dotDotEntry.encode());
+}
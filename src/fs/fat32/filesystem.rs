@@ -0,0 +1,219 @@
+use {
+  super::layout::{
+    readU32, writeU32, BiosParameterBlock, FsInfo, END_OF_CHAIN, FAT_ENTRY_MASK, FIRST_CLUSTER,
+    FREE_CLUSTER, FS_INFO_UNKNOWN, MIN_END_OF_CHAIN,
+  },
+  crate::{
+    fs::{bcache::BCACHE, disk, vfs::FsError, BLOCK_SIZE},
+    locks::sleeplock::SleepLock,
+  },
+  alloc::vec::Vec,
+};
+
+/*
+  A mounted FAT32 volume. The volume starts at the beginning of the disk (partitioned disks aren't
+  supported), and is accessed through the buffer cache.
+
+  Since the sector and cluster sizes needn't match the block size, the volume is addressed in
+  bytes. Modified blocks are written back lazily (when they're recycled, or the volume is synced).
+
+  NOTE : FAT32 isn't crash consistent : the volume may need to be checked (using fsck.vfat), if the
+  kernel crashes before syncing it.
+*/
+pub struct FileSystem {
+  diskNumber: usize,
+  bpb: BiosParameterBlock,
+
+  // Serializes cluster allocation, and protects the FSInfo hints.
+  allocator: SleepLock<FsInfo>,
+}
+
+impl FileSystem {
+  // Reads the BPB (and the FSInfo sector) of the volume in the given disk.
+  pub fn mount(diskNumber: usize) -> Result<Self, FsError> {
+    let mut bootSector = [0; 512];
+    BCACHE.readBytes(diskNumber, 0, &mut bootSector);
+
+    let bpb = BiosParameterBlock::parse(&bootSector)?;
+    if bpb.getVolumeSize() > (disk::getDisk(diskNumber).getBlocksCount() * BLOCK_SIZE) as u64 {
+      return Err(FsError::InvalidSuperBlock);
+    }
+
+    let mut fsInfoSector = [0; FsInfo::SIZE];
+    BCACHE.readBytes(diskNumber, bpb.getFsInfoOffset(), &mut fsInfoSector);
+
+    let fsInfo = match FsInfo::parse(&fsInfoSector) {
+      Some(fsInfo) if bpb.isValidCluster(fsInfo.nextFreeCluster) => fsInfo,
+      Some(fsInfo) => FsInfo {
+        nextFreeCluster: FIRST_CLUSTER,
+        ..fsInfo
+      },
+
+      // FSInfo is optional.
+      None => FsInfo {
+        freeClustersCount: FS_INFO_UNKNOWN,
+        nextFreeCluster: FIRST_CLUSTER,
+      },
+    };
+
+    Ok(Self {
+      diskNumber,
+      bpb,
+
+      allocator: SleepLock::new(fsInfo),
+    })
+  }
+
+  #[inline]
+  pub fn getDiskNumber(&self) -> usize {
+    self.diskNumber
+  }
+
+  #[inline]
+  pub fn getBpb(&self) -> &BiosParameterBlock {
+    &self.bpb
+  }
+
+  #[inline]
+  pub fn getClusterSize(&self) -> usize {
+    self.bpb.getClusterSize()
+  }
+
+  #[inline]
+  pub fn readBytes(&self, offset: u64, buffer: &mut [u8]) {
+    BCACHE.readBytes(self.diskNumber, offset, buffer);
+  }
+
+  #[inline]
+  pub fn writeBytes(&self, offset: u64, buffer: &[u8]) {
+    BCACHE.writeBytes(self.diskNumber, offset, buffer);
+  }
+
+  #[inline]
+  pub fn zeroBytes(&self, offset: u64, length: usize) {
+    BCACHE.zeroBytes(self.diskNumber, offset, length);
+  }
+
+  fn getFatEntry(&self, cluster: u32) -> u32 {
+    let mut entry = [0; 4];
+    self.readBytes(
+      self.bpb.getFatEntryOffset(self.bpb.getActiveFat(), cluster),
+      &mut entry,
+    );
+
+    readU32(&entry, 0) & FAT_ENTRY_MASK
+  }
+
+  // Sets the FAT entry of the given cluster, in each copy of the FAT (if they're mirrored).
+  fn setFatEntry(&self, cluster: u32, value: u32) {
+    let fatIndices = match self.bpb.isFatMirroringEnabled() {
+      true => 0..self.bpb.fatsCount,
+      false => self.bpb.getActiveFat()..self.bpb.getActiveFat() + 1,
+    };
+
+    for fatIndex in fatIndices {
+      let offset = self.bpb.getFatEntryOffset(fatIndex, cluster);
+
+      let mut entry = [0; 4];
+      self.readBytes(offset, &mut entry);
+
+      let value = (readU32(&entry, 0) & !FAT_ENTRY_MASK) | (value & FAT_ENTRY_MASK);
+      writeU32(&mut entry, 0, value);
+      self.writeBytes(offset, &entry);
+    }
+  }
+
+  // Returns the cluster chain starting at the given cluster (which is 0 for an empty file).
+  pub fn getChain(&self, firstCluster: u32) -> Result<Vec<u32>, FsError> {
+    let mut chain = Vec::new();
+    if firstCluster == FREE_CLUSTER {
+      return Ok(chain);
+    }
+
+    let mut cluster = firstCluster;
+    loop {
+      // A free or bad cluster can't be part of a chain. And a chain can't be longer than the
+      // number of clusters (that'd mean it has a cycle).
+      if !self.bpb.isValidCluster(cluster) || chain.len() >= self.bpb.getClustersCount() as usize {
+        println!(
+          "WARN : Corrupted cluster chain starting at cluster {} in disk {}",
+          firstCluster, self.diskNumber
+        );
+        return Err(FsError::IOError);
+      }
+      chain.push(cluster);
+
+      cluster = match self.getFatEntry(cluster) {
+        nextCluster if nextCluster >= MIN_END_OF_CHAIN => return Ok(chain),
+        nextCluster => nextCluster,
+      };
+    }
+  }
+
+  /*
+    Allocates a zeroed cluster, and appends it to the chain ending at the given cluster (if any).
+    Returns the allocated cluster.
+
+    The FAT is scanned for a free cluster, starting from where the last allocation left off.
+  */
+  pub fn allocateCluster(&self, lastCluster: Option<u32>) -> Result<u32, FsError> {
+    let mut fsInfo = self.allocator.acquire();
+
+    let clustersCount = self.bpb.getClustersCount();
+    let start = fsInfo.nextFreeCluster - FIRST_CLUSTER;
+
+    let cluster = (0..clustersCount)
+      .map(|index| FIRST_CLUSTER + (start + index) % clustersCount)
+      .find(|&cluster| self.getFatEntry(cluster) == FREE_CLUSTER)
+      .ok_or(FsError::NoSpace)?;
+
+    self.setFatEntry(cluster, END_OF_CHAIN);
+    if let Some(lastCluster) = lastCluster {
+      self.setFatEntry(lastCluster, cluster);
+    }
+
+    fsInfo.nextFreeCluster = FIRST_CLUSTER + (cluster - FIRST_CLUSTER + 1) % clustersCount;
+    if fsInfo.freeClustersCount != FS_INFO_UNKNOWN {
+      fsInfo.freeClustersCount = fsInfo.freeClustersCount.saturating_sub(1);
+    }
+    drop(fsInfo);
+
+    self.zeroBytes(self.bpb.getClusterOffset(cluster), self.getClusterSize());
+    Ok(cluster)
+  }
+
+  // Frees the given clusters (the tail of a chain). If the chain is retained till the given last
+  // cluster, then that cluster is marked as the end of the chain.
+  pub fn freeClusters(&self, clusters: &[u32], lastRetainedCluster: Option<u32>) {
+    let mut fsInfo = self.allocator.acquire();
+
+    if let Some(lastRetainedCluster) = lastRetainedCluster {
+      self.setFatEntry(lastRetainedCluster, END_OF_CHAIN);
+    }
+    for &cluster in clusters {
+      self.setFatEntry(cluster, FREE_CLUSTER);
+    }
+
+    if fsInfo.freeClustersCount != FS_INFO_UNKNOWN {
+      fsInfo.freeClustersCount += clusters.len() as u32;
+    }
+  }
+
+  // Writes the FSInfo hints (if the volume has an FSInfo sector), and then writes back all the
+  // modified blocks.
+  pub fn sync(&self) {
+    {
+      let fsInfo = self.allocator.acquire();
+
+      let mut fsInfoSector = [0; FsInfo::SIZE];
+      self.readBytes(self.bpb.getFsInfoOffset(), &mut fsInfoSector);
+
+      if FsInfo::parse(&fsInfoSector).is_some() {
+        let (offset, encoded) = fsInfo.encode();
+        self.writeBytes(self.bpb.getFsInfoOffset() + offset as u64, &encoded);
+      }
+    }
+
+    BCACHE.sync(self.diskNumber);
+  }
+}
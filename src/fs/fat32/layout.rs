@@ -0,0 +1,632 @@
+use {
+  crate::fs::vfs::FsError,
+  alloc::{string::String, vec::Vec},
+};
+
+/*
+  On disk structures of FAT32 :
+
+    [ reserved sectors (boot sector, FSInfo sector, ...) | FATs | data clusters ]
+
+  The boot sector holds the BIOS Parameter Block (BPB), which describes where each region starts.
+  The data region is divided into clusters (numbered from 2), and each file (or directory) is
+  stored in a chain of clusters. The FAT (File Allocation Table) holds the number of the next
+  cluster in the chain, for each cluster. There are usually 2 copies of the FAT, which are kept
+  in sync.
+
+  A directory is a file made up of 32 byte entries. Each file has a short (8.3) entry, which is
+  preceded by long name entries if its name doesn't fit the 8.3 format (VFAT long file names).
+
+  All the fields are little endian, and most of them aren't naturally aligned. So the structures
+  are decoded field by field.
+
+  REFER : Microsoft Extensible Firmware Initiative FAT32 File System Specification.
+*/
+
+pub const DIRECTORY_ENTRY_SIZE: usize = 32;
+
+pub const FIRST_CLUSTER: u32 = 2;
+
+// Only the lower 28 bits of a FAT entry are used. The upper 4 bits must be preserved.
+pub const FAT_ENTRY_MASK: u32 = 0x0fff_ffff;
+pub const FREE_CLUSTER: u32 = 0;
+// Any value >= MIN_END_OF_CHAIN marks the end of a chain.
+pub const MIN_END_OF_CHAIN: u32 = 0x0fff_fff8;
+pub const END_OF_CHAIN: u32 = 0x0fff_ffff;
+
+// Attributes of a directory entry.
+pub const ATTRIBUTE_READ_ONLY: u8 = 0x01;
+pub const ATTRIBUTE_HIDDEN: u8 = 0x02;
+pub const ATTRIBUTE_SYSTEM: u8 = 0x04;
+pub const ATTRIBUTE_VOLUME_ID: u8 = 0x08;
+pub const ATTRIBUTE_DIRECTORY: u8 = 0x10;
+pub const ATTRIBUTE_ARCHIVE: u8 = 0x20;
+// A long name entry.
+pub const ATTRIBUTE_LONG_NAME: u8 =
+  ATTRIBUTE_READ_ONLY | ATTRIBUTE_HIDDEN | ATTRIBUTE_SYSTEM | ATTRIBUTE_VOLUME_ID;
+
+// The first byte of the name of an unused directory entry.
+pub const DELETED_ENTRY_MARKER: u8 = 0xe5;
+// Marks the end of the directory : this entry and all the following ones are unused.
+pub const END_OF_DIRECTORY_MARKER: u8 = 0x00;
+
+// Flags in the (otherwise reserved) case byte of a short entry, which Windows NT and Linux use to
+// store 8.3 names in lower case.
+pub const CASE_LOWER_BASE: u8 = 0x08;
+pub const CASE_LOWER_EXTENSION: u8 = 0x10;
+
+// Set in the order of the long name entry, which holds the last part of the name.
+pub const LAST_LONG_ENTRY_FLAG: u8 = 0x40;
+pub const LONG_NAME_CHARACTERS_PER_ENTRY: usize = 13;
+// In UTF-16 code units.
+pub const MAX_LONG_NAME_LENGTH: usize = 255;
+
+// 1980-01-01 (the FAT epoch), since the kernel doesn't keep the wall clock time.
+pub const DEFAULT_DATE: u16 = (1 << 5) | 1;
+
+const FS_INFO_LEAD_SIGNATURE: u32 = 0x4161_5252;
+const FS_INFO_STRUCT_SIGNATURE: u32 = 0x6141_7272;
+const FS_INFO_TRAIL_SIGNATURE: u32 = 0xaa55_0000;
+// Stored in the FSInfo fields, when the value isn't known.
+pub const FS_INFO_UNKNOWN: u32 = 0xffff_ffff;
+
+#[inline]
+pub fn readU16(bytes: &[u8], offset: usize) -> u16 {
+  u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+#[inline]
+pub fn readU32(bytes: &[u8], offset: usize) -> u32 {
+  u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[inline]
+pub fn writeU16(bytes: &mut [u8], offset: usize, value: u16) {
+  bytes[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+#[inline]
+pub fn writeU32(bytes: &mut [u8], offset: usize, value: u32) {
+  bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+// The BIOS Parameter Block, stored in the boot sector (sector 0).
+#[derive(Debug, Copy, Clone)]
+pub struct BiosParameterBlock {
+  pub bytesPerSector: u32,
+  pub sectorsPerCluster: u32,
+  pub reservedSectorsCount: u32,
+  pub fatsCount: u32,
+  pub totalSectorsCount: u32,
+
+  // Size of each FAT, in sectors.
+  pub fatSize: u32,
+
+  // Bit 7 is set if the FATs aren't mirrored, in which case bits 0-3 give the active FAT.
+  pub extendedFlags: u16,
+
+  pub rootCluster: u32,
+  pub fsInfoSector: u32,
+}
+
+impl BiosParameterBlock {
+  // Decodes and validates the BPB, from the given boot sector.
+  pub fn parse(bootSector: &[u8]) -> Result<Self, FsError> {
+    if bootSector.len() < 512 || bootSector[510..512] != [0x55, 0xaa] {
+      return Err(FsError::InvalidSuperBlock);
+    }
+
+    let totalSectorsCount16 = readU16(bootSector, 19);
+    let fatSize16 = readU16(bootSector, 22);
+
+    let bpb = Self {
+      bytesPerSector: readU16(bootSector, 11) as u32,
+      sectorsPerCluster: bootSector[13] as u32,
+      reservedSectorsCount: readU16(bootSector, 14) as u32,
+      fatsCount: bootSector[16] as u32,
+      totalSectorsCount: match totalSectorsCount16 {
+        0 => readU32(bootSector, 32),
+        count => count as u32,
+      },
+
+      fatSize: readU32(bootSector, 36),
+
+      extendedFlags: readU16(bootSector, 40),
+
+      rootCluster: readU32(bootSector, 44),
+      fsInfoSector: readU16(bootSector, 48) as u32,
+    };
+
+    // FAT12 and FAT16 have a fixed size root directory, and a 16 bit FAT size.
+    let rootEntriesCount = readU16(bootSector, 17);
+    let isFat32 = rootEntriesCount == 0 && fatSize16 == 0 && bpb.fatSize != 0;
+
+    let isValid = isFat32
+      && matches!(bpb.bytesPerSector, 512 | 1024 | 2048 | 4096)
+      && bpb.sectorsPerCluster.is_power_of_two()
+      && bpb.getClusterSize() <= 64 * 1024
+      && bpb.reservedSectorsCount > 0
+      && bpb.fatsCount > 0
+      && bpb.getFirstDataSector() < bpb.totalSectorsCount as u64
+      && bpb.getClustersCount() > 0
+      // The FAT must have an entry for each cluster.
+      && bpb.getClustersCount() as u64 + FIRST_CLUSTER as u64
+        <= bpb.fatSize as u64 * bpb.bytesPerSector as u64 / 4
+      && bpb.isValidCluster(bpb.rootCluster);
+
+    match isValid {
+      true => Ok(bpb),
+      false => Err(FsError::InvalidSuperBlock),
+    }
+  }
+
+  #[inline]
+  pub fn getClusterSize(&self) -> usize {
+    (self.bytesPerSector * self.sectorsPerCluster) as usize
+  }
+
+  #[inline]
+  pub fn getFirstDataSector(&self) -> u64 {
+    self.reservedSectorsCount as u64 + self.fatsCount as u64 * self.fatSize as u64
+  }
+
+  // Number of data clusters.
+  pub fn getClustersCount(&self) -> u32 {
+    let dataSectorsCount =
+      (self.totalSectorsCount as u64).saturating_sub(self.getFirstDataSector());
+    (dataSectorsCount / self.sectorsPerCluster as u64) as u32
+  }
+
+  #[inline]
+  pub fn isValidCluster(&self, cluster: u32) -> bool {
+    (FIRST_CLUSTER..FIRST_CLUSTER + self.getClustersCount()).contains(&cluster)
+  }
+
+  // Size of the volume, in bytes.
+  #[inline]
+  pub fn getVolumeSize(&self) -> u64 {
+    self.totalSectorsCount as u64 * self.bytesPerSector as u64
+  }
+
+  // Returns the byte offset of the given cluster.
+  #[inline]
+  pub fn getClusterOffset(&self, cluster: u32) -> u64 {
+    (self.getFirstDataSector() + (cluster - FIRST_CLUSTER) as u64 * self.sectorsPerCluster as u64)
+      * self.bytesPerSector as u64
+  }
+
+  // Returns the byte offset of the entry of the given cluster, in the given copy of the FAT.
+  #[inline]
+  pub fn getFatEntryOffset(&self, fatIndex: u32, cluster: u32) -> u64 {
+    (self.reservedSectorsCount as u64 + fatIndex as u64 * self.fatSize as u64)
+      * self.bytesPerSector as u64
+      + cluster as u64 * 4
+  }
+
+  #[inline]
+  pub fn isFatMirroringEnabled(&self) -> bool {
+    self.extendedFlags & 0x80 == 0
+  }
+
+  // The FAT which is read (and, if mirroring is disabled, the only one which is written).
+  #[inline]
+  pub fn getActiveFat(&self) -> u32 {
+    match self.isFatMirroringEnabled() {
+      true => 0,
+      false => (self.extendedFlags & 0xf) as u32,
+    }
+  }
+
+  #[inline]
+  pub fn getFsInfoOffset(&self) -> u64 {
+    self.fsInfoSector as u64 * self.bytesPerSector as u64
+  }
+}
+
+// Hints stored in the FSInfo sector, so that the FAT needn't be scanned to find free clusters.
+#[derive(Debug, Copy, Clone)]
+pub struct FsInfo {
+  // FS_INFO_UNKNOWN, if it isn't known.
+  pub freeClustersCount: u32,
+
+  // Where to start looking for a free cluster.
+  pub nextFreeCluster: u32,
+}
+
+impl FsInfo {
+  pub const SIZE: usize = 512;
+
+  const FREE_CLUSTERS_COUNT_OFFSET: usize = 488;
+  const NEXT_FREE_CLUSTER_OFFSET: usize = 492;
+
+  // Returns None, if the sector doesn't contain a valid FSInfo structure.
+  pub fn parse(sector: &[u8; Self::SIZE]) -> Option<Self> {
+    let isValid = readU32(sector, 0) == FS_INFO_LEAD_SIGNATURE
+      && readU32(sector, 484) == FS_INFO_STRUCT_SIGNATURE
+      && readU32(sector, 508) == FS_INFO_TRAIL_SIGNATURE;
+
+    isValid.then(|| Self {
+      freeClustersCount: readU32(sector, Self::FREE_CLUSTERS_COUNT_OFFSET),
+      nextFreeCluster: readU32(sector, Self::NEXT_FREE_CLUSTER_OFFSET),
+    })
+  }
+
+  // Returns the byte range (relative to the start of the FSInfo sector) holding the hints, along
+  // with their encoding.
+  pub fn encode(&self) -> (usize, [u8; 8]) {
+    let mut encoded = [0; 8];
+    writeU32(&mut encoded, 0, self.freeClustersCount);
+    writeU32(&mut encoded, 4, self.nextFreeCluster);
+
+    (Self::FREE_CLUSTERS_COUNT_OFFSET, encoded)
+  }
+}
+
+// A short (8.3) directory entry.
+#[derive(Debug, Copy, Clone)]
+pub struct ShortEntry {
+  // The base name and the extension, padded with spaces.
+  pub name: [u8; 11],
+
+  pub attributes: u8,
+  pub caseFlags: u8,
+
+  pub creationTimeTenths: u8,
+  pub creationTime: u16,
+  pub creationDate: u16,
+  pub lastAccessDate: u16,
+  pub writeTime: u16,
+  pub writeDate: u16,
+
+  pub firstCluster: u32,
+  pub size: u32,
+}
+
+impl ShortEntry {
+  pub const FIRST_CLUSTER_HIGH_OFFSET: usize = 20;
+  pub const FIRST_CLUSTER_LOW_OFFSET: usize = 26;
+  pub const SIZE_OFFSET: usize = 28;
+
+  pub fn new(name: [u8; 11], caseFlags: u8, attributes: u8, firstCluster: u32) -> Self {
+    Self {
+      name,
+
+      attributes,
+      caseFlags,
+
+      creationTimeTenths: 0,
+      creationTime: 0,
+      creationDate: DEFAULT_DATE,
+      lastAccessDate: DEFAULT_DATE,
+      writeTime: 0,
+      writeDate: DEFAULT_DATE,
+
+      firstCluster,
+      size: 0,
+    }
+  }
+
+  pub fn decode(entry: &[u8; DIRECTORY_ENTRY_SIZE]) -> Self {
+    Self {
+      name: entry[..11].try_into().unwrap(),
+
+      attributes: entry[11],
+      caseFlags: entry[12],
+
+      creationTimeTenths: entry[13],
+      creationTime: readU16(entry, 14),
+      creationDate: readU16(entry, 16),
+      lastAccessDate: readU16(entry, 18),
+      writeTime: readU16(entry, 22),
+      writeDate: readU16(entry, 24),
+
+      firstCluster: (readU16(entry, Self::FIRST_CLUSTER_HIGH_OFFSET) as u32) << 16
+        | readU16(entry, Self::FIRST_CLUSTER_LOW_OFFSET) as u32,
+      size: readU32(entry, Self::SIZE_OFFSET),
+    }
+  }
+
+  pub fn encode(&self) -> [u8; DIRECTORY_ENTRY_SIZE] {
+    let mut entry = [0; DIRECTORY_ENTRY_SIZE];
+
+    entry[..11].copy_from_slice(&self.name);
+
+    entry[11] = self.attributes;
+    entry[12] = self.caseFlags;
+
+    entry[13] = self.creationTimeTenths;
+    writeU16(&mut entry, 14, self.creationTime);
+    writeU16(&mut entry, 16, self.creationDate);
+    writeU16(&mut entry, 18, self.lastAccessDate);
+    writeU16(&mut entry, 22, self.writeTime);
+    writeU16(&mut entry, 24, self.writeDate);
+
+    Self::encodeFirstCluster(&mut entry, self.firstCluster);
+    writeU32(&mut entry, Self::SIZE_OFFSET, self.size);
+
+    entry
+  }
+
+  pub fn encodeFirstCluster(entry: &mut [u8], firstCluster: u32) {
+    writeU16(
+      entry,
+      Self::FIRST_CLUSTER_HIGH_OFFSET,
+      (firstCluster >> 16) as u16,
+    );
+    writeU16(entry, Self::FIRST_CLUSTER_LOW_OFFSET, firstCluster as u16);
+  }
+
+  #[inline]
+  pub fn isDirectory(&self) -> bool {
+    self.attributes & ATTRIBUTE_DIRECTORY != 0
+  }
+
+  // Whether this is the "." or ".." entry of a directory.
+  #[inline]
+  pub fn isDotEntry(&self) -> bool {
+    self.name[0] == b'.'
+  }
+
+  // Returns the name (like README.TXT), applying the case flags.
+  pub fn getName(&self) -> Vec<u8> {
+    let trim = |part: &[u8]| -> Vec<u8> {
+      let length = part
+        .iter()
+        .rposition(|&byte| byte != b' ')
+        .map_or(0, |end| end + 1);
+      part[..length].to_vec()
+    };
+
+    let mut base = trim(&self.name[..8]);
+    let mut extension = trim(&self.name[8..]);
+
+    // 0xe5 is a valid first byte (in some code pages), which is stored as 0x05.
+    if base.first() == Some(&0x05) {
+      base[0] = DELETED_ENTRY_MARKER;
+    }
+
+    if self.caseFlags & CASE_LOWER_BASE != 0 {
+      base.make_ascii_lowercase();
+    }
+    if self.caseFlags & CASE_LOWER_EXTENSION != 0 {
+      extension.make_ascii_lowercase();
+    }
+
+    if !extension.is_empty() {
+      base.push(b'.');
+      base.extend_from_slice(&extension);
+    }
+    base
+  }
+
+  /*
+    Returns the checksum of the short name, which the long name entries preceding the short entry
+    store. This way, a long name orphaned by an implementation which doesn't know about long names
+    can be detected.
+  */
+  pub fn getChecksum(&self) -> u8 {
+    self.name.iter().fold(0u8, |checksum, &byte| {
+      checksum.rotate_right(1).wrapping_add(byte)
+    })
+  }
+}
+
+// A long name entry, holding 13 UTF-16 code units of the name.
+#[derive(Debug, Copy, Clone)]
+pub struct LongEntry {
+  // The position of the entry (starting at 1) in the name, along with LAST_LONG_ENTRY_FLAG for the
+  // last one. The entries are stored in reverse order, right before the short entry.
+  pub order: u8,
+
+  pub checksum: u8,
+  pub characters: [u16; LONG_NAME_CHARACTERS_PER_ENTRY],
+}
+
+impl LongEntry {
+  // Offsets of the characters within the entry.
+  const CHARACTER_OFFSETS: [usize; LONG_NAME_CHARACTERS_PER_ENTRY] =
+    [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30];
+
+  pub fn decode(entry: &[u8; DIRECTORY_ENTRY_SIZE]) -> Self {
+    Self {
+      order: entry[0],
+      checksum: entry[13],
+      characters: Self::CHARACTER_OFFSETS.map(|offset| readU16(entry, offset)),
+    }
+  }
+
+  pub fn encode(&self) -> [u8; DIRECTORY_ENTRY_SIZE] {
+    let mut entry = [0; DIRECTORY_ENTRY_SIZE];
+
+    entry[0] = self.order;
+    entry[11] = ATTRIBUTE_LONG_NAME;
+    entry[13] = self.checksum;
+
+    for (&offset, &character) in Self::CHARACTER_OFFSETS.iter().zip(self.characters.iter()) {
+      writeU16(&mut entry, offset, character);
+    }
+
+    entry
+  }
+
+  // Splits the given long name into long name entries, in the order they're stored.
+  pub fn fromName(name: &[u16], checksum: u8) -> Vec<Self> {
+    let entriesCount = name.len().div_ceil(LONG_NAME_CHARACTERS_PER_ENTRY);
+
+    (0..entriesCount)
+      .rev()
+      .map(|index| {
+        // The name is terminated by a NUL (if it doesn't fill the last entry), and then padded
+        // with 0xffff.
+        let mut characters = [0xffff; LONG_NAME_CHARACTERS_PER_ENTRY];
+        for (position, character) in characters.iter_mut().enumerate() {
+          let nameIndex = index * LONG_NAME_CHARACTERS_PER_ENTRY + position;
+
+          match nameIndex.cmp(&name.len()) {
+            core::cmp::Ordering::Less => *character = name[nameIndex],
+            core::cmp::Ordering::Equal => *character = 0,
+            core::cmp::Ordering::Greater => break,
+          }
+        }
+
+        let mut order = (index + 1) as u8;
+        if index == entriesCount - 1 {
+          order |= LAST_LONG_ENTRY_FLAG;
+        }
+
+        Self {
+          order,
+          checksum,
+          characters,
+        }
+      })
+      .collect()
+  }
+}
+
+// Characters, other than letters and digits, allowed in a short name.
+const SHORT_NAME_SPECIAL_CHARACTERS: &[u8] = b"$%'-_@~`!(){}^#&";
+
+// Characters, which aren't allowed in a long name.
+const LONG_NAME_INVALID_CHARACTERS: &[u8] = b"\"*/:<>?\\|";
+
+fn isShortNameCharacter(byte: u8) -> bool {
+  byte.is_ascii_uppercase()
+    || byte.is_ascii_digit()
+    || SHORT_NAME_SPECIAL_CHARACTERS.contains(&byte)
+}
+
+// Whether the given name part is entirely in upper case, or entirely in lower case. Returns the
+// case flag to use in the latter case.
+fn getCaseFlag(part: &[u8], lowerCaseFlag: u8) -> Option<u8> {
+  if !part.iter().any(u8::is_ascii_lowercase) {
+    Some(0)
+  }
+  else if !part.iter().any(u8::is_ascii_uppercase) {
+    Some(lowerCaseFlag)
+  }
+  else {
+    None
+  }
+}
+
+/*
+  Returns the short name (along with the case flags), if the given name can be stored in a short
+  entry alone : it must be a valid 8.3 name, with each part entirely in upper or lower case.
+
+    toShortName(b"README.TXT") = Some((*b"README  TXT", 0))
+    toShortName(b"readme.txt") = Some((*b"README  TXT", CASE_LOWER_BASE | CASE_LOWER_EXTENSION))
+    toShortName(b"ReadMe.txt") = toShortName(b"notes.text") = toShortName(b"a b") = None
+*/
+pub fn toShortName(name: &[u8]) -> Option<([u8; 11], u8)> {
+  let (base, extension) = match name.iter().rposition(|&byte| byte == b'.') {
+    Some(dot) => (&name[..dot], &name[dot + 1..]),
+    None => (name, &name[..0]),
+  };
+
+  let isValidPart = |part: &[u8], maxLength: usize| {
+    part.len() <= maxLength
+      && part
+        .iter()
+        .all(|byte| isShortNameCharacter(byte.to_ascii_uppercase()))
+  };
+  if base.is_empty()
+    || !isValidPart(base, 8)
+    || !isValidPart(extension, 3)
+    || (extension.is_empty() && name.ends_with(b"."))
+  {
+    return None;
+  }
+
+  let caseFlags =
+    getCaseFlag(base, CASE_LOWER_BASE)? | getCaseFlag(extension, CASE_LOWER_EXTENSION)?;
+
+  let mut shortName = [b' '; 11];
+  for (index, byte) in base.iter().enumerate() {
+    shortName[index] = byte.to_ascii_uppercase();
+  }
+  for (index, byte) in extension.iter().enumerate() {
+    shortName[8 + index] = byte.to_ascii_uppercase();
+  }
+
+  Some((shortName, caseFlags))
+}
+
+/*
+  Returns the short name to store along with the given long name, with the given numeric tail
+  (like NOTES~1.TEX for notes.text). Characters which aren't allowed in a short name are replaced
+  with underscores, and leading dots are skipped.
+*/
+pub fn generateShortName(name: &[u8], tailNumber: u32) -> [u8; 11] {
+  let name = &name[name
+    .iter()
+    .position(|&byte| byte != b'.')
+    .unwrap_or(name.len())..];
+
+  let (base, extension) = match name.iter().rposition(|&byte| byte == b'.') {
+    Some(dot) => (&name[..dot], &name[dot + 1..]),
+    None => (name, &name[..0]),
+  };
+
+  let convert = |part: &[u8]| -> Vec<u8> {
+    part
+      .iter()
+      .filter(|&&byte| byte != b' ' && byte != b'.')
+      .map(|byte| match byte.to_ascii_uppercase() {
+        byte if isShortNameCharacter(byte) => byte,
+        _ => b'_',
+      })
+      .collect()
+  };
+
+  let mut base = convert(base);
+  if base.is_empty() {
+    base.push(b'_');
+  }
+  let extension = convert(extension);
+
+  let tail = alloc::format!("~{}", tailNumber).into_bytes();
+  base.truncate(8 - tail.len());
+  base.extend_from_slice(&tail);
+
+  let mut shortName = [b' '; 11];
+  shortName[..base.len()].copy_from_slice(&base);
+  for (index, &byte) in extension.iter().take(3).enumerate() {
+    shortName[8 + index] = byte;
+  }
+
+  shortName
+}
+
+// Encodes the given (UTF-8) name as UTF-16, for storing it in long name entries.
+pub fn encodeLongName(name: &[u8]) -> Result<Vec<u16>, FsError> {
+  let name = core::str::from_utf8(name).map_err(|_| FsError::InvalidArgument)?;
+
+  if name
+    .bytes()
+    .any(|byte| byte < 0x20 || LONG_NAME_INVALID_CHARACTERS.contains(&byte))
+  {
+    return Err(FsError::InvalidArgument);
+  }
+
+  let encodedName: Vec<u16> = name.encode_utf16().collect();
+  match encodedName.len() {
+    0 => Err(FsError::InvalidArgument),
+    length if length > MAX_LONG_NAME_LENGTH => Err(FsError::NameTooLong),
+    _ => Ok(encodedName),
+  }
+}
+
+// Decodes the given long name (which may be NUL terminated) into UTF-8. Invalid UTF-16 is replaced
+// with U+FFFD.
+pub fn decodeLongName(name: &[u16]) -> Vec<u8> {
+  let length = name
+    .iter()
+    .position(|&unit| unit == 0)
+    .unwrap_or(name.len());
+
+  char::decode_utf16(name[..length].iter().copied())
+    .map(|character| character.unwrap_or(char::REPLACEMENT_CHARACTER))
+    .collect::<String>()
+    .into_bytes()
+}
@@ -0,0 +1,799 @@
+pub mod directory;
+pub mod filesystem;
+pub mod layout;
+
+use {
+  self::{
+    directory::DirectoryRecord,
+    filesystem::FileSystem,
+    layout::{
+      writeU32, ShortEntry, ATTRIBUTE_ARCHIVE, ATTRIBUTE_DIRECTORY, DIRECTORY_ENTRY_SIZE,
+      FREE_CLUSTER,
+    },
+  },
+  crate::{
+    fs::vfs::{self, mount::FileSystemType, DeviceNumber, DirectoryEntry, FileType, FsError, Stat},
+    locks::sleeplock::{SleepLock, SleepLockGuard},
+  },
+  alloc::{
+    collections::BTreeMap,
+    sync::{Arc, Weak},
+    vec::Vec,
+  },
+  core::{any::Any, ops::Range},
+};
+
+/*
+  A FAT32 driver, for exchanging files with the host (using images made by mkfs.vfat or mtools).
+  Long file names (VFAT) are supported, and names are matched ignoring the ASCII case.
+
+  FAT32 has no inodes : the metadata of a file (its first cluster and size) is stored in its
+  directory entry. So a VFS inode wraps the location of the short entry of the file, which gets
+  updated as the file is written. The inodes are cached (by that location), so that a file opened
+  multiple times is backed by a single inode.
+
+  Only regular files and directories are supported (FAT32 has no symbolic links, device files or
+  hard links).
+*/
+
+pub static FILE_SYSTEM_TYPE: FileSystemType = FileSystemType {
+  name: "vfat",
+  isDiskBased: true,
+  mount,
+};
+
+// The root directory has no directory entry.
+const ROOT_INODE_NUMBER: u64 = 1;
+
+fn mount(diskNumber: Option<usize>, _options: &[u8]) -> Result<Arc<dyn vfs::FileSystem>, FsError> {
+  let diskNumber = diskNumber.ok_or(FsError::InvalidArgument)?;
+
+  let info = Arc::new(FatFsInfo {
+    fileSystem: FileSystem::mount(diskNumber)?,
+    fileSystemID: vfs::allocateFileSystemID(),
+
+    inodes: SleepLock::new(BTreeMap::new()),
+  });
+
+  let root = FatInode::new(
+    &info,
+    ROOT_INODE_NUMBER,
+    true,
+    FatInodeState {
+      entryOffset: None,
+      parentInodeNumber: ROOT_INODE_NUMBER, // The root directory is its own parent.
+
+      firstCluster: info.fileSystem.getBpb().rootCluster,
+      size: 0,
+      clusters: None,
+
+      isUnlinked: false,
+    },
+  );
+
+  Ok(Arc::new(FatFs { info, root }))
+}
+
+// Shared by the File System instance and its inodes.
+struct FatFsInfo {
+  fileSystem: FileSystem,
+  fileSystemID: usize,
+
+  // The cached inodes, by the offset of their short entry (in the volume).
+  inodes: SleepLock<BTreeMap<u64, Weak<FatInode>>>,
+}
+
+impl FatFsInfo {
+  // Frees the cluster chain starting at the given cluster. A corrupted chain is leaked.
+  fn freeChain(&self, firstCluster: u32) {
+    if let Ok(chain) = self.fileSystem.getChain(firstCluster) {
+      self.fileSystem.freeClusters(&chain, None);
+    }
+  }
+
+  /*
+    Detaches the inode of the given file, which has been removed from its directory. If the inode
+    is cached, then it's returned, and must be marked unlinked (once the directory is unlocked).
+    Otherwise, the clusters of the file are freed right away.
+  */
+  fn detachInode(&self, record: &DirectoryRecord) -> Option<Arc<FatInode>> {
+    let inode = self
+      .inodes
+      .acquire()
+      .remove(&record.entryOffset)
+      .and_then(|inode| inode.upgrade());
+
+    if inode.is_none() {
+      self.freeChain(record.shortEntry.firstCluster);
+    }
+    inode
+  }
+}
+
+struct FatFs {
+  info: Arc<FatFsInfo>,
+  root: Arc<FatInode>,
+}
+
+impl vfs::FileSystem for FatFs {
+  fn getTypeName(&self) -> &'static str {
+    FILE_SYSTEM_TYPE.name
+  }
+
+  fn getRootInode(&self) -> Result<Arc<dyn vfs::Inode>, FsError> {
+    Ok(self.root.clone())
+  }
+
+  fn sync(&self) -> Result<(), FsError> {
+    self.info.fileSystem.sync();
+    Ok(())
+  }
+}
+
+struct FatInodeState {
+  // Offset of the short entry of the file, in the volume. None for the root directory, and for an
+  // unlinked file.
+  entryOffset: Option<u64>,
+  parentInodeNumber: u64,
+
+  // 0, if the file is empty.
+  firstCluster: u32,
+  // Always 0 for a directory.
+  size: u32,
+  // The cluster chain, read when it's first needed.
+  clusters: Option<Vec<u32>>,
+
+  // Set once the file is removed from its directory. Its clusters are freed, once the inode is
+  // dropped.
+  isUnlinked: bool,
+}
+
+impl FatInodeState {
+  fn loadClusters(&mut self, fileSystem: &FileSystem) -> Result<&mut Vec<u32>, FsError> {
+    if self.clusters.is_none() {
+      self.clusters = Some(fileSystem.getChain(self.firstCluster)?);
+    }
+
+    Ok(self.clusters.as_mut().unwrap())
+  }
+
+  // Grows the cluster chain, so that it has (at least) the given number of clusters.
+  fn allocateClusters(&mut self, fileSystem: &FileSystem, count: usize) -> Result<(), FsError> {
+    let clusters = self.loadClusters(fileSystem)?;
+
+    let mut result = Ok(());
+    while clusters.len() < count {
+      match fileSystem.allocateCluster(clusters.last().copied()) {
+        Ok(cluster) => clusters.push(cluster),
+        Err(error) => {
+          result = Err(error);
+          break;
+        }
+      }
+    }
+
+    if let Some(&firstCluster) = clusters.first() {
+      self.firstCluster = firstCluster;
+    }
+    result
+  }
+
+  // Shrinks the cluster chain, so that it has (at most) the given number of clusters.
+  fn freeClusters(&mut self, fileSystem: &FileSystem, count: usize) -> Result<(), FsError> {
+    let clusters = self.loadClusters(fileSystem)?;
+    if clusters.len() <= count {
+      return Ok(());
+    }
+
+    let freedClusters = clusters.split_off(count);
+    fileSystem.freeClusters(&freedClusters, clusters.last().copied());
+
+    if clusters.is_empty() {
+      self.firstCluster = FREE_CLUSTER;
+    }
+    Ok(())
+  }
+
+  // Zero fills the allocated (but unused) clusters past the end of the file, till the given
+  // offset. Clusters are zeroed when they're allocated, so the rest reads as zeroes already.
+  fn zeroFill(&mut self, fileSystem: &FileSystem, end: u64) -> Result<(), FsError> {
+    let start = self.size as u64;
+    let clusters = self.loadClusters(fileSystem)?;
+
+    let end = end.min((clusters.len() * fileSystem.getClusterSize()) as u64);
+    if end > start {
+      for (offset, range) in mapRange(fileSystem, clusters, start, (end - start) as usize) {
+        fileSystem.zeroBytes(offset, range.len());
+      }
+    }
+
+    Ok(())
+  }
+
+  // Writes the first cluster and the size of the file, to its short entry.
+  fn writeEntry(&self, fileSystem: &FileSystem) {
+    let Some(entryOffset) = self.entryOffset
+    else {
+      return;
+    };
+
+    let mut entry = [0; DIRECTORY_ENTRY_SIZE];
+    fileSystem.readBytes(entryOffset, &mut entry);
+
+    ShortEntry::encodeFirstCluster(&mut entry, self.firstCluster);
+    writeU32(&mut entry, ShortEntry::SIZE_OFFSET, self.size);
+
+    fileSystem.writeBytes(entryOffset, &entry);
+  }
+}
+
+/*
+  An inode of a FAT32 volume : a file or a directory.
+
+  The inode number is derived from the location of the short entry of the file, when the inode is
+  cached. So it stays the same across lookups, but may be reused once the file has been renamed.
+
+  NOTE : While renaming, the old directory, the new directory and the moved inode are locked in
+  that order. The nested SleepLocks are annotated with lockdep subclasses.
+*/
+// Lockdep subclasses of the inode SleepLocks, which get nested while renaming.
+const NEW_DIRECTORY_LOCK_SUBCLASS: usize = 1;
+const MOVED_INODE_LOCK_SUBCLASS: usize = 2;
+
+struct FatInode {
+  info: Arc<FatFsInfo>,
+
+  inodeNumber: u64,
+  isDirectory: bool,
+
+  state: SleepLock<FatInodeState>,
+}
+
+impl FatInode {
+  fn new(
+    info: &Arc<FatFsInfo>,
+    inodeNumber: u64,
+    isDirectory: bool,
+    state: FatInodeState,
+  ) -> Arc<Self> {
+    Arc::new(Self {
+      info: info.clone(),
+
+      inodeNumber,
+      isDirectory,
+
+      state: SleepLock::new(state),
+    })
+  }
+
+  // Returns the (cached) inode of the file, whose short entry is at the given offset.
+  fn get(info: &Arc<FatFsInfo>, entryOffset: u64, parentInodeNumber: u64) -> Arc<Self> {
+    let mut inodes = info.inodes.acquire();
+    if let Some(inode) = inodes.get(&entryOffset).and_then(Weak::upgrade) {
+      return inode;
+    }
+
+    // The short entry is read while the cache is locked, so that it's up to date (even if an inode
+    // for it has just been dropped).
+    let mut entry = [0; DIRECTORY_ENTRY_SIZE];
+    info.fileSystem.readBytes(entryOffset, &mut entry);
+    let shortEntry = ShortEntry::decode(&entry);
+
+    let inode = Self::new(
+      info,
+      entryOffset / DIRECTORY_ENTRY_SIZE as u64,
+      shortEntry.isDirectory(),
+      FatInodeState {
+        entryOffset: Some(entryOffset),
+        parentInodeNumber,
+
+        firstCluster: shortEntry.firstCluster,
+        size: match shortEntry.isDirectory() {
+          true => 0,
+          false => shortEntry.size,
+        },
+        clusters: None,
+
+        isUnlinked: false,
+      },
+    );
+    inodes.insert(entryOffset, Arc::downgrade(&inode));
+
+    inode
+  }
+
+  #[inline]
+  fn getFileSystem(&self) -> &FileSystem {
+    &self.info.fileSystem
+  }
+
+  #[inline]
+  fn isRoot(&self) -> bool {
+    self.inodeNumber == ROOT_INODE_NUMBER
+  }
+
+  // Returns the given inode, if it's an inode of the same File System.
+  fn downcast<'a>(&self, inode: &'a dyn vfs::Inode) -> Result<&'a Self, FsError> {
+    match inode.asAny().downcast_ref::<Self>() {
+      Some(inode) if Arc::ptr_eq(&inode.info, &self.info) => Ok(inode),
+      _ => Err(FsError::CrossDevice),
+    }
+  }
+
+  fn lockDirectory(&self) -> Result<SleepLockGuard<'_, FatInodeState>, FsError> {
+    match self.isDirectory {
+      true => Ok(self.state.acquire()),
+      false => Err(FsError::NotADirectory),
+    }
+  }
+
+  // Attaches the inode to the short entry at the given offset, once it has been moved there. The
+  // entry is brought up to date, since the inode may have changed while it was being moved.
+  fn attachEntry(&self, entryOffset: u64, parentInodeNumber: u64) {
+    let mut state = self.state.acquireNested(MOVED_INODE_LOCK_SUBCLASS);
+
+    state.entryOffset = Some(entryOffset);
+    state.parentInodeNumber = parentInodeNumber;
+    state.writeEntry(self.getFileSystem());
+  }
+
+  fn markUnlinked(&self) {
+    let mut state = self.state.acquire();
+
+    state.entryOffset = None;
+    state.isUnlinked = true;
+  }
+}
+
+impl Drop for FatInode {
+  fn drop(&mut self) {
+    let state = self.state.acquire();
+
+    if let Some(entryOffset) = state.entryOffset {
+      let mut inodes = self.info.inodes.acquire();
+
+      // The entry may already belong to a new inode (if this one was dropped midway a lookup).
+      if inodes
+        .get(&entryOffset)
+        .is_some_and(|inode| inode.strong_count() == 0)
+      {
+        inodes.remove(&entryOffset);
+      }
+    }
+
+    if state.isUnlinked {
+      self.info.freeChain(state.firstCluster);
+    }
+  }
+}
+
+// Maps the given byte range of a file stored in the given clusters (which must cover it), to byte
+// ranges in the volume. Yields the offset in the volume, along with the range relative to the
+// start of the given byte range.
+fn mapRange<'a>(
+  fileSystem: &'a FileSystem,
+  clusters: &'a [u32],
+  offset: u64,
+  length: usize,
+) -> impl Iterator<Item = (u64, Range<usize>)> + 'a {
+  let clusterSize = fileSystem.getClusterSize();
+  let mut bytesMapped = 0;
+
+  core::iter::from_fn(move || {
+    if bytesMapped >= length {
+      return None;
+    }
+
+    let position = offset + bytesMapped as u64;
+    let offsetInCluster = (position % clusterSize as u64) as usize;
+    let chunkLength = (clusterSize - offsetInCluster).min(length - bytesMapped);
+
+    let cluster = clusters[(position / clusterSize as u64) as usize];
+    let chunk = (
+      fileSystem.getBpb().getClusterOffset(cluster) + offsetInCluster as u64,
+      bytesMapped..bytesMapped + chunkLength,
+    );
+
+    bytesMapped += chunkLength;
+    Some(chunk)
+  })
+}
+
+impl vfs::Inode for FatInode {
+  fn asAny(&self) -> &dyn Any {
+    self
+  }
+
+  fn getStat(&self) -> Result<Stat, FsError> {
+    let mut state = self.state.acquire();
+
+    let size = match self.isDirectory {
+      true => {
+        (state.loadClusters(self.getFileSystem())?.len() * self.getFileSystem().getClusterSize())
+          as u64
+      }
+      false => state.size as u64,
+    };
+
+    Ok(Stat {
+      fileSystemID: self.info.fileSystemID,
+      inodeNumber: self.inodeNumber,
+
+      fileType: match self.isDirectory {
+        true => FileType::Directory,
+        false => FileType::Regular,
+      },
+      linksCount: match (state.isUnlinked, self.isDirectory) {
+        (true, _) => 0,
+        (false, true) => 2,
+        (false, false) => 1,
+      },
+
+      size,
+
+      deviceNumber: DeviceNumber::default(),
+    })
+  }
+
+  fn readAt(&self, offset: u64, buffer: &mut [u8]) -> Result<usize, FsError> {
+    if self.isDirectory {
+      return Err(FsError::IsADirectory);
+    }
+    let fileSystem = self.getFileSystem();
+
+    let mut state = self.state.acquire();
+    let size = state.size as u64;
+    if offset >= size {
+      return Ok(0);
+    }
+    let length = buffer.len().min((size - offset) as usize);
+
+    let clusters = state.loadClusters(fileSystem)?;
+    if ((clusters.len() * fileSystem.getClusterSize()) as u64) < size {
+      println!(
+        "WARN : File with first cluster {} in disk {} is larger than its cluster chain",
+        clusters[0],
+        fileSystem.getDiskNumber()
+      );
+      return Err(FsError::IOError);
+    }
+
+    for (volumeOffset, range) in mapRange(fileSystem, clusters, offset, length) {
+      fileSystem.readBytes(volumeOffset, &mut buffer[range]);
+    }
+
+    Ok(length)
+  }
+
+  // Returns fewer bytes written than requested, if the volume runs out of space midway.
+  fn writeAt(&self, offset: u64, buffer: &[u8]) -> Result<usize, FsError> {
+    if self.isDirectory {
+      return Err(FsError::IsADirectory);
+    }
+    let fileSystem = self.getFileSystem();
+    let clusterSize = fileSystem.getClusterSize() as u64;
+
+    // The size of a file is stored in 32 bits.
+    let end = offset
+      .checked_add(buffer.len() as u64)
+      .filter(|&end| end <= u32::MAX as u64)
+      .ok_or(FsError::FileTooLarge)?;
+    if buffer.is_empty() {
+      return Ok(0);
+    }
+
+    let mut state = self.state.acquire();
+
+    // FAT32 files can't have holes. So the gap (if any) past the end of the file is zero filled.
+    state.zeroFill(fileSystem, offset)?;
+    let result = state.allocateClusters(fileSystem, end.div_ceil(clusterSize) as usize);
+
+    let clusters = state.clusters.as_ref().unwrap();
+    let writableEnd = end.min(clusters.len() as u64 * clusterSize);
+    if writableEnd <= offset {
+      state.writeEntry(fileSystem);
+      return Err(result.err().unwrap_or(FsError::NoSpace));
+    }
+
+    let length = (writableEnd - offset) as usize;
+    for (volumeOffset, range) in mapRange(fileSystem, clusters, offset, length) {
+      fileSystem.writeBytes(volumeOffset, &buffer[range]);
+    }
+
+    state.size = state.size.max(writableEnd as u32);
+    state.writeEntry(fileSystem);
+
+    Ok(length)
+  }
+
+  fn truncate(&self, newSize: u64) -> Result<(), FsError> {
+    if self.isDirectory {
+      return Err(FsError::IsADirectory);
+    }
+    let fileSystem = self.getFileSystem();
+    let clusterSize = fileSystem.getClusterSize() as u64;
+
+    let newSize = u32::try_from(newSize).map_err(|_| FsError::FileTooLarge)?;
+
+    let mut state = self.state.acquire();
+    let size = state.size;
+
+    if newSize > size {
+      state.zeroFill(fileSystem, newSize as u64)?;
+
+      let requiredClustersCount = (newSize as u64).div_ceil(clusterSize) as usize;
+      if let Err(error) = state.allocateClusters(fileSystem, requiredClustersCount) {
+        // Release the clusters allocated midway.
+        state.freeClusters(fileSystem, (size as u64).div_ceil(clusterSize) as usize)?;
+        state.writeEntry(fileSystem);
+        return Err(error);
+      }
+    }
+    else {
+      state.freeClusters(fileSystem, (newSize as u64).div_ceil(clusterSize) as usize)?;
+    }
+
+    state.size = newSize;
+    state.writeEntry(fileSystem);
+
+    Ok(())
+  }
+
+  fn lookup(&self, name: &[u8]) -> Result<Arc<dyn vfs::Inode>, FsError> {
+    let fileSystem = self.getFileSystem();
+
+    let mut state = self.lockDirectory()?;
+    let clusters = state.loadClusters(fileSystem)?;
+
+    let record = directory::findRecord(fileSystem, clusters, name).ok_or(FsError::NotFound)?;
+    Ok(Self::get(&self.info, record.entryOffset, self.inodeNumber))
+  }
+
+  fn create(
+    &self,
+    name: &[u8],
+    fileType: FileType,
+    _deviceNumber: DeviceNumber,
+  ) -> Result<Arc<dyn vfs::Inode>, FsError> {
+    let isDirectory = match fileType {
+      FileType::Regular => false,
+      FileType::Directory => true,
+      _ => return Err(FsError::NotSupported),
+    };
+    let fileSystem = self.getFileSystem();
+
+    let mut state = self.lockDirectory()?;
+
+    // A removed directory can't get new entries.
+    if state.isUnlinked {
+      return Err(FsError::NotFound);
+    }
+
+    // The ".." entry of a directory refers to the root directory using cluster 0.
+    let parentCluster = match self.isRoot() {
+      true => FREE_CLUSTER,
+      false => state.firstCluster,
+    };
+    let clusters = state.loadClusters(fileSystem)?;
+
+    let shortEntry = match isDirectory {
+      true => {
+        let cluster = fileSystem.allocateCluster(None)?;
+        directory::initialize(fileSystem, cluster, parentCluster);
+
+        ShortEntry::new([b' '; 11], 0, ATTRIBUTE_DIRECTORY, cluster)
+      }
+      false => ShortEntry::new([b' '; 11], 0, ATTRIBUTE_ARCHIVE, FREE_CLUSTER),
+    };
+
+    match directory::addRecord(fileSystem, clusters, name, shortEntry) {
+      Ok(entryOffset) => Ok(Self::get(&self.info, entryOffset, self.inodeNumber)),
+
+      Err(error) => {
+        self.info.freeChain(shortEntry.firstCluster);
+        Err(error)
+      }
+    }
+  }
+
+  fn unlink(&self, name: &[u8]) -> Result<(), FsError> {
+    let fileSystem = self.getFileSystem();
+
+    let removedInode = {
+      let mut state = self.lockDirectory()?;
+      let clusters = state.loadClusters(fileSystem)?;
+
+      let record = directory::findRecord(fileSystem, clusters, name).ok_or(FsError::NotFound)?;
+      directory::removeRecord(fileSystem, &record);
+
+      self.info.detachInode(&record)
+    };
+
+    if let Some(removedInode) = removedInode {
+      removedInode.markUnlinked();
+    }
+    Ok(())
+  }
+
+  /*
+    The short entry of the file is moved to the new directory (under the new name), so the file
+    keeps its clusters. If the new name exists (and doesn't refer to the same file, being renamed
+    to a differently cased name), then the file it refers to is removed.
+
+    If adding the new entry fails, then the old entries are restored.
+
+    NOTE : Both the directories stay locked throughout. Renames are serialized by the VFS (using
+    the namespace lock), so no other rename can lock them in the opposite order.
+  */
+  fn rename(
+    &self,
+    oldName: &[u8],
+    newDirectory: &dyn vfs::Inode,
+    newName: &[u8],
+  ) -> Result<(), FsError> {
+    let newDirectory = self.downcast(newDirectory)?;
+    if !newDirectory.isDirectory {
+      return Err(FsError::NotADirectory);
+    }
+    let fileSystem = self.getFileSystem();
+
+    // Declared before the directory guards, so that it's dropped after them (dropping the last
+    // reference to an inode locks it).
+    let movedInode: Option<Arc<FatInode>>;
+
+    let mut oldDirectoryState = self.lockDirectory()?;
+    let mut newDirectoryGuard = match self.inodeNumber == newDirectory.inodeNumber {
+      true => None,
+      false => Some(newDirectory.state.acquireNested(NEW_DIRECTORY_LOCK_SUBCLASS)),
+    };
+
+    let clusters = oldDirectoryState.loadClusters(fileSystem)?;
+    let movedRecord =
+      directory::findRecord(fileSystem, clusters, oldName).ok_or(FsError::NotFound)?;
+
+    // Detach the moved inode (if it's cached) from its short entry, so that it doesn't write to the
+    // entry while it's being moved.
+    movedInode = self
+      .info
+      .inodes
+      .acquire()
+      .get(&movedRecord.entryOffset)
+      .and_then(Weak::upgrade);
+    if let Some(movedInode) = &movedInode {
+      movedInode
+        .state
+        .acquireNested(MOVED_INODE_LOCK_SUBCLASS)
+        .entryOffset = None;
+    }
+
+    let readSlots = |record: &DirectoryRecord| -> Vec<(u64, [u8; DIRECTORY_ENTRY_SIZE])> {
+      record
+        .slotOffsets
+        .iter()
+        .map(|&slotOffset| {
+          let mut slot = [0; DIRECTORY_ENTRY_SIZE];
+          fileSystem.readBytes(slotOffset, &mut slot);
+          (slotOffset, slot)
+        })
+        .collect()
+    };
+    let mut savedSlots = readSlots(&movedRecord);
+    let shortEntry = ShortEntry::decode(&savedSlots.last().unwrap().1);
+
+    directory::removeRecord(fileSystem, &movedRecord);
+
+    let newDirectoryState = newDirectoryGuard
+      .as_deref_mut()
+      .unwrap_or(&mut oldDirectoryState);
+
+    let parentCluster = match newDirectory.isRoot() {
+      true => FREE_CLUSTER,
+      false => newDirectoryState.firstCluster,
+    };
+
+    let result = match newDirectoryState.isUnlinked {
+      true => Err(FsError::NotFound),
+      false => newDirectoryState
+        .loadClusters(fileSystem)
+        .and_then(|clusters| {
+          let replacedRecord = directory::findRecord(fileSystem, clusters, newName);
+          if let Some(replacedRecord) = &replacedRecord {
+            savedSlots.extend(readSlots(replacedRecord));
+            directory::removeRecord(fileSystem, replacedRecord);
+          }
+
+          let entryOffset = directory::addRecord(fileSystem, clusters, newName, shortEntry)?;
+          Ok((entryOffset, replacedRecord))
+        }),
+    };
+
+    let (entryOffset, replacedRecord) = match result {
+      Ok(result) => result,
+
+      Err(error) => {
+        // Restore the removed entries.
+        for (slotOffset, slot) in savedSlots.iter() {
+          fileSystem.writeBytes(*slotOffset, slot);
+        }
+
+        if let Some(movedInode) = &movedInode {
+          movedInode.attachEntry(movedRecord.entryOffset, self.inodeNumber);
+        }
+        return Err(error);
+      }
+    };
+
+    // The replaced file is removed for good.
+    let replacedInode =
+      replacedRecord.and_then(|replacedRecord| self.info.detachInode(&replacedRecord));
+
+    // Move the inode (if it's cached) to the new entry.
+    if let Some(movedInode) = &movedInode {
+      movedInode.attachEntry(entryOffset, newDirectory.inodeNumber);
+    }
+    {
+      let mut inodes = self.info.inodes.acquire();
+      inodes.remove(&movedRecord.entryOffset);
+
+      if let Some(movedInode) = &movedInode {
+        inodes.insert(entryOffset, Arc::downgrade(movedInode));
+      }
+    }
+
+    if shortEntry.isDirectory() {
+      directory::setParentCluster(fileSystem, shortEntry.firstCluster, parentCluster);
+    }
+
+    drop(newDirectoryGuard);
+    drop(oldDirectoryState);
+    if let Some(replacedInode) = replacedInode {
+      replacedInode.markUnlinked();
+    }
+
+    Ok(())
+  }
+
+  // Positions 0 and 1 are the "." and ".." entries, and position n + 2 is the slot with index n in
+  // the directory.
+  fn readDirectory(&self, position: usize) -> Result<Option<(DirectoryEntry, usize)>, FsError> {
+    let fileSystem = self.getFileSystem();
+    let mut state = self.lockDirectory()?;
+
+    match position {
+      0 => Ok(Some((
+        DirectoryEntry {
+          inodeNumber: self.inodeNumber,
+          name: b".".to_vec(),
+        },
+        1,
+      ))),
+
+      1 => Ok(Some((
+        DirectoryEntry {
+          inodeNumber: state.parentInodeNumber,
+          name: b"..".to_vec(),
+        },
+        2,
+      ))),
+
+      _ => {
+        let clusters = state.loadClusters(fileSystem)?;
+
+        Ok(
+          directory::getRecords(fileSystem, clusters)
+            .into_iter()
+            .find(|record| record.index >= position - 2)
+            .map(|record| {
+              (
+                DirectoryEntry {
+                  inodeNumber: record.entryOffset / DIRECTORY_ENTRY_SIZE as u64,
+                  name: record.name,
+                },
+                record.index + 3,
+              )
+            }),
+        )
+      }
+    }
+  }
+}
@@ -0,0 +1,158 @@
+use {
+  super::{
+    file::{OpenFile, OpenFileID, OPEN_FILE_TABLE},
+    vfs::FsError,
+  },
+  crate::locks::spinlock::SpinLock,
+  alloc::{sync::Arc, vec::Vec},
+};
+
+/*
+  The file descriptor table of a process. A file descriptor is an index into it, and refers to an
+  open file in the system wide open file table.
+
+  The table is protected by a SpinLock (so that procfs can look at the table of another process).
+  Since closing a file descriptor may drop the File (which may block), the open files are released
+  only after the SpinLock is released. So, the operations removing file descriptors return the
+  removed ones, and the free functions at the end of this module do the releasing.
+*/
+pub const MAX_FILE_DESCRIPTORS: usize = 64;
+
+#[derive(Debug, Copy, Clone)]
+pub struct FileDescriptor {
+  pub openFileID: OpenFileID,
+
+  // Whether the file descriptor gets closed when the process invokes exec( ).
+  pub isCloseOnExec: bool,
+}
+
+pub struct FileDescriptorTable {
+  fileDescriptors: [Option<FileDescriptor>; MAX_FILE_DESCRIPTORS],
+}
+
+impl FileDescriptorTable {
+  pub const fn new() -> Self {
+    Self {
+      fileDescriptors: [None; MAX_FILE_DESCRIPTORS],
+    }
+  }
+
+  pub fn get(&self, fd: usize) -> Result<FileDescriptor, FsError> {
+    self
+      .fileDescriptors
+      .get(fd)
+      .copied()
+      .flatten()
+      .ok_or(FsError::BadFileDescriptor)
+  }
+
+  pub fn getMut(&mut self, fd: usize) -> Result<&mut FileDescriptor, FsError> {
+    self
+      .fileDescriptors
+      .get_mut(fd)
+      .and_then(Option::as_mut)
+      .ok_or(FsError::BadFileDescriptor)
+  }
+
+  // Returns the open file, the given file descriptor refers to.
+  pub fn getOpenFile(&self, fd: usize) -> Result<Arc<OpenFile>, FsError> {
+    Ok(OPEN_FILE_TABLE.get(self.get(fd)?.openFileID))
+  }
+
+  // Returns the open file descriptors, in increasing order.
+  pub fn getFileDescriptors(&self) -> Vec<(usize, FileDescriptor)> {
+    self
+      .fileDescriptors
+      .iter()
+      .enumerate()
+      .filter_map(|(fd, fileDescriptor)| fileDescriptor.map(|fileDescriptor| (fd, fileDescriptor)))
+      .collect()
+  }
+
+  // Stores the given file descriptor at the lowest free index, which is at least the given one.
+  // Returns that index.
+  // NOTE : The reference count of the open file isn't touched.
+  pub fn allocate(
+    &mut self,
+    lowestFD: usize,
+    fileDescriptor: FileDescriptor,
+  ) -> Result<usize, FsError> {
+    let fd = (lowestFD..MAX_FILE_DESCRIPTORS)
+      .find(|fd| self.fileDescriptors[*fd].is_none())
+      .ok_or(FsError::TooManyOpenFiles)?;
+
+    self.fileDescriptors[fd] = Some(fileDescriptor);
+    Ok(fd)
+  }
+
+  // Stores the given file descriptor at the given index. Returns the file descriptor it replaced
+  // (if any).
+  pub fn replace(
+    &mut self,
+    fd: usize,
+    fileDescriptor: FileDescriptor,
+  ) -> Result<Option<FileDescriptor>, FsError> {
+    let slot = self
+      .fileDescriptors
+      .get_mut(fd)
+      .ok_or(FsError::BadFileDescriptor)?;
+
+    Ok(slot.replace(fileDescriptor))
+  }
+
+  pub fn remove(&mut self, fd: usize) -> Result<FileDescriptor, FsError> {
+    self
+      .fileDescriptors
+      .get_mut(fd)
+      .and_then(Option::take)
+      .ok_or(FsError::BadFileDescriptor)
+  }
+
+  // Removes the file descriptors which satisfy the given predicate, and returns them.
+  fn removeIf(&mut self, predicate: impl Fn(&FileDescriptor) -> bool) -> Vec<FileDescriptor> {
+    self
+      .fileDescriptors
+      .iter_mut()
+      .filter(|fileDescriptor| fileDescriptor.as_ref().is_some_and(&predicate))
+      .filter_map(Option::take)
+      .collect()
+  }
+}
+
+// Releases the open files, the given (removed) file descriptors referred to.
+// NOTE : Must be invoked without holding any SpinLock.
+pub fn releaseAll(fileDescriptors: Vec<FileDescriptor>) {
+  for fileDescriptor in fileDescriptors {
+    OPEN_FILE_TABLE.release(fileDescriptor.openFileID);
+  }
+}
+
+// Returns a copy of the given file descriptor table, for a process being forked. The copied file
+// descriptors refer to the same open files (sharing the offsets and the status flags).
+pub fn fork(table: &SpinLock<FileDescriptorTable>) -> FileDescriptorTable {
+  let table = table.acquire();
+
+  for (_, fileDescriptor) in table.getFileDescriptors() {
+    OPEN_FILE_TABLE.duplicate(fileDescriptor.openFileID);
+  }
+
+  FileDescriptorTable {
+    fileDescriptors: table.fileDescriptors,
+  }
+}
+
+// Closes the file descriptors marked close on exec. Invoked once exec( ) has replaced the program
+// of the process.
+pub fn closeOnExec(table: &SpinLock<FileDescriptorTable>) {
+  let fileDescriptors = table
+    .acquire()
+    .removeIf(|fileDescriptor| fileDescriptor.isCloseOnExec);
+
+  releaseAll(fileDescriptors);
+}
+
+// Closes all the file descriptors. Invoked when the process exits.
+pub fn closeAll(table: &SpinLock<FileDescriptorTable>) {
+  let fileDescriptors = table.acquire().removeIf(|_| true);
+  releaseAll(fileDescriptors);
+}
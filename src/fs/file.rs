@@ -0,0 +1,224 @@
+use {
+  super::vfs::{File, FsError, OpenFlags},
+  crate::locks::{sleeplock::SleepLock, spinlock::SpinLock},
+  alloc::sync::Arc,
+  array_macro::array,
+};
+
+/*
+  An open file : a File along with the state of that particular opening of it (the offset and the
+  flags). Each call to open( ) creates a new open file, which is then shared by all the file
+  descriptors duplicated from the one open( ) returned (across processes as well, since a forked
+  process inherits the file descriptors).
+
+  REFER : section 5.1 (Overview of File I/O system calls) of The Linux Programming Interface by
+  Michael Kerrisk.
+*/
+pub struct OpenFile {
+  file: Arc<dyn File>,
+
+  // The access mode (READ / WRITE) and the status flags (APPEND / NON_BLOCKING).
+  flags: SpinLock<OpenFlags>,
+
+  // Also serializes the reads and writes of a seekable File, so that they don't use the same
+  // offset.
+  offset: SleepLock<u64>,
+}
+
+// The flags which get stored in an open file. The other ones only matter while opening.
+const ACCESS_MODE_FLAGS: OpenFlags = OpenFlags::READ.union(OpenFlags::WRITE);
+const STATUS_FLAGS: OpenFlags = OpenFlags::APPEND.union(OpenFlags::NON_BLOCKING);
+
+// Where lseek( ) takes the offset relative to.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Whence {
+  Start,
+  Current,
+  End,
+}
+
+impl OpenFile {
+  pub fn new(file: Arc<dyn File>, flags: OpenFlags) -> Self {
+    Self {
+      file,
+      flags: SpinLock::new(flags & (ACCESS_MODE_FLAGS | STATUS_FLAGS)),
+      offset: SleepLock::new(0),
+    }
+  }
+
+  #[inline]
+  pub fn getFile(&self) -> &Arc<dyn File> {
+    &self.file
+  }
+
+  #[inline]
+  pub fn getFlags(&self) -> OpenFlags {
+    *self.flags.acquire()
+  }
+
+  // Replaces the status flags (the access mode can't be changed).
+  pub fn setStatusFlags(&self, statusFlags: OpenFlags) {
+    let mut flags = self.flags.acquire();
+    *flags = (*flags & ACCESS_MODE_FLAGS) | (statusFlags & STATUS_FLAGS);
+  }
+
+  // Reads from the current offset, and advances it by the number of bytes read.
+  pub fn read(&self, buffer: &mut [u8]) -> Result<usize, FsError> {
+    let flags = self.getFlags();
+    if !flags.contains(OpenFlags::READ) {
+      return Err(FsError::BadFileDescriptor);
+    }
+
+    // A File which isn't seekable may block indefinitely (like a pipe), so the offset lock mustn't
+    // be held.
+    if !self.file.isSeekable() {
+      return match flags.contains(OpenFlags::NON_BLOCKING) {
+        true => self.file.readNonBlocking(0, buffer),
+        false => self.file.read(0, buffer),
+      };
+    }
+
+    let mut offset = self.offset.acquire();
+
+    let bytesRead = self.file.read(*offset, buffer)?;
+    *offset += bytesRead as u64;
+
+    Ok(bytesRead)
+  }
+
+  // Writes at the current offset (or the end of the file, in append mode), and advances it by the
+  // number of bytes written.
+  pub fn write(&self, buffer: &[u8]) -> Result<usize, FsError> {
+    let flags = self.getFlags();
+    if !flags.contains(OpenFlags::WRITE) {
+      return Err(FsError::BadFileDescriptor);
+    }
+
+    if !self.file.isSeekable() {
+      return match flags.contains(OpenFlags::NON_BLOCKING) {
+        true => self.file.writeNonBlocking(0, buffer),
+        false => self.file.write(0, buffer),
+      };
+    }
+
+    let mut offset = self.offset.acquire();
+
+    if flags.contains(OpenFlags::APPEND) {
+      *offset = self.file.getStat()?.size;
+    }
+
+    let bytesWritten = self.file.write(*offset, buffer)?;
+    *offset += bytesWritten as u64;
+
+    Ok(bytesWritten)
+  }
+
+  // Moves the offset, and returns the new one.
+  pub fn seek(&self, delta: i64, whence: Whence) -> Result<u64, FsError> {
+    if !self.file.isSeekable() {
+      return Err(FsError::IllegalSeek);
+    }
+
+    let mut offset = self.offset.acquire();
+
+    let base = match whence {
+      Whence::Start => 0,
+      Whence::Current => *offset,
+      Whence::End => self.file.getStat()?.size,
+    };
+
+    *offset = base
+      .checked_add_signed(delta)
+      .ok_or(FsError::InvalidArgument)?;
+
+    Ok(*offset)
+  }
+}
+
+/*
+  The system wide open file table.
+
+  Each entry counts the file descriptors referring to the open file. The open file is released once
+  the last of them gets closed. The File itself is dropped once any read / write which is in
+  progress also finishes (since those hold a reference to the open file).
+*/
+pub const MAX_OPEN_FILES: usize = 256;
+
+// Index of an open file in the open file table.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct OpenFileID(usize);
+
+struct OpenFileTableEntry {
+  refCount: usize,
+  openFile: Option<Arc<OpenFile>>,
+}
+
+pub struct OpenFileTable {
+  entries: SpinLock<[OpenFileTableEntry; MAX_OPEN_FILES]>,
+}
+
+pub static OPEN_FILE_TABLE: OpenFileTable = OpenFileTable::new();
+
+impl OpenFileTable {
+  const fn new() -> Self {
+    Self {
+      entries: SpinLock::new(array![_ => OpenFileTableEntry {
+        refCount: 0,
+        openFile: None,
+      }; MAX_OPEN_FILES]),
+    }
+  }
+
+  // Adds the given open file, referred to by a single file descriptor.
+  pub fn insert(&self, openFile: OpenFile) -> Result<OpenFileID, FsError> {
+    let mut entries = self.entries.acquire();
+
+    let (index, entry) = entries
+      .iter_mut()
+      .enumerate()
+      .find(|(_, entry)| entry.refCount == 0)
+      .ok_or(FsError::TooManyOpenFiles)?;
+
+    entry.refCount = 1;
+    entry.openFile = Some(Arc::new(openFile));
+
+    Ok(OpenFileID(index))
+  }
+
+  // Returns the given open file.
+  pub fn get(&self, openFileID: OpenFileID) -> Arc<OpenFile> {
+    self.entries.acquire()[openFileID.0]
+      .openFile
+      .clone()
+      .expect("Open file isn't in use")
+  }
+
+  // Records that another file descriptor refers to the given open file.
+  pub fn duplicate(&self, openFileID: OpenFileID) {
+    let mut entries = self.entries.acquire();
+
+    let entry = &mut entries[openFileID.0];
+    assert!(entry.refCount > 0, "Open file isn't in use");
+
+    entry.refCount += 1;
+  }
+
+  // Records that a file descriptor referring to the given open file got closed.
+  // NOTE : Must be invoked without holding any SpinLock, since dropping the File may block.
+  pub fn release(&self, openFileID: OpenFileID) {
+    let openFile = {
+      let mut entries = self.entries.acquire();
+
+      let entry = &mut entries[openFileID.0];
+      assert!(entry.refCount > 0, "Open file isn't in use");
+
+      entry.refCount -= 1;
+      match entry.refCount {
+        0 => entry.openFile.take(),
+        _ => None,
+      }
+    };
+
+    drop(openFile);
+  }
+}
@@ -0,0 +1,440 @@
+use {
+  super::disk::{self, BlockDevice, BlockOperation, BlockRequest, CompletionHook, Disk},
+  crate::{
+    arch::riscv::registers::time::{Time, TIME_FREQUENCY},
+    locks::spinlock::SpinLock,
+  },
+  alloc::vec::Vec,
+  core::sync::atomic::{AtomicUsize, Ordering},
+};
+
+/*
+  The block I/O scheduler, sitting between the users of a disk (like the Buffer Cache) and its
+  BlockDevice. Instead of handing over requests to the device in the order they're submitted, it
+  queues them up and :
+
+    (1) orders them using the elevator algorithm (C-LOOK) : requests are dispatched in increasing
+        order of their block numbers, starting from where the last dispatched request ended. Once
+        there are no requests ahead, it wraps around to the lowest block number.
+
+    (2) guarantees a deadline to each request : if the oldest pending request has been waiting
+        for too long, then it gets dispatched next, regardless of the elevator order. Reads get a
+        shorter deadline than writes, since a process is usually waiting for them.
+
+    (3) merges requests of the same kind for contiguous blocks, into a single device request (a
+        scatter-gather list, for the virtio block device).
+
+  Only a limited number of device requests are kept in flight (the queue depth of the device).
+  The rest wait in the queue, which is where the reordering and merging happen. A submitter can
+  also plug the disk, to queue up a bunch of requests (say, read-ahead) before any of them gets
+  dispatched.
+
+  A flush request acts as a barrier : it's only dispatched once all the requests submitted before
+  it have been completed, and the requests submitted after it wait till it's completed.
+*/
+
+// Time (in time CSR units) a request can wait in the queue, before it gets dispatched ahead of the
+// elevator order.
+const READ_DEADLINE: usize = TIME_FREQUENCY / 20; // 50 ms
+const WRITE_DEADLINE: usize = TIME_FREQUENCY / 2; // 500 ms
+
+struct QueuedRequest {
+  request: *const BlockRequest,
+
+  // Value of the time CSR, when the request was submitted.
+  submittedAt: usize,
+
+  // For an in flight request, the device request it has been merged into.
+  deviceRequest: usize,
+}
+
+impl QueuedRequest {
+  #[inline]
+  fn getRequest(&self) -> &BlockRequest {
+    // Requests stay alive till they're completed.
+    unsafe { &*self.request }
+  }
+
+  #[inline]
+  fn getDeadline(&self) -> usize {
+    let deadline = match self.getRequest().operation {
+      BlockOperation::Read => READ_DEADLINE,
+      _ => WRITE_DEADLINE,
+    };
+    self.submittedAt + deadline
+  }
+}
+
+struct IOSchedulerState {
+  // Requests waiting to be dispatched, in the order they were submitted.
+  pendingRequests: Vec<QueuedRequest>,
+
+  // Requests handed over to the device.
+  inFlightRequests: Vec<QueuedRequest>,
+  inFlightDeviceRequestsCount: usize,
+  nextDeviceRequest: usize,
+
+  isFlushInFlight: bool,
+
+  // The device block number, right after the end of the last dispatched request.
+  headPosition: usize,
+
+  // Number of plugs currently held. Nothing gets dispatched while the disk is plugged.
+  plugsCount: usize,
+
+  // Whether some CPU core is dispatching requests to the device.
+  isDispatching: bool,
+}
+
+// The requests are only accessed while holding the SpinLock guarding the state.
+unsafe impl Send for IOSchedulerState {}
+
+impl IOSchedulerState {
+  const fn new() -> Self {
+    Self {
+      pendingRequests: Vec::new(),
+
+      inFlightRequests: Vec::new(),
+      inFlightDeviceRequestsCount: 0,
+      nextDeviceRequest: 0,
+
+      isFlushInFlight: false,
+
+      headPosition: 0,
+
+      plugsCount: 0,
+
+      isDispatching: false,
+    }
+  }
+
+  /*
+    Picks the next batch of pending requests to be dispatched as a single device request, and
+    moves them to the in flight requests. Returns None if nothing can be dispatched right now.
+
+    Returns whether the batch was picked because a deadline expired, along with the batch.
+  */
+  fn pickNextBatch(
+    &mut self,
+    device: &dyn BlockDevice,
+    now: usize,
+  ) -> Option<(bool, Vec<*const BlockRequest>)> {
+    if self.plugsCount > 0
+      || self.isFlushInFlight
+      || self.inFlightDeviceRequestsCount >= device.getQueueDepth()
+    {
+      return None;
+    }
+
+    // Requests submitted after a flush, can't be dispatched before it.
+    let barrier = self
+      .pendingRequests
+      .iter()
+      .position(|pendingRequest| pendingRequest.getRequest().operation == BlockOperation::Flush)
+      .unwrap_or(self.pendingRequests.len());
+
+    if barrier == 0 {
+      // All the requests submitted before the flush must have been completed.
+      if self.pendingRequests.is_empty() || !self.inFlightRequests.is_empty() {
+        return None;
+      }
+
+      self.isFlushInFlight = true;
+      let batch = Vec::from([self.dispatch(0)]);
+
+      self.startDeviceRequest();
+      return Some((false, batch));
+    }
+
+    let isDeadlineExpired = self.pendingRequests[0].getDeadline() <= now;
+
+    let first = match isDeadlineExpired {
+      true => 0,
+
+      // C-LOOK.
+      false => {
+        let headPosition = self.headPosition;
+        let blockNumbers = self.pendingRequests[..barrier]
+          .iter()
+          .map(|pendingRequest| pendingRequest.getRequest().blockNumber);
+
+        let ahead = blockNumbers
+          .clone()
+          .enumerate()
+          .filter(|&(_, blockNumber)| blockNumber >= headPosition)
+          .min_by_key(|&(_, blockNumber)| blockNumber);
+        let lowest = blockNumbers
+          .enumerate()
+          .min_by_key(|&(_, blockNumber)| blockNumber);
+
+        ahead.or(lowest).unwrap().0
+      }
+    };
+
+    let deviceBlockSize = device.getBlockSize();
+    let getEnd = |request: &BlockRequest| request.blockNumber + request.length / deviceBlockSize;
+
+    let operation = self.pendingRequests[first].getRequest().operation;
+    let mut end = getEnd(self.pendingRequests[first].getRequest());
+
+    let mut batch = Vec::from([self.dispatch(first)]);
+
+    // Merge the pending requests which continue from where the batch ends.
+    while batch.len() < device.getMaxMergedRequestsCount() {
+      let Some(next) = self.pendingRequests[..barrier - batch.len()]
+        .iter()
+        .position(|pendingRequest| {
+          let request = pendingRequest.getRequest();
+          request.operation == operation && request.blockNumber == end
+        })
+      else {
+        break;
+      };
+
+      end = getEnd(self.pendingRequests[next].getRequest());
+      batch.push(self.dispatch(next));
+    }
+
+    self.headPosition = end;
+
+    self.startDeviceRequest();
+    Some((isDeadlineExpired, batch))
+  }
+
+  // Accounts for the device request formed by the requests dispatched since the last one.
+  fn startDeviceRequest(&mut self) {
+    self.inFlightDeviceRequestsCount += 1;
+    self.nextDeviceRequest += 1;
+  }
+
+  // Moves the pending request at the given index, to the in flight requests. It becomes part of the
+  // device request being formed.
+  fn dispatch(&mut self, index: usize) -> *const BlockRequest {
+    let mut queuedRequest = self.pendingRequests.remove(index);
+    let request = queuedRequest.request;
+
+    queuedRequest.deviceRequest = self.nextDeviceRequest;
+    self.inFlightRequests.push(queuedRequest);
+
+    request
+  }
+}
+
+// Per disk I/O statistics.
+// NOTE : The latencies are measured in ticks of the time CSR.
+pub struct IOStatistics {
+  requests: AtomicUsize,
+  deviceRequests: AtomicUsize,
+
+  // Requests which got merged into the device request of another request.
+  mergedRequests: AtomicUsize,
+
+  // Device requests dispatched ahead of the elevator order, due to an expired deadline.
+  expiredDeadlines: AtomicUsize,
+
+  // Number of requests submitted, but not yet completed.
+  queueDepth: AtomicUsize,
+  maxQueueDepth: AtomicUsize,
+
+  // Time between the submission and the completion of a request.
+  totalLatency: AtomicUsize,
+  maxLatency: AtomicUsize,
+}
+
+impl IOStatistics {
+  const fn new() -> Self {
+    Self {
+      requests: AtomicUsize::new(0),
+      deviceRequests: AtomicUsize::new(0),
+
+      mergedRequests: AtomicUsize::new(0),
+
+      expiredDeadlines: AtomicUsize::new(0),
+
+      queueDepth: AtomicUsize::new(0),
+      maxQueueDepth: AtomicUsize::new(0),
+
+      totalLatency: AtomicUsize::new(0),
+      maxLatency: AtomicUsize::new(0),
+    }
+  }
+
+  fn recordSubmission(&self) {
+    self.requests.fetch_add(1, Ordering::Relaxed);
+
+    let queueDepth = self.queueDepth.fetch_add(1, Ordering::Relaxed) + 1;
+    self.maxQueueDepth.fetch_max(queueDepth, Ordering::Relaxed);
+  }
+
+  fn recordDispatch(&self, batchLength: usize, isDeadlineExpired: bool) {
+    self.deviceRequests.fetch_add(1, Ordering::Relaxed);
+    self
+      .mergedRequests
+      .fetch_add(batchLength - 1, Ordering::Relaxed);
+
+    if isDeadlineExpired {
+      self.expiredDeadlines.fetch_add(1, Ordering::Relaxed);
+    }
+  }
+
+  fn recordCompletion(&self, latency: usize) {
+    self.queueDepth.fetch_sub(1, Ordering::Relaxed);
+
+    self.totalLatency.fetch_add(latency, Ordering::Relaxed);
+    self.maxLatency.fetch_max(latency, Ordering::Relaxed);
+  }
+
+  // Prints the statistics, labelled with the given disk number.
+  pub fn dump(&self, diskNumber: usize) {
+    let requests = self.requests.load(Ordering::Relaxed);
+    let completedRequests = requests - self.queueDepth.load(Ordering::Relaxed);
+
+    // Converts the given number of ticks to microseconds.
+    let toMicroseconds = |ticks: usize| ticks / (TIME_FREQUENCY / 1_000_000);
+
+    println!(
+      "Disk {} : requests = {}, device requests = {}, merged = {}, expired deadlines = {}, queue depth (current / max) = {} / {}, latency in μs (average / max) = {} / {}",
+      diskNumber,
+      requests,
+      self.deviceRequests.load(Ordering::Relaxed),
+      self.mergedRequests.load(Ordering::Relaxed),
+      self.expiredDeadlines.load(Ordering::Relaxed),
+      self.queueDepth.load(Ordering::Relaxed),
+      self.maxQueueDepth.load(Ordering::Relaxed),
+      toMicroseconds(
+        self
+          .totalLatency
+          .load(Ordering::Relaxed)
+          .checked_div(completedRequests)
+          .unwrap_or(0)
+      ),
+      toMicroseconds(self.maxLatency.load(Ordering::Relaxed)),
+    );
+  }
+}
+
+pub struct IOScheduler {
+  state: SpinLock<IOSchedulerState>,
+  statistics: IOStatistics,
+}
+
+impl IOScheduler {
+  pub const fn new() -> Self {
+    Self {
+      state: SpinLock::new(IOSchedulerState::new()),
+      statistics: IOStatistics::new(),
+    }
+  }
+
+  #[inline]
+  pub fn getStatistics(&self) -> &IOStatistics {
+    &self.statistics
+  }
+
+  /*
+    Queues up the given request, and dispatches requests to the device if possible.
+
+    # Safety
+
+    The request (and its buffer) must stay alive and must not be moved, till the request gets
+    completed.
+  */
+  pub unsafe fn submit(&self, disk: &Disk, request: &BlockRequest) {
+    request.setCompletionHook(CompletionHook {
+      function: onRequestCompleted,
+      argument: disk.getDiskNumber(),
+    });
+
+    self.statistics.recordSubmission();
+    self.state.acquire().pendingRequests.push(QueuedRequest {
+      request,
+      submittedAt: Time.read(),
+      deviceRequest: 0,
+    });
+
+    self.dispatch(disk);
+  }
+
+  pub fn plug(&self) {
+    self.state.acquire().plugsCount += 1;
+  }
+
+  pub fn unplug(&self, disk: &Disk) {
+    self.state.acquire().plugsCount -= 1;
+    self.dispatch(disk);
+  }
+
+  /*
+    Hands over requests to the device, till the device queue is full or there are no more requests
+    which can be dispatched.
+
+    Only 1 CPU core dispatches at a time. If some other CPU core (or an interrupt handler, or a
+    device completing requests synchronously from inside submit( )) tries to dispatch meanwhile,
+    then it just returns : the dispatching CPU core re-examines the queue before stopping.
+  */
+  fn dispatch(&self, disk: &Disk) {
+    let device = disk.getDevice();
+
+    let mut state = self.state.acquire();
+    if state.isDispatching {
+      return;
+    }
+    state.isDispatching = true;
+
+    while let Some((isDeadlineExpired, batch)) = state.pickNextBatch(device, Time.read()) {
+      drop(state);
+
+      self
+        .statistics
+        .recordDispatch(batch.len(), isDeadlineExpired);
+
+      let batch: Vec<&BlockRequest> = batch.iter().map(|&request| unsafe { &*request }).collect();
+      match batch[0].operation {
+        BlockOperation::Flush => unsafe { device.submit(batch[0]) },
+        _ => unsafe { device.submitMerged(&batch) },
+      }
+
+      state = self.state.acquire();
+    }
+
+    state.isDispatching = false;
+  }
+
+  // Invoked when the given in flight request gets completed by the device.
+  fn complete(&self, disk: &Disk, request: &BlockRequest) {
+    let mut state = self.state.acquire();
+
+    let index = state
+      .inFlightRequests
+      .iter()
+      .position(|inFlightRequest| inFlightRequest.request == request as *const BlockRequest)
+      .expect("Completed request isn't in flight");
+    let inFlightRequest = state.inFlightRequests.remove(index);
+
+    // The device request completes along with its last request.
+    if !state
+      .inFlightRequests
+      .iter()
+      .any(|other| other.deviceRequest == inFlightRequest.deviceRequest)
+    {
+      state.inFlightDeviceRequestsCount -= 1;
+    }
+
+    if request.operation == BlockOperation::Flush {
+      state.isFlushInFlight = false;
+    }
+    drop(state);
+
+    self
+      .statistics
+      .recordCompletion(Time.read().saturating_sub(inFlightRequest.submittedAt));
+
+    self.dispatch(disk);
+  }
+}
+
+// The completion hook of every request submitted through an IOScheduler.
+fn onRequestCompleted(request: &BlockRequest, diskNumber: usize) {
+  let disk = disk::getDisk(diskNumber);
+  disk.getScheduler().complete(disk, request);
+}
@@ -0,0 +1,361 @@
+use {
+  super::{
+    bcache::{BCacheNode, BCACHE},
+    arnofs::layout::LOG_SIZE,
+    disk::{self, BlockOperation, BlockRequest},
+    BLOCK_SIZE, FS_OP_MAX_BLOCK_WRITES,
+  },
+  crate::locks::{spinlock::SpinLock, waitqueue::WaitQueue},
+  alloc::{boxed::Box, vec::Vec},
+};
+
+/*
+  The log (write-ahead log) makes File System operations crash consistent. An operation may write
+  to multiple blocks (say, an inode, a bitmap block and a directory block). If the system crashes
+  after only some of them have reached the disk, then the File System gets corrupted. With the
+  log, either all of an operation's writes reach their home locations on the disk, or none do.
+
+  An operation wraps its writes in a transaction :
+
+    let logOperation = log.beginOp( );
+    let mut bcacheNode = BCACHE.read(diskNumber, blockNumber);
+    // modify bcacheNode
+    logOperation.write(&bcacheNode);
+    drop(bcacheNode);
+    drop(logOperation); // endOp( )
+
+  logOperation.write( ) only records the block number, and pins the block in the Buffer Cache (so
+  that it doesn't get written back to its home location prematurely). Repeated writes to the same
+  block within a transaction are absorbed into a single log entry.
+
+  Concurrent operations get grouped into a single transaction (group commit). Once the last of them
+  ends, the transaction is committed :
+
+    (1) the modified blocks are written to the log area.
+
+    (2) the log header, containing their home block numbers, is written. This is the commit point :
+        if the system crashes after this, the transaction is replayed when the log is initialized
+        next time.
+
+    (3) the modified blocks are written to their home locations (installed).
+
+    (4) the log header is cleared.
+
+  On disk layout of the log area : the log header block, followed by the log blocks.
+
+  REFER : section 8.4 of the xv6 book.
+*/
+
+/*
+  The log header, stored in the first block of the log area. It's encoded as little endian u32s :
+  the number of logged blocks, followed by their home block numbers.
+
+  NOTE : The encoded header fits in a single sector, whose write the disk performs atomically. So
+  a crash can't leave behind a partially written header.
+*/
+#[derive(Clone)]
+pub struct LogHeader {
+  pub blockNumbers: Vec<u32>,
+}
+
+const _: () = assert!(
+  (LOG_SIZE + 1) * 4 <= 512,
+  "Log header doesn't fit in a sector"
+);
+
+impl LogHeader {
+  pub const fn new() -> Self {
+    Self {
+      blockNumbers: Vec::new(),
+    }
+  }
+
+  pub fn decode(block: &[u8; BLOCK_SIZE]) -> Self {
+    let readU32 =
+      |index: usize| u32::from_le_bytes(block[index * 4..(index + 1) * 4].try_into().unwrap());
+
+    let blocksCount = (readU32(0) as usize).min(LOG_SIZE);
+    Self {
+      blockNumbers: (1..=blocksCount).map(readU32).collect(),
+    }
+  }
+
+  pub fn encode(&self, block: &mut [u8; BLOCK_SIZE]) {
+    block.fill(0);
+
+    block[..4].copy_from_slice(&(self.blockNumbers.len() as u32).to_le_bytes());
+    for (index, blockNumber) in self.blockNumbers.iter().enumerate() {
+      block[(index + 1) * 4..(index + 2) * 4].copy_from_slice(&blockNumber.to_le_bytes());
+    }
+  }
+}
+
+struct LogState {
+  diskNumber: usize,
+
+  // Block number of the log header. The log blocks follow it.
+  start: usize,
+
+  // Number of log blocks.
+  size: usize,
+
+  // Number of File System operations in the ongoing transaction, which haven't ended yet.
+  outstandingOpsCount: usize,
+
+  // Whether the ongoing transaction is being committed.
+  isCommitting: bool,
+
+  // In memory copy of the log header, of the ongoing transaction.
+  logHeader: LogHeader,
+}
+
+pub struct Log {
+  state: SpinLock<LogState>,
+
+  // Processes waiting to begin an operation.
+  waitQueue: WaitQueue,
+}
+
+impl Log {
+  pub const fn new() -> Self {
+    Self {
+      state: SpinLock::new(LogState {
+        diskNumber: 0,
+
+        start: 0,
+        size: 0,
+
+        outstandingOpsCount: 0,
+        isCommitting: false,
+
+        logHeader: LogHeader::new(),
+      }),
+      waitQueue: WaitQueue::new(),
+    }
+  }
+
+  // Initializes the log, whose area starts at the given block of the given disk and spans the
+  // given number of blocks (including the log header). Replays the committed transaction (if any).
+  // NOTE : Should be invoked when the File System is mounted, before any operation begins.
+  pub fn init(&self, diskNumber: usize, start: usize, blocksCount: usize) {
+    assert!(
+      blocksCount >= 2,
+      "Log area must have a header block and a log block"
+    );
+
+    let mut state = self.state.acquire();
+
+    state.diskNumber = diskNumber;
+    state.start = start;
+    state.size = (blocksCount - 1).min(LOG_SIZE);
+
+    let size = state.size;
+    drop(state);
+
+    self.recover(diskNumber, start, size);
+  }
+
+  // Replays the transaction recorded in the log header (if any), and clears the log header.
+  fn recover(&self, diskNumber: usize, start: usize, size: usize) {
+    let disk = disk::getDisk(diskNumber);
+    let mut block = Box::new([0; BLOCK_SIZE]);
+
+    disk
+      .readBlock(start, &mut block)
+      .expect("Failed reading log header");
+    let logHeader = LogHeader::decode(&block);
+
+    if logHeader.blockNumbers.is_empty() {
+      return;
+    }
+    assert!(
+      logHeader.blockNumbers.len() <= size,
+      "Log header is corrupted"
+    );
+
+    println!(
+      "INFO : Replaying {} logged blocks in disk {}",
+      logHeader.blockNumbers.len(),
+      diskNumber
+    );
+
+    for (index, &blockNumber) in logHeader.blockNumbers.iter().enumerate() {
+      disk
+        .readBlock(start + 1 + index, &mut block)
+        .expect("Failed reading log block");
+
+      let mut bcacheNode = BCACHE.read(diskNumber, blockNumber as usize);
+      bcacheNode.copy_from_slice(&*block);
+      BCACHE.write(&mut bcacheNode);
+    }
+
+    // The installed blocks must be durable before the log header gets cleared. Otherwise, a crash
+    // can lose the committed transaction, if the disk has a volatile write cache.
+    BCACHE.sync(diskNumber);
+
+    self.writeLogHeader(diskNumber, start, &LogHeader::new());
+  }
+
+  /*
+    Begins a File System operation. It ends when the returned LogOperation is dropped.
+
+    Waits while the ongoing transaction is being committed, or while the log doesn't have enough
+    space for the operation (assuming that each outstanding operation writes to the maximum number
+    of blocks).
+  */
+  pub fn beginOp(&self) -> LogOperation<'_> {
+    let mut state = self.waitQueue.waitUntil(self.state.acquire(), |state| {
+      !state.isCommitting
+        && state.logHeader.blockNumbers.len()
+          + (state.outstandingOpsCount + 1) * FS_OP_MAX_BLOCK_WRITES
+          <= state.size
+    });
+    state.outstandingOpsCount += 1;
+
+    LogOperation { log: self }
+  }
+
+  // Records that the given (modified) cached block is part of the ongoing transaction.
+  fn write(&self, bcacheNode: &BCacheNode) {
+    let mut state = self.state.acquire();
+
+    assert!(
+      state.outstandingOpsCount > 0,
+      "Block written to the log outside a File System operation"
+    );
+    assert_eq!(
+      bcacheNode.getDiskNumber(),
+      state.diskNumber,
+      "Block written to the log of another disk"
+    );
+
+    let blockNumber = bcacheNode.getBlockNumber() as u32;
+
+    // Absorption.
+    if state.logHeader.blockNumbers.contains(&blockNumber) {
+      return;
+    }
+
+    assert!(
+      state.logHeader.blockNumbers.len() < state.size,
+      "Transaction is too big for the log"
+    );
+    state.logHeader.blockNumbers.push(blockNumber);
+
+    // The block must stay cached (and must not be written back to its home location) till the
+    // transaction is committed.
+    BCACHE.pin(bcacheNode);
+  }
+
+  // Ends a File System operation. If it's the last outstanding operation, then the transaction is
+  // committed.
+  fn endOp(&self) {
+    let mut state = self.state.acquire();
+
+    assert!(!state.isCommitting, "Operation ended during a commit");
+    state.outstandingOpsCount -= 1;
+
+    if state.outstandingOpsCount > 0 {
+      drop(state);
+
+      // Some space in the log has been freed up, since this operation has ended.
+      self.waitQueue.notifyAll();
+      return;
+    }
+
+    state.isCommitting = true;
+    let (diskNumber, start) = (state.diskNumber, state.start);
+    let logHeader = state.logHeader.clone();
+    drop(state);
+
+    // The log blocks are written without holding the SpinLock, since we may go to sleep.
+    self.commit(diskNumber, start, &logHeader);
+
+    let mut state = self.state.acquire();
+    state.logHeader.blockNumbers.clear();
+    state.isCommitting = false;
+    drop(state);
+
+    self.waitQueue.notifyAll();
+  }
+
+  fn commit(&self, diskNumber: usize, start: usize, logHeader: &LogHeader) {
+    if logHeader.blockNumbers.is_empty() {
+      return;
+    }
+
+    let disk = disk::getDisk(diskNumber);
+
+    // Write the modified blocks to the log area. The log blocks are contiguous, so they're written
+    // using a single request.
+    let mut logBlocks = alloc::vec![0; logHeader.blockNumbers.len() * BLOCK_SIZE];
+    for (&blockNumber, logBlock) in logHeader
+      .blockNumbers
+      .iter()
+      .zip(logBlocks.as_chunks_mut::<BLOCK_SIZE>().0)
+    {
+      logBlock.copy_from_slice(&*BCACHE.read(diskNumber, blockNumber as usize));
+    }
+
+    let request = BlockRequest::new(
+      BlockOperation::Write,
+      disk.toDeviceBlockNumber(start + 1),
+      logBlocks.as_mut_ptr(),
+      logBlocks.len(),
+    );
+    unsafe { disk.submit(&request) };
+    disk.waitFor(&request).expect("Failed writing log blocks");
+
+    // The log blocks must reach the disk before the log header.
+    disk.flush().expect("Failed flushing disk");
+
+    // Commit point.
+    self.writeLogHeader(diskNumber, start, logHeader);
+
+    // Install the transaction.
+    for &blockNumber in logHeader.blockNumbers.iter() {
+      let mut bcacheNode = BCACHE.read(diskNumber, blockNumber as usize);
+      bcacheNode.markDirty();
+
+      BCACHE.unpin(&bcacheNode);
+      BCACHE.startWriteBack(bcacheNode);
+    }
+    BCACHE.sync(diskNumber);
+
+    // The transaction has been installed. So it must not be replayed anymore.
+    self.writeLogHeader(diskNumber, start, &LogHeader::new());
+  }
+
+  // Writes the given log header to the disk, and waits till it reaches stable storage.
+  fn writeLogHeader(&self, diskNumber: usize, start: usize, logHeader: &LogHeader) {
+    let disk = disk::getDisk(diskNumber);
+
+    let mut block = Box::new([0; BLOCK_SIZE]);
+    logHeader.encode(&mut block);
+
+    disk
+      .writeBlock(start, &block)
+      .expect("Failed writing log header");
+    disk.flush().expect("Failed flushing disk");
+  }
+}
+
+// A File System operation, which is part of the ongoing transaction. The operation ends (endOp)
+// when it's dropped.
+pub struct LogOperation<'a> {
+  log: &'a Log,
+}
+
+impl LogOperation<'_> {
+  // Records that the given cached block has been modified by this operation. Use this instead of
+  // BCache::write( ).
+  pub fn write(&self, bcacheNode: &BCacheNode) {
+    self.log.write(bcacheNode);
+  }
+}
+
+impl Drop for LogOperation<'_> {
+  fn drop(&mut self) {
+    self.log.endOp();
+  }
+}
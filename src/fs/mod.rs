@@ -1,16 +1,22 @@
 pub const MAX_DISKS: usize = 10;
 
-// A Disk is considered as a numbered sequence of Blocks.
-// All reads and writes done by the kernel against the disk, are done in units of block.
-//
-// On the other hand, all reads and writes done by the disk itself, are done in a unit called
-// sector. And usually, the sector size < block size.
-//
-// In UNIX systems for example, block size = 4096 and sector size = 512.
-pub const BLOCK_SIZE: usize = 1024;
-
-// Maximum number of blocks aa File System (FS) operation can write to.
-pub const FS_OP_MAX_BLOCK_WRITES: usize = 10;
+// NOTE : The block size and the size of the log area are part of the on disk format of ArnoFS, so
+// they're defined in its layout module (which is shared with the host side tools).
+pub use arnofs::layout::{BLOCK_SIZE, FS_OP_MAX_BLOCK_WRITES};
 
+pub mod arnofs;
 pub mod bcache;
+pub mod devfs;
+pub mod device;
 pub mod disk;
+pub mod ext2;
+pub mod fat32;
+pub mod fdtable;
+pub mod file;
+pub mod iosched;
+pub mod log;
+pub mod pipe;
+pub mod procfs;
+pub mod syscalls;
+pub mod tmpfs;
+pub mod vfs;
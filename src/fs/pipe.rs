@@ -0,0 +1,403 @@
+use {
+  super::vfs::{lookup::ResolvedPath, File, FileType, FsError, OpenFlags, PollEvents, Stat},
+  crate::{
+    locks::{spinlock::SpinLock, waitqueue::WaitQueue},
+    process::{
+      process::getCurrentProcess,
+      signal::{self, SIGPIPE},
+    },
+  },
+  alloc::{boxed::Box, collections::BTreeMap, sync::Arc, sync::Weak},
+  core::sync::atomic::{AtomicU64, Ordering},
+};
+
+/*
+  A pipe is a unidirectional channel : bytes written to its write end are buffered in the kernel,
+  till they're read from its read end.
+
+    (1) An anonymous pipe (pipe2( )) is created along with its 2 ends, which can then only be
+        shared by duplicating / inheriting the file descriptors.
+
+    (2) A FIFO (named pipe) is a file (created using mkfifo( )) : each open( ) of it returns an end
+        of the same pipe. Opening an end blocks, till the other end has also been opened.
+
+  Reading from an empty pipe blocks till some bytes are written, or returns 0 (end of file) once
+  all the write ends are closed. Writing to a full pipe blocks till some bytes are read, or fails
+  with BrokenPipe (and sends SIGPIPE to the writer) once all the read ends are closed.
+
+  REFER : chapter 44 (Pipes and FIFOs) of The Linux Programming Interface by Michael Kerrisk.
+*/
+
+// Capacity of a pipe, in bytes. Writes of at most these many bytes are atomic : they never get
+// interleaved with other writes.
+pub const PIPE_SIZE: usize = 4096;
+
+struct PipeState {
+  buffer: Box<[u8; PIPE_SIZE]>,
+
+  // Free running indices : the buffered bytes are the ones in [readIndex, writeIndex).
+  readIndex: usize,
+  writeIndex: usize,
+
+  // Number of open read / write ends.
+  readersCount: usize,
+  writersCount: usize,
+
+  // Number of times the read / write end has been opened. Lets the opener of a FIFO (waiting for
+  // the other end) notice an opening, even if the other end gets closed right after.
+  readerOpensCount: usize,
+  writerOpensCount: usize,
+}
+
+impl PipeState {
+  #[inline]
+  fn getLength(&self) -> usize {
+    self.writeIndex - self.readIndex
+  }
+
+  #[inline]
+  fn getFreeSpace(&self) -> usize {
+    PIPE_SIZE - self.getLength()
+  }
+
+  // Buffers as many of the given bytes as fit. Returns the number of bytes buffered.
+  fn push(&mut self, bytes: &[u8]) -> usize {
+    let length = bytes.len().min(self.getFreeSpace());
+
+    for byte in &bytes[..length] {
+      self.buffer[self.writeIndex % PIPE_SIZE] = *byte;
+      self.writeIndex += 1;
+    }
+
+    length
+  }
+
+  // Moves as many buffered bytes as fit, into the given buffer. Returns the number of bytes moved.
+  fn pop(&mut self, bytes: &mut [u8]) -> usize {
+    let length = bytes.len().min(self.getLength());
+
+    for byte in &mut bytes[..length] {
+      *byte = self.buffer[self.readIndex % PIPE_SIZE];
+      self.readIndex += 1;
+    }
+
+    length
+  }
+}
+
+pub struct Pipe {
+  state: SpinLock<PipeState>,
+
+  // Readers waiting for bytes (or for the write end to get closed), and openers of the read end of
+  // a FIFO waiting for the write end to get opened.
+  readWaitQueue: WaitQueue,
+
+  // Writers waiting for free space (or for the read end to get closed), and openers of the write
+  // end of a FIFO waiting for the read end to get opened.
+  writeWaitQueue: WaitQueue,
+
+  // Reported as the inode number of an anonymous pipe's ends.
+  id: u64,
+}
+
+static NEXT_PIPE_ID: AtomicU64 = AtomicU64::new(1);
+
+impl Pipe {
+  fn new() -> Arc<Self> {
+    Arc::new(Self {
+      state: SpinLock::new(PipeState {
+        buffer: Box::new([0; PIPE_SIZE]),
+        readIndex: 0,
+        writeIndex: 0,
+        readersCount: 0,
+        writersCount: 0,
+        readerOpensCount: 0,
+        writerOpensCount: 0,
+      }),
+      readWaitQueue: WaitQueue::new(),
+      writeWaitQueue: WaitQueue::new(),
+      id: NEXT_PIPE_ID.fetch_add(1, Ordering::Relaxed),
+    })
+  }
+
+  fn read(&self, buffer: &mut [u8], isNonBlocking: bool) -> Result<usize, FsError> {
+    if buffer.is_empty() {
+      return Ok(0);
+    }
+
+    let mut state = self.state.acquire();
+
+    while state.getLength() == 0 {
+      // End of file.
+      if state.writersCount == 0 {
+        return Ok(0);
+      }
+
+      if isNonBlocking {
+        return Err(FsError::WouldBlock);
+      }
+      state = self.readWaitQueue.wait(state);
+    }
+
+    let bytesRead = state.pop(buffer);
+    drop(state);
+
+    self.writeWaitQueue.notifyAll();
+    Ok(bytesRead)
+  }
+
+  fn write(&self, buffer: &[u8], isNonBlocking: bool) -> Result<usize, FsError> {
+    let mut state = self.state.acquire();
+    let mut bytesWritten = 0;
+
+    while bytesWritten < buffer.len() {
+      if state.readersCount == 0 {
+        drop(state);
+
+        if let Some(process) = getCurrentProcess() {
+          signal::sendSignal(process, SIGPIPE);
+        }
+        return match bytesWritten {
+          0 => Err(FsError::BrokenPipe),
+          _ => Ok(bytesWritten),
+        };
+      }
+
+      // An atomic write waits till the whole of it fits.
+      let requiredFreeSpace = match buffer.len() <= PIPE_SIZE {
+        true => buffer.len(),
+        false => 1,
+      };
+
+      if state.getFreeSpace() < requiredFreeSpace {
+        if isNonBlocking {
+          return match bytesWritten {
+            0 => Err(FsError::WouldBlock),
+            _ => Ok(bytesWritten),
+          };
+        }
+
+        state = self.writeWaitQueue.wait(state);
+        continue;
+      }
+
+      bytesWritten += state.push(&buffer[bytesWritten..]);
+      self.readWaitQueue.notifyAll();
+    }
+
+    Ok(bytesWritten)
+  }
+
+  fn poll(&self) -> PollEvents {
+    let state = self.state.acquire();
+
+    let mut pollEvents = PollEvents::empty();
+    if state.getLength() > 0 || state.writersCount == 0 {
+      pollEvents |= PollEvents::READABLE;
+    }
+    if state.getFreeSpace() > 0 || state.readersCount == 0 {
+      pollEvents |= PollEvents::WRITABLE;
+    }
+
+    pollEvents
+  }
+}
+
+// An end of a pipe. A FIFO opened for both reading and writing, is both the ends.
+struct PipeEnd {
+  pipe: Arc<Pipe>,
+
+  isReadEnd: bool,
+  isWriteEnd: bool,
+
+  // The FIFO (none for an anonymous pipe). Also keeps the mounted File System busy, while the File
+  // is open.
+  path: Option<ResolvedPath>,
+}
+
+impl PipeEnd {
+  fn new(
+    pipe: &Arc<Pipe>,
+    isReadEnd: bool,
+    isWriteEnd: bool,
+    path: Option<ResolvedPath>,
+  ) -> Arc<Self> {
+    let mut state = pipe.state.acquire();
+
+    if isReadEnd {
+      state.readersCount += 1;
+      state.readerOpensCount += 1;
+    }
+    if isWriteEnd {
+      state.writersCount += 1;
+      state.writerOpensCount += 1;
+    }
+    drop(state);
+
+    // Wake up the openers of the other end of the FIFO.
+    pipe.writeWaitQueue.notifyAll();
+    pipe.readWaitQueue.notifyAll();
+
+    Arc::new(Self {
+      pipe: pipe.clone(),
+      isReadEnd,
+      isWriteEnd,
+      path,
+    })
+  }
+}
+
+impl Drop for PipeEnd {
+  fn drop(&mut self) {
+    let mut state = self.pipe.state.acquire();
+
+    if self.isReadEnd {
+      state.readersCount -= 1;
+    }
+    if self.isWriteEnd {
+      state.writersCount -= 1;
+    }
+    drop(state);
+
+    // Readers now see the end of file / writers now fail.
+    self.pipe.readWaitQueue.notifyAll();
+    self.pipe.writeWaitQueue.notifyAll();
+  }
+}
+
+impl File for PipeEnd {
+  fn read(&self, _offset: u64, buffer: &mut [u8]) -> Result<usize, FsError> {
+    match self.isReadEnd {
+      true => self.pipe.read(buffer, false),
+      false => Err(FsError::BadFileDescriptor),
+    }
+  }
+
+  fn write(&self, _offset: u64, buffer: &[u8]) -> Result<usize, FsError> {
+    match self.isWriteEnd {
+      true => self.pipe.write(buffer, false),
+      false => Err(FsError::BadFileDescriptor),
+    }
+  }
+
+  fn readNonBlocking(&self, _offset: u64, buffer: &mut [u8]) -> Result<usize, FsError> {
+    match self.isReadEnd {
+      true => self.pipe.read(buffer, true),
+      false => Err(FsError::BadFileDescriptor),
+    }
+  }
+
+  fn writeNonBlocking(&self, _offset: u64, buffer: &[u8]) -> Result<usize, FsError> {
+    match self.isWriteEnd {
+      true => self.pipe.write(buffer, true),
+      false => Err(FsError::BadFileDescriptor),
+    }
+  }
+
+  // The size reported is the number of buffered bytes.
+  fn getStat(&self) -> Result<Stat, FsError> {
+    let size = self.pipe.state.acquire().getLength() as u64;
+
+    match &self.path {
+      Some(path) => Ok(Stat {
+        size,
+        ..path.getInode().getStat()?
+      }),
+
+      None => Ok(Stat {
+        fileSystemID: 0,
+        inodeNumber: self.pipe.id,
+
+        fileType: FileType::Fifo,
+        linksCount: 0,
+
+        size,
+
+        deviceNumber: Default::default(),
+      }),
+    }
+  }
+
+  fn isSeekable(&self) -> bool {
+    false
+  }
+
+  fn poll(&self) -> PollEvents {
+    self.pipe.poll()
+  }
+
+  fn getPath(&self) -> Option<&ResolvedPath> {
+    self.path.as_ref()
+  }
+}
+
+// Creates an anonymous pipe. Returns its read end and write end.
+pub fn create() -> (Arc<dyn File>, Arc<dyn File>) {
+  let pipe = Pipe::new();
+  (
+    PipeEnd::new(&pipe, true, false, None),
+    PipeEnd::new(&pipe, false, true, None),
+  )
+}
+
+// The pipes of the FIFOs which are open, by the File System ID and inode number of the FIFOs.
+static FIFO_PIPES: SpinLock<BTreeMap<(usize, u64), Weak<Pipe>>> = SpinLock::new(BTreeMap::new());
+
+// Returns the pipe of the given FIFO, creating it if the FIFO isn't open.
+fn getFifoPipe(fileSystemID: usize, inodeNumber: u64) -> Arc<Pipe> {
+  let mut fifoPipes = FIFO_PIPES.acquire();
+
+  if let Some(pipe) = fifoPipes
+    .get(&(fileSystemID, inodeNumber))
+    .and_then(Weak::upgrade)
+  {
+    return pipe;
+  }
+
+  // Forget the pipes of the FIFOs which got closed.
+  fifoPipes.retain(|_, pipe| pipe.strong_count() > 0);
+
+  let pipe = Pipe::new();
+  fifoPipes.insert((fileSystemID, inodeNumber), Arc::downgrade(&pipe));
+  pipe
+}
+
+/*
+  Opens the given FIFO. Opening it only for reading (writing) blocks till it's opened for writing
+  (reading) as well. In non blocking mode, opening it only for reading succeeds right away, while
+  opening it only for writing fails with NoReaders (ENXIO in Linux) if it isn't open for reading.
+*/
+pub fn openFifo(path: ResolvedPath, flags: OpenFlags) -> Result<Arc<dyn File>, FsError> {
+  let stat = path.getInode().getStat()?;
+  let pipe = getFifoPipe(stat.fileSystemID, stat.inodeNumber);
+
+  let isReadEnd = flags.contains(OpenFlags::READ);
+  let isWriteEnd = flags.contains(OpenFlags::WRITE);
+  let isNonBlocking = flags.contains(OpenFlags::NON_BLOCKING);
+
+  if isWriteEnd && !isReadEnd && isNonBlocking && pipe.state.acquire().readersCount == 0 {
+    return Err(FsError::NoReaders);
+  }
+
+  let state = pipe.state.acquire();
+  let (readerOpensCount, writerOpensCount) = (state.readerOpensCount, state.writerOpensCount);
+  drop(state);
+
+  let pipeEnd = PipeEnd::new(&pipe, isReadEnd, isWriteEnd, Some(path));
+
+  if !isNonBlocking {
+    let hasOtherEndOpened = |state: &mut PipeState| match (isReadEnd, isWriteEnd) {
+      (true, false) => state.writersCount > 0 || state.writerOpensCount != writerOpensCount,
+      (false, true) => state.readersCount > 0 || state.readerOpensCount != readerOpensCount,
+      _ => true,
+    };
+
+    let waitQueue = match isReadEnd {
+      true => &pipe.readWaitQueue,
+      false => &pipe.writeWaitQueue,
+    };
+    drop(waitQueue.waitUntil(pipe.state.acquire(), hasOtherEndOpened));
+  }
+
+  Ok(pipeEnd)
+}
@@ -0,0 +1,194 @@
+use {
+  crate::{
+    arch::riscv::{hart, registers::misa::getISAString},
+    fs::{bcache::BCACHE, vfs::FsError},
+    ipi,
+    memory::{allocator::GLOBAL_ALLOCATOR, page_table::entry::PTEBitFlags},
+    process::manager::PROCESS_MANAGER,
+    timer,
+    trap::{self, InterruptSource},
+  },
+  alloc::{format, string::String, vec::Vec},
+  core::{fmt::Write, ptr::addr_of},
+};
+
+/*
+  Generates the contents of the files in procfs. The formats loosely follow Linux's, so that they're
+  familiar (and easy to parse) : one "Key : value" pair per line, or a table.
+*/
+
+// /proc/meminfo : the memory usage, as seen by the global allocator.
+pub fn getMemInfo() -> Vec<u8> {
+  let statistics = unsafe { (*addr_of!(GLOBAL_ALLOCATOR)).getStatistics() };
+  let freeMemorySize = statistics
+    .availableMemorySize
+    .saturating_sub(statistics.allocatedMemorySize);
+
+  let mut contents = String::new();
+  let _ = writeln!(
+    contents,
+    "MemTotal:\t{} kB",
+    statistics.availableMemorySize / 1024
+  );
+  let _ = writeln!(
+    contents,
+    "MemUsed:\t{} kB",
+    statistics.allocatedMemorySize / 1024
+  );
+  let _ = writeln!(contents, "MemFree:\t{} kB", freeMemorySize / 1024);
+  let _ = writeln!(contents, "Allocations:\t{}", statistics.allocationsCount);
+
+  contents.into_bytes()
+}
+
+// /proc/interrupts : the number of interrupts taken by each online hart, from each source.
+pub fn getInterrupts() -> Vec<u8> {
+  let harts = ipi::getOnlineHarts();
+
+  let mut contents = String::from("     ");
+  for hartID in harts.iter() {
+    let _ = write!(contents, " {:>10}", alloc::format!("CPU{}", hartID));
+  }
+  contents.push('\n');
+
+  for source in InterruptSource::getAll() {
+    let (label, description) = match source {
+      InterruptSource::Software => (String::from("IPI"), "Inter-processor interrupts"),
+      InterruptSource::Timer => (String::from("TMR"), "Timer interrupts"),
+      InterruptSource::External(irq) if irq == crate::drivers::plic::UART_IRQ => {
+        (alloc::format!("{}", irq), "PLIC uart")
+      }
+      InterruptSource::External(irq) => (alloc::format!("{}", irq), "PLIC virtio"),
+    };
+
+    let _ = write!(contents, "{:>4}:", label);
+    for hartID in harts.iter() {
+      let _ = write!(
+        contents,
+        " {:>10}",
+        trap::getInterruptsCount(hartID, source)
+      );
+    }
+    let _ = writeln!(contents, "  {}", description);
+  }
+
+  contents.into_bytes()
+}
+
+// /proc/bcache : the buffer cache statistics.
+pub fn getBCacheStatistics() -> Vec<u8> {
+  let statistics = BCACHE.getStatistics();
+
+  let mut contents = String::new();
+  let _ = writeln!(contents, "Buffers:\t{}", statistics.buffersCount);
+  let _ = writeln!(contents, "Hits:\t{}", statistics.hits);
+  let _ = writeln!(contents, "Misses:\t{}", statistics.misses);
+  let _ = writeln!(contents, "Evictions:\t{}", statistics.evictions);
+  let _ = writeln!(contents, "WriteBacks:\t{}", statistics.writeBacks);
+  let _ = writeln!(contents, "ReadAheads:\t{}", statistics.readAheads);
+
+  contents.into_bytes()
+}
+
+// /proc/cpuinfo : a block per online hart.
+pub fn getCpuInfo() -> Vec<u8> {
+  let mut contents = String::new();
+
+  for hartID in ipi::getOnlineHarts().iter() {
+    let _ = writeln!(contents, "processor\t: {}", hartID);
+    let _ = writeln!(contents, "hart\t\t: {}", hartID);
+    // The Sstc extension is always used (for the timer interrupts).
+    let _ = writeln!(
+      contents,
+      "isa\t\t: {}_sstc",
+      getISAString(hart::getISA(hartID))
+    );
+    contents.push('\n');
+  }
+
+  contents.into_bytes()
+}
+
+// /proc/<pid>/status : the scheduling state of the process.
+pub fn getProcessStatus(pid: usize) -> Result<Vec<u8>, FsError> {
+  let process = PROCESS_MANAGER.findProcess(pid).ok_or(FsError::NotFound)?;
+  let metadata = process.metadata.acquire();
+
+  // The process may have exited, and the slot reused, after it was found.
+  if metadata.pid != pid {
+    return Err(FsError::NotFound);
+  }
+
+  let mut contents = String::new();
+  let _ = writeln!(contents, "Pid:\t{}", metadata.pid);
+  let _ = writeln!(contents, "State:\t{}", metadata.state.getName());
+  let _ = writeln!(contents, "WaitChannel:\t{:#x}", metadata.waitChannel);
+  match metadata.wakeupDeadline {
+    Some(deadline) => {
+      let _ = writeln!(
+        contents,
+        "WakeupDeadline:\t{} (in {} ticks)",
+        deadline,
+        deadline.saturating_sub(timer::getTicks())
+      );
+    }
+    None => {
+      let _ = writeln!(contents, "WakeupDeadline:\tnone");
+    }
+  }
+  let _ = writeln!(contents, "SigPnd:\t{:016x}", metadata.pendingSignals);
+
+  Ok(contents.into_bytes())
+}
+
+// /proc/<pid>/maps : the memory regions mapped in the address space of the process. Nothing is
+// listed for kernel threads (which don't have a user address space).
+pub fn getProcessMaps(pid: usize) -> Result<Vec<u8>, FsError> {
+  let process = PROCESS_MANAGER.findProcess(pid).ok_or(FsError::NotFound)?;
+
+  let mut contents = String::new();
+  if let Some(addressSpace) = &*process.getAddressSpace().acquire() {
+    for region in addressSpace.getRegions() {
+      let permissions = [
+        (PTEBitFlags::R, 'r'),
+        (PTEBitFlags::W, 'w'),
+        (PTEBitFlags::X, 'x'),
+      ]
+      .map(|(bitFlag, character)| match region.bitFlags.contains(bitFlag) {
+        true => character,
+        false => '-',
+      });
+
+      let _ = writeln!(
+        contents,
+        "{:08x}-{:08x} {}{}{}p 00000000 00:00 0\t{}",
+        region.startingVA,
+        region.endingVA,
+        permissions[0],
+        permissions[1],
+        permissions[2],
+        region.name
+      );
+    }
+  }
+
+  Ok(contents.into_bytes())
+}
+
+// /proc/<pid>/fd/<fd> : the path opened by the given file descriptor. Files without a path (like
+// the ones procfs generates) are shown as anonymous ones.
+pub fn getOpenedPath(pid: usize, fd: usize) -> Result<Vec<u8>, FsError> {
+  let process = PROCESS_MANAGER.findProcess(pid).ok_or(FsError::NotFound)?;
+
+  let openFile = process
+    .getFileDescriptorTable()
+    .acquire()
+    .getOpenFile(fd)
+    .map_err(|_| FsError::NotFound)?;
+
+  let file = openFile.getFile();
+  match file.getPath() {
+    Some(path) => Ok(path.getAbsolutePath()),
+    None => Ok(format!("anon_inode:[{}]", file.getStat()?.inodeNumber).into_bytes()),
+  }
+}
@@ -0,0 +1,393 @@
+mod files;
+
+use {
+  super::vfs::{
+    self, mount::FileSystemType, DeviceNumber, DirectoryEntry, File, FileType, FsError, Inode,
+    OpenFlags, Stat,
+  },
+  crate::process::{
+    manager::PROCESS_MANAGER,
+    process::{getCurrentProcess, Process},
+  },
+  alloc::{format, sync::Arc, vec::Vec},
+  core::any::Any,
+};
+
+/*
+  procfs is a pseudo File System (mounted on /proc), exposing the state of the kernel as read only
+  files. Their contents are generated whenever they're read, so nothing is stored.
+
+    /proc/meminfo       Memory usage, as seen by the global allocator.
+    /proc/interrupts    Interrupts taken by each hart, by their source.
+    /proc/bcache        Buffer cache statistics.
+    /proc/cpuinfo       The online harts, along with their ISA.
+    /proc/self          Symbolic link to the directory of the current process.
+
+    /proc/<pid>/status  Scheduling state of the process.
+    /proc/<pid>/maps    Memory regions mapped in the address space of the process.
+    /proc/<pid>/fd      Files opened by the process : symbolic links (named by the file
+                        descriptors) to the opened paths.
+
+  A generated file is snapshotted when it's opened, so that reading it in chunks gives consistent
+  contents.
+
+  NOTE : The directory of a process which has exited may stay cached by the VFS for a while. Its
+  files then fail with NotFound.
+*/
+
+pub static FILE_SYSTEM_TYPE: FileSystemType = FileSystemType {
+  name: "proc",
+  isDiskBased: false,
+  mount,
+};
+
+fn mount(_diskNumber: Option<usize>, options: &[u8]) -> Result<Arc<dyn vfs::FileSystem>, FsError> {
+  if !options.is_empty() {
+    return Err(FsError::InvalidArgument);
+  }
+
+  let info = Arc::new(ProcFsInfo {
+    fileSystemID: vfs::allocateFileSystemID(),
+  });
+
+  Ok(Arc::new(ProcFs {
+    root: ProcInode::new(&info, Node::Root),
+  }))
+}
+
+struct ProcFsInfo {
+  fileSystemID: usize,
+}
+
+struct ProcFs {
+  root: Arc<ProcInode>,
+}
+
+impl vfs::FileSystem for ProcFs {
+  fn getTypeName(&self) -> &'static str {
+    FILE_SYSTEM_TYPE.name
+  }
+
+  fn getRootInode(&self) -> Result<Arc<dyn Inode>, FsError> {
+    Ok(self.root.clone())
+  }
+}
+
+// The files and directories in procfs.
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum Node {
+  Root,
+
+  MemInfo,
+  Interrupts,
+  BCache,
+  CpuInfo,
+  SelfLink,
+
+  // The directory of the process with the given PID, and its contents.
+  Process(usize),
+  ProcessStatus(usize),
+  ProcessMaps(usize),
+  ProcessFiles(usize),
+  // A file descriptor of the process, with the given PID.
+  ProcessFile(usize, usize),
+}
+
+// The entries in the root directory, other than the process directories.
+const ROOT_ENTRIES: [(&[u8], Node); 5] = [
+  (b"meminfo", Node::MemInfo),
+  (b"interrupts", Node::Interrupts),
+  (b"bcache", Node::BCache),
+  (b"cpuinfo", Node::CpuInfo),
+  (b"self", Node::SelfLink),
+];
+
+impl Node {
+  // The nodes of a process get inode numbers (pid << 8) + n, which don't collide with the ones of
+  // the other nodes (since PIDs start from 1).
+  fn getInodeNumber(&self) -> u64 {
+    match *self {
+      Self::Root => 1,
+
+      Self::MemInfo => 2,
+      Self::Interrupts => 3,
+      Self::BCache => 4,
+      Self::CpuInfo => 5,
+      Self::SelfLink => 6,
+
+      Self::Process(pid) => (pid as u64) << 8,
+      Self::ProcessStatus(pid) => ((pid as u64) << 8) + 1,
+      Self::ProcessMaps(pid) => ((pid as u64) << 8) + 2,
+      Self::ProcessFiles(pid) => ((pid as u64) << 8) + 3,
+      Self::ProcessFile(pid, fd) => ((pid as u64) << 8) + 4 + fd as u64,
+    }
+  }
+
+  fn getFileType(&self) -> FileType {
+    match self {
+      Self::Root | Self::Process(_) | Self::ProcessFiles(_) => FileType::Directory,
+      Self::SelfLink | Self::ProcessFile(..) => FileType::Symlink,
+      _ => FileType::Regular,
+    }
+  }
+
+  // Returns the parent directory (the root directory is its own parent).
+  fn getParent(&self) -> Self {
+    match *self {
+      Self::ProcessStatus(pid) | Self::ProcessMaps(pid) | Self::ProcessFiles(pid) => {
+        Self::Process(pid)
+      }
+      Self::ProcessFile(pid, _) => Self::ProcessFiles(pid),
+      _ => Self::Root,
+    }
+  }
+
+  // Returns the PID of the process, the node belongs to (if any).
+  fn getPID(&self) -> Option<usize> {
+    match *self {
+      Self::Process(pid)
+      | Self::ProcessStatus(pid)
+      | Self::ProcessMaps(pid)
+      | Self::ProcessFiles(pid)
+      | Self::ProcessFile(pid, _) => Some(pid),
+      _ => None,
+    }
+  }
+
+  // Returns the entries in this directory, other than "." and "..".
+  fn getEntries(&self) -> Vec<(Vec<u8>, Node)> {
+    match *self {
+      Self::Root => ROOT_ENTRIES
+        .iter()
+        .map(|(name, node)| (name.to_vec(), *node))
+        .chain(
+          PROCESS_MANAGER
+            .getPIDs()
+            .into_iter()
+            .map(|pid| (format!("{}", pid).into_bytes(), Self::Process(pid))),
+        )
+        .collect(),
+
+      Self::Process(pid) => alloc::vec![
+        (b"status".to_vec(), Self::ProcessStatus(pid)),
+        (b"maps".to_vec(), Self::ProcessMaps(pid)),
+        (b"fd".to_vec(), Self::ProcessFiles(pid)),
+      ],
+
+      Self::ProcessFiles(pid) => PROCESS_MANAGER
+        .findProcess(pid)
+        .map(Process::getFileDescriptorTable)
+        .map_or_else(Vec::new, |table| {
+          table
+            .acquire()
+            .getFileDescriptors()
+            .into_iter()
+            .map(|(fd, _)| (format!("{}", fd).into_bytes(), Self::ProcessFile(pid, fd)))
+            .collect()
+        }),
+
+      _ => Vec::new(),
+    }
+  }
+
+  // Generates the contents of this regular file.
+  fn generateContents(&self) -> Result<Vec<u8>, FsError> {
+    match *self {
+      Self::MemInfo => Ok(files::getMemInfo()),
+      Self::Interrupts => Ok(files::getInterrupts()),
+      Self::BCache => Ok(files::getBCacheStatistics()),
+      Self::CpuInfo => Ok(files::getCpuInfo()),
+
+      Self::ProcessStatus(pid) => files::getProcessStatus(pid),
+      Self::ProcessMaps(pid) => files::getProcessMaps(pid),
+
+      _ => Err(FsError::IsADirectory),
+    }
+  }
+}
+
+// Returns whether the process with the given PID exists.
+fn doesProcessExist(pid: usize) -> bool {
+  PROCESS_MANAGER.findProcess(pid).is_some()
+}
+
+// Parses the name of a process directory (the PID, in decimal).
+fn parsePID(name: &[u8]) -> Option<usize> {
+  if name.first() == Some(&b'0') {
+    return None;
+  }
+
+  core::str::from_utf8(name).ok()?.parse().ok()
+}
+
+struct ProcInode {
+  info: Arc<ProcFsInfo>,
+  node: Node,
+}
+
+impl ProcInode {
+  fn new(info: &Arc<ProcFsInfo>, node: Node) -> Arc<Self> {
+    Arc::new(Self {
+      info: info.clone(),
+      node,
+    })
+  }
+
+  // Fails with NotFound, if the process this inode belongs to has exited.
+  fn checkProcess(&self) -> Result<(), FsError> {
+    match self.node.getPID() {
+      Some(pid) if !doesProcessExist(pid) => Err(FsError::NotFound),
+      _ => Ok(()),
+    }
+  }
+}
+
+impl Inode for ProcInode {
+  fn asAny(&self) -> &dyn Any {
+    self
+  }
+
+  // Generated files report a size of 0, since their contents aren't known till they're read.
+  fn getStat(&self) -> Result<Stat, FsError> {
+    let (linksCount, size) = match self.node {
+      Node::Root | Node::Process(_) | Node::ProcessFiles(_) => (2, 0),
+      Node::SelfLink | Node::ProcessFile(..) => {
+        (1, self.readLink().map_or(0, |target| target.len() as u64))
+      }
+      _ => (1, 0),
+    };
+
+    Ok(Stat {
+      fileSystemID: self.info.fileSystemID,
+      inodeNumber: self.node.getInodeNumber(),
+
+      fileType: self.node.getFileType(),
+      linksCount,
+
+      size,
+
+      deviceNumber: DeviceNumber::default(),
+    })
+  }
+
+  fn readAt(&self, offset: u64, buffer: &mut [u8]) -> Result<usize, FsError> {
+    let contents = self.node.generateContents()?;
+    Ok(readSnapshot(&contents, offset, buffer))
+  }
+
+  fn lookup(&self, name: &[u8]) -> Result<Arc<dyn Inode>, FsError> {
+    self.checkProcess()?;
+
+    let node = match self.node {
+      Node::Root => match parsePID(name) {
+        Some(pid) if doesProcessExist(pid) => Node::Process(pid),
+        Some(_) => return Err(FsError::NotFound),
+
+        None => ROOT_ENTRIES
+          .iter()
+          .find(|(entryName, _)| *entryName == name)
+          .map(|(_, node)| *node)
+          .ok_or(FsError::NotFound)?,
+      },
+
+      _ => self
+        .node
+        .getEntries()
+        .into_iter()
+        .find(|(entryName, _)| entryName == name)
+        .map(|(_, node)| node)
+        .ok_or(FsError::NotFound)?,
+    };
+
+    Ok(ProcInode::new(&self.info, node))
+  }
+
+  fn readDirectory(&self, position: usize) -> Result<Option<(DirectoryEntry, usize)>, FsError> {
+    if self.node.getFileType() != FileType::Directory {
+      return Err(FsError::NotADirectory);
+    }
+    self.checkProcess()?;
+
+    let directoryEntry = match position {
+      0 => Some((b".".to_vec(), self.node)),
+      1 => Some((b"..".to_vec(), self.node.getParent())),
+      _ => self.node.getEntries().into_iter().nth(position - 2),
+    };
+
+    Ok(directoryEntry.map(|(name, node)| {
+      (
+        DirectoryEntry {
+          inodeNumber: node.getInodeNumber(),
+          name,
+        },
+        position + 1,
+      )
+    }))
+  }
+
+  // /proc/self points to the directory of the process resolving it.
+  fn readLink(&self) -> Result<Vec<u8>, FsError> {
+    match self.node {
+      Node::SelfLink => {
+        let process = getCurrentProcess().ok_or(FsError::NotFound)?;
+        Ok(format!("{}", process.getPID()).into_bytes())
+      }
+
+      Node::ProcessFile(pid, fd) => files::getOpenedPath(pid, fd),
+
+      _ => Err(FsError::InvalidArgument),
+    }
+  }
+
+  fn open(&self, flags: OpenFlags) -> Result<Option<Arc<dyn File>>, FsError> {
+    if flags.contains(OpenFlags::WRITE) {
+      return Err(FsError::ReadOnly);
+    }
+    self.checkProcess()?;
+
+    match self.node.getFileType() {
+      FileType::Regular => Ok(Some(Arc::new(SnapshotFile {
+        contents: self.node.generateContents()?,
+        stat: self.getStat()?,
+      }))),
+
+      _ => Ok(None),
+    }
+  }
+}
+
+// Copies the contents starting at the given offset, into the given buffer. Returns the number of
+// bytes copied.
+fn readSnapshot(contents: &[u8], offset: u64, buffer: &mut [u8]) -> usize {
+  let Ok(offset) = usize::try_from(offset)
+  else {
+    return 0;
+  };
+  if offset >= contents.len() {
+    return 0;
+  }
+
+  let length = buffer.len().min(contents.len() - offset);
+  buffer[..length].copy_from_slice(&contents[offset..offset + length]);
+  length
+}
+
+// An opened generated file, holding the contents generated when it was opened.
+struct SnapshotFile {
+  contents: Vec<u8>,
+  stat: Stat,
+}
+
+impl File for SnapshotFile {
+  fn read(&self, offset: u64, buffer: &mut [u8]) -> Result<usize, FsError> {
+    Ok(readSnapshot(&self.contents, offset, buffer))
+  }
+
+  fn write(&self, _offset: u64, _buffer: &[u8]) -> Result<usize, FsError> {
+    Err(FsError::ReadOnly)
+  }
+
+  fn getStat(&self) -> Result<Stat, FsError> {
+    Ok(self.stat)
+  }
+}
@@ -0,0 +1,365 @@
+use {
+  super::{
+    fdtable::{FileDescriptor, FileDescriptorTable},
+    file::{OpenFile, Whence, OPEN_FILE_TABLE},
+    pipe,
+    vfs::{mount, namespace, DeviceNumber, FileType, FsError, OpenFlags},
+  },
+  crate::{locks::spinlock::SpinLock, process::process::getCurrentProcess},
+};
+
+/*
+  The File System related system calls, acting on the current process. The flags and the
+  commands use the same values as Linux on RISC-V.
+
+  NOTE : Buffers and paths are kernel memory here. Copying them from / to user memory is left to
+  the system call dispatcher (REFER : ../syscall/fs.rs).
+*/
+
+// Access modes.
+pub const O_RDONLY: usize = 0;
+pub const O_WRONLY: usize = 1;
+pub const O_RDWR: usize = 2;
+const O_ACCMODE: usize = 3;
+
+pub const O_CREAT: usize = 0o100;
+pub const O_EXCL: usize = 0o200;
+pub const O_TRUNC: usize = 0o1000;
+pub const O_APPEND: usize = 0o2000;
+pub const O_NONBLOCK: usize = 0o4000;
+pub const O_DIRECTORY: usize = 0o200000;
+pub const O_NOFOLLOW: usize = 0o400000;
+pub const O_CLOEXEC: usize = 0o2000000;
+
+pub const SEEK_SET: usize = 0;
+pub const SEEK_CUR: usize = 1;
+pub const SEEK_END: usize = 2;
+
+// fcntl( ) commands.
+pub const F_DUPFD: usize = 0;
+pub const F_GETFD: usize = 1;
+pub const F_SETFD: usize = 2;
+pub const F_GETFL: usize = 3;
+pub const F_SETFL: usize = 4;
+pub const F_DUPFD_CLOEXEC: usize = 1030;
+
+// The only file descriptor flag.
+pub const FD_CLOEXEC: usize = 1;
+
+// Flags which map 1:1 to the OpenFlags.
+const FLAGS: [(usize, OpenFlags); 7] = [
+  (O_CREAT, OpenFlags::CREATE),
+  (O_EXCL, OpenFlags::EXCLUSIVE),
+  (O_TRUNC, OpenFlags::TRUNCATE),
+  (O_APPEND, OpenFlags::APPEND),
+  (O_NONBLOCK, OpenFlags::NON_BLOCKING),
+  (O_DIRECTORY, OpenFlags::DIRECTORY),
+  (O_NOFOLLOW, OpenFlags::NO_FOLLOW),
+];
+
+fn toOpenFlags(flags: usize) -> Result<OpenFlags, FsError> {
+  let mut openFlags = match flags & O_ACCMODE {
+    O_RDONLY => OpenFlags::READ,
+    O_WRONLY => OpenFlags::WRITE,
+    O_RDWR => OpenFlags::READ | OpenFlags::WRITE,
+    _ => return Err(FsError::InvalidArgument),
+  };
+
+  for (flag, openFlag) in FLAGS {
+    if flags & flag != 0 {
+      openFlags |= openFlag;
+    }
+  }
+
+  Ok(openFlags)
+}
+
+fn fromOpenFlags(openFlags: OpenFlags) -> usize {
+  let mut flags = match (
+    openFlags.contains(OpenFlags::READ),
+    openFlags.contains(OpenFlags::WRITE),
+  ) {
+    (true, true) => O_RDWR,
+    (false, true) => O_WRONLY,
+    _ => O_RDONLY,
+  };
+
+  for (flag, openFlag) in FLAGS {
+    if openFlags.contains(openFlag) {
+      flags |= flag;
+    }
+  }
+
+  flags
+}
+
+#[inline]
+fn getFileDescriptorTable() -> &'static SpinLock<FileDescriptorTable> {
+  getCurrentProcess()
+    .expect("File descriptor system call invoked, without a current process")
+    .getFileDescriptorTable()
+}
+
+// Stores a file descriptor referring to the given open file, at the lowest free index which is at
+// least the given one. The reference the file descriptor holds is released, if that fails.
+fn installFileDescriptor(
+  lowestFD: usize,
+  fileDescriptor: FileDescriptor,
+) -> Result<usize, FsError> {
+  let result = getFileDescriptorTable()
+    .acquire()
+    .allocate(lowestFD, fileDescriptor);
+
+  if result.is_err() {
+    OPEN_FILE_TABLE.release(fileDescriptor.openFileID);
+  }
+  result
+}
+
+// Opens the file at the given path, and returns a new file descriptor referring to it.
+// NOTE : Processes don't have working directories yet, so relative paths are resolved from the
+// root directory.
+pub fn sysOpen(path: &[u8], flags: usize) -> Result<usize, FsError> {
+  let openFlags = toOpenFlags(flags)?;
+
+  let file = namespace::open(path, None, openFlags)?;
+  let openFileID = OPEN_FILE_TABLE.insert(OpenFile::new(file, openFlags))?;
+
+  installFileDescriptor(
+    0,
+    FileDescriptor {
+      openFileID,
+      isCloseOnExec: flags & O_CLOEXEC != 0,
+    },
+  )
+}
+
+pub fn sysClose(fd: usize) -> Result<usize, FsError> {
+  let fileDescriptor = getFileDescriptorTable().acquire().remove(fd)?;
+  OPEN_FILE_TABLE.release(fileDescriptor.openFileID);
+
+  Ok(0)
+}
+
+pub fn sysRead(fd: usize, buffer: &mut [u8]) -> Result<usize, FsError> {
+  let openFile = getFileDescriptorTable().acquire().getOpenFile(fd)?;
+  openFile.read(buffer)
+}
+
+pub fn sysWrite(fd: usize, buffer: &[u8]) -> Result<usize, FsError> {
+  let openFile = getFileDescriptorTable().acquire().getOpenFile(fd)?;
+  openFile.write(buffer)
+}
+
+// Returns the new offset.
+pub fn sysLseek(fd: usize, offset: i64, whence: usize) -> Result<usize, FsError> {
+  let whence = match whence {
+    SEEK_SET => Whence::Start,
+    SEEK_CUR => Whence::Current,
+    SEEK_END => Whence::End,
+    _ => return Err(FsError::InvalidArgument),
+  };
+
+  let openFile = getFileDescriptorTable().acquire().getOpenFile(fd)?;
+  Ok(openFile.seek(offset, whence)? as usize)
+}
+
+// Returns a new file descriptor (the lowest free one, which is at least the given one), referring
+// to the same open file as the given file descriptor.
+fn duplicate(fd: usize, lowestFD: usize, isCloseOnExec: bool) -> Result<usize, FsError> {
+  let mut table = getFileDescriptorTable().acquire();
+
+  let openFileID = table.get(fd)?.openFileID;
+  let newFD = table.allocate(
+    lowestFD,
+    FileDescriptor {
+      openFileID,
+      isCloseOnExec,
+    },
+  )?;
+
+  OPEN_FILE_TABLE.duplicate(openFileID);
+  Ok(newFD)
+}
+
+// The duplicated file descriptor isn't close on exec.
+pub fn sysDup(fd: usize) -> Result<usize, FsError> {
+  duplicate(fd, 0, false)
+}
+
+// Makes the new file descriptor refer to the same open file as the old one. If the new file
+// descriptor is open, it gets closed first. The new file descriptor isn't close on exec.
+pub fn sysDup2(oldFD: usize, newFD: usize) -> Result<usize, FsError> {
+  duplicateTo(oldFD, newFD, false)
+}
+
+// Same as sysDup2( ), except that the file descriptors must be different. Only O_CLOEXEC is
+// allowed in the flags.
+pub fn sysDup3(oldFD: usize, newFD: usize, flags: usize) -> Result<usize, FsError> {
+  if flags & !O_CLOEXEC != 0 || oldFD == newFD {
+    return Err(FsError::InvalidArgument);
+  }
+
+  duplicateTo(oldFD, newFD, flags & O_CLOEXEC != 0)
+}
+
+fn duplicateTo(oldFD: usize, newFD: usize, isCloseOnExec: bool) -> Result<usize, FsError> {
+  let replacedFileDescriptor = {
+    let mut table = getFileDescriptorTable().acquire();
+
+    let openFileID = table.get(oldFD)?.openFileID;
+    if oldFD == newFD {
+      return Ok(newFD);
+    }
+
+    let replacedFileDescriptor = table.replace(
+      newFD,
+      FileDescriptor {
+        openFileID,
+        isCloseOnExec,
+      },
+    )?;
+
+    OPEN_FILE_TABLE.duplicate(openFileID);
+    replacedFileDescriptor
+  };
+
+  if let Some(replacedFileDescriptor) = replacedFileDescriptor {
+    OPEN_FILE_TABLE.release(replacedFileDescriptor.openFileID);
+  }
+  Ok(newFD)
+}
+
+pub fn sysFcntl(fd: usize, command: usize, argument: usize) -> Result<usize, FsError> {
+  match command {
+    F_DUPFD => duplicate(fd, argument, false),
+    F_DUPFD_CLOEXEC => duplicate(fd, argument, true),
+
+    F_GETFD => {
+      let fileDescriptor = getFileDescriptorTable().acquire().get(fd)?;
+      Ok(match fileDescriptor.isCloseOnExec {
+        true => FD_CLOEXEC,
+        false => 0,
+      })
+    }
+
+    F_SETFD => {
+      getFileDescriptorTable().acquire().getMut(fd)?.isCloseOnExec = argument & FD_CLOEXEC != 0;
+      Ok(0)
+    }
+
+    F_GETFL => {
+      let openFile = getFileDescriptorTable().acquire().getOpenFile(fd)?;
+      Ok(fromOpenFlags(openFile.getFlags()))
+    }
+
+    // Only the status flags (O_APPEND and O_NONBLOCK) can be changed. The rest are ignored.
+    F_SETFL => {
+      let openFile = getFileDescriptorTable().acquire().getOpenFile(fd)?;
+      openFile.setStatusFlags(toOpenFlags(argument & !O_ACCMODE)?);
+      Ok(0)
+    }
+
+    _ => Err(FsError::InvalidArgument),
+  }
+}
+
+// Creates an anonymous pipe. Returns the file descriptors referring to its read end and write end.
+// Only O_CLOEXEC and O_NONBLOCK are allowed in the flags.
+pub fn sysPipe2(flags: usize) -> Result<(usize, usize), FsError> {
+  if flags & !(O_CLOEXEC | O_NONBLOCK) != 0 {
+    return Err(FsError::InvalidArgument);
+  }
+
+  let statusFlags = match flags & O_NONBLOCK {
+    0 => OpenFlags::empty(),
+    _ => OpenFlags::NON_BLOCKING,
+  };
+  let isCloseOnExec = flags & O_CLOEXEC != 0;
+
+  let (readEnd, writeEnd) = pipe::create();
+
+  let readOpenFileID =
+    OPEN_FILE_TABLE.insert(OpenFile::new(readEnd, OpenFlags::READ | statusFlags))?;
+  let writeOpenFileID =
+    match OPEN_FILE_TABLE.insert(OpenFile::new(writeEnd, OpenFlags::WRITE | statusFlags)) {
+      Ok(writeOpenFileID) => writeOpenFileID,
+      Err(error) => {
+        OPEN_FILE_TABLE.release(readOpenFileID);
+        return Err(error);
+      }
+    };
+
+  // Either both the file descriptors get allocated, or none.
+  let fds = {
+    let mut table = getFileDescriptorTable().acquire();
+
+    table
+      .allocate(
+        0,
+        FileDescriptor {
+          openFileID: readOpenFileID,
+          isCloseOnExec,
+        },
+      )
+      .and_then(|readFD| {
+        let writeFD = table.allocate(
+          0,
+          FileDescriptor {
+            openFileID: writeOpenFileID,
+            isCloseOnExec,
+          },
+        );
+
+        if writeFD.is_err() {
+          let _ = table.remove(readFD);
+        }
+        Ok((readFD, writeFD?))
+      })
+  };
+
+  if fds.is_err() {
+    OPEN_FILE_TABLE.release(readOpenFileID);
+    OPEN_FILE_TABLE.release(writeOpenFileID);
+  }
+  fds
+}
+
+// Creates a FIFO at the given path.
+pub fn sysMkfifo(path: &[u8]) -> Result<usize, FsError> {
+  namespace::create(path, None, FileType::Fifo, DeviceNumber::default())?;
+  Ok(0)
+}
+
+/*
+  Mounts a File System of the given type on the given directory. The options are passed on to the
+  File System (like "ro" for ext2).
+
+  NOTE : Mount flags aren't supported. Read only mounts are requested using the "ro" option instead
+  (by the File Systems which support them).
+*/
+pub fn sysMount(
+  source: &[u8],
+  target: &[u8],
+  fileSystemType: &[u8],
+  flags: usize,
+  options: &[u8],
+) -> Result<usize, FsError> {
+  if flags != 0 {
+    return Err(FsError::NotSupported);
+  }
+
+  mount::mount(fileSystemType, source, target, options, None)?;
+  Ok(0)
+}
+
+// Unmounts the File System mounted on the given directory.
+// NOTE : Unmount flags (like MNT_FORCE and MNT_DETACH) aren't supported.
+pub fn sysUmount(target: &[u8], flags: usize) -> Result<usize, FsError> {
+  if flags != 0 {
+    return Err(FsError::NotSupported);
+  }
+
+  mount::unmount(target, None)?;
+  Ok(0)
+}
@@ -0,0 +1,645 @@
+use {
+  super::vfs::{
+    self, mount::FileSystemType, DeviceNumber, DirectoryEntry, FileType, FsError, Inode, Stat,
+  },
+  crate::{arch::riscv::qemu::PAGE_SIZE, locks::sleeplock::SleepLock},
+  alloc::{
+    boxed::Box,
+    collections::{btree_map::Entry, BTreeMap},
+    sync::{Arc, Weak},
+    vec::Vec,
+  },
+  core::{
+    any::Any,
+    mem::align_of,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+  },
+};
+
+/*
+  tmpfs is an in memory File System, used for temporary files (mounted on /tmp) and as scratch
+  space while the kernel is booting. Its contents are lost once it's unmounted.
+
+  The contents of a regular file are stored in page sized frames, which are allocated lazily as the
+  file gets written. So files can be sparse : a hole (a frame which has never been written) reads
+  as zeroes.
+
+  The size of a tmpfs instance can be limited using the size mount option (like size=16m), after
+  which writes fail with NoSpace. Only the contents of regular files count towards the limit.
+*/
+
+pub static FILE_SYSTEM_TYPE: FileSystemType = FileSystemType {
+  name: "tmpfs",
+  isDiskBased: false,
+  mount,
+};
+
+fn mount(_diskNumber: Option<usize>, options: &[u8]) -> Result<Arc<dyn vfs::FileSystem>, FsError> {
+  let maxFramesCount = match parseSizeLimit(options)? {
+    Some(sizeLimit) => sizeLimit / PAGE_SIZE,
+    None => usize::MAX,
+  };
+
+  let info = Arc::new(TmpFsInfo {
+    fileSystemID: vfs::allocateFileSystemID(),
+
+    maxFramesCount,
+    framesCount: AtomicUsize::new(0),
+
+    nextInodeNumber: AtomicU64::new(1),
+  });
+
+  let root = TmpInode::new(
+    &info,
+    FileType::Directory,
+    DeviceNumber::default(),
+    Contents::Directory {
+      entries: BTreeMap::new(),
+      parentInodeNumber: 1, // The root directory is its own parent.
+    },
+  );
+
+  Ok(Arc::new(TmpFs { root }))
+}
+
+/*
+  Parses the mount options, which are separated by commas. Returns the size limit (in bytes), if
+  one is given.
+
+    parseSizeLimit(b"") = Ok(None)
+    parseSizeLimit(b"size=4096") = Ok(Some(4096))
+    parseSizeLimit(b"size=16m") = Ok(Some(16 * 1024 * 1024))
+*/
+fn parseSizeLimit(options: &[u8]) -> Result<Option<usize>, FsError> {
+  let mut sizeLimit = None;
+
+  for option in options.split(|&byte| byte == b',') {
+    if option.is_empty() {
+      continue;
+    }
+
+    let Some(size) = option.strip_prefix(b"size=")
+    else {
+      return Err(FsError::InvalidArgument);
+    };
+
+    let (digits, multiplier) = match size.split_last() {
+      Some((b'k' | b'K', digits)) => (digits, 1 << 10),
+      Some((b'm' | b'M', digits)) => (digits, 1 << 20),
+      Some((b'g' | b'G', digits)) => (digits, 1 << 30),
+      _ => (size, 1),
+    };
+
+    sizeLimit = core::str::from_utf8(digits)
+      .ok()
+      .and_then(|digits| digits.parse::<usize>().ok())
+      .and_then(|size| size.checked_mul(multiplier));
+    if sizeLimit.is_none() {
+      return Err(FsError::InvalidArgument);
+    }
+  }
+
+  Ok(sizeLimit)
+}
+
+// A page sized (and aligned) frame, holding a part of the contents of a regular file.
+#[repr(C, align(4096))]
+struct Frame([u8; PAGE_SIZE]);
+
+const _: () = assert!(align_of::<Frame>() == PAGE_SIZE);
+
+// Shared by the File System instance and its inodes.
+struct TmpFsInfo {
+  fileSystemID: usize,
+
+  // Maximum number of frames, which can be allocated (usize::MAX, if there's no size limit).
+  maxFramesCount: usize,
+  framesCount: AtomicUsize,
+
+  nextInodeNumber: AtomicU64,
+}
+
+impl TmpFsInfo {
+  // Allocates a zeroed frame, unless the size limit has been reached (or the kernel is out of
+  // memory).
+  fn allocateFrame(&self) -> Result<Box<Frame>, FsError> {
+    if self.framesCount.fetch_add(1, Ordering::Relaxed) >= self.maxFramesCount {
+      self.framesCount.fetch_sub(1, Ordering::Relaxed);
+      return Err(FsError::NoSpace);
+    }
+
+    match Box::<Frame>::try_new_zeroed() {
+      Ok(frame) => Ok(unsafe { frame.assume_init() }),
+
+      Err(_) => {
+        self.framesCount.fetch_sub(1, Ordering::Relaxed);
+        Err(FsError::NoSpace)
+      }
+    }
+  }
+
+  fn freeFrames(&self, count: usize) {
+    self.framesCount.fetch_sub(count, Ordering::Relaxed);
+  }
+}
+
+// Its inodes are reachable from the root directory, and are dropped along with it.
+struct TmpFs {
+  root: Arc<TmpInode>,
+}
+
+impl vfs::FileSystem for TmpFs {
+  fn getTypeName(&self) -> &'static str {
+    FILE_SYSTEM_TYPE.name
+  }
+
+  fn getRootInode(&self) -> Result<Arc<dyn Inode>, FsError> {
+    Ok(self.root.clone())
+  }
+}
+
+enum Contents {
+  RegularFile {
+    size: usize,
+
+    // Indexed by the position of the frame in the file. Holes aren't present.
+    frames: BTreeMap<usize, Box<Frame>>,
+  },
+
+  Directory {
+    entries: BTreeMap<Vec<u8>, Arc<TmpInode>>,
+    parentInodeNumber: u64,
+  },
+
+  Symlink {
+    target: Vec<u8>,
+  },
+
+  // Device files and FIFOs, whose contents aren't stored in the File System.
+  Special,
+}
+
+struct TmpInodeState {
+  // A directory has a link from its parent, one from its "." entry, and one from the ".." entry of
+  // each of its subdirectories.
+  linksCount: u32,
+
+  contents: Contents,
+}
+
+/*
+  An inode of a tmpfs instance. A directory holds references to the inodes it contains, so an inode
+  lives till it's unlinked from all the directories, and the VFS drops it.
+
+  NOTE : The VFS serializes the operations modifying the directory tree. So no operation locks
+  more than one inode at once.
+*/
+struct TmpInode {
+  // Lets the inode hand out references to itself (say, when it's linked into a directory).
+  this: Weak<TmpInode>,
+
+  info: Arc<TmpFsInfo>,
+
+  inodeNumber: u64,
+  fileType: FileType,
+  deviceNumber: DeviceNumber,
+
+  state: SleepLock<TmpInodeState>,
+}
+
+impl TmpInode {
+  fn new(
+    info: &Arc<TmpFsInfo>,
+    fileType: FileType,
+    deviceNumber: DeviceNumber,
+    contents: Contents,
+  ) -> Arc<Self> {
+    Arc::new_cyclic(|this| Self {
+      this: this.clone(),
+
+      info: info.clone(),
+
+      inodeNumber: info.nextInodeNumber.fetch_add(1, Ordering::Relaxed),
+      fileType,
+      deviceNumber,
+
+      state: SleepLock::new(TmpInodeState {
+        linksCount: match fileType {
+          FileType::Directory => 2,
+          _ => 1,
+        },
+        contents,
+      }),
+    })
+  }
+
+  #[inline]
+  fn isDirectory(&self) -> bool {
+    self.fileType == FileType::Directory
+  }
+
+  // Returns the given inode, if it's an inode of the same File System.
+  fn downcast<'a>(&self, inode: &'a dyn Inode) -> Result<&'a Self, FsError> {
+    match inode.asAny().downcast_ref::<Self>() {
+      Some(inode) if Arc::ptr_eq(&inode.info, &self.info) => Ok(inode),
+      _ => Err(FsError::CrossDevice),
+    }
+  }
+
+  // Creates an inode with the given type and contents, and adds it to this directory with the
+  // given name.
+  fn createChild(
+    &self,
+    name: &[u8],
+    fileType: FileType,
+    deviceNumber: DeviceNumber,
+    contents: Contents,
+  ) -> Result<Arc<dyn Inode>, FsError> {
+    let mut state = self.state.acquire();
+
+    // A removed directory can't get new entries.
+    if state.linksCount == 0 {
+      return Err(FsError::NotFound);
+    }
+    let Contents::Directory { entries, .. } = &mut state.contents
+    else {
+      return Err(FsError::NotADirectory);
+    };
+
+    let Entry::Vacant(entry) = entries.entry(name.to_vec())
+    else {
+      return Err(FsError::AlreadyExists);
+    };
+
+    let inode = Self::new(&self.info, fileType, deviceNumber, contents);
+    entry.insert(inode.clone());
+
+    // The ".." entry of the directory.
+    if fileType == FileType::Directory {
+      state.linksCount += 1;
+    }
+
+    Ok(inode)
+  }
+
+  // Drops a link to this inode, which has been removed from a directory.
+  fn dropLink(&self) {
+    let mut state = self.state.acquire();
+
+    state.linksCount = match self.isDirectory() {
+      // Along with the link from its "." entry.
+      true => 0,
+      false => state.linksCount - 1,
+    };
+  }
+}
+
+impl Drop for TmpInode {
+  fn drop(&mut self) {
+    let state = self.state.acquire();
+
+    if let Contents::RegularFile { frames, .. } = &state.contents {
+      self.info.freeFrames(frames.len());
+    }
+
+    // NOTE : The inodes in a directory are dropped after releasing the SleepLock.
+  }
+}
+
+impl Inode for TmpInode {
+  fn asAny(&self) -> &dyn Any {
+    self
+  }
+
+  fn getStat(&self) -> Result<Stat, FsError> {
+    let state = self.state.acquire();
+
+    let size = match &state.contents {
+      Contents::RegularFile { size, .. } => *size,
+      Contents::Directory { entries, .. } => entries.len(),
+      Contents::Symlink { target } => target.len(),
+      Contents::Special => 0,
+    };
+
+    Ok(Stat {
+      fileSystemID: self.info.fileSystemID,
+      inodeNumber: self.inodeNumber,
+
+      fileType: self.fileType,
+      linksCount: state.linksCount,
+
+      size: size as u64,
+
+      deviceNumber: self.deviceNumber,
+    })
+  }
+
+  fn readAt(&self, offset: u64, buffer: &mut [u8]) -> Result<usize, FsError> {
+    let state = self.state.acquire();
+
+    let Contents::RegularFile { size, frames } = &state.contents
+    else {
+      return match self.isDirectory() {
+        true => Err(FsError::IsADirectory),
+        false => Err(FsError::InvalidArgument),
+      };
+    };
+
+    let offset = usize::try_from(offset).map_err(|_| FsError::InvalidArgument)?;
+    if offset >= *size {
+      return Ok(0);
+    }
+    let length = buffer.len().min(size - offset);
+
+    let mut bytesRead = 0;
+    while bytesRead < length {
+      let position = offset + bytesRead;
+      let offsetInFrame = position % PAGE_SIZE;
+      let chunkLength = (PAGE_SIZE - offsetInFrame).min(length - bytesRead);
+      let chunk = &mut buffer[bytesRead..bytesRead + chunkLength];
+
+      match frames.get(&(position / PAGE_SIZE)) {
+        Some(frame) => chunk.copy_from_slice(&frame.0[offsetInFrame..offsetInFrame + chunkLength]),
+
+        // A hole in the file.
+        None => chunk.fill(0),
+      }
+
+      bytesRead += chunkLength;
+    }
+
+    Ok(bytesRead)
+  }
+
+  // Returns fewer bytes written than requested, if the size limit is reached midway.
+  fn writeAt(&self, offset: u64, buffer: &[u8]) -> Result<usize, FsError> {
+    let mut state = self.state.acquire();
+
+    let Contents::RegularFile { size, frames } = &mut state.contents
+    else {
+      return match self.isDirectory() {
+        true => Err(FsError::IsADirectory),
+        false => Err(FsError::InvalidArgument),
+      };
+    };
+
+    let offset = usize::try_from(offset).map_err(|_| FsError::FileTooLarge)?;
+    if offset.checked_add(buffer.len()).is_none() {
+      return Err(FsError::FileTooLarge);
+    }
+
+    let mut bytesWritten = 0;
+    let mut result = Ok(());
+
+    while bytesWritten < buffer.len() {
+      let position = offset + bytesWritten;
+      let offsetInFrame = position % PAGE_SIZE;
+      let chunkLength = (PAGE_SIZE - offsetInFrame).min(buffer.len() - bytesWritten);
+
+      let frame = match frames.entry(position / PAGE_SIZE) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => match self.info.allocateFrame() {
+          Ok(frame) => entry.insert(frame),
+          Err(error) => {
+            result = Err(error);
+            break;
+          }
+        },
+      };
+      frame.0[offsetInFrame..offsetInFrame + chunkLength]
+        .copy_from_slice(&buffer[bytesWritten..bytesWritten + chunkLength]);
+
+      bytesWritten += chunkLength;
+    }
+
+    if bytesWritten > 0 {
+      *size = (*size).max(offset + bytesWritten);
+    }
+
+    match (bytesWritten, result) {
+      (0, Err(error)) => Err(error),
+      _ => Ok(bytesWritten),
+    }
+  }
+
+  // Growing a file leaves a hole at its end, so no frames get allocated.
+  fn truncate(&self, newSize: u64) -> Result<(), FsError> {
+    let newSize = usize::try_from(newSize).map_err(|_| FsError::FileTooLarge)?;
+
+    let removedFrames = {
+      let mut state = self.state.acquire();
+
+      let Contents::RegularFile { size, frames } = &mut state.contents
+      else {
+        return match self.isDirectory() {
+          true => Err(FsError::IsADirectory),
+          false => Err(FsError::InvalidArgument),
+        };
+      };
+
+      let removedFrames = frames.split_off(&newSize.div_ceil(PAGE_SIZE));
+      self.info.freeFrames(removedFrames.len());
+
+      // The tail of the (partially) retained last frame must read as zeroes, if the file grows
+      // again.
+      if let Some(frame) = frames.get_mut(&(newSize / PAGE_SIZE)) {
+        frame.0[newSize % PAGE_SIZE..].fill(0);
+      }
+
+      *size = newSize;
+      removedFrames
+    };
+    drop(removedFrames);
+
+    Ok(())
+  }
+
+  fn lookup(&self, name: &[u8]) -> Result<Arc<dyn Inode>, FsError> {
+    let state = self.state.acquire();
+
+    let Contents::Directory { entries, .. } = &state.contents
+    else {
+      return Err(FsError::NotADirectory);
+    };
+
+    match entries.get(name) {
+      Some(inode) => Ok(inode.clone()),
+      None => Err(FsError::NotFound),
+    }
+  }
+
+  fn create(
+    &self,
+    name: &[u8],
+    fileType: FileType,
+    deviceNumber: DeviceNumber,
+  ) -> Result<Arc<dyn Inode>, FsError> {
+    let contents = match fileType {
+      FileType::Regular => Contents::RegularFile {
+        size: 0,
+        frames: BTreeMap::new(),
+      },
+      FileType::Directory => Contents::Directory {
+        entries: BTreeMap::new(),
+        parentInodeNumber: self.inodeNumber,
+      },
+      FileType::Symlink => return Err(FsError::InvalidArgument),
+      FileType::CharDevice | FileType::BlockDevice | FileType::Fifo => Contents::Special,
+    };
+
+    self.createChild(name, fileType, deviceNumber, contents)
+  }
+
+  fn symlink(&self, name: &[u8], target: &[u8]) -> Result<Arc<dyn Inode>, FsError> {
+    let contents = Contents::Symlink {
+      target: target.to_vec(),
+    };
+
+    self.createChild(name, FileType::Symlink, DeviceNumber::default(), contents)
+  }
+
+  fn link(&self, name: &[u8], inode: &dyn Inode) -> Result<(), FsError> {
+    let inode = self.downcast(inode)?;
+    if inode.isDirectory() {
+      return Err(FsError::IsADirectory);
+    }
+
+    {
+      let mut state = self.state.acquire();
+
+      if state.linksCount == 0 {
+        return Err(FsError::NotFound);
+      }
+      let Contents::Directory { entries, .. } = &mut state.contents
+      else {
+        return Err(FsError::NotADirectory);
+      };
+
+      let Entry::Vacant(entry) = entries.entry(name.to_vec())
+      else {
+        return Err(FsError::AlreadyExists);
+      };
+      entry.insert(inode.this.upgrade().expect("Linking a dropped inode"));
+    }
+
+    inode.state.acquire().linksCount += 1;
+    Ok(())
+  }
+
+  fn unlink(&self, name: &[u8]) -> Result<(), FsError> {
+    let removedInode = {
+      let mut state = self.state.acquire();
+
+      let Contents::Directory { entries, .. } = &mut state.contents
+      else {
+        return Err(FsError::NotADirectory);
+      };
+      let removedInode = entries.remove(name).ok_or(FsError::NotFound)?;
+
+      // The ".." entry of the removed directory.
+      if removedInode.isDirectory() {
+        state.linksCount -= 1;
+      }
+      removedInode
+    };
+
+    removedInode.dropLink();
+    Ok(())
+  }
+
+  fn rename(
+    &self,
+    oldName: &[u8],
+    newDirectory: &dyn Inode,
+    newName: &[u8],
+  ) -> Result<(), FsError> {
+    let newDirectory = self.downcast(newDirectory)?;
+    if !newDirectory.isDirectory() {
+      return Err(FsError::NotADirectory);
+    }
+
+    let movedInode = {
+      let mut state = self.state.acquire();
+
+      let Contents::Directory { entries, .. } = &mut state.contents
+      else {
+        return Err(FsError::NotADirectory);
+      };
+      let movedInode = entries.remove(oldName).ok_or(FsError::NotFound)?;
+
+      if movedInode.isDirectory() {
+        state.linksCount -= 1;
+      }
+      movedInode
+    };
+
+    let replacedInode = {
+      let mut state = newDirectory.state.acquire();
+
+      let Contents::Directory { entries, .. } = &mut state.contents
+      else {
+        unreachable!("Renaming into a non directory inode");
+      };
+      let replacedInode = entries.insert(newName.to_vec(), movedInode.clone());
+
+      if movedInode.isDirectory() {
+        state.linksCount += 1;
+      }
+      if replacedInode
+        .as_ref()
+        .is_some_and(|replacedInode| replacedInode.isDirectory())
+      {
+        state.linksCount -= 1;
+      }
+      replacedInode
+    };
+
+    if let Some(replacedInode) = replacedInode {
+      replacedInode.dropLink();
+    }
+
+    // Point the ".." entry of the moved directory to its new parent.
+    if let Contents::Directory {
+      parentInodeNumber, ..
+    } = &mut movedInode.state.acquire().contents
+    {
+      *parentInodeNumber = newDirectory.inodeNumber;
+    }
+
+    Ok(())
+  }
+
+  // The position is the index of the directory entry, with "." and ".." coming first.
+  fn readDirectory(&self, position: usize) -> Result<Option<(DirectoryEntry, usize)>, FsError> {
+    let state = self.state.acquire();
+
+    let Contents::Directory {
+      entries,
+      parentInodeNumber,
+    } = &state.contents
+    else {
+      return Err(FsError::NotADirectory);
+    };
+
+    let directoryEntry = match position {
+      0 => Some((b".".to_vec(), self.inodeNumber)),
+      1 => Some((b"..".to_vec(), *parentInodeNumber)),
+      _ => entries
+        .iter()
+        .nth(position - 2)
+        .map(|(name, inode)| (name.clone(), inode.inodeNumber)),
+    };
+
+    Ok(
+      directoryEntry
+        .map(|(name, inodeNumber)| (DirectoryEntry { inodeNumber, name }, position + 1)),
+    )
+  }
+
+  fn readLink(&self) -> Result<Vec<u8>, FsError> {
+    match &self.state.acquire().contents {
+      Contents::Symlink { target } => Ok(target.clone()),
+      _ => Err(FsError::InvalidArgument),
+    }
+  }
+}
@@ -0,0 +1,310 @@
+use {
+  super::{FileType, FsError, Inode},
+  crate::locks::spinlock::SpinLock,
+  alloc::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Weak},
+    vec::Vec,
+  },
+  core::{mem, ptr},
+};
+
+// Number of recently used Dentries, which are kept cached even if nothing else refers to them.
+// NOTE : Each of them keeps its inode (and its ancestors' inodes) in use.
+pub const DENTRY_CACHE_SIZE: usize = 64;
+
+struct DentryLink {
+  name: Vec<u8>,
+
+  // None for the root directory of a File System.
+  parent: Option<Arc<Dentry>>,
+}
+
+struct DentryChildren {
+  // The children which have been looked up. They're dropped once they aren't used anymore, and
+  // evicted from the Dentry cache.
+  entries: BTreeMap<Vec<u8>, Weak<Dentry>>,
+
+  // Incremented whenever an entry of the directory is removed or renamed. A lookup which misses
+  // the cache doesn't cache its result, if the directory was modified while the File System was
+  // looking the name up.
+  generation: usize,
+}
+
+/*
+  A Dentry (directory entry) names an inode, within its parent directory. The Dentries form a tree
+  per mounted File System, mirroring the parts of its directory tree which are in use.
+
+  A Dentry refers to its parent (so the ancestors of a Dentry which is in use stay cached), while a
+  directory only weakly refers to its children.
+*/
+pub struct Dentry {
+  link: SpinLock<DentryLink>,
+  children: SpinLock<DentryChildren>,
+
+  inode: Arc<dyn Inode>,
+
+  // Cached from the inode's Stat, since they never change.
+  fileSystemID: usize,
+  fileType: FileType,
+}
+
+impl Dentry {
+  fn new(name: &[u8], parent: Option<Arc<Dentry>>, inode: Arc<dyn Inode>) -> Result<Self, FsError> {
+    let stat = inode.getStat()?;
+
+    Ok(Self {
+      link: SpinLock::new(DentryLink {
+        name: name.to_vec(),
+        parent,
+      }),
+      children: SpinLock::new(DentryChildren {
+        entries: BTreeMap::new(),
+        generation: 0,
+      }),
+
+      inode,
+
+      fileSystemID: stat.fileSystemID,
+      fileType: stat.fileType,
+    })
+  }
+
+  // Returns a Dentry for the root directory of a File System.
+  pub fn newRoot(inode: Arc<dyn Inode>) -> Result<Arc<Self>, FsError> {
+    let dentry = Self::new(b"/", None, inode)?;
+
+    match dentry.fileType {
+      FileType::Directory => Ok(Arc::new(dentry)),
+      _ => Err(FsError::NotADirectory),
+    }
+  }
+
+  #[inline]
+  pub fn getInode(&self) -> &Arc<dyn Inode> {
+    &self.inode
+  }
+
+  #[inline]
+  pub fn getFileType(&self) -> FileType {
+    self.fileType
+  }
+
+  #[inline]
+  pub fn getFileSystemID(&self) -> usize {
+    self.fileSystemID
+  }
+
+  #[inline]
+  pub fn isDirectory(&self) -> bool {
+    self.fileType == FileType::Directory
+  }
+
+  pub fn getName(&self) -> Vec<u8> {
+    self.link.acquire().name.clone()
+  }
+
+  pub fn getParent(&self) -> Option<Arc<Dentry>> {
+    self.link.acquire().parent.clone()
+  }
+
+  // Returns the Dentry with the given name, in this directory. Asks the File System to look it up,
+  // if it isn't cached.
+  pub fn lookupChild(self: &Arc<Self>, name: &[u8]) -> Result<Arc<Dentry>, FsError> {
+    let generation = {
+      let children = self.children.acquire();
+
+      if let Some(child) = children.entries.get(name).and_then(Weak::upgrade) {
+        drop(children);
+
+        DENTRY_CACHE.touch(&child);
+        return Ok(child);
+      }
+      children.generation
+    };
+
+    // The File System may need to sleep (say, while reading the directory from the disk). So the
+    // SpinLock isn't held meanwhile.
+    let inode = self.inode.lookup(name)?;
+    let child = Arc::new(Self::new(name, Some(self.clone()), inode)?);
+
+    let cachedChild = {
+      let mut children = self.children.acquire();
+
+      match children.entries.get(name).and_then(Weak::upgrade) {
+        // Another process has looked the name up meanwhile.
+        Some(cachedChild) => Some(cachedChild),
+
+        None => {
+          if children.generation == generation {
+            children
+              .entries
+              .insert(name.to_vec(), Arc::downgrade(&child));
+          }
+          None
+        }
+      }
+    };
+
+    // NOTE : The Dentry which isn't used is dropped after releasing the SpinLock, since dropping
+    // its inode may sleep.
+    let child = cachedChild.unwrap_or(child);
+
+    DENTRY_CACHE.touch(&child);
+    Ok(child)
+  }
+
+  // Caches a Dentry for the given inode, which has just been created with the given name in this
+  // directory.
+  pub fn addChild(
+    self: &Arc<Self>,
+    name: &[u8],
+    inode: Arc<dyn Inode>,
+  ) -> Result<Arc<Dentry>, FsError> {
+    let child = Arc::new(Self::new(name, Some(self.clone()), inode)?);
+
+    let replacedChild = {
+      let mut children = self.children.acquire();
+
+      children.generation += 1;
+      children
+        .entries
+        .insert(name.to_vec(), Arc::downgrade(&child))
+    };
+    drop(replacedChild);
+
+    DENTRY_CACHE.touch(&child);
+    Ok(child)
+  }
+
+  // Forgets the child with the given name, which has been removed from this directory.
+  pub fn removeChild(&self, name: &[u8]) {
+    let removedChild = {
+      let mut children = self.children.acquire();
+
+      children.generation += 1;
+      children.entries.remove(name)
+    };
+
+    if let Some(removedChild) = removedChild {
+      DENTRY_CACHE.evict(|dentry| ptr::eq(Arc::as_ptr(dentry), removedChild.as_ptr()));
+    }
+  }
+
+  // Moves the child with the given name in this directory, to the given name in the given
+  // directory. The moved Dentry (if cached) stays valid for whoever is using it.
+  pub fn moveChild(&self, oldName: &[u8], newParent: &Arc<Dentry>, newName: &[u8]) {
+    let movedChild = {
+      let mut children = self.children.acquire();
+
+      children.generation += 1;
+      children
+        .entries
+        .remove(oldName)
+        .and_then(|child| child.upgrade())
+    };
+
+    let replacedChild = {
+      let mut children = newParent.children.acquire();
+
+      children.generation += 1;
+      match movedChild.as_ref() {
+        Some(movedChild) => children
+          .entries
+          .insert(newName.to_vec(), Arc::downgrade(movedChild)),
+        None => children.entries.remove(newName),
+      }
+    };
+
+    if let Some(movedChild) = movedChild.as_ref() {
+      let oldParent = {
+        let mut link = movedChild.link.acquire();
+
+        link.name = newName.to_vec();
+        mem::replace(&mut link.parent, Some(newParent.clone()))
+      };
+      drop(oldParent);
+    }
+
+    if let Some(replacedChild) = replacedChild {
+      DENTRY_CACHE.evict(|dentry| ptr::eq(Arc::as_ptr(dentry), replacedChild.as_ptr()));
+    }
+  }
+}
+
+impl Drop for Dentry {
+  // Removes the entry from the parent's children (if it hasn't been replaced meanwhile).
+  fn drop(&mut self) {
+    let (name, parent) = {
+      let mut link = self.link.acquire();
+      (mem::take(&mut link.name), link.parent.take())
+    };
+
+    if let Some(parent) = parent.as_ref() {
+      let mut children = parent.children.acquire();
+
+      if children
+        .entries
+        .get(&name)
+        .is_some_and(|child| child.strong_count() == 0)
+      {
+        children.entries.remove(&name);
+      }
+    }
+
+    // NOTE : The parent (if this was its last reference) is dropped only after releasing its
+    // SpinLock.
+  }
+}
+
+/*
+  Keeps the recently used Dentries cached, even if nothing else refers to them. This way, looking
+  up a path repeatedly doesn't go to the File System each time.
+
+  NOTE : A Dentry may appear multiple times. Dentries are always dropped after releasing the
+  SpinLock, since dropping their inodes may sleep.
+*/
+pub struct DentryCache {
+  recentlyUsed: SpinLock<VecDeque<Arc<Dentry>>>,
+}
+
+impl DentryCache {
+  const fn new() -> Self {
+    Self {
+      recentlyUsed: SpinLock::new(VecDeque::new()),
+    }
+  }
+
+  fn touch(&self, dentry: &Arc<Dentry>) {
+    let evictedDentry = {
+      let mut recentlyUsed = self.recentlyUsed.acquire();
+
+      recentlyUsed.push_back(dentry.clone());
+      match recentlyUsed.len() > DENTRY_CACHE_SIZE {
+        true => recentlyUsed.pop_front(),
+        false => None,
+      }
+    };
+    drop(evictedDentry);
+  }
+
+  // Evicts the Dentries matching the given predicate.
+  pub fn evict(&self, predicate: impl Fn(&Arc<Dentry>) -> bool) {
+    let mut evictedDentries = Vec::new();
+
+    {
+      let mut recentlyUsed = self.recentlyUsed.acquire();
+
+      for dentry in mem::take(&mut *recentlyUsed) {
+        match predicate(&dentry) {
+          true => evictedDentries.push(dentry),
+          false => recentlyUsed.push_back(dentry),
+        }
+      }
+    }
+
+    drop(evictedDentries);
+  }
+}
+
+pub static DENTRY_CACHE: DentryCache = DentryCache::new();
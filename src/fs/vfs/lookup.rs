@@ -0,0 +1,276 @@
+use {
+  super::{
+    dentry::Dentry,
+    mount::{self, Mount},
+    FileType, FsError, Inode,
+  },
+  alloc::{sync::Arc, vec::Vec},
+};
+
+// Maximum length of a path element.
+pub const MAX_NAME_LENGTH: usize = 255;
+
+// Maximum number of symbolic links, which can be followed while resolving a path.
+pub const MAX_SYMLINKS_FOLLOWED: usize = 8;
+
+/*
+  A resolved path : a Dentry, along with the mounted File System it belongs to. The same Dentry can
+  be reached through multiple paths, only when it's the root directory of a mounted File System.
+
+  NOTE : A ResolvedPath keeps the mounted File System busy, so it can't be unmounted meanwhile.
+*/
+#[derive(Clone)]
+pub struct ResolvedPath {
+  mount: Arc<Mount>,
+  dentry: Arc<Dentry>,
+}
+
+impl ResolvedPath {
+  pub fn new(mount: Arc<Mount>, dentry: Arc<Dentry>) -> Self {
+    Self { mount, dentry }
+  }
+
+  #[inline]
+  pub fn getMount(&self) -> &Arc<Mount> {
+    &self.mount
+  }
+
+  #[inline]
+  pub fn getDentry(&self) -> &Arc<Dentry> {
+    &self.dentry
+  }
+
+  #[inline]
+  pub fn getInode(&self) -> &Arc<dyn Inode> {
+    self.dentry.getInode()
+  }
+
+  #[inline]
+  pub fn getFileType(&self) -> FileType {
+    self.dentry.getFileType()
+  }
+
+  // Whether this is the root directory of a mounted File System.
+  #[inline]
+  pub fn isMountRoot(&self) -> bool {
+    Arc::ptr_eq(&self.dentry, self.mount.getRoot())
+  }
+
+  // Whether both refer to the same Dentry, in the same mounted File System.
+  pub fn isSameAs(&self, other: &ResolvedPath) -> bool {
+    Arc::ptr_eq(&self.mount, &other.mount) && Arc::ptr_eq(&self.dentry, &other.dentry)
+  }
+
+  // Follows the mounts (if any) stacked on top of this directory, to the root directory of the one
+  // mounted last.
+  fn crossMountPoints(mut self) -> Self {
+    while let Some(mount) = mount::findMountedOn(&self) {
+      self = Self::new(mount.clone(), mount.getRoot().clone());
+    }
+    self
+  }
+
+  // Returns the parent directory. The root directory of a mounted File System is replaced by its
+  // mount point first, and the root directory is its own parent.
+  pub fn getParent(&self) -> Self {
+    let mut path = self.clone();
+
+    while path.isMountRoot() {
+      match path.mount.getMountPoint() {
+        Some(mountPoint) => path = mountPoint.clone(),
+        None => return path,
+      }
+    }
+
+    let parent = path
+      .dentry
+      .getParent()
+      .expect("Dentry other than a mount root doesn't have a parent");
+    Self::new(path.mount, parent)
+  }
+
+  // Returns the given element (other than a symbolic link, which is returned as it is) of this
+  // directory.
+  pub fn getChild(&self, name: &[u8]) -> Result<Self, FsError> {
+    if !self.dentry.isDirectory() {
+      return Err(FsError::NotADirectory);
+    }
+
+    match name {
+      b"" | b"." => Ok(self.clone()),
+      b".." => Ok(self.getParent()),
+
+      _ if name.len() > MAX_NAME_LENGTH => Err(FsError::NameTooLong),
+      _ => {
+        let dentry = self.dentry.lookupChild(name)?;
+        Ok(Self::new(self.mount.clone(), dentry).crossMountPoints())
+      }
+    }
+  }
+
+  // Returns the absolute path (like /mnt/disk/file).
+  pub fn getAbsolutePath(&self) -> Vec<u8> {
+    let mut names = Vec::new();
+
+    let mut path = self.clone();
+    loop {
+      while path.isMountRoot() {
+        match path.mount.getMountPoint() {
+          Some(mountPoint) => path = mountPoint.clone(),
+          None => break,
+        }
+      }
+      if path.isMountRoot() {
+        break;
+      }
+
+      names.push(path.dentry.getName());
+      path = path.getParent();
+    }
+
+    if names.is_empty() {
+      return b"/".to_vec();
+    }
+
+    let mut absolutePath = Vec::new();
+    for name in names.iter().rev() {
+      absolutePath.push(b'/');
+      absolutePath.extend_from_slice(name);
+    }
+    absolutePath
+  }
+}
+
+/*
+  Splits off the next element of the given path. Returns the element, along with the rest of the
+  path (with the leading slashes skipped). Returns None if there are no more elements.
+
+    getNextElement(b"a/bb/c") = Some((b"a", b"bb/c"))
+    getNextElement(b"///a//bb") = Some((b"a", b"bb"))
+    getNextElement(b"a") = Some((b"a", b""))
+    getNextElement(b"") = getNextElement(b"////") = None
+*/
+fn getNextElement(path: &[u8]) -> Option<(&[u8], &[u8])> {
+  let start = path.iter().position(|&byte| byte != b'/')?;
+  let path = &path[start..];
+
+  let end = path
+    .iter()
+    .position(|&byte| byte == b'/')
+    .unwrap_or(path.len());
+  let (element, rest) = path.split_at(end);
+
+  let restStart = rest
+    .iter()
+    .position(|&byte| byte != b'/')
+    .unwrap_or(rest.len());
+
+  Some((element, &rest[restStart..]))
+}
+
+// Returns where resolving the given path starts : the root directory for an absolute path, and the
+// given working directory (or the root directory, if none is given) for a relative one.
+fn getStart(path: &[u8], workingDirectory: Option<&ResolvedPath>) -> Result<ResolvedPath, FsError> {
+  match (path.first(), workingDirectory) {
+    (Some(b'/'), _) | (_, None) => mount::getRootPath(),
+    (_, Some(workingDirectory)) => Ok(workingDirectory.clone()),
+  }
+}
+
+fn walk(
+  start: ResolvedPath,
+  path: &[u8],
+  shouldFollowLast: bool,
+  symlinksFollowed: &mut usize,
+) -> Result<ResolvedPath, FsError> {
+  let mut current = start;
+
+  let mut path = path;
+  while let Some((element, rest)) = getNextElement(path) {
+    path = rest;
+
+    let child = current.getChild(element)?;
+    current = match child.getFileType() {
+      FileType::Symlink if shouldFollowLast || !rest.is_empty() => {
+        followSymlink(current, &child, symlinksFollowed)?
+      }
+      _ => child,
+    };
+  }
+
+  Ok(current)
+}
+
+// Resolves the target of the given symbolic link, in the given directory.
+fn followSymlink(
+  directory: ResolvedPath,
+  symlink: &ResolvedPath,
+  symlinksFollowed: &mut usize,
+) -> Result<ResolvedPath, FsError> {
+  *symlinksFollowed += 1;
+  if *symlinksFollowed > MAX_SYMLINKS_FOLLOWED {
+    return Err(FsError::TooManySymlinks);
+  }
+
+  let target = symlink.getInode().readLink()?;
+  if target.is_empty() {
+    return Err(FsError::NotFound);
+  }
+
+  let start = getStart(&target, Some(&directory))?;
+  walk(start, &target, true, symlinksFollowed)
+}
+
+// Resolves the given path. A symbolic link in the last element of the path is followed, only if
+// shouldFollowLast is true.
+pub fn resolve(
+  path: &[u8],
+  workingDirectory: Option<&ResolvedPath>,
+  shouldFollowLast: bool,
+) -> Result<ResolvedPath, FsError> {
+  if path.is_empty() {
+    return Err(FsError::NotFound);
+  }
+
+  let start = getStart(path, workingDirectory)?;
+  walk(start, path, shouldFollowLast, &mut 0)
+}
+
+/*
+  Resolves the parent directory of the given path. Returns it along with the last element of the
+  path, which is empty only for the root directory (like "/" or "//").
+
+    resolveParent(b"/a/b/c") = (/a/b, b"c")
+    resolveParent(b"a/b/") = (<working directory>/a, b"b")
+    resolveParent(b"c") = (<working directory>, b"c")
+*/
+pub fn resolveParent<'a>(
+  path: &'a [u8],
+  workingDirectory: Option<&ResolvedPath>,
+) -> Result<(ResolvedPath, &'a [u8]), FsError> {
+  if path.is_empty() {
+    return Err(FsError::NotFound);
+  }
+
+  let path = match path.iter().rposition(|&byte| byte != b'/') {
+    Some(end) => &path[..=end],
+    None => &path[..1], // The root directory.
+  };
+
+  let (parentPath, name) = match path.iter().rposition(|&byte| byte == b'/') {
+    Some(separator) => (&path[..=separator], &path[separator + 1..]),
+    None => (&path[..0], path),
+  };
+
+  if name.len() > MAX_NAME_LENGTH {
+    return Err(FsError::NameTooLong);
+  }
+
+  let start = getStart(path, workingDirectory)?;
+  let parent = walk(start, parentPath, true, &mut 0)?;
+
+  match parent.dentry.isDirectory() {
+    true => Ok((parent, name)),
+    false => Err(FsError::NotADirectory),
+  }
+}
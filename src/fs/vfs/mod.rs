@@ -0,0 +1,454 @@
+use {
+  alloc::{sync::Arc, vec::Vec},
+  bitflags::bitflags,
+  core::{
+    any::Any,
+    sync::atomic::{AtomicUsize, Ordering},
+  },
+};
+
+/*
+  The Virtual File System (VFS) layer, which keeps the rest of the kernel independent of the
+  File System implementations. Each File System (on disk, in memory or a pseudo one) implements
+  the FileSystem and Inode traits (and optionally the File trait), and registers its type with the
+  VFS. Instances of it can then be mounted on directories, forming a single directory tree.
+
+    (1) A Dentry (directory entry) names an inode, within its parent directory. Dentries are cached
+        (see the dentry module), so that path lookups don't need to go to the File Systems.
+
+    (2) A Mount attaches the root directory of a mounted File System to a mount point : a directory
+        in another mounted File System. Path lookups cross mount points transparently (see the
+        lookup module).
+
+    (3) A File is an opened inode. By default, it simply forwards reads and writes to the inode.
+        Opening a device file gives a File which forwards to the device instead (see the device
+        module), and opening a FIFO gives an end of a pipe (see the pipe module).
+
+  REFER : chapter 12 (The Virtual Filesystem) of Linux Kernel Development by Robert Love.
+*/
+
+pub mod dentry;
+pub mod lookup;
+pub mod mount;
+pub mod namespace;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum FsError {
+  NotFound,
+  AlreadyExists,
+
+  NotADirectory,
+  IsADirectory,
+  DirectoryNotEmpty,
+
+  // No free data blocks.
+  NoSpace,
+  // No free inodes.
+  NoInodes,
+
+  FileTooLarge,
+  NameTooLong,
+  InvalidArgument,
+
+  // The disk doesn't contain a valid File System.
+  InvalidSuperBlock,
+
+  // The File System doesn't support the operation.
+  NotSupported,
+
+  // The File System is mounted read only.
+  ReadOnly,
+
+  // No File System type is registered with the given name.
+  UnknownFileSystemType,
+
+  // The File System (or mount point) is in use.
+  Busy,
+
+  // The operation spans multiple mounted File Systems (like linking across them).
+  CrossDevice,
+
+  // Too many symbolic links were encountered, while resolving a path.
+  TooManySymlinks,
+
+  // The file descriptor isn't open (or isn't open for the operation).
+  BadFileDescriptor,
+
+  // The file descriptor table of the process / the system wide open file table is full.
+  TooManyOpenFiles,
+
+  // The File isn't seekable (like a pipe).
+  IllegalSeek,
+
+  // A non blocking read / write would have blocked.
+  WouldBlock,
+
+  // Writing to a pipe, which nobody has open for reading.
+  BrokenPipe,
+
+  // Opening a FIFO only for writing in non blocking mode, while nobody has it open for reading.
+  NoReaders,
+
+  // The file isn't in an executable format (like ELF), which we can run.
+  NotExecutable,
+
+  // The arguments and the environment passed to exec( ) don't fit on the stack.
+  ArgumentsTooLong,
+
+  // A pointer passed to a system call doesn't point to (accessible) user memory.
+  BadAddress,
+
+  // The kernel ran out of memory.
+  OutOfMemory,
+
+  IOError,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum FileType {
+  Regular,
+  Directory,
+  Symlink,
+  CharDevice,
+  BlockDevice,
+  Fifo,
+}
+
+// Identifies a device : the major number identifies the driver, and the minor number identifies
+// the device among the ones the driver manages.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Default)]
+pub struct DeviceNumber {
+  pub major: u32,
+  pub minor: u32,
+}
+
+impl DeviceNumber {
+  pub const fn new(major: u32, minor: u32) -> Self {
+    Self { major, minor }
+  }
+}
+
+// Metadata of an inode, uniform across all the File Systems.
+#[derive(Debug, Copy, Clone)]
+pub struct Stat {
+  // Identifies the File System instance the inode belongs to (see allocateFileSystemID( )).
+  pub fileSystemID: usize,
+  pub inodeNumber: u64,
+
+  pub fileType: FileType,
+  pub linksCount: u32,
+
+  // Size of the file, in bytes.
+  pub size: u64,
+
+  // The device, a device file refers to.
+  pub deviceNumber: DeviceNumber,
+}
+
+#[derive(Debug, Clone)]
+pub struct DirectoryEntry {
+  pub inodeNumber: u64,
+  pub name: Vec<u8>,
+}
+
+bitflags! {
+  #[derive(Debug, PartialEq, Copy, Clone)]
+  pub struct OpenFlags: usize {
+    const READ = 1 << 0;
+    const WRITE = 1 << 1;
+
+    // Create the file, if it doesn't exist.
+    const CREATE = 1 << 2;
+    // Along with CREATE : fail if the file already exists.
+    const EXCLUSIVE = 1 << 3;
+
+    // Discard the contents of the file.
+    const TRUNCATE = 1 << 4;
+    // Each write appends to the file.
+    const APPEND = 1 << 5;
+
+    // Fail if the file isn't a directory.
+    const DIRECTORY = 1 << 6;
+    // Don't follow a symbolic link, in the last element of the path.
+    const NO_FOLLOW = 1 << 7;
+
+    // Reads and writes which would block, fail instead.
+    const NON_BLOCKING = 1 << 8;
+  }
+}
+
+bitflags! {
+  // Readiness of a File for I/O, as reported by poll( ).
+  #[derive(Debug, PartialEq, Copy, Clone)]
+  pub struct PollEvents: usize {
+    // A read won't block.
+    const READABLE = 1 << 0;
+    // A write won't block.
+    const WRITABLE = 1 << 1;
+  }
+}
+
+// A mounted instance of a File System.
+pub trait FileSystem: Send + Sync {
+  fn getTypeName(&self) -> &'static str;
+
+  fn getRootInode(&self) -> Result<Arc<dyn Inode>, FsError>;
+
+  // Writes the modified data (if any) to the backing storage.
+  fn sync(&self) -> Result<(), FsError> {
+    Ok(())
+  }
+
+  // Invoked once the File System is unmounted, and nothing refers to it anymore.
+  fn unmount(&self) -> Result<(), FsError> {
+    self.sync()
+  }
+}
+
+/*
+  An inode of a mounted File System. The VFS holds the inode while it's cached or opened, so the
+  File System must keep it alive (even if it's unlinked) till it's dropped.
+
+  Operations which don't make sense for the inode's type have default implementations returning
+  an error. The VFS checks the types beforehand (say, that lookup( ) is only invoked on a
+  directory), so implementations needn't.
+
+  NOTE : The VFS serializes all the operations modifying the directory tree (see the namespace
+  module).
+*/
+pub trait Inode: Send + Sync {
+  // Used to get the concrete inode type back (say, from the target of link( )).
+  fn asAny(&self) -> &dyn Any;
+
+  fn getStat(&self) -> Result<Stat, FsError>;
+
+  // Reads from the file starting at the given offset. Returns the number of bytes read (0 at the
+  // end of the file).
+  fn readAt(&self, _offset: u64, _buffer: &mut [u8]) -> Result<usize, FsError> {
+    Err(FsError::NotSupported)
+  }
+
+  // Writes to the file starting at the given offset, growing it if required. Returns the number of
+  // bytes written.
+  fn writeAt(&self, _offset: u64, _buffer: &[u8]) -> Result<usize, FsError> {
+    Err(FsError::NotSupported)
+  }
+
+  // Sets the size of the file, discarding or zero filling its contents as required.
+  fn truncate(&self, _size: u64) -> Result<(), FsError> {
+    Err(FsError::NotSupported)
+  }
+
+  // Returns the inode with the given name, in this directory.
+  fn lookup(&self, _name: &[u8]) -> Result<Arc<dyn Inode>, FsError> {
+    Err(FsError::NotADirectory)
+  }
+
+  // Creates an inode of the given type (other than a symbolic link), with the given name in this
+  // directory.
+  fn create(
+    &self,
+    _name: &[u8],
+    _fileType: FileType,
+    _deviceNumber: DeviceNumber,
+  ) -> Result<Arc<dyn Inode>, FsError> {
+    Err(FsError::NotSupported)
+  }
+
+  // Creates a symbolic link with the given name in this directory, pointing to the given target.
+  fn symlink(&self, _name: &[u8], _target: &[u8]) -> Result<Arc<dyn Inode>, FsError> {
+    Err(FsError::NotSupported)
+  }
+
+  // Adds a hard link with the given name in this directory, to the given (non directory) inode of
+  // the same File System.
+  fn link(&self, _name: &[u8], _inode: &dyn Inode) -> Result<(), FsError> {
+    Err(FsError::NotSupported)
+  }
+
+  // Removes the given name from this directory. If it refers to a directory, then that directory
+  // is empty.
+  fn unlink(&self, _name: &[u8]) -> Result<(), FsError> {
+    Err(FsError::NotSupported)
+  }
+
+  /*
+    Moves the given name in this directory, to the given name in the given directory (of the same
+    File System). If the new name exists, then it's replaced : the VFS has checked that both refer
+    to directories or both don't, and that the replaced directory is empty.
+  */
+  fn rename(
+    &self,
+    _oldName: &[u8],
+    _newDirectory: &dyn Inode,
+    _newName: &[u8],
+  ) -> Result<(), FsError> {
+    Err(FsError::NotSupported)
+  }
+
+  // Returns the directory entry at the given position in this directory, along with the position
+  // of the next one. Returns None once there are no more entries.
+  fn readDirectory(&self, _position: usize) -> Result<Option<(DirectoryEntry, usize)>, FsError> {
+    Err(FsError::NotADirectory)
+  }
+
+  // Returns the target of this symbolic link.
+  fn readLink(&self) -> Result<Vec<u8>, FsError> {
+    Err(FsError::InvalidArgument)
+  }
+
+  // Lets the File System provide its own File, when the inode is opened (say, to snapshot
+  // generated contents). Returns None to use the default File, which forwards to the inode.
+  fn open(&self, _flags: OpenFlags) -> Result<Option<Arc<dyn File>>, FsError> {
+    Ok(None)
+  }
+
+  // Writes the modified data of the inode (if any) to the backing storage.
+  fn sync(&self) -> Result<(), FsError> {
+    Ok(())
+  }
+}
+
+/*
+  An opened inode (or another object which can be read and written, like a device).
+
+  Offsets are passed in explicitly, since they belong to whoever opened the File. Objects which
+  aren't seekable (like pipes) ignore them.
+*/
+pub trait File: Send + Sync {
+  fn read(&self, offset: u64, buffer: &mut [u8]) -> Result<usize, FsError>;
+
+  fn write(&self, offset: u64, buffer: &[u8]) -> Result<usize, FsError>;
+
+  // Same as read( ) / write( ), except that they fail with WouldBlock instead of blocking. Files
+  // which never block needn't override them.
+  fn readNonBlocking(&self, offset: u64, buffer: &mut [u8]) -> Result<usize, FsError> {
+    self.read(offset, buffer)
+  }
+
+  fn writeNonBlocking(&self, offset: u64, buffer: &[u8]) -> Result<usize, FsError> {
+    self.write(offset, buffer)
+  }
+
+  fn getStat(&self) -> Result<Stat, FsError>;
+
+  fn isSeekable(&self) -> bool {
+    true
+  }
+
+  fn truncate(&self, _size: u64) -> Result<(), FsError> {
+    Err(FsError::InvalidArgument)
+  }
+
+  fn readDirectory(&self, _position: usize) -> Result<Option<(DirectoryEntry, usize)>, FsError> {
+    Err(FsError::NotADirectory)
+  }
+
+  // Performs a device specific operation. Returns a request specific value.
+  fn ioctl(&self, _request: usize, _argument: usize) -> Result<usize, FsError> {
+    Err(FsError::NotSupported)
+  }
+
+  // Regular files and directories never block.
+  fn poll(&self) -> PollEvents {
+    PollEvents::READABLE | PollEvents::WRITABLE
+  }
+
+  fn sync(&self) -> Result<(), FsError> {
+    Ok(())
+  }
+
+  // Returns the opened path, if the File has one.
+  fn getPath(&self) -> Option<&lookup::ResolvedPath> {
+    None
+  }
+}
+
+// The default File, which forwards to the opened inode.
+pub struct InodeFile {
+  // Keeps the mounted File System busy, while the File is open.
+  path: lookup::ResolvedPath,
+}
+
+impl InodeFile {
+  pub fn new(path: lookup::ResolvedPath) -> Self {
+    Self { path }
+  }
+
+  #[inline]
+  fn getInode(&self) -> &Arc<dyn Inode> {
+    self.path.getInode()
+  }
+}
+
+impl File for InodeFile {
+  fn read(&self, offset: u64, buffer: &mut [u8]) -> Result<usize, FsError> {
+    self.getInode().readAt(offset, buffer)
+  }
+
+  fn write(&self, offset: u64, buffer: &[u8]) -> Result<usize, FsError> {
+    self.getInode().writeAt(offset, buffer)
+  }
+
+  fn getStat(&self) -> Result<Stat, FsError> {
+    self.getInode().getStat()
+  }
+
+  fn truncate(&self, size: u64) -> Result<(), FsError> {
+    self.getInode().truncate(size)
+  }
+
+  fn readDirectory(&self, position: usize) -> Result<Option<(DirectoryEntry, usize)>, FsError> {
+    self.getInode().readDirectory(position)
+  }
+
+  fn sync(&self) -> Result<(), FsError> {
+    self.getInode().sync()
+  }
+
+  fn getPath(&self) -> Option<&lookup::ResolvedPath> {
+    Some(&self.path)
+  }
+}
+
+static NEXT_FILE_SYSTEM_ID: AtomicUsize = AtomicUsize::new(1);
+
+// Returns a unique ID, for a File System instance to report in the Stats of its inodes.
+pub fn allocateFileSystemID() -> usize {
+  NEXT_FILE_SYSTEM_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+// Registers the built in File System types, and mounts the root File System along with a tmpfs
+// on /tmp, procfs on /proc and devfs on /dev (if the root File System has those directories).
+// NOTE : Should only be invoked while the kernel is initializing.
+pub fn init() {
+  mount::registerFileSystemType(&crate::fs::arnofs::FILE_SYSTEM_TYPE);
+  mount::registerFileSystemType(&crate::fs::devfs::FILE_SYSTEM_TYPE);
+  mount::registerFileSystemType(&crate::fs::ext2::FILE_SYSTEM_TYPE);
+  mount::registerFileSystemType(&crate::fs::fat32::FILE_SYSTEM_TYPE);
+  mount::registerFileSystemType(&crate::fs::procfs::FILE_SYSTEM_TYPE);
+  mount::registerFileSystemType(&crate::fs::tmpfs::FILE_SYSTEM_TYPE);
+
+  mount::mountRoot(&crate::fs::tmpfs::FILE_SYSTEM_TYPE);
+
+  match mount::mount(b"tmpfs", b"", b"/tmp", b"", None) {
+    Ok(()) | Err(FsError::NotFound) => {}
+    Err(error) => {
+      println!("WARN : Failed mounting tmpfs on /tmp : {:?}", error);
+    }
+  }
+
+  match mount::mount(b"proc", b"", b"/proc", b"", None) {
+    Ok(()) | Err(FsError::NotFound) => {}
+    Err(error) => {
+      println!("WARN : Failed mounting procfs on /proc : {:?}", error);
+    }
+  }
+
+  match mount::mount(b"devfs", b"", b"/dev", b"", None) {
+    Ok(()) | Err(FsError::NotFound) => {}
+    Err(error) => {
+      println!("WARN : Failed mounting devfs on /dev : {:?}", error);
+    }
+  }
+}
@@ -0,0 +1,280 @@
+use {
+  super::{
+    dentry::{Dentry, DENTRY_CACHE},
+    lookup::{self, ResolvedPath},
+    namespace, FileSystem, FsError,
+  },
+  crate::{fs::disk, locks::spinlock::SpinLock},
+  alloc::{format, sync::Arc, vec::Vec},
+};
+
+// A type of File System, which can be mounted.
+pub struct FileSystemType {
+  pub name: &'static str,
+
+  // Whether the File System is stored in a disk, which must then be given when mounting it.
+  pub isDiskBased: bool,
+
+  // Mounts a new instance of the File System (stored in the given disk, if it's disk based), with
+  // the given mount options (like size=16m). The options are File System specific.
+  pub mount: fn(diskNumber: Option<usize>, options: &[u8]) -> Result<Arc<dyn FileSystem>, FsError>,
+}
+
+static FILE_SYSTEM_TYPES: SpinLock<Vec<&'static FileSystemType>> = SpinLock::new(Vec::new());
+
+pub fn registerFileSystemType(fileSystemType: &'static FileSystemType) {
+  let mut fileSystemTypes = FILE_SYSTEM_TYPES.acquire();
+
+  assert!(
+    fileSystemTypes
+      .iter()
+      .all(|registeredType| registeredType.name != fileSystemType.name),
+    "File System type {} is already registered",
+    fileSystemType.name
+  );
+  fileSystemTypes.push(fileSystemType);
+}
+
+fn getFileSystemType(name: &[u8]) -> Result<&'static FileSystemType, FsError> {
+  FILE_SYSTEM_TYPES
+    .acquire()
+    .iter()
+    .find(|fileSystemType| fileSystemType.name.as_bytes() == name)
+    .copied()
+    .ok_or(FsError::UnknownFileSystemType)
+}
+
+// A mounted File System.
+pub struct Mount {
+  fileSystem: Arc<dyn FileSystem>,
+  root: Arc<Dentry>,
+
+  // The directory it's mounted on (None for the root File System).
+  mountPoint: Option<ResolvedPath>,
+
+  // The disk it's stored in (if it's disk based).
+  diskNumber: Option<usize>,
+}
+
+impl Mount {
+  #[inline]
+  pub fn getFileSystem(&self) -> &Arc<dyn FileSystem> {
+    &self.fileSystem
+  }
+
+  #[inline]
+  pub fn getRoot(&self) -> &Arc<Dentry> {
+    &self.root
+  }
+
+  #[inline]
+  pub fn getMountPoint(&self) -> Option<&ResolvedPath> {
+    self.mountPoint.as_ref()
+  }
+
+  #[inline]
+  pub fn getDiskNumber(&self) -> Option<usize> {
+    self.diskNumber
+  }
+}
+
+// The mounted File Systems, in the order they were mounted.
+static MOUNTS: SpinLock<Vec<Arc<Mount>>> = SpinLock::new(Vec::new());
+
+// Returns the mounted File Systems.
+pub fn getMounts() -> Vec<Arc<Mount>> {
+  MOUNTS.acquire().clone()
+}
+
+// Returns the root directory of the root File System.
+pub fn getRootPath() -> Result<ResolvedPath, FsError> {
+  let rootMount = MOUNTS
+    .acquire()
+    .iter()
+    .find(|mount| mount.mountPoint.is_none())
+    .cloned()
+    .ok_or(FsError::NotFound)?;
+
+  let root = rootMount.root.clone();
+  Ok(ResolvedPath::new(rootMount, root))
+}
+
+// Returns the File System mounted last on the given directory (if any).
+pub fn findMountedOn(path: &ResolvedPath) -> Option<Arc<Mount>> {
+  MOUNTS
+    .acquire()
+    .iter()
+    .rev()
+    .find(|mount| {
+      mount
+        .mountPoint
+        .as_ref()
+        .is_some_and(|mountPoint| mountPoint.isSameAs(path))
+    })
+    .cloned()
+}
+
+/*
+  Disks are named vda, vdb, ... by their disk numbers. The name can be prefixed with /dev/.
+*/
+pub fn getDiskName(diskNumber: usize) -> Vec<u8> {
+  format!("vd{}", (b'a' + diskNumber as u8) as char).into_bytes()
+}
+
+pub fn parseDiskName(name: &[u8]) -> Result<usize, FsError> {
+  let name = name.strip_prefix(b"/dev/").unwrap_or(name);
+
+  let diskNumber = match name {
+    [b'v', b'd', letter @ b'a'..=b'z'] => (letter - b'a') as usize,
+    _ => return Err(FsError::NotFound),
+  };
+
+  match diskNumber < disk::getAttachedDisksCount() {
+    true => Ok(diskNumber),
+    false => Err(FsError::NotFound),
+  }
+}
+
+fn addMount(
+  fileSystem: Arc<dyn FileSystem>,
+  mountPoint: Option<ResolvedPath>,
+  diskNumber: Option<usize>,
+) -> Result<(), FsError> {
+  let root = Dentry::newRoot(fileSystem.getRootInode()?)?;
+
+  let mount = Arc::new(Mount {
+    fileSystem,
+    root,
+    mountPoint,
+    diskNumber,
+  });
+  MOUNTS.acquire().push(mount);
+
+  Ok(())
+}
+
+/*
+  Mounts a File System of the given type on the given directory (mount). The source is the disk
+  (like /dev/vdb) for a disk based File System, and is ignored otherwise. The options are passed
+  on to the File System.
+*/
+pub fn mount(
+  fileSystemTypeName: &[u8],
+  source: &[u8],
+  target: &[u8],
+  options: &[u8],
+  workingDirectory: Option<&ResolvedPath>,
+) -> Result<(), FsError> {
+  let _namespaceGuard = namespace::lockNamespace();
+
+  let fileSystemType = getFileSystemType(fileSystemTypeName)?;
+  let diskNumber = match fileSystemType.isDiskBased {
+    true => Some(parseDiskName(source)?),
+    false => None,
+  };
+
+  let mountPoint = lookup::resolve(target, workingDirectory, true)?;
+  if !mountPoint.getDentry().isDirectory() {
+    return Err(FsError::NotADirectory);
+  }
+
+  // A disk can't be mounted more than once.
+  if diskNumber.is_some()
+    && MOUNTS
+      .acquire()
+      .iter()
+      .any(|mount| mount.diskNumber == diskNumber)
+  {
+    return Err(FsError::Busy);
+  }
+
+  let fileSystem = (fileSystemType.mount)(diskNumber, options)?;
+  addMount(fileSystem, Some(mountPoint), diskNumber)
+}
+
+// Unmounts the File System mounted on the given directory (umount). Fails if it's in use.
+pub fn unmount(target: &[u8], workingDirectory: Option<&ResolvedPath>) -> Result<(), FsError> {
+  let _namespaceGuard = namespace::lockNamespace();
+
+  let path = lookup::resolve(target, workingDirectory, true)?;
+  if !path.isMountRoot() {
+    return Err(FsError::InvalidArgument);
+  }
+
+  let mount = path.getMount().clone();
+  drop(path);
+
+  // The root File System is always in use.
+  if mount.mountPoint.is_none() {
+    return Err(FsError::Busy);
+  }
+
+  // Nothing may be using the File System's cached Dentries anymore.
+  let fileSystemID = mount.root.getFileSystemID();
+  DENTRY_CACHE.evict(|dentry| dentry.getFileSystemID() == fileSystemID);
+
+  {
+    let mut mounts = MOUNTS.acquire();
+
+    // The mount table holds a reference, and so do we. Any other (like an opened file, a working
+    // directory or a File System mounted on top) means the File System is in use.
+    if Arc::strong_count(&mount) > 2 {
+      return Err(FsError::Busy);
+    }
+    mounts.retain(|otherMount| !Arc::ptr_eq(otherMount, &mount));
+  }
+
+  let fileSystem = mount.fileSystem.clone();
+  drop(mount);
+
+  fileSystem.unmount()
+}
+
+/*
+  Mounts the root File System, from the first disk containing a File System of a registered type.
+  If there's none, then an instance of the given (non disk based) File System type is mounted
+  instead.
+
+  NOTE : Should only be invoked while the kernel is initializing.
+*/
+pub fn mountRoot(fallbackType: &'static FileSystemType) {
+  let fileSystemTypes = FILE_SYSTEM_TYPES.acquire().clone();
+
+  for diskNumber in 0..disk::getAttachedDisksCount() {
+    for fileSystemType in fileSystemTypes.iter() {
+      if !fileSystemType.isDiskBased {
+        continue;
+      }
+
+      let Ok(fileSystem) = (fileSystemType.mount)(Some(diskNumber), b"")
+      else {
+        continue;
+      };
+
+      match addMount(fileSystem, None, Some(diskNumber)) {
+        Ok(()) => {
+          println!(
+            "INFO : Mounted the root File System ({}) from disk {}",
+            fileSystemType.name, diskNumber
+          );
+          return;
+        }
+
+        Err(error) => {
+          println!(
+            "WARN : Failed mounting the root File System ({}) from disk {} : {:?}",
+            fileSystemType.name, diskNumber, error
+          );
+        }
+      }
+    }
+  }
+
+  println!(
+    "WARN : No disk contains a File System, so using an empty {} as the root File System",
+    fallbackType.name
+  );
+
+  let fileSystem = (fallbackType.mount)(None, b"").expect("Failed mounting the root File System");
+  addMount(fileSystem, None, None).expect("Failed mounting the root File System");
+}
@@ -0,0 +1,326 @@
+use {
+  super::{
+    lookup::{self, ResolvedPath},
+    DeviceNumber, File, FileType, FsError, InodeFile, OpenFlags, Stat,
+  },
+  crate::{
+    fs::{device, pipe},
+    locks::sleeplock::{SleepLock, SleepLockGuard},
+  },
+  alloc::{sync::Arc, vec::Vec},
+};
+
+/*
+  Operations on the directory tree, which the system calls are built on. Paths are resolved
+  relative to the given working directory (or the root directory, if none is given).
+
+  All the operations modifying the directory tree (including mounting and unmounting) are
+  serialized using the namespace lock. This way, the checks they do (like whether a directory is
+  empty, or whether a directory is being moved into itself) can't be invalidated midway, and the
+  File Systems needn't lock multiple directories at once. Path lookups don't take the lock.
+*/
+
+static NAMESPACE_LOCK: SleepLock<()> = SleepLock::new(());
+
+#[track_caller]
+pub fn lockNamespace() -> SleepLockGuard<'static, ()> {
+  NAMESPACE_LOCK.acquire()
+}
+
+// Returns the given child of the given directory, which must belong to the same mounted File
+// System (so, no File System is mounted on it).
+fn getChildInSameMount(directory: &ResolvedPath, name: &[u8]) -> Result<ResolvedPath, FsError> {
+  let child = directory.getChild(name)?;
+
+  match Arc::ptr_eq(child.getMount(), directory.getMount()) {
+    true => Ok(child),
+    false => Err(FsError::Busy),
+  }
+}
+
+fn isDirectoryEmpty(directory: &ResolvedPath) -> Result<bool, FsError> {
+  let mut position = 0;
+
+  while let Some((directoryEntry, nextPosition)) = directory.getInode().readDirectory(position)? {
+    if !matches!(directoryEntry.name.as_slice(), b"." | b"..") {
+      return Ok(false);
+    }
+    position = nextPosition;
+  }
+
+  Ok(true)
+}
+
+// Creates an inode of the given type (other than a symbolic link) at the given path (mknod /
+// mkdir). Returns the created inode.
+pub fn create(
+  path: &[u8],
+  workingDirectory: Option<&ResolvedPath>,
+  fileType: FileType,
+  deviceNumber: DeviceNumber,
+) -> Result<ResolvedPath, FsError> {
+  if fileType == FileType::Symlink {
+    return Err(FsError::InvalidArgument);
+  }
+
+  let _namespaceGuard = lockNamespace();
+  createLocked(path, workingDirectory, fileType, deviceNumber)
+}
+
+fn createLocked(
+  path: &[u8],
+  workingDirectory: Option<&ResolvedPath>,
+  fileType: FileType,
+  deviceNumber: DeviceNumber,
+) -> Result<ResolvedPath, FsError> {
+  let (parent, name) = lookup::resolveParent(path, workingDirectory)?;
+
+  match parent.getChild(name) {
+    Ok(_) => return Err(FsError::AlreadyExists),
+    Err(FsError::NotFound) => {}
+    Err(error) => return Err(error),
+  }
+
+  let inode = parent.getInode().create(name, fileType, deviceNumber)?;
+  let dentry = parent.getDentry().addChild(name, inode)?;
+
+  Ok(ResolvedPath::new(parent.getMount().clone(), dentry))
+}
+
+// Creates a symbolic link at the given path, pointing to the given target (symlink).
+pub fn symlink(
+  target: &[u8],
+  path: &[u8],
+  workingDirectory: Option<&ResolvedPath>,
+) -> Result<(), FsError> {
+  let _namespaceGuard = lockNamespace();
+
+  let (parent, name) = lookup::resolveParent(path, workingDirectory)?;
+
+  match parent.getChild(name) {
+    Ok(_) => return Err(FsError::AlreadyExists),
+    Err(FsError::NotFound) => {}
+    Err(error) => return Err(error),
+  }
+
+  let inode = parent.getInode().symlink(name, target)?;
+  parent.getDentry().addChild(name, inode)?;
+
+  Ok(())
+}
+
+// Adds a hard link at the given new path, to the (non directory) inode at the given old path
+// (link).
+pub fn link(
+  oldPath: &[u8],
+  newPath: &[u8],
+  workingDirectory: Option<&ResolvedPath>,
+) -> Result<(), FsError> {
+  let _namespaceGuard = lockNamespace();
+
+  let old = lookup::resolve(oldPath, workingDirectory, false)?;
+  if old.getDentry().isDirectory() {
+    return Err(FsError::IsADirectory);
+  }
+
+  let (parent, name) = lookup::resolveParent(newPath, workingDirectory)?;
+  if !Arc::ptr_eq(parent.getMount(), old.getMount()) {
+    return Err(FsError::CrossDevice);
+  }
+
+  match parent.getChild(name) {
+    Ok(_) => return Err(FsError::AlreadyExists),
+    Err(FsError::NotFound) => {}
+    Err(error) => return Err(error),
+  }
+
+  parent.getInode().link(name, &**old.getInode())?;
+  parent.getDentry().addChild(name, old.getInode().clone())?;
+
+  Ok(())
+}
+
+// Removes the given (non directory) path (unlink).
+pub fn unlink(path: &[u8], workingDirectory: Option<&ResolvedPath>) -> Result<(), FsError> {
+  let _namespaceGuard = lockNamespace();
+
+  let (parent, name) = lookup::resolveParent(path, workingDirectory)?;
+  if matches!(name, b"" | b"." | b"..") {
+    return Err(FsError::IsADirectory);
+  }
+
+  let child = getChildInSameMount(&parent, name)?;
+  if child.getDentry().isDirectory() {
+    return Err(FsError::IsADirectory);
+  }
+
+  parent.getInode().unlink(name)?;
+  parent.getDentry().removeChild(name);
+
+  Ok(())
+}
+
+// Removes the given empty directory (rmdir).
+pub fn removeDirectory(
+  path: &[u8],
+  workingDirectory: Option<&ResolvedPath>,
+) -> Result<(), FsError> {
+  let _namespaceGuard = lockNamespace();
+
+  let (parent, name) = lookup::resolveParent(path, workingDirectory)?;
+  match name {
+    b"" => return Err(FsError::Busy),
+    b"." => return Err(FsError::InvalidArgument),
+    b".." => return Err(FsError::DirectoryNotEmpty),
+    _ => {}
+  }
+
+  let child = getChildInSameMount(&parent, name)?;
+  if !child.getDentry().isDirectory() {
+    return Err(FsError::NotADirectory);
+  }
+  if !isDirectoryEmpty(&child)? {
+    return Err(FsError::DirectoryNotEmpty);
+  }
+
+  parent.getInode().unlink(name)?;
+  parent.getDentry().removeChild(name);
+
+  Ok(())
+}
+
+// Moves the given old path to the given new path, replacing the new path if it exists (rename).
+pub fn rename(
+  oldPath: &[u8],
+  newPath: &[u8],
+  workingDirectory: Option<&ResolvedPath>,
+) -> Result<(), FsError> {
+  let _namespaceGuard = lockNamespace();
+
+  let (oldParent, oldName) = lookup::resolveParent(oldPath, workingDirectory)?;
+  let (newParent, newName) = lookup::resolveParent(newPath, workingDirectory)?;
+
+  if matches!(oldName, b"" | b"." | b"..") || matches!(newName, b"" | b"." | b"..") {
+    return Err(FsError::InvalidArgument);
+  }
+  if !Arc::ptr_eq(oldParent.getMount(), newParent.getMount()) {
+    return Err(FsError::CrossDevice);
+  }
+
+  let old = getChildInSameMount(&oldParent, oldName)?;
+
+  // A directory can't be moved into itself, or into one of its subdirectories.
+  if old.getDentry().isDirectory() {
+    let mut ancestor = Some(newParent.getDentry().clone());
+
+    while let Some(dentry) = ancestor {
+      if Arc::ptr_eq(&dentry, old.getDentry()) {
+        return Err(FsError::InvalidArgument);
+      }
+      ancestor = dentry.getParent();
+    }
+  }
+
+  match getChildInSameMount(&newParent, newName) {
+    Ok(new) => {
+      // Both refer to the same inode.
+      let (oldStat, newStat) = (old.getInode().getStat()?, new.getInode().getStat()?);
+      if oldStat.inodeNumber == newStat.inodeNumber {
+        return Ok(());
+      }
+
+      match (old.getDentry().isDirectory(), new.getDentry().isDirectory()) {
+        (true, false) => return Err(FsError::NotADirectory),
+        (false, true) => return Err(FsError::IsADirectory),
+        (true, true) if !isDirectoryEmpty(&new)? => return Err(FsError::DirectoryNotEmpty),
+        _ => {}
+      }
+    }
+
+    Err(FsError::NotFound) => {}
+    Err(error) => return Err(error),
+  }
+
+  oldParent
+    .getInode()
+    .rename(oldName, &**newParent.getInode(), newName)?;
+  oldParent
+    .getDentry()
+    .moveChild(oldName, newParent.getDentry(), newName);
+
+  Ok(())
+}
+
+// Returns the target of the given symbolic link (readlink).
+pub fn readLink(path: &[u8], workingDirectory: Option<&ResolvedPath>) -> Result<Vec<u8>, FsError> {
+  let path = lookup::resolve(path, workingDirectory, false)?;
+
+  match path.getFileType() {
+    FileType::Symlink => path.getInode().readLink(),
+    _ => Err(FsError::InvalidArgument),
+  }
+}
+
+// Returns the Stat of the given path (stat / lstat).
+pub fn stat(
+  path: &[u8],
+  workingDirectory: Option<&ResolvedPath>,
+  shouldFollowSymlink: bool,
+) -> Result<Stat, FsError> {
+  lookup::resolve(path, workingDirectory, shouldFollowSymlink)?
+    .getInode()
+    .getStat()
+}
+
+// Opens the given path (open).
+pub fn open(
+  path: &[u8],
+  workingDirectory: Option<&ResolvedPath>,
+  flags: OpenFlags,
+) -> Result<Arc<dyn File>, FsError> {
+  let shouldFollowSymlink = !flags.contains(OpenFlags::NO_FOLLOW);
+
+  let resolvedPath = match flags.contains(OpenFlags::CREATE) {
+    true => {
+      let _namespaceGuard = lockNamespace();
+
+      match lookup::resolve(path, workingDirectory, shouldFollowSymlink) {
+        Ok(_) if flags.contains(OpenFlags::EXCLUSIVE) => return Err(FsError::AlreadyExists),
+        Ok(resolvedPath) => resolvedPath,
+
+        Err(FsError::NotFound) => createLocked(
+          path,
+          workingDirectory,
+          FileType::Regular,
+          DeviceNumber::default(),
+        )?,
+        Err(error) => return Err(error),
+      }
+    }
+
+    false => lookup::resolve(path, workingDirectory, shouldFollowSymlink)?,
+  };
+
+  match resolvedPath.getFileType() {
+    FileType::Symlink => return Err(FsError::TooManySymlinks),
+
+    FileType::Directory if flags.contains(OpenFlags::WRITE) => return Err(FsError::IsADirectory),
+    FileType::Directory => {}
+    _ if flags.contains(OpenFlags::DIRECTORY) => return Err(FsError::NotADirectory),
+
+    FileType::Regular if flags.contains(OpenFlags::TRUNCATE | OpenFlags::WRITE) => {
+      resolvedPath.getInode().truncate(0)?
+    }
+
+    // Device files and FIFOs behave the same, irrespective of the File System they're on.
+    FileType::CharDevice | FileType::BlockDevice => return device::open(resolvedPath),
+    FileType::Fifo => return pipe::openFifo(resolvedPath, flags),
+
+    _ => {}
+  }
+
+  match resolvedPath.getInode().open(flags)? {
+    Some(file) => Ok(file),
+    None => Ok(Arc::new(InodeFile::new(resolvedPath))),
+  }
+}
@@ -0,0 +1,203 @@
+pub mod queue;
+
+use {
+  crate::{
+    arch::riscv::{
+      qemu::MAX_CORES,
+      registers::{sip::Sip, tp::Tp},
+    },
+    drivers::clint::CLINTDriver,
+    locks::spinlock::SpinLock,
+    process::core::Core,
+  },
+  array_macro::array,
+  core::{
+    hint::spin_loop,
+    sync::atomic::{AtomicUsize, Ordering},
+  },
+  queue::IPIMessageQueue,
+};
+
+/*
+  An Inter-Processor Interrupt (IPI) is an interrupt, which a hart raises on another hart.
+
+  We use IPIs to make another hart do something on our behalf, for example : flush stale entries
+  from its TLB, after we've modified a Page Table it may be using.
+
+  To send an IPI message to a hart, we :
+
+    (1) enqueue the message into that hart's IPIMessageQueue.
+
+    (2) raise a software interrupt on that hart, by writing to its msip register in the CLINT.
+
+  The receiving hart then drains its IPIMessageQueue, from the supervisor software interrupt
+  handler.
+*/
+static IPI_MESSAGE_QUEUES: [SpinLock<IPIMessageQueue>; MAX_CORES] =
+  array![_ => SpinLock::new(IPIMessageQueue::new()); MAX_CORES];
+
+// Bit i is set if hart i has booted, and can thus receive IPIs.
+static ONLINE_HARTS: AtomicUsize = AtomicUsize::new(0);
+
+// A set of harts. Bit i is set if hart i is a member of the set.
+#[derive(PartialEq, Copy, Clone)]
+pub struct HartMask(pub usize);
+
+impl HartMask {
+  pub const fn empty() -> Self {
+    Self(0)
+  }
+
+  pub const fn single(hartID: usize) -> Self {
+    Self(1 << hartID)
+  }
+
+  #[inline]
+  pub fn contains(&self, hartID: usize) -> bool {
+    (self.0 & (1 << hartID)) != 0
+  }
+
+  #[inline]
+  pub fn insert(&mut self, hartID: usize) {
+    self.0 |= 1 << hartID;
+  }
+
+  #[inline]
+  pub fn remove(&mut self, hartID: usize) {
+    self.0 &= !(1 << hartID);
+  }
+
+  #[inline]
+  pub fn intersection(&self, other: HartMask) -> Self {
+    Self(self.0 & other.0)
+  }
+
+  #[inline]
+  pub fn count(&self) -> usize {
+    self.0.count_ones() as usize
+  }
+
+  // Returns the IDs of the harts present in this set, in increasing order.
+  pub fn iter(&self) -> impl Iterator<Item = usize> {
+    let bits = self.0;
+    (0..MAX_CORES).filter(move |hartID| (bits & (1 << hartID)) != 0)
+  }
+}
+
+// Marks the given hart as online, so it can start receiving IPIs.
+// NOTE : Should be invoked once on every hart, while the Kernel is initializing.
+pub fn markHartOnline(hartID: usize) {
+  ONLINE_HARTS.fetch_or(1 << hartID, Ordering::Release);
+}
+
+// Returns the set of harts which have booted.
+pub fn getOnlineHarts() -> HartMask {
+  HartMask(ONLINE_HARTS.load(Ordering::Acquire))
+}
+
+// A request to execute a function on the receiving hart.
+pub struct IPIMessage {
+  function: fn(usize),
+  argument: usize,
+
+  // Number of harts which are yet to execute the function.
+  // NOTE : Lives on the stack of the sender hart, which waits till the counter drops to 0.
+  pendingHartsCount: *const AtomicUsize,
+}
+
+impl IPIMessage {
+  // Executes the requested function and lets the sender hart know about it.
+  fn deliver(self) {
+    (self.function)(self.argument);
+
+    // Release memory ordering : all the memory operations done by the function, become visible to
+    // the sender hart once it sees the decremented counter.
+    unsafe { (*self.pendingHartsCount).fetch_sub(1, Ordering::Release) };
+  }
+}
+
+/*
+  Executes the given function (with the given argument) on each of the given harts, which are
+  online. Returns only after all of them have finished executing it.
+
+  If the current hart is a member of the given set of harts, then the function is executed on the
+  current hart directly (with interrupts disabled).
+
+  NOTE : While waiting, we keep draining the IPIMessageQueue of the current hart. Otherwise, if 2
+  harts (having interrupts disabled) invoke this at the same time, targeting each other, they
+  would wait for each other forever.
+*/
+pub fn callOnHarts(harts: HartMask, function: fn(usize), argument: usize) {
+  Core::enterInterruptsDisabledSection();
+
+  let currentHartID = unsafe { Tp.read() };
+
+  let mut remoteHarts = harts.intersection(getOnlineHarts());
+  remoteHarts.remove(currentHartID);
+
+  let pendingHartsCount = AtomicUsize::new(remoteHarts.count());
+
+  for hartID in remoteHarts.iter() {
+    let mut message = IPIMessage {
+      function,
+      argument,
+      pendingHartsCount: &pendingHartsCount,
+    };
+
+    // If the IPIMessageQueue of the receiving hart is full, then we wait for it to drain.
+    // NOTE : The SpinLock guarding that IPIMessageQueue must not be held while we're waiting.
+    loop {
+      let pushResult = IPI_MESSAGE_QUEUES[hartID].acquire().push(message);
+      match pushResult {
+        Ok(()) => break,
+        Err(rejectedMessage) => message = rejectedMessage,
+      }
+
+      handlePendingMessages(currentHartID);
+      spin_loop();
+    }
+
+    unsafe { CLINTDriver.raiseSoftwareInterrupt(hartID) };
+  }
+
+  if harts.contains(currentHartID) {
+    function(argument);
+  }
+
+  // Acquire memory ordering, to establish the happens-before relationship with the Release
+  // decrements done by the receiving harts.
+  while pendingHartsCount.load(Ordering::Acquire) != 0 {
+    handlePendingMessages(currentHartID);
+    spin_loop();
+  }
+
+  Core::exitInterruptsDisabledSection();
+}
+
+// Executes the given function (with the given argument) on all the online harts (including the
+// current one).
+pub fn callOnAllHarts(function: fn(usize), argument: usize) {
+  callOnHarts(getOnlineHarts(), function, argument);
+}
+
+// Handles a supervisor software interrupt, which is raised by another hart sending us an IPI.
+// Invoked by the kernel trap handler.
+pub fn handleSoftwareInterrupt() {
+  // Acknowledge the software interrupt.
+  unsafe { Sip.clearSoftwareInterruptPending() };
+
+  handlePendingMessages(unsafe { Tp.read() });
+}
+
+// Drains the IPIMessageQueue of the given hart, delivering each message.
+fn handlePendingMessages(hartID: usize) {
+  loop {
+    // NOTE : The SpinLock guarding the IPIMessageQueue is released before the message gets
+    // delivered. So the executed function can itself send IPIs.
+    let message = IPI_MESSAGE_QUEUES[hartID].acquire().pop();
+    match message {
+      Some(message) => message.deliver(),
+      None => break,
+    }
+  }
+}
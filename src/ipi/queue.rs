@@ -0,0 +1,57 @@
+use {super::IPIMessage, array_macro::array};
+
+// Maximum number of messages which can be pending in an IPI message queue at any moment of time.
+pub const IPI_MESSAGE_QUEUE_CAPACITY: usize = 16;
+
+// Each hart owns an IPIMessageQueue, where other harts enqueue messages before interrupting it.
+// NOTE : Implemented using a Ring Buffer.
+pub struct IPIMessageQueue {
+  messages: [Option<IPIMessage>; IPI_MESSAGE_QUEUE_CAPACITY],
+
+  // Index of the oldest message in the messages array.
+  head: usize,
+  // Number of messages currently present in the queue.
+  length: usize,
+}
+
+impl IPIMessageQueue {
+  pub const fn new() -> Self {
+    Self {
+      messages: array![_ => None; IPI_MESSAGE_QUEUE_CAPACITY],
+
+      head: 0,
+      length: 0,
+    }
+  }
+
+  // Appends the given message to the tail of the queue.
+  // If the queue is full, then the message is handed back to the invoker.
+  pub fn push(&mut self, message: IPIMessage) -> Result<(), IPIMessage> {
+    if self.length == IPI_MESSAGE_QUEUE_CAPACITY {
+      return Err(message);
+    }
+
+    let tail = (self.head + self.length) % IPI_MESSAGE_QUEUE_CAPACITY;
+    self.messages[tail] = Some(message);
+    self.length += 1;
+
+    Ok(())
+  }
+
+  // Removes and returns the message present at the head of the queue.
+  pub fn pop(&mut self) -> Option<IPIMessage> {
+    if self.length == 0 {
+      return None;
+    }
+
+    let message = self.messages[self.head].take();
+    self.head = (self.head + 1) % IPI_MESSAGE_QUEUE_CAPACITY;
+    self.length -= 1;
+
+    message
+  }
+}
+
+// IPIMessage contains raw pointers (pointing to the stack of the sender hart). The sender hart
+// waits until the message is delivered, so it's safe to move the message across harts.
+unsafe impl Send for IPIMessageQueue {}
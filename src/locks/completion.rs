@@ -0,0 +1,81 @@
+use super::{spinlock::SpinLock, waitqueue::WaitQueue};
+
+/*
+  Lets processes wait for some one-off event (like an I/O request finishing), which gets signalled
+  by someone else (like an interrupt handler).
+
+  Each complete( ) lets one waiter through, while completeAll( ) lets all current and future waiters
+  through (till the Completion is reinitialized).
+*/
+pub struct Completion {
+  // Number of waiters, which can still be let through.
+  done: SpinLock<usize>,
+  waitQueue: WaitQueue,
+}
+
+// Value of the done counter, after completeAll( ) has been invoked.
+const COMPLETED_FOR_ALL: usize = usize::MAX;
+
+impl Completion {
+  #[track_caller]
+  pub const fn new() -> Self {
+    Self {
+      done: SpinLock::new(0),
+      waitQueue: WaitQueue::new(),
+    }
+  }
+
+  // Resets the Completion, so that it can be reused.
+  pub fn reinit(&self) {
+    *self.done.acquire() = 0;
+  }
+
+  /*
+    NOTE : Waiters are notified while holding the done SpinLock. Since a waiter has to re-acquire it
+    before returning, the Completion (which often lives in an object owned by the waiter) can't get
+    freed while we're still notifying.
+  */
+  pub fn complete(&self) {
+    let mut done = self.done.acquire();
+    if *done != COMPLETED_FOR_ALL {
+      *done += 1;
+    }
+
+    self.waitQueue.notifyOne();
+  }
+
+  pub fn completeAll(&self) {
+    let mut done = self.done.acquire();
+    *done = COMPLETED_FOR_ALL;
+
+    self.waitQueue.notifyAll();
+  }
+
+  // Returns whether a waiter would be let through, without blocking.
+  pub fn isDone(&self) -> bool {
+    *self.done.acquire() > 0
+  }
+
+  pub fn waitForCompletion(&self) {
+    let mut done = self
+      .waitQueue
+      .waitUntil(self.done.acquire(), |done| *done > 0);
+    if *done != COMPLETED_FOR_ALL {
+      *done -= 1;
+    }
+  }
+
+  // Same as waitForCompletion( ), but gives up after the given number of ticks. Returns whether
+  // the Completion was signalled.
+  pub fn waitForCompletionTimeout(&self, timeout: usize) -> bool {
+    let (mut done, isDone) =
+      self
+        .waitQueue
+        .waitUntilTimeout(self.done.acquire(), timeout, |done| *done > 0);
+
+    if isDone && *done != COMPLETED_FOR_ALL {
+      *done -= 1;
+    }
+    isDone
+  }
+}
@@ -0,0 +1,117 @@
+use super::{
+  sleeplock::SleepLockGuard,
+  spinlock::{SpinLock, SpinLockGuard},
+  waitqueue::WaitQueue,
+};
+
+/*
+  A condition variable, which lets a process atomically release a lock (SpinLock / SleepLock) and
+  start waiting, till it's notified.
+
+  Each notification bumps a sequence number, protected by an internal SpinLock. A waiter reads the
+  sequence number before releasing the lock which protects the condition, and then sleeps till the
+  sequence number changes. So, a notification sent in between releasing the lock and going to
+  sleep, doesn't get lost.
+  NOTE : Spurious wakeups are possible. So, the condition must always be re-checked in a loop.
+*/
+pub struct Condvar {
+  sequence: SpinLock<usize>,
+  waitQueue: WaitQueue,
+}
+
+impl Condvar {
+  #[track_caller]
+  pub const fn new() -> Self {
+    Self {
+      sequence: SpinLock::new(0),
+      waitQueue: WaitQueue::new(),
+    }
+  }
+
+  // Releases the given lock guard and sleeps till notified / the given number of ticks (if any)
+  // elapse. Returns whether the wait timed out.
+  fn block<G>(&self, lockGuard: G, timeout: Option<usize>) -> bool {
+    let sequence = self.sequence.acquire();
+    let observedSequence = *sequence;
+
+    drop(lockGuard);
+
+    let (_, isNotified) = match timeout {
+      None => (
+        self
+          .waitQueue
+          .waitUntil(sequence, |sequence| *sequence != observedSequence),
+        true,
+      ),
+
+      Some(timeout) => self
+        .waitQueue
+        .waitUntilTimeout(sequence, timeout, |sequence| *sequence != observedSequence),
+    };
+
+    !isNotified
+  }
+
+  // Releases the given SpinLock guard and sleeps till notified. The SpinLock is re-acquired before
+  // returning.
+  #[track_caller]
+  pub fn wait<'a, T>(&self, spinLockGuard: SpinLockGuard<'a, T>) -> SpinLockGuard<'a, T> {
+    let spinLock = spinLockGuard.getSpinLock();
+
+    self.block(spinLockGuard, None);
+    spinLock.acquire()
+  }
+
+  // Same as wait( ), but gives up after the given number of ticks. Along with the re-acquired
+  // SpinLock's guard, returns whether the wait timed out.
+  #[track_caller]
+  pub fn waitTimeout<'a, T>(
+    &self,
+    spinLockGuard: SpinLockGuard<'a, T>,
+    timeout: usize,
+  ) -> (SpinLockGuard<'a, T>, bool) {
+    let spinLock = spinLockGuard.getSpinLock();
+
+    let hasTimedOut = self.block(spinLockGuard, Some(timeout));
+    (spinLock.acquire(), hasTimedOut)
+  }
+
+  // Releases the given SleepLock guard and sleeps till notified. The SleepLock is re-acquired
+  // before returning.
+  #[track_caller]
+  pub fn waitSleepLock<'a, T>(
+    &self,
+    sleepLockGuard: SleepLockGuard<'a, T>,
+  ) -> SleepLockGuard<'a, T> {
+    let sleepLock = sleepLockGuard.getSleepLock();
+
+    self.block(sleepLockGuard, None);
+    sleepLock.acquire()
+  }
+
+  // Same as waitSleepLock( ), but gives up after the given number of ticks. Along with the
+  // re-acquired SleepLock's guard, returns whether the wait timed out.
+  #[track_caller]
+  pub fn waitSleepLockTimeout<'a, T>(
+    &self,
+    sleepLockGuard: SleepLockGuard<'a, T>,
+    timeout: usize,
+  ) -> (SleepLockGuard<'a, T>, bool) {
+    let sleepLock = sleepLockGuard.getSleepLock();
+
+    let hasTimedOut = self.block(sleepLockGuard, Some(timeout));
+    (sleepLock.acquire(), hasTimedOut)
+  }
+
+  // Wakes up one of the waiting processes (if any).
+  pub fn notifyOne(&self) {
+    *self.sequence.acquire() += 1;
+    self.waitQueue.notifyOne();
+  }
+
+  // Wakes up all the waiting processes.
+  pub fn notifyAll(&self) {
+    *self.sequence.acquire() += 1;
+    self.waitQueue.notifyAll();
+  }
+}
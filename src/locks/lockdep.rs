@@ -0,0 +1,654 @@
+use {
+  crate::{
+    arch::riscv::{qemu::MAX_CORES, registers::tp::Tp},
+    process::{core::Core, process::getCurrentProcess},
+  },
+  core::{
+    hint::spin_loop,
+    panic::Location,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+  },
+};
+
+/*
+  Lockdep is a runtime lock dependency validator (modelled after the one in Linux). It's enabled
+  using the lockdep cargo feature.
+
+  Instead of tracking individual locks, it tracks lock classes. All the locks constructed at the
+  same place in the source code (say, the metadata SpinLocks of all the processes) belong to the
+  same lock class.
+
+  Sometimes locks of the same class do need to be nested (say, the SleepLocks of 2 directory inodes,
+  while renaming), with the nesting order guaranteed by some other means. The invoker then
+  annotates the inner acquisitions with a subclass, which lockdep treats as a separate lock class.
+
+  Each time a lock of class B is acquired, while holding a lock of class A, the dependency A -> B
+  is recorded. If we ever find that B -> ... -> A has already been recorded, then there's a
+  possible deadlock : one CPU core can hold A and wait for B, while another one holds B and waits
+  for A. We report it, even though the deadlock hasn't actually happened (yet).
+
+  Apart from lock-order inversions, it reports :
+
+    (1) sleeping (or acquiring a SleepLock) while holding a SpinLock.
+
+    (2) acquiring a lock with interrupts enabled, while handling an interrupt.
+
+  Only the first problem gets reported. After that, lockdep turns itself off, since the lock state
+  can't be trusted anymore.
+
+  REFER : https://docs.kernel.org/locking/lockdep-design.html.
+*/
+
+const MAX_LOCK_CLASSES: usize = 256;
+const MAX_LOCK_DEPENDENCIES: usize = 1024;
+
+// Maximum number of locks a CPU core / process can hold at any moment of time.
+pub const MAX_HELD_LOCKS: usize = 32;
+
+// Maximum number of subclasses a lock class can be split into, when nesting locks of that class.
+pub const MAX_LOCK_SUBCLASSES: usize = 8;
+
+const UNREGISTERED_LOCK_CLASS: usize = usize::MAX;
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum LockKind {
+  SpinLock,
+  TicketLock,
+  McsLock,
+  RwSpinLock,
+
+  SleepLock,
+}
+
+impl LockKind {
+  // Returns the name of the lock type.
+  fn getName(&self) -> &'static str {
+    match self {
+      LockKind::SpinLock => "SpinLock",
+      LockKind::TicketLock => "TicketLock",
+      LockKind::McsLock => "McsLock",
+      LockKind::RwSpinLock => "RwSpinLock",
+
+      LockKind::SleepLock => "SleepLock",
+    }
+  }
+}
+
+// Identifies a lock class.
+#[derive(Copy, Clone)]
+pub struct LockClassKey {
+  // Type of the data protected by the lock.
+  pub name: &'static str,
+
+  // Where in the source code the lock was constructed.
+  pub site: &'static Location<'static>,
+}
+
+impl LockClassKey {
+  #[inline]
+  fn matches(&self, other: &LockClassKey) -> bool {
+    core::ptr::eq(self.site, other.site) && core::ptr::eq(self.name, other.name)
+  }
+}
+
+// Embedded in each lock. Caches the index of the lock class (subclass 0), once it gets registered.
+pub struct LockdepMap {
+  pub key: LockClassKey,
+  pub kind: LockKind,
+
+  lockClassIndex: AtomicUsize,
+}
+
+impl LockdepMap {
+  // NOTE : The lock class is determined by the location of the invoker.
+  #[track_caller]
+  pub const fn new(name: &'static str, kind: LockKind) -> Self {
+    Self {
+      key: LockClassKey {
+        name,
+        site: Location::caller(),
+      },
+      kind,
+
+      lockClassIndex: AtomicUsize::new(UNREGISTERED_LOCK_CLASS),
+    }
+  }
+}
+
+#[derive(Copy, Clone)]
+struct HeldLock {
+  lockAddress: usize,
+  lockClassIndex: usize,
+
+  // Where the lock was acquired.
+  site: &'static Location<'static>,
+}
+
+// Stack of locks currently held by a CPU core (SpinLocks) / process (SleepLocks).
+pub struct HeldLocks {
+  locks: [Option<HeldLock>; MAX_HELD_LOCKS],
+  count: usize,
+}
+
+impl HeldLocks {
+  pub const fn new() -> Self {
+    Self {
+      locks: [None; MAX_HELD_LOCKS],
+      count: 0,
+    }
+  }
+
+  fn push(&mut self, heldLock: HeldLock) {
+    assert!(
+      self.count < MAX_HELD_LOCKS,
+      "LOCKDEP : Too many locks being held"
+    );
+
+    self.locks[self.count] = Some(heldLock);
+    self.count += 1;
+  }
+
+  // Locks needn't be released in the reverse order of acquisition. So, we look for the lock
+  // throughout the stack.
+  fn remove(&mut self, lockAddress: usize) -> bool {
+    let Some(index) = self
+      .iter()
+      .position(|heldLock| heldLock.lockAddress == lockAddress)
+    else {
+      return false;
+    };
+
+    self.locks.copy_within(index + 1..self.count, index);
+    self.count -= 1;
+    self.locks[self.count] = None;
+
+    true
+  }
+
+  fn iter(&self) -> impl Iterator<Item = &HeldLock> {
+    self.locks[..self.count].iter().flatten()
+  }
+}
+
+struct LockClass {
+  key: LockClassKey,
+  subclass: usize,
+  kind: LockKind,
+}
+
+// Records the first occurrence of a dependency between 2 lock classes.
+struct LockDependency {
+  from: usize,
+  to: usize,
+
+  // Where the lock of the from class was acquired.
+  fromSite: &'static Location<'static>,
+  // Where the lock of the to class was acquired, while holding the from class lock.
+  toSite: &'static Location<'static>,
+}
+
+struct LockDependencyGraph {
+  classes: [Option<LockClass>; MAX_LOCK_CLASSES],
+  classCount: usize,
+
+  // Bit j of edges[i] is set, if the dependency i -> j has been recorded.
+  edges: [[u64; MAX_LOCK_CLASSES / 64]; MAX_LOCK_CLASSES],
+
+  dependencies: [Option<LockDependency>; MAX_LOCK_DEPENDENCIES],
+  dependencyCount: usize,
+
+  // Spinning locks (SpinLocks, TicketLocks, McsLocks and RwSpinLocks) held by each CPU core.
+  heldSpinLocks: [HeldLocks; MAX_CORES],
+  // SleepLocks held by each CPU core, while it isn't running any process (during boot).
+  heldSleepLocksWithoutProcess: [HeldLocks; MAX_CORES],
+
+  // Number of nested interrupts each CPU core is currently handling.
+  interruptDepths: [usize; MAX_CORES],
+}
+
+// NOTE : Lockdep can't use a SpinLock to guard its own state (that would make it recurse). So
+// we use a bare test-and-set lock, with interrupts disabled.
+static LOCKDEP_LOCK: AtomicBool = AtomicBool::new(false);
+static mut LOCK_DEPENDENCY_GRAPH: LockDependencyGraph = LockDependencyGraph::new();
+
+static IS_LOCKDEP_ENABLED: AtomicBool = AtomicBool::new(true);
+
+impl LockDependencyGraph {
+  const fn new() -> Self {
+    Self {
+      classes: [const { None }; MAX_LOCK_CLASSES],
+      classCount: 0,
+
+      edges: [[0; MAX_LOCK_CLASSES / 64]; MAX_LOCK_CLASSES],
+
+      dependencies: [const { None }; MAX_LOCK_DEPENDENCIES],
+      dependencyCount: 0,
+
+      heldSpinLocks: [const { HeldLocks::new() }; MAX_CORES],
+      heldSleepLocksWithoutProcess: [const { HeldLocks::new() }; MAX_CORES],
+
+      interruptDepths: [0; MAX_CORES],
+    }
+  }
+
+  // Returns the index of the lock class (with the given subclass) of the given lock, registering
+  // the lock class if required.
+  fn getLockClassIndex(&mut self, lockdepMap: &LockdepMap, subclass: usize) -> usize {
+    assert!(
+      subclass < MAX_LOCK_SUBCLASSES,
+      "LOCKDEP : Lock subclass out of range"
+    );
+
+    let cachedLockClassIndex = lockdepMap.lockClassIndex.load(Ordering::Relaxed);
+    if subclass == 0 && cachedLockClassIndex != UNREGISTERED_LOCK_CLASS {
+      return cachedLockClassIndex;
+    }
+
+    let lockClassIndex = match self.classes[..self.classCount]
+      .iter()
+      .flatten()
+      .position(|lockClass| {
+        lockClass.key.matches(&lockdepMap.key) && lockClass.subclass == subclass
+      }) {
+      Some(lockClassIndex) => lockClassIndex,
+
+      None => {
+        assert!(
+          self.classCount < MAX_LOCK_CLASSES,
+          "LOCKDEP : Too many lock classes"
+        );
+
+        self.classes[self.classCount] = Some(LockClass {
+          key: lockdepMap.key,
+          subclass,
+          kind: lockdepMap.kind,
+        });
+        self.classCount += 1;
+
+        self.classCount - 1
+      }
+    };
+
+    // Only the index of subclass 0 is cached, since that's what most acquisitions use.
+    if subclass == 0 {
+      lockdepMap
+        .lockClassIndex
+        .store(lockClassIndex, Ordering::Relaxed);
+    }
+    lockClassIndex
+  }
+
+  fn getLockClass(&self, lockClassIndex: usize) -> &LockClass {
+    self.classes[lockClassIndex].as_ref().unwrap()
+  }
+
+  #[inline]
+  fn hasEdge(&self, from: usize, to: usize) -> bool {
+    (self.edges[from][to / 64] & (1 << (to % 64))) != 0
+  }
+
+  // Returns whether the to lock class is reachable from the from lock class, by following the
+  // recorded dependencies.
+  fn isReachable(&self, from: usize, to: usize) -> bool {
+    let mut visited = [0u64; MAX_LOCK_CLASSES / 64];
+    let mut stack = [0usize; MAX_LOCK_CLASSES];
+    let mut stackSize = 0;
+
+    stack[0] = from;
+    stackSize += 1;
+    visited[from / 64] |= 1 << (from % 64);
+
+    while stackSize > 0 {
+      stackSize -= 1;
+      let current = stack[stackSize];
+
+      if current == to {
+        return true;
+      }
+
+      for next in 0..self.classCount {
+        if self.hasEdge(current, next) && (visited[next / 64] & (1 << (next % 64))) == 0 {
+          visited[next / 64] |= 1 << (next % 64);
+
+          stack[stackSize] = next;
+          stackSize += 1;
+        }
+      }
+    }
+
+    false
+  }
+
+  fn addDependency(&mut self, from: &HeldLock, to: usize, toSite: &'static Location<'static>) {
+    if self.hasEdge(from.lockClassIndex, to) {
+      return;
+    }
+    self.edges[from.lockClassIndex][to / 64] |= 1 << (to % 64);
+
+    // Remember where the dependency was first seen, so we can point at it while reporting.
+    if self.dependencyCount < MAX_LOCK_DEPENDENCIES {
+      self.dependencies[self.dependencyCount] = Some(LockDependency {
+        from: from.lockClassIndex,
+        to,
+
+        fromSite: from.site,
+        toSite,
+      });
+      self.dependencyCount += 1;
+    }
+  }
+
+  fn findDependency(&self, from: usize, to: usize) -> Option<&LockDependency> {
+    self.dependencies[..self.dependencyCount]
+      .iter()
+      .flatten()
+      .find(|dependency| dependency.from == from && dependency.to == to)
+  }
+
+  // Returns the SleepLocks held by the current process (or by the current CPU core, if it isn't
+  // running any process).
+  fn getHeldSleepLocks(&mut self, hartID: usize) -> &mut HeldLocks {
+    match getCurrentProcess() {
+      Some(process) => unsafe { &mut (*process.data.get()).heldSleepLocks },
+      None => &mut self.heldSleepLocksWithoutProcess[hartID],
+    }
+  }
+
+  fn printLockClass(&self, lockClassIndex: usize) {
+    let lockClass = self.getLockClass(lockClassIndex);
+    print!(
+      "{} {} (created at {})",
+      lockClass.kind.getName(),
+      lockClass.key.name,
+      lockClass.key.site
+    );
+    if lockClass.subclass > 0 {
+      print!(" subclass {}", lockClass.subclass);
+    }
+  }
+
+  fn printHeldLocks(&mut self, hartID: usize) {
+    println!("LOCKDEP : Locks held by CPU core {} :", hartID);
+
+    let heldSpinLocks = &self.heldSpinLocks[hartID] as *const HeldLocks;
+    let heldSleepLocks = self.getHeldSleepLocks(hartID) as *const HeldLocks;
+
+    for heldLocks in [heldSleepLocks, heldSpinLocks] {
+      for heldLock in unsafe { (*heldLocks).iter() } {
+        print!("  ");
+        self.printLockClass(heldLock.lockClassIndex);
+        println!(", acquired at {}", heldLock.site);
+      }
+    }
+  }
+}
+
+// Acquires the lockdep lock and returns the lock dependency graph, if lockdep is still enabled.
+// NOTE : Interrupts stay disabled until unlockLockdep( ) is invoked.
+fn lockLockdep() -> Option<&'static mut LockDependencyGraph> {
+  if !IS_LOCKDEP_ENABLED.load(Ordering::Relaxed) {
+    return None;
+  }
+
+  Core::enterInterruptsDisabledSection();
+
+  while LOCKDEP_LOCK
+    .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+    .is_err()
+  {
+    while LOCKDEP_LOCK.load(Ordering::Relaxed) {
+      spin_loop();
+    }
+  }
+
+  Some(unsafe { &mut *core::ptr::addr_of_mut!(LOCK_DEPENDENCY_GRAPH) })
+}
+
+fn unlockLockdep() {
+  LOCKDEP_LOCK.store(false, Ordering::Release);
+  Core::exitInterruptsDisabledSection();
+}
+
+// Reports the problem found, along with the locks held by the current CPU core. Then turns off
+// lockdep.
+fn report(
+  graph: &mut LockDependencyGraph,
+  hartID: usize,
+  describeProblem: impl FnOnce(&LockDependencyGraph),
+) {
+  IS_LOCKDEP_ENABLED.store(false, Ordering::Relaxed);
+
+  println!("LOCKDEP : ============================================");
+  describeProblem(graph);
+  graph.printHeldLocks(hartID);
+  println!("LOCKDEP : Turning off lockdep");
+  println!("LOCKDEP : ============================================");
+}
+
+/*
+  Invoked before trying to acquire a lock. Validates the acquisition against the locks which are
+  currently held, and records the new dependencies.
+
+  NOTE : Must be invoked before spinning / sleeping for the lock. Otherwise, if there actually is a
+  deadlock, we'd never get to report it.
+*/
+pub fn beforeAcquire(
+  lockdepMap: &LockdepMap,
+  wereInterruptsEnabled: bool,
+  site: &'static Location<'static>,
+) {
+  beforeAcquireNested(lockdepMap, 0, wereInterruptsEnabled, site)
+}
+
+// Same as beforeAcquire( ), but the lock is treated as belonging to the given subclass of its lock
+// class.
+pub fn beforeAcquireNested(
+  lockdepMap: &LockdepMap,
+  subclass: usize,
+  wereInterruptsEnabled: bool,
+  site: &'static Location<'static>,
+) {
+  let Some(graph) = lockLockdep()
+  else {
+    return;
+  };
+
+  let hartID = unsafe { Tp.read() };
+  let lockClassIndex = graph.getLockClassIndex(lockdepMap, subclass);
+  let isInInterruptContext = graph.interruptDepths[hartID] > 0;
+
+  // Acquiring a lock with interrupts enabled, while handling an interrupt. Another interrupt can
+  // arrive and try to acquire the same lock.
+  if isInInterruptContext && wereInterruptsEnabled {
+    report(graph, hartID, |graph| {
+      println!("LOCKDEP : Lock acquired with interrupts enabled, in interrupt context");
+      print!("LOCKDEP : Acquiring ");
+      graph.printLockClass(lockClassIndex);
+      println!(" at {}", site);
+    });
+    return unlockLockdep();
+  }
+
+  if lockdepMap.kind == LockKind::SleepLock {
+    // Acquiring a SleepLock may put the invoker to sleep.
+    if isInInterruptContext || graph.heldSpinLocks[hartID].count > 0 {
+      report(graph, hartID, |graph| {
+        println!(
+          "LOCKDEP : SleepLock acquired while {}",
+          match isInInterruptContext {
+            true => "handling an interrupt",
+            false => "holding a SpinLock",
+          }
+        );
+        print!("LOCKDEP : Acquiring ");
+        graph.printLockClass(lockClassIndex);
+        println!(" at {}", site);
+      });
+      return unlockLockdep();
+    }
+  }
+
+  // Validate and record the dependency between each held lock's class and the new lock's class.
+  let heldSpinLocks = &graph.heldSpinLocks[hartID] as *const HeldLocks;
+  let heldSleepLocks = graph.getHeldSleepLocks(hartID) as *const HeldLocks;
+
+  for heldLocks in [heldSleepLocks, heldSpinLocks] {
+    for heldLock in unsafe { (*heldLocks).iter() } {
+      // Locks of the same class (and subclass) are being nested. If another CPU core nests them in
+      // the opposite order, we get a deadlock.
+      if heldLock.lockClassIndex == lockClassIndex {
+        report(graph, hartID, |graph| {
+          println!("LOCKDEP : Possible recursive locking detected");
+          print!("LOCKDEP : Acquiring ");
+          graph.printLockClass(lockClassIndex);
+          println!(" at {}", site);
+          println!(
+            "LOCKDEP : while already holding a lock of the same class, acquired at {}",
+            heldLock.site
+          );
+        });
+        return unlockLockdep();
+      }
+
+      // Lock-order inversion.
+      if graph.isReachable(lockClassIndex, heldLock.lockClassIndex) {
+        report(graph, hartID, |graph| {
+          println!("LOCKDEP : Possible lock-order inversion (deadlock) detected");
+          print!("LOCKDEP : Acquiring ");
+          graph.printLockClass(lockClassIndex);
+          println!(" at {}", site);
+          print!("LOCKDEP : while holding ");
+          graph.printLockClass(heldLock.lockClassIndex);
+          println!(", acquired at {}", heldLock.site);
+
+          print!("LOCKDEP : but the reverse dependency has already been recorded");
+          match graph.findDependency(lockClassIndex, heldLock.lockClassIndex) {
+            Some(dependency) => {
+              println!(
+                " : first lock acquired at {}, second lock acquired at {}",
+                dependency.fromSite, dependency.toSite
+              );
+            }
+            None => {
+              println!(" (through other lock classes)");
+            }
+          }
+        });
+        return unlockLockdep();
+      }
+
+      graph.addDependency(heldLock, lockClassIndex, site);
+    }
+  }
+
+  unlockLockdep();
+}
+
+// Invoked after a lock has been acquired. Pushes it into the stack of held locks.
+pub fn afterAcquire(lockdepMap: &LockdepMap, lockAddress: usize, site: &'static Location<'static>) {
+  afterAcquireNested(lockdepMap, 0, lockAddress, site)
+}
+
+// Same as afterAcquire( ), for a lock acquired using beforeAcquireNested( ).
+pub fn afterAcquireNested(
+  lockdepMap: &LockdepMap,
+  subclass: usize,
+  lockAddress: usize,
+  site: &'static Location<'static>,
+) {
+  let Some(graph) = lockLockdep()
+  else {
+    return;
+  };
+
+  let hartID = unsafe { Tp.read() };
+  let heldLock = HeldLock {
+    lockAddress,
+    lockClassIndex: graph.getLockClassIndex(lockdepMap, subclass),
+    site,
+  };
+
+  match lockdepMap.kind {
+    LockKind::SleepLock => graph.getHeldSleepLocks(hartID).push(heldLock),
+    _ => graph.heldSpinLocks[hartID].push(heldLock),
+  }
+
+  unlockLockdep();
+}
+
+// Invoked when a lock is released. Removes it from the stack of held locks.
+pub fn afterRelease(lockdepMap: &LockdepMap, lockAddress: usize) {
+  let Some(graph) = lockLockdep()
+  else {
+    return;
+  };
+
+  let hartID = unsafe { Tp.read() };
+  let wasHeld = match lockdepMap.kind {
+    LockKind::SleepLock => graph.getHeldSleepLocks(hartID).remove(lockAddress),
+    _ => graph.heldSpinLocks[hartID].remove(lockAddress),
+  };
+
+  if !wasHeld {
+    let lockClassIndex = graph.getLockClassIndex(lockdepMap, 0);
+    report(graph, hartID, |graph| {
+      print!("LOCKDEP : Releasing ");
+      graph.printLockClass(lockClassIndex);
+      println!(", which isn't held");
+    });
+  }
+
+  unlockLockdep();
+}
+
+/*
+  Invoked before the current process goes to sleep. The process must not be holding any SpinLock,
+  except the one (if any) whose address is given, which gets released while going to sleep.
+*/
+#[track_caller]
+pub fn checkMightSleep(releasedSpinLockAddress: Option<usize>) {
+  let site = Location::caller();
+
+  let Some(graph) = lockLockdep()
+  else {
+    return;
+  };
+
+  let hartID = unsafe { Tp.read() };
+
+  let isInInterruptContext = graph.interruptDepths[hartID] > 0;
+  let isHoldingSpinLocks = graph.heldSpinLocks[hartID]
+    .iter()
+    .any(|heldLock| Some(heldLock.lockAddress) != releasedSpinLockAddress);
+
+  if isInInterruptContext || isHoldingSpinLocks {
+    report(graph, hartID, |_| {
+      println!(
+        "LOCKDEP : Sleeping while {}, at {}",
+        match isInInterruptContext {
+          true => "handling an interrupt",
+          false => "holding a SpinLock",
+        },
+        site
+      );
+    });
+  }
+
+  unlockLockdep();
+}
+
+// Invoked by the trap handler, when the current CPU core starts handling an interrupt.
+pub fn enterInterruptContext() {
+  if let Some(graph) = lockLockdep() {
+    graph.interruptDepths[unsafe { Tp.read() }] += 1;
+    unlockLockdep();
+  }
+}
+
+// Invoked by the trap handler, when the current CPU core is done handling an interrupt.
+pub fn exitInterruptContext() {
+  if let Some(graph) = lockLockdep() {
+    graph.interruptDepths[unsafe { Tp.read() }] -= 1;
+    unlockLockdep();
+  }
+}
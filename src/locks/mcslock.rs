@@ -0,0 +1,257 @@
+#[cfg(feature = "lockdep")]
+use {
+  super::lockdep::{self, LockKind, LockdepMap},
+  crate::arch::riscv::registers::sstatus::Sstatus,
+  core::{any::type_name, panic::Location},
+};
+use {
+  super::statistics::LockStatistics,
+  crate::{
+    arch::riscv::{qemu::MAX_CORES, registers::tp::Tp},
+    process::core::Core,
+  },
+  core::{
+    cell::{Cell, UnsafeCell},
+    hint::spin_loop,
+    ops::{Deref, DerefMut},
+    ptr,
+    sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering},
+  },
+};
+
+/*
+  An McsLock (named after its inventors, Mellor-Crummey and Scott) is a queue lock. The CPU cores
+  waiting to acquire it, form a linked list of queue nodes (in FIFO order), and the lock itself only
+  points to the tail of the list.
+
+  Each waiting CPU core spins on a flag in its own queue node. The releasing CPU core hands the lock
+  over, by clearing the flag in the next queue node. So, unlike the TicketLock, a release only
+  touches the cache line of the next waiter.
+
+  Each CPU core has a small stack of preallocated queue nodes (like in Linux's qspinlock), one for
+  each level of McsLock nesting.
+
+  Just like with SpinLocks, interrupts remain disabled while an McsLock is held.
+
+  REFER : https://lwn.net/Articles/590243/.
+*/
+
+// Maximum number of McsLocks a CPU core can hold at the same time.
+const MAX_NESTED_MCS_LOCKS: usize = 4;
+
+pub struct McsNode {
+  next: AtomicPtr<McsNode>,
+
+  // Whether the owner of the queue node needs to keep waiting.
+  isWaiting: AtomicBool,
+}
+
+impl McsNode {
+  const fn new() -> Self {
+    Self {
+      next: AtomicPtr::new(ptr::null_mut()),
+      isWaiting: AtomicBool::new(false),
+    }
+  }
+}
+
+static MCS_NODES: [[McsNode; MAX_NESTED_MCS_LOCKS]; MAX_CORES] =
+  [const { [const { McsNode::new() }; MAX_NESTED_MCS_LOCKS] }; MAX_CORES];
+
+// Number of McsLocks each CPU core is currently holding (or waiting on). Indexes into MCS_NODES.
+// NOTE : Only accessed by the owning CPU core, with interrupts disabled.
+static MCS_NODE_DEPTHS: [AtomicUsize; MAX_CORES] = [const { AtomicUsize::new(0) }; MAX_CORES];
+
+pub struct McsLock<T> {
+  data: UnsafeCell<T>,
+
+  // The last queue node in the queue. Null if the McsLock isn't held.
+  tail: AtomicPtr<McsNode>,
+
+  ownerCPUCoreID: Cell<isize>, // ID of the CPU core which has acquired the McsLock.
+                               // NOTE : -1 means the McsLock is currently acquired by none.
+  acquiredAt: Cell<usize>,
+  statistics: LockStatistics,
+
+  #[cfg(feature = "lockdep")]
+  lockdepMap: LockdepMap,
+}
+
+impl<T> McsLock<T> {
+  #[track_caller]
+  pub const fn new(data: T) -> Self {
+    Self {
+      data: UnsafeCell::new(data),
+
+      tail: AtomicPtr::new(ptr::null_mut()),
+
+      ownerCPUCoreID: Cell::new(-1),
+
+      acquiredAt: Cell::new(0),
+      statistics: LockStatistics::new(),
+
+      #[cfg(feature = "lockdep")]
+      lockdepMap: LockdepMap::new(type_name::<T>(), LockKind::McsLock),
+    }
+  }
+
+  #[track_caller]
+  pub fn acquire(&self) -> McsLockGuard<'_, T> {
+    #[cfg(feature = "lockdep")]
+    let wereInterruptsEnabled = unsafe { Sstatus.areInterruptsEnabled() };
+
+    Core::enterInterruptsDisabledSection();
+
+    assert!(
+      !self.isCurrentCPUCoreHolding(),
+      "Current CPU core is already holding the McsLock"
+    );
+
+    #[cfg(feature = "lockdep")]
+    lockdep::beforeAcquire(&self.lockdepMap, wereInterruptsEnabled, Location::caller());
+
+    // Take the next free queue node of the current CPU core.
+    let hartID = unsafe { Tp.read() };
+    let depth = MCS_NODE_DEPTHS[hartID].fetch_add(1, Ordering::Relaxed);
+    assert!(depth < MAX_NESTED_MCS_LOCKS, "Too many nested McsLocks");
+
+    let node = &MCS_NODES[hartID][depth];
+    node.next.store(ptr::null_mut(), Ordering::Relaxed);
+    node.isWaiting.store(true, Ordering::Relaxed);
+
+    let nodePointer = node as *const McsNode as *mut McsNode;
+
+    // Append our queue node to the queue. AcqRel memory ordering : Release so that the previous
+    // tail sees our initialized queue node, Acquire so that we see the previous tail's.
+    let previousTail = self.tail.swap(nodePointer, Ordering::AcqRel);
+
+    let mut spins = 0;
+    if !previousTail.is_null() {
+      // Somebody holds (or is waiting for) the McsLock. Link ourselves behind them, and wait till
+      // they hand the McsLock over to us.
+      unsafe { (*previousTail).next.store(nodePointer, Ordering::Release) };
+
+      while node.isWaiting.load(Ordering::Acquire) {
+        spin_loop();
+        spins += 1;
+      }
+    }
+
+    self.ownerCPUCoreID.set(hartID as isize);
+    self
+      .acquiredAt
+      .set(self.statistics.recordAcquisition(spins));
+
+    #[cfg(feature = "lockdep")]
+    lockdep::afterAcquire(&self.lockdepMap, self.getAddress(), Location::caller());
+
+    McsLockGuard { lock: self, node }
+  }
+
+  fn release(&self, node: &McsNode) {
+    assert!(
+      self.isCurrentCPUCoreHolding(),
+      "Current CPU core isn't already holding this McsLock"
+    );
+
+    self.statistics.recordRelease(self.acquiredAt.get());
+    self.ownerCPUCoreID.set(-1);
+
+    let nodePointer = node as *const McsNode as *mut McsNode;
+
+    let mut next = node.next.load(Ordering::Acquire);
+    if next.is_null() {
+      // If we're the tail, then nobody is waiting. Mark the McsLock as free.
+      if self
+        .tail
+        .compare_exchange(
+          nodePointer,
+          ptr::null_mut(),
+          Ordering::Release,
+          Ordering::Relaxed,
+        )
+        .is_ok()
+      {
+        return self.finishRelease(node);
+      }
+
+      // Someone has swapped themselves in as the tail, but hasn't linked their queue node behind
+      // ours yet.
+      loop {
+        next = node.next.load(Ordering::Acquire);
+        if !next.is_null() {
+          break;
+        }
+        spin_loop();
+      }
+    }
+
+    // Hand the McsLock over to the next waiter.
+    unsafe { (*next).isWaiting.store(false, Ordering::Release) };
+
+    self.finishRelease(node);
+  }
+
+  // Gives the queue node back to the current CPU core and leaves the interrupts-disabled section.
+  fn finishRelease(&self, node: &McsNode) {
+    let hartID = unsafe { Tp.read() };
+    let depth = MCS_NODE_DEPTHS[hartID].fetch_sub(1, Ordering::Relaxed) - 1;
+
+    // NOTE : The queue nodes are used as a stack. So, nested McsLocks must be released in the
+    // reverse order of acquisition.
+    assert!(
+      ptr::eq(node, &MCS_NODES[hartID][depth]),
+      "McsLocks must be released in the reverse order of acquisition"
+    );
+
+    #[cfg(feature = "lockdep")]
+    lockdep::afterRelease(&self.lockdepMap, self.getAddress());
+
+    Core::exitInterruptsDisabledSection();
+  }
+
+  #[inline]
+  pub fn getAddress(&self) -> usize {
+    self as *const Self as usize
+  }
+
+  // Returns whether the CPU core of the invoker is already holding the McsLock or not.
+  pub fn isCurrentCPUCoreHolding(&self) -> bool {
+    self.ownerCPUCoreID.get() == unsafe { Tp.read() as isize }
+  }
+
+  pub fn dumpStatistics(&self, lockName: &str) {
+    self.statistics.dump(lockName);
+  }
+}
+
+unsafe impl<T> Send for McsLock<T> where T: Send {}
+unsafe impl<T> Sync for McsLock<T> where T: Send {}
+
+pub struct McsLockGuard<'a, T> {
+  lock: &'a McsLock<T>,
+
+  // Queue node of the current CPU core, used while acquiring the McsLock.
+  node: &'static McsNode,
+}
+
+impl<T> Deref for McsLockGuard<'_, T> {
+  type Target = T;
+
+  fn deref(&self) -> &Self::Target {
+    unsafe { &*self.lock.data.get() }
+  }
+}
+
+impl<T> DerefMut for McsLockGuard<'_, T> {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    unsafe { &mut *self.lock.data.get() }
+  }
+}
+
+// Release the McsLock when the McsLockGuard is dropped.
+impl<T> Drop for McsLockGuard<'_, T> {
+  fn drop(&mut self) {
+    self.lock.release(self.node)
+  }
+}
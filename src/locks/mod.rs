@@ -1,2 +1,13 @@
+pub mod completion;
+pub mod condvar;
+#[cfg(feature = "lockdep")]
+pub mod lockdep;
+pub mod mcslock;
+pub mod rcu;
+pub mod rwspinlock;
+pub mod semaphore;
 pub mod sleeplock;
 pub mod spinlock;
+pub mod statistics;
+pub mod ticketlock;
+pub mod waitqueue;
@@ -0,0 +1,354 @@
+use {
+  super::spinlock::SpinLock,
+  crate::{
+    arch::riscv::{qemu::MAX_CORES, registers::tp::Tp},
+    ipi::{self, HartMask},
+    process::core::Core,
+  },
+  alloc::{boxed::Box, collections::VecDeque, vec::Vec},
+  core::{
+    hint::spin_loop,
+    marker::PhantomData,
+    ptr,
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
+  },
+};
+
+/*
+  RCU (Read-Copy-Update) is a synchronization mechanism for data which is read far more often than
+  it's written (like the process lookup table or the mount table).
+
+  Readers don't take any lock. They just mark the section of code in which they access the data
+  (the read-side critical section). A writer never modifies the data in place. Instead, it :
+
+    (1) makes a copy of the data, and updates the copy.
+
+    (2) publishes the updated copy, by atomically replacing the pointer the readers use.
+
+    (3) waits for a grace period, i.e., till all the readers which could be still using the old
+        copy, have left their read-side critical sections. Then frees the old copy.
+
+  We use the classic (non-preemptible) flavour of RCU : a read-side critical section runs with
+  interrupts disabled, so the CPU core can't context switch while inside one. Thus, whenever a CPU
+  core is seen outside any read-side critical section (it context switched, or the scheduler is
+  idling, or a scheduler tick interrupted it), it's said to have passed through a quiescent state.
+  A grace period ends, once every online CPU core has passed through a quiescent state after the
+  grace period started.
+
+  REFER : https://www.kernel.org/doc/html/latest/RCU/whatisRCU.html.
+*/
+
+// Read-side critical section nesting depth of each CPU core.
+static RCU_READ_DEPTHS: [AtomicUsize; MAX_CORES] = [const { AtomicUsize::new(0) }; MAX_CORES];
+
+// Marks a read-side critical section, which lasts till the returned guard is dropped.
+// NOTE : The invoker must not sleep, while inside the read-side critical section.
+pub fn rcuReadLock() -> RcuReadGuard {
+  Core::enterInterruptsDisabledSection();
+  RCU_READ_DEPTHS[unsafe { Tp.read() }].fetch_add(1, Ordering::Relaxed);
+
+  RcuReadGuard {
+    _notSend: PhantomData,
+  }
+}
+
+pub struct RcuReadGuard {
+  // The guard must be dropped on the same CPU core it was created on.
+  _notSend: PhantomData<*const ()>,
+}
+
+// Ends the read-side critical section when the RcuReadGuard is dropped.
+impl Drop for RcuReadGuard {
+  fn drop(&mut self) {
+    RCU_READ_DEPTHS[unsafe { Tp.read() }].fetch_sub(1, Ordering::Relaxed);
+    Core::exitInterruptsDisabledSection();
+  }
+}
+
+// Returns whether the current CPU core is inside a read-side critical section.
+#[inline]
+fn isInsideReadSideCriticalSection() -> bool {
+  RCU_READ_DEPTHS[unsafe { Tp.read() }].load(Ordering::Relaxed) > 0
+}
+
+// A function which gets invoked (with the given argument) after a grace period.
+struct RcuCallback {
+  function: fn(usize),
+  argument: usize,
+
+  // The grace period, after whose completion the callback can be invoked.
+  gracePeriod: usize,
+}
+
+struct RcuState {
+  // Number of the latest grace period which has been started.
+  startedGracePeriod: usize,
+  // Number of the latest grace period which has been completed.
+  completedGracePeriod: usize,
+
+  // CPU cores which are yet to pass through a quiescent state, in the ongoing grace period.
+  hartsPendingQuiescentState: HartMask,
+
+  // Whether another grace period needs to be started, once the ongoing one completes.
+  isAnotherGracePeriodRequested: bool,
+
+  // Pending callbacks, in increasing order of their grace periods.
+  callbacks: VecDeque<RcuCallback>,
+}
+
+impl RcuState {
+  const fn new() -> Self {
+    Self {
+      startedGracePeriod: 0,
+      completedGracePeriod: 0,
+
+      hartsPendingQuiescentState: HartMask::empty(),
+
+      isAnotherGracePeriodRequested: false,
+
+      callbacks: VecDeque::new(),
+    }
+  }
+
+  #[inline]
+  fn isGracePeriodOngoing(&self) -> bool {
+    self.startedGracePeriod > self.completedGracePeriod
+  }
+
+  fn startGracePeriod(&mut self) {
+    self.startedGracePeriod += 1;
+    self.hartsPendingQuiescentState = ipi::getOnlineHarts();
+    self.isAnotherGracePeriodRequested = false;
+  }
+
+  // Returns the number of the grace period, whose completion guarantees that all the read-side
+  // critical sections which have already started, have ended. Starts that grace period, if
+  // required.
+  fn requestGracePeriod(&mut self) -> usize {
+    match self.isGracePeriodOngoing() {
+      // The ongoing grace period may have started before some of the currently running read-side
+      // critical sections. So we need the next one.
+      true => {
+        self.isAnotherGracePeriodRequested = true;
+        self.startedGracePeriod + 1
+      }
+
+      false => {
+        self.startGracePeriod();
+        self.startedGracePeriod
+      }
+    }
+  }
+
+  // Records that the given CPU core has passed through a quiescent state.
+  // Returns whether that has completed the ongoing grace period.
+  fn reportQuiescentState(&mut self, hartID: usize) -> bool {
+    if !self.isGracePeriodOngoing() || !self.hartsPendingQuiescentState.contains(hartID) {
+      return false;
+    }
+
+    self.hartsPendingQuiescentState.remove(hartID);
+    if self.hartsPendingQuiescentState.count() > 0 {
+      return false;
+    }
+
+    self.completedGracePeriod = self.startedGracePeriod;
+
+    if self.isAnotherGracePeriodRequested
+      || self
+        .callbacks
+        .back()
+        .is_some_and(|callback| callback.gracePeriod > self.completedGracePeriod)
+    {
+      self.startGracePeriod();
+    }
+
+    true
+  }
+
+  // Removes and returns the callbacks, whose grace periods have completed.
+  fn takeReadyCallbacks(&mut self) -> Vec<RcuCallback> {
+    let mut readyCallbacks = Vec::new();
+
+    while self
+      .callbacks
+      .front()
+      .is_some_and(|callback| callback.gracePeriod <= self.completedGracePeriod)
+    {
+      readyCallbacks.push(self.callbacks.pop_front().unwrap());
+    }
+
+    readyCallbacks
+  }
+}
+
+static RCU_STATE: SpinLock<RcuState> = SpinLock::new(RcuState::new());
+
+/*
+  Records that the current CPU core has passed through a quiescent state. If that completes the
+  ongoing grace period, then the ready callbacks are invoked.
+
+  Invoked by the scheduler (on each context switch and each idle iteration) and on each scheduler
+  tick.
+  NOTE : Must not be invoked from inside a read-side critical section.
+*/
+pub fn noteQuiescentState() {
+  assert!(
+    !isInsideReadSideCriticalSection(),
+    "Quiescent state reported from inside an RCU read-side critical section"
+  );
+
+  let mut rcuState = RCU_STATE.acquire();
+
+  if !rcuState.reportQuiescentState(unsafe { Tp.read() }) {
+    return;
+  }
+
+  let readyCallbacks = rcuState.takeReadyCallbacks();
+  drop(rcuState);
+
+  for callback in readyCallbacks {
+    (callback.function)(callback.argument);
+  }
+}
+
+// Executed (via an IPI) on the CPU cores, which are holding up a grace period.
+fn forceQuiescentState(_: usize) {
+  // The IPIMessageQueue may also get drained by a CPU core waiting in callOnHarts( ), which can be
+  // inside a read-side critical section.
+  if !isInsideReadSideCriticalSection() {
+    noteQuiescentState();
+  }
+}
+
+/*
+  Waits till a grace period has elapsed, i.e., till all the read-side critical sections which are
+  currently running (on any CPU core) have ended.
+
+  A CPU core idling in the scheduler (waiting for an interrupt) doesn't report quiescent states.
+  So, instead of waiting for the next scheduler tick, the CPU cores holding up the grace period are
+  sent IPIs, forcing them to report.
+  NOTE : Must not be invoked from inside a read-side critical section.
+*/
+pub fn synchronizeRcu() {
+  assert!(
+    !isInsideReadSideCriticalSection(),
+    "synchronizeRcu( ) invoked from inside an RCU read-side critical section"
+  );
+
+  let gracePeriod = RCU_STATE.acquire().requestGracePeriod();
+
+  loop {
+    // The current CPU core isn't inside a read-side critical section.
+    noteQuiescentState();
+
+    let rcuState = RCU_STATE.acquire();
+    if rcuState.completedGracePeriod >= gracePeriod {
+      break;
+    }
+
+    let mut hartsPendingQuiescentState = rcuState.hartsPendingQuiescentState;
+    drop(rcuState);
+
+    hartsPendingQuiescentState.remove(unsafe { Tp.read() });
+    ipi::callOnHarts(hartsPendingQuiescentState, forceQuiescentState, 0);
+
+    spin_loop();
+  }
+}
+
+/*
+  Schedules the given function to be invoked (with the given argument), after a grace period has
+  elapsed. Doesn't block.
+
+  NOTE : The function is invoked by whichever CPU core completes the grace period, usually from
+  interrupt context (the scheduler tick or an IPI handler). So it must not sleep, and must not
+  acquire any lock which is held with interrupts enabled.
+*/
+pub fn callRcu(function: fn(usize), argument: usize) {
+  let mut rcuState = RCU_STATE.acquire();
+
+  let gracePeriod = rcuState.requestGracePeriod();
+  rcuState.callbacks.push_back(RcuCallback {
+    function,
+    argument,
+    gracePeriod,
+  });
+}
+
+/*
+  A pointer to RCU protected data.
+
+  Readers dereference it inside a read-side critical section. Writers publish a new version of the
+  data by replacing it, and then reclaim the old version after a grace period.
+  NOTE : Writers must serialize among themselves (using a SpinLock, say).
+*/
+pub struct RcuPointer<T> {
+  pointer: AtomicPtr<T>,
+}
+
+impl<T> RcuPointer<T> {
+  pub const fn null() -> Self {
+    Self {
+      pointer: AtomicPtr::new(ptr::null_mut()),
+    }
+  }
+
+  pub fn new(data: Box<T>) -> Self {
+    Self {
+      pointer: AtomicPtr::new(Box::into_raw(data)),
+    }
+  }
+
+  // Returns a reference to the current version of the data, valid till the end of the read-side
+  // critical section.
+  pub fn dereference<'a>(&self, _rcuReadGuard: &'a RcuReadGuard) -> Option<&'a T> {
+    // Acquire memory ordering : pairs with the Release store in replace( ), so that we see the
+    // data fully initialized.
+    unsafe { self.pointer.load(Ordering::Acquire).as_ref() }
+  }
+
+  // Publishes the given version of the data. Returns the old version, which must only be
+  // reclaimed after a grace period.
+  #[must_use]
+  pub fn replace(&self, data: Option<Box<T>>) -> RcuRetired<T> {
+    let newPointer = data.map_or(ptr::null_mut(), Box::into_raw);
+
+    RcuRetired {
+      pointer: self.pointer.swap(newPointer, Ordering::AcqRel),
+    }
+  }
+}
+
+unsafe impl<T> Send for RcuPointer<T> where T: Send + Sync {}
+unsafe impl<T> Sync for RcuPointer<T> where T: Send + Sync {}
+
+// An old version of RCU protected data, which readers may still be using.
+// NOTE : If dropped without being reclaimed, then the data is leaked.
+pub struct RcuRetired<T> {
+  pointer: *mut T,
+}
+
+impl<T> RcuRetired<T> {
+  // Waits for a grace period and then frees the data.
+  pub fn reclaim(self) {
+    if self.pointer.is_null() {
+      return;
+    }
+
+    synchronizeRcu();
+    drop(unsafe { Box::from_raw(self.pointer) });
+  }
+
+  // Frees the data after a grace period, without blocking.
+  pub fn deferReclaim(self) {
+    if self.pointer.is_null() {
+      return;
+    }
+
+    callRcu(
+      |pointer| drop(unsafe { Box::from_raw(pointer as *mut T) }),
+      self.pointer as usize,
+    );
+  }
+}
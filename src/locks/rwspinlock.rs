@@ -0,0 +1,252 @@
+#[cfg(feature = "lockdep")]
+use {
+  super::lockdep::{self, LockKind, LockdepMap},
+  crate::arch::riscv::registers::sstatus::Sstatus,
+  core::{any::type_name, panic::Location},
+};
+use {
+  super::statistics::LockStatistics,
+  crate::{arch::riscv::registers::tp::Tp, process::core::Core},
+  core::{
+    cell::{Cell, UnsafeCell},
+    hint::spin_loop,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicUsize, Ordering},
+  },
+};
+
+/*
+  A RwSpinLock (Reader-Writer SpinLock) can be held by either multiple readers (which can only read
+  the protected data) or a single writer at the same time.
+
+  It's writer preferring : once a writer starts waiting, new readers wait too, so that the writer
+  doesn't starve behind a continuous stream of readers.
+  NOTE : So, a CPU core must not acquire a RwSpinLock for reading, while already holding it for
+  reading. A writer waiting in between, would cause a deadlock.
+
+  Just like with SpinLocks, interrupts remain disabled while a RwSpinLock is held.
+*/
+pub struct RwSpinLock<T> {
+  data: UnsafeCell<T>,
+
+  // Bit layout : | WRITER | WRITER_WAITING | reader count (remaining bits) |
+  state: AtomicUsize,
+
+  writerCPUCoreID: Cell<isize>, // ID of the CPU core which has acquired the RwSpinLock for writing.
+                                // NOTE : -1 means the RwSpinLock isn't acquired for writing.
+  writerAcquiredAt: Cell<usize>,
+  statistics: LockStatistics,
+
+  #[cfg(feature = "lockdep")]
+  lockdepMap: LockdepMap,
+}
+
+const WRITER: usize = 1 << (usize::BITS - 1);
+const WRITER_WAITING: usize = 1 << (usize::BITS - 2);
+const READER_COUNT_MASK: usize = WRITER_WAITING - 1;
+
+impl<T> RwSpinLock<T> {
+  #[track_caller]
+  pub const fn new(data: T) -> Self {
+    Self {
+      data: UnsafeCell::new(data),
+
+      state: AtomicUsize::new(0),
+
+      writerCPUCoreID: Cell::new(-1),
+
+      writerAcquiredAt: Cell::new(0),
+      statistics: LockStatistics::new(),
+
+      #[cfg(feature = "lockdep")]
+      lockdepMap: LockdepMap::new(type_name::<T>(), LockKind::RwSpinLock),
+    }
+  }
+
+  // Acquires the RwSpinLock for reading.
+  #[track_caller]
+  pub fn read(&self) -> RwSpinLockReadGuard<'_, T> {
+    #[cfg(feature = "lockdep")]
+    let wereInterruptsEnabled = unsafe { Sstatus.areInterruptsEnabled() };
+
+    Core::enterInterruptsDisabledSection();
+
+    assert!(
+      !self.isCurrentCPUCoreWriting(),
+      "Current CPU core is already holding the RwSpinLock for writing"
+    );
+
+    #[cfg(feature = "lockdep")]
+    lockdep::beforeAcquire(&self.lockdepMap, wereInterruptsEnabled, Location::caller());
+
+    let mut spins = 0;
+    loop {
+      let state = self.state.load(Ordering::Relaxed);
+
+      if (state & (WRITER | WRITER_WAITING)) == 0
+        && self
+          .state
+          .compare_exchange_weak(state, state + 1, Ordering::Acquire, Ordering::Relaxed)
+          .is_ok()
+      {
+        break;
+      }
+
+      spin_loop();
+      spins += 1;
+    }
+
+    let acquiredAt = self.statistics.recordAcquisition(spins);
+
+    #[cfg(feature = "lockdep")]
+    lockdep::afterAcquire(&self.lockdepMap, self.getAddress(), Location::caller());
+
+    RwSpinLockReadGuard {
+      lock: self,
+      acquiredAt,
+    }
+  }
+
+  // Acquires the RwSpinLock for writing.
+  #[track_caller]
+  pub fn write(&self) -> RwSpinLockWriteGuard<'_, T> {
+    #[cfg(feature = "lockdep")]
+    let wereInterruptsEnabled = unsafe { Sstatus.areInterruptsEnabled() };
+
+    Core::enterInterruptsDisabledSection();
+
+    assert!(
+      !self.isCurrentCPUCoreWriting(),
+      "Current CPU core is already holding the RwSpinLock for writing"
+    );
+
+    #[cfg(feature = "lockdep")]
+    lockdep::beforeAcquire(&self.lockdepMap, wereInterruptsEnabled, Location::caller());
+
+    let mut spins = 0;
+    loop {
+      let state = self.state.load(Ordering::Relaxed);
+
+      // Nobody holds the RwSpinLock. Take it (clearing the WRITER_WAITING bit : any other waiting
+      // writer will set it again).
+      if (state & (WRITER | READER_COUNT_MASK)) == 0 {
+        if self
+          .state
+          .compare_exchange_weak(state, WRITER, Ordering::Acquire, Ordering::Relaxed)
+          .is_ok()
+        {
+          break;
+        }
+      }
+      // Stop new readers from coming in.
+      else if (state & WRITER_WAITING) == 0 {
+        self.state.fetch_or(WRITER_WAITING, Ordering::Relaxed);
+      }
+
+      spin_loop();
+      spins += 1;
+    }
+
+    unsafe { self.writerCPUCoreID.set(Tp.read() as isize) };
+    self
+      .writerAcquiredAt
+      .set(self.statistics.recordAcquisition(spins));
+
+    #[cfg(feature = "lockdep")]
+    lockdep::afterAcquire(&self.lockdepMap, self.getAddress(), Location::caller());
+
+    RwSpinLockWriteGuard(self)
+  }
+
+  fn releaseRead(&self, acquiredAt: usize) {
+    self.statistics.recordRelease(acquiredAt);
+
+    self.state.fetch_sub(1, Ordering::Release);
+
+    #[cfg(feature = "lockdep")]
+    lockdep::afterRelease(&self.lockdepMap, self.getAddress());
+
+    Core::exitInterruptsDisabledSection();
+  }
+
+  fn releaseWrite(&self) {
+    assert!(
+      self.isCurrentCPUCoreWriting(),
+      "Current CPU core isn't holding this RwSpinLock for writing"
+    );
+
+    self.statistics.recordRelease(self.writerAcquiredAt.get());
+    self.writerCPUCoreID.set(-1);
+
+    // NOTE : The WRITER_WAITING bit (set by some other waiting writer) is preserved.
+    self.state.fetch_and(!WRITER, Ordering::Release);
+
+    #[cfg(feature = "lockdep")]
+    lockdep::afterRelease(&self.lockdepMap, self.getAddress());
+
+    Core::exitInterruptsDisabledSection();
+  }
+
+  #[inline]
+  pub fn getAddress(&self) -> usize {
+    self as *const Self as usize
+  }
+
+  // Returns whether the CPU core of the invoker is holding the RwSpinLock for writing or not.
+  pub fn isCurrentCPUCoreWriting(&self) -> bool {
+    self.writerCPUCoreID.get() == unsafe { Tp.read() as isize }
+  }
+
+  pub fn dumpStatistics(&self, lockName: &str) {
+    self.statistics.dump(lockName);
+  }
+}
+
+unsafe impl<T> Send for RwSpinLock<T> where T: Send {}
+unsafe impl<T> Sync for RwSpinLock<T> where T: Send + Sync {}
+
+pub struct RwSpinLockReadGuard<'a, T> {
+  lock: &'a RwSpinLock<T>,
+  acquiredAt: usize,
+}
+
+// Automatic dereference conversion from &RwSpinLockReadGuard<T> to &T.
+impl<T> Deref for RwSpinLockReadGuard<'_, T> {
+  type Target = T;
+
+  fn deref(&self) -> &Self::Target {
+    unsafe { &*self.lock.data.get() }
+  }
+}
+
+// Release the RwSpinLock when the RwSpinLockReadGuard is dropped.
+impl<T> Drop for RwSpinLockReadGuard<'_, T> {
+  fn drop(&mut self) {
+    self.lock.releaseRead(self.acquiredAt)
+  }
+}
+
+pub struct RwSpinLockWriteGuard<'a, T>(&'a RwSpinLock<T>);
+
+// Automatic dereference conversion from &RwSpinLockWriteGuard<T> to &T.
+impl<T> Deref for RwSpinLockWriteGuard<'_, T> {
+  type Target = T;
+
+  fn deref(&self) -> &Self::Target {
+    unsafe { &*self.0.data.get() }
+  }
+}
+
+// Automatic dereference conversion from &mut RwSpinLockWriteGuard<T> to &mut T.
+impl<T> DerefMut for RwSpinLockWriteGuard<'_, T> {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    unsafe { &mut *self.0.data.get() }
+  }
+}
+
+// Release the RwSpinLock when the RwSpinLockWriteGuard is dropped.
+impl<T> Drop for RwSpinLockWriteGuard<'_, T> {
+  fn drop(&mut self) {
+    self.0.releaseWrite()
+  }
+}
@@ -0,0 +1,56 @@
+use super::{spinlock::SpinLock, waitqueue::WaitQueue};
+
+// A counting semaphore. down( ) blocks while the count is 0, and then decrements it. up( )
+// increments the count, waking up a waiting process.
+pub struct Semaphore {
+  count: SpinLock<usize>,
+  waitQueue: WaitQueue,
+}
+
+impl Semaphore {
+  #[track_caller]
+  pub const fn new(count: usize) -> Self {
+    Self {
+      count: SpinLock::new(count),
+      waitQueue: WaitQueue::new(),
+    }
+  }
+
+  pub fn down(&self) {
+    let mut count = self
+      .waitQueue
+      .waitUntil(self.count.acquire(), |count| *count > 0);
+    *count -= 1;
+  }
+
+  // Same as down( ), but gives up after the given number of ticks. Returns whether the count was
+  // decremented.
+  pub fn downTimeout(&self, timeout: usize) -> bool {
+    let (mut count, isPositive) =
+      self
+        .waitQueue
+        .waitUntilTimeout(self.count.acquire(), timeout, |count| *count > 0);
+
+    if isPositive {
+      *count -= 1;
+    }
+    isPositive
+  }
+
+  // Decrements the count if it's positive, without blocking. Returns whether it did.
+  pub fn tryDown(&self) -> bool {
+    let mut count = self.count.acquire();
+
+    if *count == 0 {
+      return false;
+    }
+
+    *count -= 1;
+    true
+  }
+
+  pub fn up(&self) {
+    *self.count.acquire() += 1;
+    self.waitQueue.notifyOne();
+  }
+}
@@ -1,55 +1,128 @@
+#[cfg(feature = "lockdep")]
+use {
+  super::lockdep::{self, LockKind, LockdepMap},
+  core::{any::type_name, panic::Location},
+};
 use {
   super::spinlock::SpinLock,
+  crate::process::{manager::PROCESS_MANAGER, process::getCurrentProcess},
   core::{
-    cell::{Cell, UnsafeCell},
+    cell::UnsafeCell,
     ops::{Deref, DerefMut},
   },
 };
 
 // Unlike Spin Locks, we can hold a Sleep Lock for a very long amount of time. While holding the
 // lock, we can sleep and context-switch.
+//
+// A process trying to acquire a Sleep Lock which is held by some other process, goes to sleep
+// (with the address of the Sleep Lock as the wait channel), instead of spinning. It's woken up when
+// the Sleep Lock gets released.
 pub struct SleepLock<T> {
   data: UnsafeCell<T>,
 
   // Used to atomically release / try to acquire this SleepLock without any interruptions /
   // context-switches.
-  spinLock: SpinLock<()>,
+  state: SpinLock<SleepLockState>,
+
+  // The lock class of the SleepLock is determined by where it's constructed.
+  #[cfg(feature = "lockdep")]
+  lockdepMap: LockdepMap,
+}
 
-  isAcquired: Cell<bool>,
+struct SleepLockState {
+  isAcquired: bool,
   ownerPID: usize, // ID of the process which is currently holding this Sleep Lock.
-                   // NOTE : 0 means the SleepLock is currently acquired by none.
+                   // NOTE : 0 means the SleepLock is currently acquired by none (or by the kernel,
+                   //        while it isn't running any process).
 }
 
 impl<T> SleepLock<T> {
+  #[track_caller]
   pub const fn new(data: T) -> Self {
     Self {
-      spinLock: SpinLock::new(()),
+      data: UnsafeCell::new(data),
 
-      isAcquired: Cell::new(false),
-      ownerPID: 0,
+      state: SpinLock::new(SleepLockState {
+        isAcquired: false,
+        ownerPID: 0,
+      }),
 
-      data: UnsafeCell::new(data),
+      #[cfg(feature = "lockdep")]
+      lockdepMap: LockdepMap::new(type_name::<T>(), LockKind::SleepLock),
     }
   }
 
+  // The address of the SleepLock is used as the wait channel, by the processes waiting to acquire
+  // it.
+  #[inline]
+  fn getWaitChannel(&self) -> usize {
+    self as *const Self as usize
+  }
+
+  #[track_caller]
   pub fn acquire(&self) -> SleepLockGuard<T> {
-    let spinLockGuard = self.spinLock.acquire();
+    self.acquireNested(0)
+  }
+
+  /*
+    Same as acquire( ), but tells lockdep that the SleepLock belongs to the given subclass of its
+    lock class. Used when SleepLocks of the same lock class need to be held at once, with the
+    invoker guaranteeing the nesting order : each nesting level gets its own subclass.
+
+    REFER : mutex_lock_nested( ) in Linux.
+  */
+  #[track_caller]
+  #[cfg_attr(not(feature = "lockdep"), allow(unused_variables))]
+  pub fn acquireNested(&self, subclass: usize) -> SleepLockGuard<'_, T> {
+    #[cfg(feature = "lockdep")]
+    lockdep::beforeAcquireNested(&self.lockdepMap, subclass, false, Location::caller());
 
-    while self.isAcquired.get() {
-      // TODO : Let the process go to sleep.
-      todo!("Let the process go to sleep");
+    let mut state = self.state.acquire();
+
+    while state.isAcquired {
+      let currentProcess = getCurrentProcess()
+        .expect("SleepLock is contended, but there's no process to put to sleep");
+
+      // Releases the SpinLock while the process is sleeping, and re-acquires it once the process
+      // is woken up.
+      state = currentProcess.sleep(self.getWaitChannel(), state);
     }
 
-    self.isAcquired.set(true);
+    state.isAcquired = true;
+    state.ownerPID = getCurrentProcess().map_or(0, |process| process.getPID());
+
+    #[cfg(feature = "lockdep")]
+    lockdep::afterAcquireNested(
+      &self.lockdepMap,
+      subclass,
+      self.getWaitChannel(),
+      Location::caller(),
+    );
 
     SleepLockGuard(self)
   }
 
-  pub fn release(&self) {
-    let spinLockGuard = self.spinLock.acquire();
+  fn release(&self) {
+    let mut state = self.state.acquire();
 
-    self.isAcquired.set(true);
-    // TODO : Wake up all the other sleeping processes waiting to acquire this SleepLock.
+    state.isAcquired = false;
+    state.ownerPID = 0;
+
+    #[cfg(feature = "lockdep")]
+    lockdep::afterRelease(&self.lockdepMap, self.getWaitChannel());
+
+    // Wake up all the other sleeping processes waiting to acquire this SleepLock.
+    PROCESS_MANAGER.wakeup(self.getWaitChannel());
+  }
+
+  // Returns whether the SleepLock is held by the current process or not.
+  // Useful for assertions.
+  pub fn isHeldByCurrentProcess(&self) -> bool {
+    let state = self.state.acquire();
+
+    state.isAcquired
+      && (state.ownerPID == getCurrentProcess().map_or(0, |process| process.getPID()))
   }
 }
 
@@ -57,6 +130,14 @@ unsafe impl<T> Sync for SleepLock<T> {}
 
 pub struct SleepLockGuard<'a, T>(&'a SleepLock<T>);
 
+impl<'a, T> SleepLockGuard<'a, T> {
+  // Returns the SleepLock this guard belongs to.
+  #[inline]
+  pub fn getSleepLock(&self) -> &'a SleepLock<T> {
+    self.0
+  }
+}
+
 // Automatic dereference conversion from &SleepLockGuard<T> to &T.
 impl<T> Deref for SleepLockGuard<'_, T> {
   type Target = T;
@@ -73,7 +154,7 @@ impl<T> DerefMut for SleepLockGuard<'_, T> {
   }
 }
 
-// Release the SpinLock when the SleepLockGuard is dropped.
+// Release the SleepLock when the SleepLockGuard is dropped.
 impl<T> Drop for SleepLockGuard<'_, T> {
   fn drop(&mut self) {
     self.0.release()
@@ -1,3 +1,9 @@
+#[cfg(feature = "lockdep")]
+use {
+  super::lockdep::{self, LockKind, LockdepMap},
+  crate::arch::riscv::registers::sstatus::Sstatus,
+  core::{any::type_name, panic::Location},
+};
 use {
   crate::{arch::riscv::registers::tp::Tp, process::core::Core},
   core::{
@@ -25,21 +31,33 @@ pub struct SpinLock<T> {
   isAcquired: AtomicBool,
   ownerCPUCoreID: Cell<isize>, // ID of the CPU core which has acquired the SpinLock.
                                // NOTE : -1 means the SpinLock is currently acuired by none.
+
+  // The lock class of the SpinLock is determined by where it's constructed.
+  #[cfg(feature = "lockdep")]
+  lockdepMap: LockdepMap,
 }
 
 impl<T> SpinLock<T> {
+  #[track_caller]
   pub const fn new(data: T) -> Self {
     Self {
       data: UnsafeCell::new(data),
 
       isAcquired: AtomicBool::new(false),
       ownerCPUCoreID: Cell::new(-1),
+
+      #[cfg(feature = "lockdep")]
+      lockdepMap: LockdepMap::new(type_name::<T>(), LockKind::SpinLock),
     }
   }
 
   // Acquires the SpinLock.
   // Returns an immutable reference to the SpinLock.
+  #[track_caller]
   pub fn acquire(&self) -> SpinLockGuard<'_, T> {
+    #[cfg(feature = "lockdep")]
+    let wereInterruptsEnabled = unsafe { Sstatus.areInterruptsEnabled() };
+
     Core::enterInterruptsDisabledSection();
 
     // Ensure that the current CPU core isn't already owning the SpinLock.
@@ -48,6 +66,9 @@ impl<T> SpinLock<T> {
       "Current CPU core is already holding the SpinLock"
     );
 
+    #[cfg(feature = "lockdep")]
+    lockdep::beforeAcquire(&self.lockdepMap, wereInterruptsEnabled, Location::caller());
+
     /*
       Steps of approaching to this solution :
 
@@ -108,14 +129,33 @@ impl<T> SpinLock<T> {
 
     unsafe { self.ownerCPUCoreID.set(Tp.read() as isize) };
 
+    #[cfg(feature = "lockdep")]
+    lockdep::afterAcquire(&self.lockdepMap, self.getAddress(), Location::caller());
+
     SpinLockGuard(self)
   }
 
+  // Returns the address of the SpinLock, which identifies it.
+  #[inline]
+  pub fn getAddress(&self) -> usize {
+    self as *const Self as usize
+  }
+
   // Returns whether the CPU core of the invoker is already holding the SpinLock or not.
   pub fn isCurrentCPUCoreHolding(&self) -> bool {
     self.isAcquired.load(Ordering::Relaxed)
       && (self.ownerCPUCoreID.get() == unsafe { Tp.read() as isize })
   }
+
+  /*
+    Releases the SpinLock, which the current CPU core is holding without a SpinLockGuard.
+
+    NOTE : Only meant for a process running for the first time, which must release its metadata
+    SpinLock that the scheduler acquired before switching to it (REFER : ../process/scheduler.rs).
+  */
+  pub unsafe fn forceRelease(&self) {
+    self.release();
+  }
 }
 
 unsafe impl<T> Send for SpinLock<T> where T: Send {}
@@ -123,6 +163,14 @@ unsafe impl<T> Sync for SpinLock<T> where T: Send {}
 
 pub struct SpinLockGuard<'a, T>(&'a SpinLock<T>);
 
+impl<'a, T> SpinLockGuard<'a, T> {
+  // Returns the SpinLock this guard belongs to.
+  #[inline]
+  pub fn getSpinLock(&self) -> &'a SpinLock<T> {
+    self.0
+  }
+}
+
 // Automatic dereference conversion from &SpinLockGuard<T> to &T.
 impl<T> Deref for SpinLockGuard<'_, T> {
   type Target = T;
@@ -170,6 +218,9 @@ impl<T> SpinLock<T> {
     */
     self.isAcquired.store(false, Ordering::Release);
 
+    #[cfg(feature = "lockdep")]
+    lockdep::afterRelease(&self.lockdepMap, self.getAddress());
+
     Core::exitInterruptsDisabledSection();
   }
 }
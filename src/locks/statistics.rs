@@ -0,0 +1,79 @@
+use {
+  crate::arch::riscv::registers::time::{Time, TIME_FREQUENCY},
+  core::sync::atomic::{AtomicUsize, Ordering},
+};
+
+// Contention statistics of a lock.
+// NOTE : The hold times are measured in ticks of the time CSR.
+pub struct LockStatistics {
+  acquisitions: AtomicUsize,
+
+  // Acquisitions, where the lock wasn't immediately available.
+  contendedAcquisitions: AtomicUsize,
+
+  // Number of iterations spent spinning, waiting for the lock to become available.
+  totalSpins: AtomicUsize,
+  maxSpins: AtomicUsize,
+
+  totalHoldTime: AtomicUsize,
+  maxHoldTime: AtomicUsize,
+}
+
+impl LockStatistics {
+  pub const fn new() -> Self {
+    Self {
+      acquisitions: AtomicUsize::new(0),
+      contendedAcquisitions: AtomicUsize::new(0),
+
+      totalSpins: AtomicUsize::new(0),
+      maxSpins: AtomicUsize::new(0),
+
+      totalHoldTime: AtomicUsize::new(0),
+      maxHoldTime: AtomicUsize::new(0),
+    }
+  }
+
+  // Records an acquisition, which spun for the given number of iterations.
+  // Returns the timestamp of the acquisition, which must be passed to recordRelease( ).
+  pub fn recordAcquisition(&self, spins: usize) -> usize {
+    self.acquisitions.fetch_add(1, Ordering::Relaxed);
+
+    if spins > 0 {
+      self.contendedAcquisitions.fetch_add(1, Ordering::Relaxed);
+      self.totalSpins.fetch_add(spins, Ordering::Relaxed);
+      self.maxSpins.fetch_max(spins, Ordering::Relaxed);
+    }
+
+    Time.read()
+  }
+
+  // Records a release of the lock, which was acquired at the given timestamp.
+  pub fn recordRelease(&self, acquiredAt: usize) {
+    let holdTime = Time.read().saturating_sub(acquiredAt);
+
+    self.totalHoldTime.fetch_add(holdTime, Ordering::Relaxed);
+    self.maxHoldTime.fetch_max(holdTime, Ordering::Relaxed);
+  }
+
+  // Prints the statistics, labelled with the given lock name.
+  pub fn dump(&self, lockName: &str) {
+    let acquisitions = self.acquisitions.load(Ordering::Relaxed);
+    let contendedAcquisitions = self.contendedAcquisitions.load(Ordering::Relaxed);
+    let totalSpins = self.totalSpins.load(Ordering::Relaxed);
+    let totalHoldTime = self.totalHoldTime.load(Ordering::Relaxed);
+
+    // Converts the given number of ticks to microseconds.
+    let toMicroseconds = |ticks: usize| ticks / (TIME_FREQUENCY / 1_000_000);
+
+    println!(
+      "{} : acquisitions = {}, contended = {}, spins (total / max) = {} / {}, hold time in μs (average / max) = {} / {}",
+      lockName,
+      acquisitions,
+      contendedAcquisitions,
+      totalSpins,
+      self.maxSpins.load(Ordering::Relaxed),
+      toMicroseconds(totalHoldTime.checked_div(acquisitions).unwrap_or(0)),
+      toMicroseconds(self.maxHoldTime.load(Ordering::Relaxed)),
+    );
+  }
+}
@@ -0,0 +1,159 @@
+#[cfg(feature = "lockdep")]
+use {
+  super::lockdep::{self, LockKind, LockdepMap},
+  crate::arch::riscv::registers::sstatus::Sstatus,
+  core::{any::type_name, panic::Location},
+};
+use {
+  super::statistics::LockStatistics,
+  crate::{arch::riscv::registers::tp::Tp, process::core::Core},
+  core::{
+    cell::{Cell, UnsafeCell},
+    hint::spin_loop,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicUsize, Ordering},
+  },
+};
+
+/*
+  A TicketLock works like the token system at a bank counter : each CPU core trying to acquire the
+  lock takes the next ticket, and waits until the ticket being served is its own. Releasing the
+  lock serves the next ticket.
+
+  Unlike the SpinLock (where whichever CPU core wins the compare_exchange gets the lock), the CPU
+  cores acquire a TicketLock in FIFO order. So, none of them can starve under contention.
+
+  The downside is that all the waiting CPU cores spin on the same nowServing counter. Each release
+  invalidates the cache line in all of them. The McsLock doesn't have this problem.
+
+  Just like with SpinLocks, interrupts remain disabled while a TicketLock is held.
+*/
+pub struct TicketLock<T> {
+  data: UnsafeCell<T>,
+
+  nextTicket: AtomicUsize,
+  nowServing: AtomicUsize,
+
+  ownerCPUCoreID: Cell<isize>, // ID of the CPU core which has acquired the TicketLock.
+                               // NOTE : -1 means the TicketLock is currently acquired by none.
+  acquiredAt: Cell<usize>,
+  statistics: LockStatistics,
+
+  #[cfg(feature = "lockdep")]
+  lockdepMap: LockdepMap,
+}
+
+impl<T> TicketLock<T> {
+  #[track_caller]
+  pub const fn new(data: T) -> Self {
+    Self {
+      data: UnsafeCell::new(data),
+
+      nextTicket: AtomicUsize::new(0),
+      nowServing: AtomicUsize::new(0),
+
+      ownerCPUCoreID: Cell::new(-1),
+
+      acquiredAt: Cell::new(0),
+      statistics: LockStatistics::new(),
+
+      #[cfg(feature = "lockdep")]
+      lockdepMap: LockdepMap::new(type_name::<T>(), LockKind::TicketLock),
+    }
+  }
+
+  #[track_caller]
+  pub fn acquire(&self) -> TicketLockGuard<'_, T> {
+    #[cfg(feature = "lockdep")]
+    let wereInterruptsEnabled = unsafe { Sstatus.areInterruptsEnabled() };
+
+    Core::enterInterruptsDisabledSection();
+
+    assert!(
+      !self.isCurrentCPUCoreHolding(),
+      "Current CPU core is already holding the TicketLock"
+    );
+
+    #[cfg(feature = "lockdep")]
+    lockdep::beforeAcquire(&self.lockdepMap, wereInterruptsEnabled, Location::caller());
+
+    // NOTE : Relaxed memory ordering is enough here. The happens-before relationship with the
+    // previous holder is established through nowServing.
+    let ticket = self.nextTicket.fetch_add(1, Ordering::Relaxed);
+
+    let mut spins = 0;
+    while self.nowServing.load(Ordering::Acquire) != ticket {
+      spin_loop();
+      spins += 1;
+    }
+
+    unsafe { self.ownerCPUCoreID.set(Tp.read() as isize) };
+    self
+      .acquiredAt
+      .set(self.statistics.recordAcquisition(spins));
+
+    #[cfg(feature = "lockdep")]
+    lockdep::afterAcquire(&self.lockdepMap, self.getAddress(), Location::caller());
+
+    TicketLockGuard(self)
+  }
+
+  fn release(&self) {
+    assert!(
+      self.isCurrentCPUCoreHolding(),
+      "Current CPU core isn't already holding this TicketLock"
+    );
+
+    self.statistics.recordRelease(self.acquiredAt.get());
+    self.ownerCPUCoreID.set(-1);
+
+    // Only the holder modifies nowServing, so a plain load followed by a store is enough.
+    let nowServing = self.nowServing.load(Ordering::Relaxed);
+    self.nowServing.store(nowServing + 1, Ordering::Release);
+
+    #[cfg(feature = "lockdep")]
+    lockdep::afterRelease(&self.lockdepMap, self.getAddress());
+
+    Core::exitInterruptsDisabledSection();
+  }
+
+  #[inline]
+  pub fn getAddress(&self) -> usize {
+    self as *const Self as usize
+  }
+
+  // Returns whether the CPU core of the invoker is already holding the TicketLock or not.
+  pub fn isCurrentCPUCoreHolding(&self) -> bool {
+    self.ownerCPUCoreID.get() == unsafe { Tp.read() as isize }
+  }
+
+  pub fn dumpStatistics(&self, lockName: &str) {
+    self.statistics.dump(lockName);
+  }
+}
+
+unsafe impl<T> Send for TicketLock<T> where T: Send {}
+unsafe impl<T> Sync for TicketLock<T> where T: Send {}
+
+pub struct TicketLockGuard<'a, T>(&'a TicketLock<T>);
+
+impl<T> Deref for TicketLockGuard<'_, T> {
+  type Target = T;
+
+  fn deref(&self) -> &Self::Target {
+    unsafe { &*self.0.data.get() }
+  }
+}
+
+impl<T> DerefMut for TicketLockGuard<'_, T> {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    unsafe { &mut *self.0.data.get() }
+  }
+}
+
+// Release the TicketLock when the TicketLockGuard is dropped.
+impl<T> Drop for TicketLockGuard<'_, T> {
+  fn drop(&mut self) {
+    self.0.release()
+  }
+}
@@ -0,0 +1,150 @@
+use {
+  super::spinlock::SpinLockGuard,
+  crate::{
+    process::{
+      manager::PROCESS_MANAGER,
+      process::{getCurrentProcess, Process},
+    },
+    timer,
+  },
+  core::sync::atomic::{AtomicUsize, Ordering},
+};
+
+/*
+  A queue of processes, waiting for some event.
+
+  The condition being waited for must be protected by a SpinLock. A waiter checks the condition
+  while holding that SpinLock, and passes in the SpinLock guard while starting to wait. The
+  SpinLock gets released only after the waiter is on its way to sleep. So, a notifier which
+  changes the condition (while holding the SpinLock) and then notifies the WaitQueue, can't miss
+  the waiter.
+
+  The address of the WaitQueue is used as the wait channel, by the sleeping processes.
+  NOTE : A waiter can get woken up without the condition being true (say, when a notifier wakes
+  everyone up). So, the condition must always be re-checked after waking up.
+*/
+pub struct WaitQueue {
+  // Number of processes currently waiting. Lets notifiers skip scanning the process table, when
+  // there are none.
+  waitersCount: AtomicUsize,
+}
+
+impl WaitQueue {
+  pub const fn new() -> Self {
+    Self {
+      waitersCount: AtomicUsize::new(0),
+    }
+  }
+
+  #[inline]
+  fn getWaitChannel(&self) -> usize {
+    self as *const Self as usize
+  }
+
+  // Puts the current process to sleep, till it's woken up by a notifier / the given deadline (if
+  // any) is reached.
+  fn sleep<'a, T>(
+    &self,
+    currentProcess: &Process,
+    conditionSpinLockGuard: SpinLockGuard<'a, T>,
+    deadline: Option<usize>,
+  ) -> (SpinLockGuard<'a, T>, bool) {
+    // Incremented while holding the condition SpinLock. So, a notifier which has changed the
+    // condition is guaranteed to see it.
+    self.waitersCount.fetch_add(1, Ordering::AcqRel);
+
+    let (conditionSpinLockGuard, hasDeadlineReached) =
+      currentProcess.sleepUntil(self.getWaitChannel(), conditionSpinLockGuard, deadline);
+
+    self.waitersCount.fetch_sub(1, Ordering::AcqRel);
+
+    (conditionSpinLockGuard, hasDeadlineReached)
+  }
+
+  // Puts the current process to sleep, till it's woken up by a notifier.
+  pub fn wait<'a, T>(&self, conditionSpinLockGuard: SpinLockGuard<'a, T>) -> SpinLockGuard<'a, T> {
+    self
+      .sleep(getCurrentProcessToBlock(), conditionSpinLockGuard, None)
+      .0
+  }
+
+  // Puts the current process to sleep, till it's woken up by a notifier / the given number of
+  // ticks elapse. Along with the re-acquired SpinLock's guard, returns whether the wait timed out.
+  pub fn waitTimeout<'a, T>(
+    &self,
+    conditionSpinLockGuard: SpinLockGuard<'a, T>,
+    timeout: usize,
+  ) -> (SpinLockGuard<'a, T>, bool) {
+    let deadline = timer::getDeadline(timeout);
+
+    self.sleep(
+      getCurrentProcessToBlock(),
+      conditionSpinLockGuard,
+      Some(deadline),
+    )
+  }
+
+  // Keeps the current process sleeping, till the given condition becomes true.
+  pub fn waitUntil<'a, T>(
+    &self,
+    mut conditionSpinLockGuard: SpinLockGuard<'a, T>,
+    mut condition: impl FnMut(&mut T) -> bool,
+  ) -> SpinLockGuard<'a, T> {
+    while !condition(&mut conditionSpinLockGuard) {
+      conditionSpinLockGuard = self
+        .sleep(getCurrentProcessToBlock(), conditionSpinLockGuard, None)
+        .0;
+    }
+
+    conditionSpinLockGuard
+  }
+
+  // Keeps the current process sleeping, till the given condition becomes true / the given number
+  // of ticks elapse. Along with the re-acquired SpinLock's guard, returns whether the condition
+  // became true.
+  pub fn waitUntilTimeout<'a, T>(
+    &self,
+    mut conditionSpinLockGuard: SpinLockGuard<'a, T>,
+    timeout: usize,
+    mut condition: impl FnMut(&mut T) -> bool,
+  ) -> (SpinLockGuard<'a, T>, bool) {
+    let deadline = timer::getDeadline(timeout);
+
+    loop {
+      if condition(&mut conditionSpinLockGuard) {
+        return (conditionSpinLockGuard, true);
+      }
+
+      let hasDeadlineReached;
+      (conditionSpinLockGuard, hasDeadlineReached) = self.sleep(
+        getCurrentProcessToBlock(),
+        conditionSpinLockGuard,
+        Some(deadline),
+      );
+
+      if hasDeadlineReached {
+        let isConditionTrue = condition(&mut conditionSpinLockGuard);
+        return (conditionSpinLockGuard, isConditionTrue);
+      }
+    }
+  }
+
+  // Wakes up one of the waiting processes (if any).
+  pub fn notifyOne(&self) {
+    if self.waitersCount.load(Ordering::Acquire) > 0 {
+      PROCESS_MANAGER.wakeupOne(self.getWaitChannel());
+    }
+  }
+
+  // Wakes up all the waiting processes.
+  pub fn notifyAll(&self) {
+    if self.waitersCount.load(Ordering::Acquire) > 0 {
+      PROCESS_MANAGER.wakeup(self.getWaitChannel());
+    }
+  }
+}
+
+#[inline]
+fn getCurrentProcessToBlock() -> &'static Process {
+  getCurrentProcess().expect("Blocking on a WaitQueue, but there's no process to put to sleep")
+}
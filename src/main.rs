@@ -1,4 +1,12 @@
-use crate::{memory::allocator::GLOBAL_ALLOCATOR, println};
+use crate::{
+  arch::riscv::registers::tp::Tp,
+  drivers::{mem, ramdisk, uart, virtio},
+  fs::{bcache::BCACHE, vfs},
+  memory::{allocator::GLOBAL_ALLOCATOR, asid, page_table::kernel},
+  println,
+  process::{init, scheduler::scheduler},
+  trap,
+};
 
 #[no_mangle]
 pub unsafe extern "C" fn main() {
@@ -6,4 +14,38 @@ pub unsafe extern "C" fn main() {
 
   // Initialize the physical memory allocator.
   GLOBAL_ALLOCATOR.init();
+
+  // Enable Virtual Address Translation, using the kernel Page Table (built by hart 0).
+  if Tp.read() == 0 {
+    kernel::init();
+  }
+  kernel::initHart();
+
+  // Probe the number of ASID bits, now that VAT is enabled.
+  if Tp.read() == 0 {
+    asid::init();
+  }
+
+  // Start handling traps taken while the kernel is executing (like IPIs sent by other harts and
+  // timer interrupts).
+  trap::initHart();
+
+  // Register the character devices (like the UART). Discover the virtio devices (like disks) and
+  // initialize their drivers. Then attach the RAM disk (if a file system image has been embedded),
+  // the buffer cache and the VFS (which mounts the root file system). Then create the init process,
+  // which the scheduler starts running.
+  if Tp.read() == 0 {
+    uart::init();
+    mem::init();
+
+    virtio::init();
+    ramdisk::init();
+
+    BCACHE.init();
+    vfs::init();
+
+    init::createInitProcess();
+  }
+
+  scheduler();
 }
@@ -0,0 +1,260 @@
+use {
+  super::{
+    address::{physical::PhysicalAddress, r#virtual::VirtualAddress, Address},
+    page_table::{entry::PTEBitFlags, user::UserPageTable, MAX_VA},
+  },
+  crate::{arch::riscv::qemu::PAGE_SIZE, fs::vfs::FsError},
+  alloc::{boxed::Box, collections::BTreeMap, vec, vec::Vec},
+};
+
+/*
+  The address space of a user process : its Page Table, along with the frames mapped into it. The
+  frames are freed when the address space is dropped.
+
+  Layout of the address space :
+
+    [ program segments (from the ELF) | ... | guard page | stack | ... | trap frame | trampoline ]
+
+  The pages at the top (above USER_STACK_TOP) are reserved for the kernel's trap handling
+  machinery (REFER : ../asm/trampoline.S). They're only accessible from S-mode.
+
+  NOTE : The kernel Page Table identity maps the DRAM, so the kernel accesses the frames using
+  their Physical Addresses.
+*/
+
+// The trampoline is mapped at the same Virtual Address (VA) in the kernel Page Table.
+pub const TRAMPOLINE_VA: usize = MAX_VA - PAGE_SIZE;
+pub const TRAP_FRAME_VA: usize = TRAMPOLINE_VA - PAGE_SIZE;
+
+pub const USER_STACK_TOP: usize = MAX_VA - 16 * PAGE_SIZE;
+pub const USER_STACK_SIZE: usize = 16 * PAGE_SIZE;
+
+// The lowest address of the stack. The page below it is never mapped, so that a stack overflow
+// faults instead of silently overwriting the program's data.
+pub const USER_STACK_BOTTOM: usize = USER_STACK_TOP - USER_STACK_SIZE;
+
+// The highest address (exclusive) the program segments can be loaded till.
+pub const MAX_USER_PROGRAM_VA: usize = USER_STACK_BOTTOM - PAGE_SIZE;
+
+// A page sized (and aligned) frame of user memory.
+#[repr(C, align(4096))]
+struct Frame([u8; PAGE_SIZE]);
+
+const _: () = assert!(align_of::<Frame>() == PAGE_SIZE);
+
+// A range of pages mapped with the same permissions, as listed in /proc/<pid>/maps.
+#[derive(Copy, Clone)]
+pub struct Region {
+  pub startingVA: usize,
+  pub endingVA: usize,
+
+  pub bitFlags: PTEBitFlags,
+
+  // Like [stack]. Empty for the program segments.
+  pub name: &'static str,
+}
+
+pub struct AddressSpace {
+  pageTable: UserPageTable,
+
+  // The mapped frames (and their permissions), by their page aligned Virtual Addresses (VAs).
+  frames: BTreeMap<usize, (Box<Frame>, PTEBitFlags)>,
+
+  regions: Vec<Region>,
+}
+
+impl AddressSpace {
+  // Creates an empty address space, with the trampoline and the given trap frame (of the process
+  // it's meant for) mapped.
+  pub fn new(trapFramePA: usize) -> Self {
+    extern "C" {
+      fn _trampoline(); // Points to the _trampoline linker symbol.
+    }
+
+    let mut pageTable = UserPageTable::new();
+    pageTable.mapForKernel(
+      VirtualAddress::new(TRAMPOLINE_VA),
+      PhysicalAddress::new(_trampoline as usize),
+      PAGE_SIZE,
+      PTEBitFlags::R | PTEBitFlags::X,
+    );
+    pageTable.mapForKernel(
+      VirtualAddress::new(TRAP_FRAME_VA),
+      PhysicalAddress::new(trapFramePA),
+      PAGE_SIZE,
+      PTEBitFlags::R | PTEBitFlags::W,
+    );
+
+    Self {
+      pageTable,
+      frames: BTreeMap::new(),
+      regions: Vec::new(),
+    }
+  }
+
+  #[inline]
+  pub fn getPageTable(&self) -> &UserPageTable {
+    &self.pageTable
+  }
+
+  #[inline]
+  pub fn getRegions(&self) -> &[Region] {
+    &self.regions
+  }
+
+  /*
+    Maps zero filled frames over the pages overlapping with the given Virtual Address (VA) range,
+    with the given permissions.
+
+    Adjacent ELF segments may share a page (like the end of the text and the start of the data). A
+    page which is already mapped keeps its frame (and contents), and gets the union of the
+    permissions.
+
+    Fails with OutOfMemory, if a frame (or a Page Table) can't be allocated. The pages mapped till
+    then stay mapped, and are freed along with the address space.
+  */
+  pub fn mapZeroed(
+    &mut self,
+    startingVA: usize,
+    endingVA: usize,
+    bitFlags: PTEBitFlags,
+    name: &'static str,
+  ) -> Result<(), FsError> {
+    let startingVA = startingVA & !(PAGE_SIZE - 1);
+    let endingVA = endingVA.next_multiple_of(PAGE_SIZE);
+    assert!(
+      startingVA < endingVA && endingVA <= MAX_VA,
+      "Invalid Virtual Address (VA) range"
+    );
+
+    for va in (startingVA..endingVA).step_by(PAGE_SIZE) {
+      match self.frames.get_mut(&va) {
+        Some((_, pageBitFlags)) => {
+          *pageBitFlags |= bitFlags;
+          self
+            .pageTable
+            .protect(VirtualAddress::new(va), PAGE_SIZE, *pageBitFlags);
+        }
+
+        None => {
+          let frame = Box::<Frame>::try_new_zeroed().map_err(|_| FsError::OutOfMemory)?;
+          let frame = unsafe { frame.assume_init() };
+          self.pageTable.map(
+            VirtualAddress::new(va),
+            PhysicalAddress::new(&*frame as *const Frame as usize),
+            PAGE_SIZE,
+            bitFlags,
+          )?;
+
+          self.frames.insert(va, (frame, bitFlags));
+        }
+      }
+    }
+
+    self.regions.push(Region {
+      startingVA,
+      endingVA,
+      bitFlags,
+      name,
+    });
+    Ok(())
+  }
+
+  // Copies the given bytes into the address space, starting at the given Virtual Address (VA).
+  // NOTE : Panics if any of the pages isn't mapped.
+  pub fn write(&mut self, va: usize, bytes: &[u8]) {
+    self
+      .forEachChunk(va, bytes.len(), PTEBitFlags::empty(), |chunk, offset| {
+        chunk.copy_from_slice(&bytes[offset..offset + chunk.len()])
+      })
+      .expect("Virtual Address (VA) isn't mapped");
+  }
+
+  // Copies bytes from the user memory at the given Virtual Address (VA), into the given buffer.
+  // Fails with BadAddress, if the user can't read any of those bytes.
+  pub fn copyIn(&mut self, va: usize, buffer: &mut [u8]) -> Result<(), FsError> {
+    self.forEachChunk(va, buffer.len(), PTEBitFlags::R, |chunk, offset| {
+      buffer[offset..offset + chunk.len()].copy_from_slice(chunk)
+    })
+  }
+
+  // Copies the given bytes to the user memory at the given Virtual Address (VA). Fails with
+  // BadAddress, if the user can't write any of those bytes.
+  pub fn copyOut(&mut self, va: usize, bytes: &[u8]) -> Result<(), FsError> {
+    self.forEachChunk(va, bytes.len(), PTEBitFlags::W, |chunk, offset| {
+      chunk.copy_from_slice(&bytes[offset..offset + chunk.len()])
+    })
+  }
+
+  // Copies the NUL terminated string at the given Virtual Address (VA) from the user memory. The
+  // NUL isn't included. Fails with NameTooLong, if the string is longer than the given length.
+  pub fn copyInString(&mut self, va: usize, maxLength: usize) -> Result<Vec<u8>, FsError> {
+    let mut string = Vec::new();
+
+    loop {
+      // Read till the end of the current page, so that we don't touch the next one unless the
+      // string continues into it.
+      let position = va.checked_add(string.len()).ok_or(FsError::BadAddress)?;
+      let chunkLength = PAGE_SIZE - position % PAGE_SIZE;
+
+      let mut chunk = vec![0; chunkLength];
+      self.copyIn(position, &mut chunk)?;
+
+      match chunk.iter().position(|byte| *byte == 0) {
+        Some(length) => string.extend_from_slice(&chunk[..length]),
+        None => string.extend_from_slice(&chunk),
+      }
+
+      if string.len() > maxLength {
+        return Err(FsError::NameTooLong);
+      }
+      if string.len() < position - va + chunkLength {
+        return Ok(string);
+      }
+    }
+  }
+
+  /*
+    Invokes the given function on each part of the given Virtual Address (VA) range, which lies
+    within a single page : along with the bytes of the frame backing that part, the function gets
+    the offset of that part in the range.
+
+    Fails with BadAddress, if any of the pages isn't mapped (with the given permissions).
+  */
+  fn forEachChunk(
+    &mut self,
+    va: usize,
+    length: usize,
+    bitFlags: PTEBitFlags,
+    mut function: impl FnMut(&mut [u8], usize),
+  ) -> Result<(), FsError> {
+    let endingVA = va.checked_add(length).ok_or(FsError::BadAddress)?;
+    if endingVA > MAX_VA {
+      return Err(FsError::BadAddress);
+    }
+
+    let mut offset = 0;
+    while offset < length {
+      let position = va + offset;
+      let offsetInFrame = position % PAGE_SIZE;
+      let chunkLength = (PAGE_SIZE - offsetInFrame).min(length - offset);
+
+      let (frame, pageBitFlags) = self
+        .frames
+        .get_mut(&(position - offsetInFrame))
+        .ok_or(FsError::BadAddress)?;
+      if !pageBitFlags.contains(bitFlags) {
+        return Err(FsError::BadAddress);
+      }
+
+      function(
+        &mut frame.0[offsetInFrame..offsetInFrame + chunkLength],
+        offset,
+      );
+
+      offset += chunkLength;
+    }
+
+    Ok(())
+  }
+}
@@ -1,17 +1,45 @@
-use {super::buddy::BuddyAllocator, crate::locks::spinlock::SpinLock, core::alloc::GlobalAlloc};
+use {
+  super::buddy::BuddyAllocator,
+  crate::locks::spinlock::SpinLock,
+  core::{
+    alloc::GlobalAlloc,
+    sync::atomic::{AtomicUsize, Ordering},
+  },
+};
 
-const DRAM_STARTING_ADDRESS: usize = 0x80000000;
+pub const DRAM_STARTING_ADDRESS: usize = 0x80000000;
 const DRAM_SIZE: usize = 256 * 1024 * 1024; // (256 MB).
-const DRAM_ENDING_ADDRESS: usize = DRAM_STARTING_ADDRESS + DRAM_SIZE;
+pub const DRAM_ENDING_ADDRESS: usize = DRAM_STARTING_ADDRESS + DRAM_SIZE;
 
 pub struct ArnoAllocator {
   buddyAllocator: SpinLock<BuddyAllocator>,
+
+  // Size of the memory (in bytes) handed over to the Buddy allocator, when it was initialized.
+  availableMemorySize: usize,
+
+  // Total size (in bytes) of the live allocations, and their count. The sizes are the requested
+  // ones, so the rounding done by the Buddy allocator isn't accounted for.
+  allocatedMemorySize: AtomicUsize,
+  allocationsCount: AtomicUsize,
+}
+
+// A snapshot of the memory usage.
+#[derive(Debug, Copy, Clone)]
+pub struct MemoryStatistics {
+  pub availableMemorySize: usize,
+  pub allocatedMemorySize: usize,
+  pub allocationsCount: usize,
 }
 
 impl ArnoAllocator {
   pub const fn new() -> Self {
     Self {
       buddyAllocator: SpinLock::new(BuddyAllocator::new()),
+
+      availableMemorySize: 0,
+
+      allocatedMemorySize: AtomicUsize::new(0),
+      allocationsCount: AtomicUsize::new(0),
     }
   }
 
@@ -35,15 +63,46 @@ impl ArnoAllocator {
       16,   // Leaf size = 16 bytes.
       4096, // Max alignment size = 4 KB.
     );
+
+    self.availableMemorySize = DRAM_ENDING_ADDRESS - kernelEndAddress;
+  }
+
+  // Returns the size of the memory (in bytes) that was available for allocations at boot. Useful
+  // for sizing caches.
+  #[inline]
+  pub fn getAvailableMemorySize(&self) -> usize {
+    self.availableMemorySize
+  }
+
+  // Returns a snapshot of the memory usage (for /proc/meminfo).
+  pub fn getStatistics(&self) -> MemoryStatistics {
+    MemoryStatistics {
+      availableMemorySize: self.availableMemorySize,
+      allocatedMemorySize: self.allocatedMemorySize.load(Ordering::Relaxed),
+      allocationsCount: self.allocationsCount.load(Ordering::Relaxed),
+    }
   }
 }
 
 unsafe impl GlobalAlloc for ArnoAllocator {
   unsafe fn alloc(&self, layout: core::alloc::Layout) -> *mut u8 {
-    self.buddyAllocator.acquire().alloc(layout)
+    let pointer = self.buddyAllocator.acquire().alloc(layout);
+
+    if !pointer.is_null() {
+      self
+        .allocatedMemorySize
+        .fetch_add(layout.size(), Ordering::Relaxed);
+      self.allocationsCount.fetch_add(1, Ordering::Relaxed);
+    }
+    pointer
   }
 
   unsafe fn dealloc(&self, ptr: *mut u8, layout: core::alloc::Layout) {
     self.buddyAllocator.acquire().dealloc(ptr, layout);
+
+    self
+      .allocatedMemorySize
+      .fetch_sub(layout.size(), Ordering::Relaxed);
+    self.allocationsCount.fetch_sub(1, Ordering::Relaxed);
   }
 }
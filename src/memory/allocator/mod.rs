@@ -3,6 +3,7 @@ mod buddy;
 mod list;
 mod utils;
 
+pub use allocator::{MemoryStatistics, DRAM_ENDING_ADDRESS, DRAM_STARTING_ADDRESS};
 use allocator::ArnoAllocator;
 
 // Route all default allocation requests to Arno allocator.
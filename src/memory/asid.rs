@@ -0,0 +1,266 @@
+use {
+  crate::{
+    arch::riscv::{qemu::MAX_CORES, registers::satp::Satp},
+    ipi::{self, HartMask},
+    locks::spinlock::SpinLock,
+  },
+  core::sync::atomic::{AtomicUsize, Ordering},
+};
+
+/*
+  Each address space (UserPageTable) gets tagged with an ASID (Address Space Identifier), which the
+  hart writes into the satp register while switching to it. Translations cached in the TLB are
+  tagged with the ASID. So, while context switching, we don't need to flush the TLB : translations
+  belonging to the other address spaces just don't match.
+
+  The number of ASIDs is limited (at most 2^16 in Sv39), so they need to be recycled. We use a
+  generation based scheme (the same as in Linux) :
+
+    (1) An ASID context is the current generation number ORed with an ASID. The generation number
+        lives in the bits above the ASID bits.
+
+    (2) An address space whose ASID context doesn't belong to the current generation, needs to get a
+        new ASID, before it can be switched to.
+
+    (3) When we run out of ASIDs, we start a new generation (this is called a rollover) : all ASIDs
+        become free again (except the ones currently active on some hart - those get reserved), and
+        every hart flushes its whole TLB before switching to another address space.
+
+  REFER : https://github.com/torvalds/linux/blob/master/arch/riscv/mm/context.c.
+*/
+
+// Maximum number of ASID bits a hart can implement in Sv39.
+const MAX_ASID_BITS: usize = 16;
+const MAX_ASID_COUNT: usize = 1 << MAX_ASID_BITS;
+
+// ASID 0 is reserved for the kernel.
+pub const KERNEL_ASID: usize = 0;
+
+// An ASID context with value 0 means no ASID has ever been allocated to the address space.
+pub const NO_ASID_CONTEXT: usize = 0;
+
+pub struct ASIDAllocator {
+  // Number of ASID bits implemented by the harts. Probed by init( ) (or lazily), since the probe is
+  // only meaningful once Virtual Address Translation (VAT) is enabled.
+  asidBits: Option<usize>,
+
+  // Current generation number. Always a multiple of 2^asidBits.
+  generation: usize,
+
+  // Bit i is set, if the ASID i has been allocated in the current generation.
+  usedASIDs: [u64; MAX_ASID_COUNT / 64],
+  // Where we'll start looking for a free ASID from.
+  nextASID: usize,
+
+  // ASID context currently active on each hart.
+  activeASIDContexts: [usize; MAX_CORES],
+  // ASID contexts which were active on each hart during the last rollover. They survive the
+  // rollover, since their translations may still be in use.
+  reservedASIDContexts: [usize; MAX_CORES],
+
+  // Harts which need to flush their whole TLB before switching to another address space, since a
+  // rollover has happened.
+  hartsPendingTLBFlush: HartMask,
+}
+
+impl ASIDAllocator {
+  pub const fn new() -> Self {
+    Self {
+      asidBits: None,
+
+      generation: 0,
+
+      usedASIDs: [0; MAX_ASID_COUNT / 64],
+      nextASID: KERNEL_ASID + 1,
+
+      activeASIDContexts: [NO_ASID_CONTEXT; MAX_CORES],
+      reservedASIDContexts: [NO_ASID_CONTEXT; MAX_CORES],
+
+      hartsPendingTLBFlush: HartMask::empty(),
+    }
+  }
+
+  // Returns the number of ASID bits implemented by the harts. Probes them if not done yet.
+  fn getASIDBits(&mut self) -> usize {
+    match self.asidBits {
+      Some(asidBits) => asidBits,
+
+      None => {
+        let asidBits = unsafe { Satp.probeASIDBits() };
+
+        self.asidBits = Some(asidBits);
+        self.startGeneration(1 << asidBits);
+
+        asidBits
+      }
+    }
+  }
+
+  #[inline]
+  fn getASIDCount(&mut self) -> usize {
+    1 << self.getASIDBits()
+  }
+
+  // Returns whether the given ASID context belongs to the current generation.
+  #[inline]
+  fn isFromCurrentGeneration(&mut self, asidContext: usize) -> bool {
+    let asidCount = self.getASIDCount();
+    (asidContext & !(asidCount - 1)) == self.generation
+  }
+
+  #[inline]
+  fn isASIDUsed(&self, asid: usize) -> bool {
+    (self.usedASIDs[asid / 64] & (1 << (asid % 64))) != 0
+  }
+
+  #[inline]
+  fn markASIDUsed(&mut self, asid: usize) {
+    self.usedASIDs[asid / 64] |= 1 << (asid % 64);
+  }
+
+  // Switches to the given generation, marking all ASIDs (except the kernel's) as free.
+  fn startGeneration(&mut self, generation: usize) {
+    self.generation = generation;
+
+    self.usedASIDs.fill(0);
+    self.markASIDUsed(KERNEL_ASID);
+    self.nextASID = KERNEL_ASID + 1;
+  }
+
+  // Starts a new generation, since we've run out of ASIDs.
+  fn rollover(&mut self) {
+    let asidCount = self.getASIDCount();
+
+    self.startGeneration(self.generation + asidCount);
+
+    // The ASIDs currently active on the harts get reserved, since those harts are still using them.
+    for hartID in 0..MAX_CORES {
+      let activeASIDContext = self.activeASIDContexts[hartID];
+      if activeASIDContext != NO_ASID_CONTEXT {
+        self.reservedASIDContexts[hartID] = activeASIDContext;
+      }
+
+      let reservedASIDContext = self.reservedASIDContexts[hartID];
+      if reservedASIDContext != NO_ASID_CONTEXT {
+        self.markASIDUsed(reservedASIDContext & (asidCount - 1));
+      }
+    }
+
+    // The freed ASIDs may still have stale translations cached in the TLBs.
+    self.hartsPendingTLBFlush = ipi::getOnlineHarts();
+  }
+
+  // Returns an ASID context from the current generation, for an address space whose ASID context
+  // is outdated.
+  fn allocateASIDContext(&mut self, outdatedASIDContext: usize) -> usize {
+    let asidCount = self.getASIDCount();
+
+    // CASE : The harts don't implement ASIDs. Every address space shares the kernel's ASID, and the
+    // TLB gets flushed on every switch.
+    if asidCount == 1 {
+      return self.generation | KERNEL_ASID;
+    }
+
+    if outdatedASIDContext != NO_ASID_CONTEXT {
+      let asid = outdatedASIDContext & (asidCount - 1);
+      let newASIDContext = self.generation | asid;
+
+      // If the ASID was reserved during the last rollover, the address space keeps using it.
+      let mut isReserved = false;
+      for reservedASIDContext in self.reservedASIDContexts.iter_mut() {
+        if *reservedASIDContext == outdatedASIDContext {
+          *reservedASIDContext = newASIDContext;
+          isReserved = true;
+        }
+      }
+      if isReserved {
+        return newASIDContext;
+      }
+
+      // Try to keep using the same ASID, if nobody has taken it in the current generation.
+      if !self.isASIDUsed(asid) {
+        self.markASIDUsed(asid);
+        return newASIDContext;
+      }
+    }
+
+    // Look for a free ASID, starting from nextASID and wrapping around. On failure, do a rollover
+    // and look again.
+    for _ in 0..2 {
+      for asid in (self.nextASID..asidCount).chain(KERNEL_ASID + 1..self.nextASID) {
+        if !self.isASIDUsed(asid) {
+          self.markASIDUsed(asid);
+          self.nextASID = asid + 1;
+
+          return self.generation | asid;
+        }
+      }
+
+      self.rollover();
+    }
+
+    // CASE : All the ASIDs are reserved, even after a rollover (possible when the harts implement
+    // very few ASID bits). We fall back to sharing the kernel's ASID, and make every hart flush its
+    // whole TLB.
+    self.hartsPendingTLBFlush = ipi::getOnlineHarts();
+    self.generation | KERNEL_ASID
+  }
+}
+
+pub static ASID_ALLOCATOR: SpinLock<ASIDAllocator> = SpinLock::new(ASIDAllocator::new());
+
+// Probes the number of ASID bits implemented by the harts.
+// NOTE : Must be invoked once (by hart 0), after Virtual Address Translation (VAT) has been
+// enabled.
+pub fn init() {
+  let asidBits = ASID_ALLOCATOR.acquire().getASIDBits();
+  println!("INFO : Harts implement {} ASID bits", asidBits);
+}
+
+// Returned by assignASIDContext( ).
+pub struct ASIDAssignment {
+  pub asidContext: usize,
+  pub asid: usize,
+
+  // Whether the current hart must flush its whole TLB, after writing the ASID into satp.
+  pub mustFlushTLB: bool,
+}
+
+// Makes sure the given ASID context (of the address space, which the current hart is switching to)
+// belongs to the current generation, allocating a new one if required. Records it as the active
+// ASID context of the current hart.
+pub fn assignASIDContext(asidContext: &AtomicUsize, hartID: usize) -> ASIDAssignment {
+  let mut asidAllocator = ASID_ALLOCATOR.acquire();
+
+  let mut currentASIDContext = asidContext.load(Ordering::Relaxed);
+  if !asidAllocator.isFromCurrentGeneration(currentASIDContext) {
+    currentASIDContext = asidAllocator.allocateASIDContext(currentASIDContext);
+    asidContext.store(currentASIDContext, Ordering::Relaxed);
+  }
+
+  asidAllocator.activeASIDContexts[hartID] = currentASIDContext;
+
+  let asidCount = asidAllocator.getASIDCount();
+  let asid = currentASIDContext & (asidCount - 1);
+
+  // NOTE : Address spaces sharing the kernel's ASID can't be told apart in the TLB.
+  let mustFlushTLB = asidAllocator.hartsPendingTLBFlush.contains(hartID) || (asid == KERNEL_ASID);
+  asidAllocator.hartsPendingTLBFlush.remove(hartID);
+
+  ASIDAssignment {
+    asidContext: currentASIDContext,
+    asid,
+    mustFlushTLB,
+  }
+}
+
+// Returns the ASID of the given ASID context, if it belongs to the current generation (meaning, no
+// other address space is using that ASID).
+pub fn getLiveASID(asidContext: usize) -> Option<usize> {
+  let mut asidAllocator = ASID_ALLOCATOR.acquire();
+
+  match asidContext != NO_ASID_CONTEXT && asidAllocator.isFromCurrentGeneration(asidContext) {
+    true => Some(asidContext & (asidAllocator.getASIDCount() - 1)),
+    false => None,
+  }
+}
@@ -1,3 +1,6 @@
 pub mod address;
+pub mod address_space;
 pub mod allocator;
+pub mod asid;
 pub mod page_table;
+pub mod tlb;
@@ -47,4 +47,31 @@ impl PageTableEntry {
   pub fn setPhysicalAddress(&mut self, physicalAddress: usize, bitFlags: PTEBitFlags) {
     self.0 = ((physicalAddress >> 12) << 10) | (bitFlags | PTEBitFlags::V).bits();
   }
+
+  // Returns whether the Page Table Entry (PTE) is a leaf PTE, i.e., it points to a Physical Page
+  // and not to the next level of the Page Table.
+  #[inline]
+  pub fn isLeaf(&self) -> bool {
+    self
+      .getBitFlags()
+      .intersects(PTEBitFlags::R | PTEBitFlags::W | PTEBitFlags::X)
+  }
+
+  #[inline]
+  pub fn getBitFlags(&self) -> PTEBitFlags {
+    PTEBitFlags::from_bits_truncate(self.0)
+  }
+
+  // Replaces the bit flags of the Page Table Entry (PTE), keeping it pointed to the same Physical
+  // Page.
+  // NOTE : The V bit is turned on regardless of whatever value is passed via the bit flags.
+  pub fn setBitFlags(&mut self, bitFlags: PTEBitFlags) {
+    self.setPhysicalAddress(self.toPhysicalAddress(), bitFlags);
+  }
+
+  // Invalidates the Page Table Entry (PTE), by clearing all its bits.
+  #[inline]
+  pub fn invalidate(&mut self) {
+    self.0 = 0;
+  }
 }
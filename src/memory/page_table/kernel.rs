@@ -1,3 +1,127 @@
-use super::PageTable;
+use {
+  super::{entry::PTEBitFlags, PageTable},
+  crate::{
+    arch::riscv::{
+      qemu::PAGE_SIZE,
+      registers::{satp::Satp, tp::Tp},
+    },
+    drivers::{
+      clint::CLINT_BASE_REGISTER,
+      plic::PLIC_BASE_REGISTER,
+      uart::UART_BASE_REGISTER,
+      virtio::{VIRTIO_MMIO_BASE_REGISTER, VIRTIO_MMIO_SLOTS_COUNT, VIRTIO_MMIO_SLOT_SIZE},
+    },
+    memory::{
+      address::{physical::PhysicalAddress, r#virtual::VirtualAddress, Address},
+      address_space::TRAMPOLINE_VA,
+      allocator::{DRAM_ENDING_ADDRESS, DRAM_STARTING_ADDRESS},
+      asid::KERNEL_ASID,
+      tlb,
+    },
+  },
+  core::{
+    hint,
+    ptr::{addr_of, addr_of_mut},
+    sync::atomic::{AtomicBool, Ordering},
+  },
+};
 
+/*
+  The kernel's Page Table. Apart from the trampoline, everything is identity mapped (the Virtual
+  Address is the same as the Physical Address). So, Physical Addresses (like the ones the allocator
+  hands out) keep working after Virtual Address Translation (VAT) gets enabled.
+
+  Layout of the mappings :
+
+    [ CLINT | PLIC | UART | virtio devices | kernel code (RX) | rest of the DRAM (RW) | trampoline ]
+
+  The trampoline is mapped at the top, at the same Virtual Address (VA) as in the user Page Tables.
+
+  NOTE : The Page Table is never modified after it's built, and it's tagged with the kernel's ASID.
+  So, no TLB shootdowns are ever required for it.
+*/
 pub static mut KERNEL_PAGE_TABLE: PageTable = PageTable::empty();
+
+// Size of the PLIC's memory mapped registers.
+const PLIC_SIZE: usize = 0x40_0000;
+
+// Size of the CLINT's memory mapped registers.
+const CLINT_SIZE: usize = 0x1_0000;
+
+// Set once the kernel Page Table has been built.
+static IS_KERNEL_PAGE_TABLE_BUILT: AtomicBool = AtomicBool::new(false);
+
+// Builds the kernel Page Table.
+// NOTE : Must be invoked only once (by hart 0), after the allocator has been initialized.
+pub unsafe fn init() {
+  extern "C" {
+    fn _trampoline(); // Points to the _trampoline linker symbol.
+    fn _textEndAddress(); // Points to the _textEndAddress linker symbol.
+  }
+
+  let kernelPageTable = &mut *addr_of_mut!(KERNEL_PAGE_TABLE);
+  let mut identityMap = |startingAddress: usize, endingAddress: usize, bitFlags: PTEBitFlags| {
+    kernelPageTable.map(
+      VirtualAddress::new(startingAddress),
+      PhysicalAddress::new(startingAddress),
+      endingAddress - startingAddress,
+      bitFlags,
+    )
+  };
+
+  let readWrite = PTEBitFlags::R | PTEBitFlags::W;
+
+  identityMap(
+    CLINT_BASE_REGISTER,
+    CLINT_BASE_REGISTER + CLINT_SIZE,
+    readWrite,
+  );
+  identityMap(
+    PLIC_BASE_REGISTER,
+    PLIC_BASE_REGISTER + PLIC_SIZE,
+    readWrite,
+  );
+  identityMap(
+    UART_BASE_REGISTER,
+    UART_BASE_REGISTER + PAGE_SIZE,
+    readWrite,
+  );
+  identityMap(
+    VIRTIO_MMIO_BASE_REGISTER,
+    VIRTIO_MMIO_BASE_REGISTER + VIRTIO_MMIO_SLOTS_COUNT * VIRTIO_MMIO_SLOT_SIZE,
+    readWrite,
+  );
+
+  let textEndAddress = _textEndAddress as usize;
+  identityMap(
+    DRAM_STARTING_ADDRESS,
+    textEndAddress,
+    PTEBitFlags::R | PTEBitFlags::X,
+  );
+  identityMap(textEndAddress, DRAM_ENDING_ADDRESS, readWrite);
+
+  kernelPageTable.map(
+    VirtualAddress::new(TRAMPOLINE_VA),
+    PhysicalAddress::new(_trampoline as usize),
+    PAGE_SIZE,
+    PTEBitFlags::R | PTEBitFlags::X,
+  );
+
+  IS_KERNEL_PAGE_TABLE_BUILT.store(true, Ordering::Release);
+}
+
+// Switches the current hart to the kernel Page Table, once hart 0 has built it.
+// NOTE : Should be invoked once on every hart, while the Kernel is initializing.
+pub unsafe fn initHart() {
+  while !IS_KERNEL_PAGE_TABLE_BUILT.load(Ordering::Acquire) {
+    hint::spin_loop();
+  }
+
+  Satp.enableVirtualAddressTranslation(addr_of!(KERNEL_PAGE_TABLE) as usize, KERNEL_ASID);
+  tlb::flushLocalTLB(None);
+
+  println!(
+    "INFO : Enabled Virtual Address Translation on hart {}",
+    Tp.read()
+  );
+}
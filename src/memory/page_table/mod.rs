@@ -1,15 +1,19 @@
 pub mod entry;
 pub mod kernel;
+pub mod user;
 
 use {
-  super::address::{physical::PhysicalAddress, r#virtual::VirtualAddress},
-  crate::memory::address::Address,
+  super::{
+    address::{physical::PhysicalAddress, r#virtual::VirtualAddress},
+    tlb::{self, TLBShootdownScope},
+  },
+  crate::{fs::vfs::FsError, memory::address::Address},
   alloc::boxed::Box,
   entry::{PTEBitFlags, PageTableEntry},
 };
 
 const TOTAL_PAGE_COUNT: usize = 512;
-const MAX_VA: usize = 1 << (9 + 9 + 9 + 12 - 1); // TODO : Understand.
+pub const MAX_VA: usize = 1 << (9 + 9 + 9 + 12 - 1); // TODO : Understand.
 
 /*
   When RV64, three paged virtual-memory schemes are defined: Sv39, Sv48, and Sv57.
@@ -59,6 +63,20 @@ impl PageTable {
     rangeSize: usize, // (in bytes)
     bitFlags: PTEBitFlags,
   ) {
+    self
+      .tryMap(startingVA, startingPA, rangeSize, bitFlags)
+      .expect("Failed allocating a child Page Table");
+  }
+
+  // Same as map( ), except that it fails with OutOfMemory, if a child Page Table can't be
+  // allocated. The pages mapped till then stay mapped.
+  fn tryMap(
+    &mut self,
+    startingVA: VirtualAddress,
+    startingPA: PhysicalAddress,
+    rangeSize: usize, // (in bytes)
+    bitFlags: PTEBitFlags,
+  ) -> Result<(), FsError> {
     assert!(rangeSize > 0, "Memory range size must be more than 0");
 
     // TODO : Page align the starting VA and rangeSize.
@@ -68,7 +86,7 @@ impl PageTable {
 
     let (mut currentVA, mut currentPA) = (startingVA, startingPA);
     while currentVA != endingVA {
-      let leafPTE = self.getLeafPTE(currentVA);
+      let leafPTE = self.getLeafPTE(currentVA)?;
 
       assert!(!leafPTE.isValid(), "Virtual Address (VA) is already mapped");
       leafPTE.setPhysicalAddress(currentPA.asUsize(), bitFlags);
@@ -76,13 +94,16 @@ impl PageTable {
       currentVA.increaseByAPage();
       currentPA.increaseByAPage();
     }
+
+    Ok(())
   }
 
   // Walks the Page Table and returns the leaf Page Table Entry (PTE) corresponding to the given
   // Virtual Address (VA).
-  fn getLeafPTE(&mut self, va: VirtualAddress) -> &mut PageTableEntry {
+  // Fails with OutOfMemory, if a missing child Page Table can't be allocated.
+  fn getLeafPTE(&mut self, va: VirtualAddress) -> Result<&mut PageTableEntry, FsError> {
     assert!(
-      va.asUsize() > MAX_VA,
+      va.asUsize() < MAX_VA,
       "Virtual Address (VA) is greater than the allowed maximum"
     );
 
@@ -99,7 +120,9 @@ impl PageTable {
         // currently doesn't exist.
         false => {
           // So, we'll first create the child PTE.
-          let createdChildPTE = unsafe { Box::into_raw(Box::new_zeroed().assume_init()) };
+          let createdChildPTE = Box::<PageTable>::try_new_zeroed()
+            .map(|childPageTable| Box::into_raw(unsafe { childPageTable.assume_init() }))
+            .map_err(|_| FsError::OutOfMemory)?;
           //
           // Then make the current PTE point to that child PTE.
           // NOTE : When all of the R, W and X bits are zero, the PTE is a pointer to the next
@@ -113,6 +136,112 @@ impl PageTable {
       }
     }
 
-    unsafe { &mut (*currentNode).entries[va.getCorrespondingPPN(0)] }
+    Ok(unsafe { &mut (*currentNode).entries[va.getCorrespondingPPN(0)] })
+  }
+}
+
+impl PageTable {
+  // Unmaps the pages present in the given Virtual Address (VA) space range, and then flushes the
+  // stale translations from the TLBs of the harts in the given TLB shootdown scope.
+  // NOTE : Freeing the Physical Pages (the pages were mapped to) is up to the invoker.
+  pub fn unmap(
+    &mut self,
+    startingVA: VirtualAddress,
+    rangeSize: usize, // (in bytes)
+    tlbShootdownScope: TLBShootdownScope,
+  ) {
+    self.forEachLeafPTE(startingVA, rangeSize, |leafPTE| leafPTE.invalidate());
+
+    tlb::shootdown(tlbShootdownScope, startingVA, rangeSize);
+  }
+
+  // Changes the bit flags of the Page Table Entries (PTEs) mapping the pages present in the given
+  // Virtual Address (VA) space range, and then flushes the stale translations from the TLBs of the
+  // harts in the given TLB shootdown scope.
+  pub fn protect(
+    &mut self,
+    startingVA: VirtualAddress,
+    rangeSize: usize, // (in bytes)
+    bitFlags: PTEBitFlags,
+    tlbShootdownScope: TLBShootdownScope,
+  ) {
+    self.forEachLeafPTE(startingVA, rangeSize, |leafPTE| {
+      leafPTE.setBitFlags(bitFlags)
+    });
+
+    tlb::shootdown(tlbShootdownScope, startingVA, rangeSize);
+  }
+
+  // Invokes the given function on each of the leaf Page Table Entries (PTEs) mapping the pages
+  // present in the given Virtual Address (VA) space range.
+  // NOTE : Panics if any of those pages isn't mapped.
+  fn forEachLeafPTE(
+    &mut self,
+    startingVA: VirtualAddress,
+    rangeSize: usize, // (in bytes)
+    mut function: impl FnMut(&mut PageTableEntry),
+  ) {
+    assert!(rangeSize > 0, "Memory range size must be more than 0");
+
+    let endingVA = VirtualAddress::new(startingVA.asUsize() + rangeSize);
+
+    let mut currentVA = startingVA;
+    while currentVA.asUsize() < endingVA.asUsize() {
+      let leafPTE = self
+        .findLeafPTE(currentVA)
+        .expect("Virtual Address (VA) isn't mapped");
+
+      function(leafPTE);
+
+      currentVA.increaseByAPage();
+    }
+  }
+
+  // Walks the Page Table and returns the valid leaf Page Table Entry (PTE) corresponding to the
+  // given Virtual Address (VA). Unlike getLeafPTE( ), doesn't create any missing child PTEs.
+  fn findLeafPTE(&mut self, va: VirtualAddress) -> Option<&mut PageTableEntry> {
+    assert!(
+      va.asUsize() < MAX_VA,
+      "Virtual Address (VA) is greater than the allowed maximum"
+    );
+
+    let mut currentNode = self as *mut PageTable;
+
+    for level in (1..=2).rev() {
+      let currentPTE = unsafe { &mut (*currentNode).entries[va.getCorrespondingPPN(level)] };
+      if !currentPTE.isValid() || currentPTE.isLeaf() {
+        return None;
+      }
+
+      currentNode = currentPTE.toPhysicalAddress() as *mut PageTable;
+    }
+
+    let leafPTE = unsafe { &mut (*currentNode).entries[va.getCorrespondingPPN(0)] };
+    match leafPTE.isValid() && leafPTE.isLeaf() {
+      true => Some(leafPTE),
+      false => None,
+    }
+  }
+}
+
+impl PageTable {
+  // Frees the child Page Tables (the non leaf levels), which were created by getLeafPTE( ). The
+  // given level is the level of this Page Table.
+  // NOTE : Freeing the Physical Pages (the leaf PTEs point to) is up to the invoker.
+  fn freeChildPageTables(&mut self, level: usize) {
+    if level == 0 {
+      return;
+    }
+
+    for pte in self.entries.iter_mut() {
+      if !pte.isValid() || pte.isLeaf() {
+        continue;
+      }
+
+      let mut childPageTable = unsafe { Box::from_raw(pte.toPhysicalAddress() as *mut PageTable) };
+      childPageTable.freeChildPageTables(level - 1);
+
+      pte.invalidate();
+    }
   }
 }
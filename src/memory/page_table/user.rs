@@ -0,0 +1,151 @@
+use {
+  super::{entry::PTEBitFlags, PageTable},
+  crate::{
+    arch::riscv::registers::{satp::Satp, tp::Tp},
+    fs::vfs::FsError,
+    ipi::HartMask,
+    memory::{
+      address::{physical::PhysicalAddress, r#virtual::VirtualAddress},
+      asid::{self, NO_ASID_CONTEXT},
+      tlb::{self, TLBShootdownScope},
+    },
+  },
+  alloc::boxed::Box,
+  core::sync::atomic::{AtomicUsize, Ordering},
+};
+
+// The Page Table of a user process (its address space), tagged with an ASID.
+// REFER : ../asid.rs for how ASIDs get assigned.
+pub struct UserPageTable {
+  pageTable: Box<PageTable>,
+
+  // Generation number ORed with the ASID, assigned the last time a hart switched to this address
+  // space.
+  asidContext: AtomicUsize,
+
+  // Harts which have switched to this address space, and thus might have its translations cached
+  // in their TLBs.
+  activeHarts: AtomicUsize,
+}
+
+impl UserPageTable {
+  pub fn new() -> Self {
+    Self {
+      pageTable: unsafe { Box::new_zeroed().assume_init() },
+
+      asidContext: AtomicUsize::new(NO_ASID_CONTEXT),
+      activeHarts: AtomicUsize::new(0),
+    }
+  }
+
+  // Returns the Physical Address (PA) of the root Page Table.
+  #[inline]
+  pub fn getRootPhysicalAddress(&self) -> usize {
+    &*self.pageTable as *const PageTable as usize
+  }
+
+  // Maps the pages present in the given Virtual Address (VA) space range to the pages present in
+  // the given Physical Address (PA) space range.
+  // Fails with OutOfMemory, if a child Page Table can't be allocated.
+  pub fn map(
+    &mut self,
+    startingVA: VirtualAddress,
+    startingPA: PhysicalAddress,
+    rangeSize: usize, // (in bytes)
+    bitFlags: PTEBitFlags,
+  ) -> Result<(), FsError> {
+    self
+      .pageTable
+      .tryMap(startingVA, startingPA, rangeSize, bitFlags | PTEBitFlags::U)
+  }
+
+  // Same as map( ), except that the pages are only accessible from S-mode (like the trap frame).
+  // NOTE : Panics if a child Page Table can't be allocated.
+  pub fn mapForKernel(
+    &mut self,
+    startingVA: VirtualAddress,
+    startingPA: PhysicalAddress,
+    rangeSize: usize, // (in bytes)
+    bitFlags: PTEBitFlags,
+  ) {
+    self
+      .pageTable
+      .map(startingVA, startingPA, rangeSize, bitFlags);
+  }
+
+  // Unmaps the pages present in the given Virtual Address (VA) space range.
+  pub fn unmap(&mut self, startingVA: VirtualAddress, rangeSize: usize) {
+    let tlbShootdownScope = self.getTLBShootdownScope();
+    self
+      .pageTable
+      .unmap(startingVA, rangeSize, tlbShootdownScope);
+  }
+
+  // Changes the bit flags of the pages present in the given Virtual Address (VA) space range.
+  pub fn protect(&mut self, startingVA: VirtualAddress, rangeSize: usize, bitFlags: PTEBitFlags) {
+    let tlbShootdownScope = self.getTLBShootdownScope();
+    self.pageTable.protect(
+      startingVA,
+      rangeSize,
+      bitFlags | PTEBitFlags::U,
+      tlbShootdownScope,
+    );
+  }
+
+  // Only the harts which have switched to this address space, can have its translations cached.
+  fn getTLBShootdownScope(&self) -> TLBShootdownScope {
+    let harts = HartMask(self.activeHarts.load(Ordering::Acquire));
+
+    match asid::getLiveASID(self.asidContext.load(Ordering::Relaxed)) {
+      Some(asid) => TLBShootdownScope {
+        harts,
+        asid: Some(asid),
+      },
+
+      // The ASID is outdated (a rollover has happened). A hart might still be running with it
+      // though (if it was reserved during the rollover), so we flush under all ASIDs.
+      None => TLBShootdownScope { harts, asid: None },
+    }
+  }
+
+  /*
+    Prepares the current hart for switching to this address space. Returns the value of the satp
+    register (the root Page Table and the ASID), which the trampoline writes while returning to
+    user mode.
+
+    The TLB only gets flushed, if an ASID rollover has happened since this hart last flushed it
+    (or if the harts don't implement ASIDs).
+  */
+  pub fn activate(&self) -> Activation {
+    let hartID = unsafe { Tp.read() };
+
+    let asidAssignment = asid::assignASIDContext(&self.asidContext, hartID);
+
+    self.activeHarts.fetch_or(1 << hartID, Ordering::Release);
+
+    if asidAssignment.mustFlushTLB {
+      unsafe { tlb::flushLocalTLB(None) };
+    }
+
+    Activation {
+      satp: Satp::getSv39Bits(self.getRootPhysicalAddress(), asidAssignment.asid),
+      isUsingKernelASID: asidAssignment.asid == asid::KERNEL_ASID,
+    }
+  }
+}
+
+// Returned by UserPageTable::activate( ).
+pub struct Activation {
+  pub satp: usize,
+
+  // Whether the address space shares the kernel's ASID. Its translations then can't be told apart
+  // from the kernel's in the TLB, so the TLB must be flushed while switching between the two.
+  pub isUsingKernelASID: bool,
+}
+
+impl Drop for UserPageTable {
+  fn drop(&mut self) {
+    // NOTE : Level 2 is the topmost level.
+    self.pageTable.freeChildPageTables(2);
+  }
+}
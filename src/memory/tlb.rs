@@ -0,0 +1,120 @@
+use {
+  super::address::{r#virtual::VirtualAddress, Address},
+  crate::{
+    arch::riscv::qemu::PAGE_SIZE,
+    ipi::{self, HartMask},
+  },
+  core::arch::asm,
+};
+
+/*
+  The TLB (Translation Lookaside Buffer) caches Virtual Address (VA) to Physical Address (PA)
+  translations. Each hart has its own TLB, and it isn't kept coherent with the Page Tables by the
+  hardware.
+
+  So after a hart unmaps a page / changes the permissions of a page, the other harts (which might be
+  using the same Page Table) still have the stale translation cached in their TLBs. To get rid of
+  it, the hart modifying the Page Table, must make each of those harts execute an SFENCE.VMA
+  instruction. This is called a TLB shootdown.
+
+  REFER : section 10.2.1 in privileged ISA manual.
+*/
+
+// If a TLB shootdown covers more pages than this, then we flush the whole TLB (for the address
+// space), instead of flushing page by page.
+const PAGE_BY_PAGE_FLUSH_THRESHOLD: usize = 32;
+
+// Describes which harts might have cached translations from a Page Table, and which address space
+// (ASID) those translations are tagged with.
+#[derive(Copy, Clone)]
+pub struct TLBShootdownScope {
+  pub harts: HartMask,
+
+  // None means the translations must be flushed for all address spaces.
+  pub asid: Option<usize>,
+}
+
+impl TLBShootdownScope {
+  // The Page Table might be in use by any of the online harts, under any address space.
+  pub fn global() -> Self {
+    Self {
+      harts: ipi::getOnlineHarts(),
+      asid: None,
+    }
+  }
+}
+
+struct TLBShootdownRequest {
+  asid: Option<usize>,
+
+  // Page aligned range of Virtual Addresses (VAs), whose translations need to be flushed.
+  startingVA: usize,
+  endingVA: usize,
+}
+
+impl TLBShootdownRequest {
+  // Flushes the requested translations from the TLB of the current hart.
+  fn executeOnCurrentHart(&self) {
+    let pageCount = (self.endingVA - self.startingVA) / PAGE_SIZE;
+    if pageCount > PAGE_BY_PAGE_FLUSH_THRESHOLD {
+      return unsafe { flushLocalTLB(self.asid) };
+    }
+
+    let mut va = self.startingVA;
+    while va < self.endingVA {
+      unsafe { flushLocalTLBPage(va, self.asid) };
+      va += PAGE_SIZE;
+    }
+  }
+}
+
+// Flushes the translations of the pages in the given Virtual Address (VA) range, from the TLBs of
+// all the harts in the given scope. Returns only after all of them are done.
+// NOTE : Must be invoked after the Page Table has been modified.
+pub fn shootdown(scope: TLBShootdownScope, startingVA: VirtualAddress, rangeSize: usize) {
+  let startingVA = startingVA.asUsize();
+
+  let request = TLBShootdownRequest {
+    asid: scope.asid,
+
+    startingVA: startingVA & !(PAGE_SIZE - 1),
+    endingVA: (startingVA + rangeSize).next_multiple_of(PAGE_SIZE),
+  };
+
+  ipi::callOnHarts(
+    scope.harts,
+    |argument| unsafe { (*(argument as *const TLBShootdownRequest)).executeOnCurrentHart() },
+    &request as *const TLBShootdownRequest as usize,
+  );
+}
+
+/*
+  SFENCE.VMA rs1, rs2 :
+
+    (1) If rs1 = x0, then the fence applies to all Virtual Addresses. Otherwise, only to the leaf
+        Page Table Entries (PTEs) corresponding to the Virtual Address in rs1.
+
+    (2) If rs2 = x0, then the fence applies to all address spaces (including global mappings).
+        Otherwise, only to the address space identified by the ASID in rs2 (global mappings are
+        excluded).
+*/
+
+// Flushes the translation of the page containing the given Virtual Address (VA), from the TLB of
+// the current hart.
+#[inline]
+pub unsafe fn flushLocalTLBPage(va: usize, asid: Option<usize>) {
+  match asid {
+    Some(asid) => asm!("sfence.vma {}, {}", in(reg)va, in(reg)asid),
+    None => asm!("sfence.vma {}, zero", in(reg)va),
+  }
+}
+
+// Flushes all translations (belonging to the given address space), from the TLB of the current
+// hart.
+#[inline]
+pub unsafe fn flushLocalTLB(asid: Option<usize>) {
+  match asid {
+    Some(asid) => asm!("sfence.vma zero, {}", in(reg)asid),
+    None => asm!("sfence.vma zero, zero"),
+  }
+}
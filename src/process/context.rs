@@ -0,0 +1,28 @@
+// Registers saved while switching from one kernel execution context (a process's kernel thread or
+// a CPU core's scheduler) to another.
+// Only the callee-saved registers (and ra, sp) need to be saved. The invoker of switchContext( )
+// has already saved the caller-saved ones on its stack.
+// NOTE : The layout must match the offsets used in ../asm/switch.S.
+#[repr(C)]
+pub struct Context {
+  pub ra: usize,
+  pub sp: usize,
+
+  // s0 - s11.
+  pub s: [usize; 12],
+}
+
+impl Context {
+  pub const fn new() -> Self {
+    Self {
+      ra: 0,
+      sp: 0,
+      s: [0; 12],
+    }
+  }
+}
+
+extern "C" {
+  // Defined in ../asm/switch.S.
+  pub fn switchContext(old: *mut Context, new: *const Context);
+}
@@ -1,6 +1,17 @@
-use {super::cpu::CPU, crate::arch::riscv::registers::sstatus::Sstatus};
+use {
+  super::{context::Context, cpu::CPU, process::Process},
+  crate::arch::riscv::registers::sstatus::Sstatus,
+  core::ptr,
+};
 
 pub struct Core {
+  // The process currently running on this CPU core. Null if none.
+  pub process: *mut Process,
+
+  // Execution context of the scheduler running on this CPU core. We switch to it, whenever the
+  // current process gives up the CPU core.
+  pub schedulerContext: Context,
+
   /*
     Each time while entering an interrupts-disabled section, we :
 
@@ -23,13 +34,16 @@ pub struct Core {
 
     REFER : https://www.youtube.com/watch?v=gQdflOUZQvA.
   */
-  noff: usize,
-  intena: bool,
+  pub(super) noff: usize,
+  pub(super) intena: bool,
 }
 
 impl Core {
   pub const fn new() -> Self {
     Self {
+      process: ptr::null_mut(),
+      schedulerContext: Context::new(),
+
       noff: 0,
       intena: false,
     }
@@ -83,7 +97,7 @@ impl Core {
       // Enable interrupts if they were enabled before entering the outermost interrupts-disabled
       // section.
       if core.intena {
-        unsafe { Sstatus.disableInterrupts() };
+        unsafe { Sstatus.enableInterrupts() };
       }
     }
   }
@@ -0,0 +1,142 @@
+use {crate::fs::vfs::FsError, alloc::vec::Vec};
+
+/*
+  ELF (Executable and Linkable Format) is the format of the executables we run :
+
+    [ ELF header | program headers | ... segments ... | section headers ]
+
+  The ELF header identifies the file (class, byte order, target machine) and locates the program
+  headers. Each program header describes a segment. The loadable (PT_LOAD) segments get mapped
+  into the address space of the process : p_filesz bytes are copied from the file, and the rest of
+  the p_memsz bytes (the .bss) are zero filled.
+
+  Only statically linked RISC-V ELF64 (little endian) executables are supported.
+
+  REFER : https://refspecs.linuxfoundation.org/elf/gabi4+/contents.html and
+  https://github.com/riscv-non-isa/riscv-elf-psabi-doc.
+*/
+
+pub const ELF_HEADER_SIZE: usize = 64;
+pub const PROGRAM_HEADER_SIZE: usize = 56;
+
+const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const EV_CURRENT: u8 = 1;
+
+const ET_EXEC: u16 = 2;
+const EM_RISCV: u16 = 243;
+
+// Upper limit on the number of program headers, so that a corrupt ELF header can't make us allocate
+// a huge buffer.
+const MAX_PROGRAM_HEADERS_COUNT: usize = 64;
+
+// Program header types.
+pub const PT_LOAD: u32 = 1;
+pub const PT_INTERP: u32 = 3;
+
+// Program header flags.
+pub const PF_X: u32 = 1 << 0;
+pub const PF_W: u32 = 1 << 1;
+pub const PF_R: u32 = 1 << 2;
+
+#[inline]
+fn readU16(bytes: &[u8], offset: usize) -> u16 {
+  u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+#[inline]
+fn readU32(bytes: &[u8], offset: usize) -> u32 {
+  u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[inline]
+fn readU64(bytes: &[u8], offset: usize) -> u64 {
+  u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct ElfHeader {
+  pub entryPoint: u64,
+
+  // File offset and number of the program headers.
+  pub programHeadersOffset: u64,
+  pub programHeadersCount: usize,
+}
+
+impl ElfHeader {
+  // Parses and validates the ELF header, at the start of the given bytes.
+  pub fn parse(bytes: &[u8]) -> Result<Self, FsError> {
+    if bytes.len() < ELF_HEADER_SIZE || bytes[..4] != ELF_MAGIC {
+      return Err(FsError::NotExecutable);
+    }
+
+    let (class, byteOrder, version) = (bytes[4], bytes[5], bytes[6]);
+    if class != ELFCLASS64 || byteOrder != ELFDATA2LSB || version != EV_CURRENT {
+      return Err(FsError::NotExecutable);
+    }
+
+    if readU16(bytes, 16) != ET_EXEC || readU16(bytes, 18) != EM_RISCV {
+      return Err(FsError::NotExecutable);
+    }
+
+    let programHeaderSize = readU16(bytes, 54) as usize;
+    let programHeadersCount = readU16(bytes, 56) as usize;
+    if programHeaderSize != PROGRAM_HEADER_SIZE
+      || programHeadersCount == 0
+      || programHeadersCount > MAX_PROGRAM_HEADERS_COUNT
+    {
+      return Err(FsError::NotExecutable);
+    }
+
+    Ok(Self {
+      entryPoint: readU64(bytes, 24),
+
+      programHeadersOffset: readU64(bytes, 32),
+      programHeadersCount,
+    })
+  }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct ProgramHeader {
+  pub r#type: u32,
+  pub flags: u32,
+
+  pub fileOffset: u64,
+  pub va: u64,
+
+  pub fileSize: u64,
+  pub memorySize: u64,
+
+  pub alignment: u64,
+}
+
+impl ProgramHeader {
+  pub fn parse(bytes: &[u8]) -> Self {
+    Self {
+      r#type: readU32(bytes, 0),
+      flags: readU32(bytes, 4),
+
+      fileOffset: readU64(bytes, 8),
+      va: readU64(bytes, 16),
+
+      fileSize: readU64(bytes, 32),
+      memorySize: readU64(bytes, 40),
+
+      alignment: readU64(bytes, 48),
+    }
+  }
+}
+
+// Parses the program headers, from the given bytes (read from the offset in the ELF header).
+pub fn parseProgramHeaders(elfHeader: &ElfHeader, bytes: &[u8]) -> Vec<ProgramHeader> {
+  bytes
+    .as_chunks::<PROGRAM_HEADER_SIZE>()
+    .0
+    .iter()
+    .take(elfHeader.programHeadersCount)
+    .map(|bytes| ProgramHeader::parse(bytes))
+    .collect()
+}
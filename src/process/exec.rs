@@ -0,0 +1,407 @@
+use {
+  super::{
+    elf::{
+      self, ElfHeader, ProgramHeader, PF_R, PF_W, PF_X, PROGRAM_HEADER_SIZE, PT_INTERP, PT_LOAD,
+    },
+    process::getCurrentProcess,
+  },
+  crate::{
+    arch::riscv::qemu::PAGE_SIZE,
+    drivers::mem,
+    fs::{
+      fdtable,
+      vfs::{namespace, File, FileType, FsError, OpenFlags},
+    },
+    memory::{
+      address_space::{
+        AddressSpace, MAX_USER_PROGRAM_VA, USER_STACK_BOTTOM, USER_STACK_SIZE, USER_STACK_TOP,
+      },
+      page_table::entry::PTEBitFlags,
+    },
+  },
+  alloc::{vec, vec::Vec},
+};
+
+/*
+  exec( ) replaces the program the current process runs, with the one in the given executable :
+
+    (1) A script starting with #! (shebang) gets run by the interpreter named in its first line.
+        The interpreter gets the optional argument from that line and the script's path, prepended
+        to the arguments.
+
+    (2) An ELF executable gets loaded into a new address space, and the arguments, the environment
+        and the auxiliary vector get copied onto its stack.
+
+  The new address space replaces the current one, only once everything has succeeded. So if exec( )
+  fails, the process continues running its current program.
+
+  REFER : chapter 27 (Program Execution) of The Linux Programming Interface by Michael Kerrisk.
+*/
+
+// Number of bytes read from the start of the executable, to find out its format. A shebang line
+// must fit in these many bytes.
+const HEADER_BUFFER_SIZE: usize = 256;
+
+// Maximum number of interpreters, a script can be nested in (same as Linux).
+const MAX_INTERPRETER_DEPTH: usize = 4;
+
+// Maximum space the arguments and the environment can take on the stack (same as Linux, a quarter
+// of the stack).
+const MAX_ARGUMENTS_SIZE: usize = USER_STACK_SIZE / 4;
+
+// Auxiliary vector entry types.
+const AT_NULL: usize = 0;
+const AT_PHDR: usize = 3;
+const AT_PHENT: usize = 4;
+const AT_PHNUM: usize = 5;
+const AT_PAGESZ: usize = 6;
+const AT_ENTRY: usize = 9;
+const AT_RANDOM: usize = 25;
+const AT_EXECFN: usize = 31;
+
+// Number of random bytes AT_RANDOM points to.
+const RANDOM_BYTES_COUNT: usize = 16;
+
+// Where the new program starts executing, and the initial values of its registers.
+#[derive(Debug, Copy, Clone)]
+pub struct UserEntry {
+  pub entryPoint: usize,
+  pub stackPointer: usize,
+
+  pub argc: usize,
+  pub argvPointer: usize,
+  pub envpPointer: usize,
+}
+
+pub fn exec(
+  path: &[u8],
+  arguments: &[Vec<u8>],
+  environment: &[Vec<u8>],
+) -> Result<UserEntry, FsError> {
+  let process = getCurrentProcess().expect("exec( ) invoked, without a current process");
+
+  let mut path = path.to_vec();
+  let mut arguments = arguments.to_vec();
+
+  for _ in 0..=MAX_INTERPRETER_DEPTH {
+    let file = namespace::open(&path, None, OpenFlags::READ)?;
+    if file.getStat()?.fileType != FileType::Regular {
+      return Err(FsError::NotExecutable);
+    }
+
+    let mut header = vec![0; HEADER_BUFFER_SIZE];
+    let headerLength = readFully(&*file, 0, &mut header)?;
+    header.truncate(headerLength);
+
+    if header.starts_with(b"#!") {
+      let (interpreter, interpreterArgument) = parseShebang(&header)?;
+
+      let mut interpreterArguments = vec![interpreter.clone()];
+      interpreterArguments.extend(interpreterArgument);
+      interpreterArguments.push(path);
+      interpreterArguments.extend(arguments.into_iter().skip(1));
+
+      (path, arguments) = (interpreter, interpreterArguments);
+      continue;
+    }
+
+    let (addressSpace, userEntry) = loadElf(
+      process.getTrapFrame() as usize,
+      &*file,
+      &header,
+      &path,
+      &arguments,
+      environment,
+    )?;
+
+    // exec( ) can't fail from here on. So, the new program can be committed.
+    let oldAddressSpace = process.getAddressSpace().acquire().replace(addressSpace);
+    drop(oldAddressSpace);
+
+    fdtable::closeOnExec(process.getFileDescriptorTable());
+
+    return Ok(userEntry);
+  }
+
+  // ELOOP in Linux.
+  Err(FsError::TooManySymlinks)
+}
+
+// Reads from the given offset, till the buffer is full / the end of the file is reached. Returns
+// the number of bytes read.
+fn readFully(file: &dyn File, offset: u64, buffer: &mut [u8]) -> Result<usize, FsError> {
+  let mut bytesRead = 0;
+
+  while bytesRead < buffer.len() {
+    match file.read(offset + bytesRead as u64, &mut buffer[bytesRead..])? {
+      0 => break,
+      length => bytesRead += length,
+    }
+  }
+
+  Ok(bytesRead)
+}
+
+// Parses the shebang line (#!interpreter [argument]). Returns the interpreter path, and the
+// optional argument (the rest of the line, which may contain spaces).
+fn parseShebang(header: &[u8]) -> Result<(Vec<u8>, Option<Vec<u8>>), FsError> {
+  let line = &header[2..];
+  let lineLength = line
+    .iter()
+    .position(|byte| *byte == b'\n')
+    .ok_or(FsError::NotExecutable)?;
+
+  let line = line[..lineLength].trim_ascii();
+
+  let interpreterLength = line
+    .iter()
+    .position(|byte| byte.is_ascii_whitespace())
+    .unwrap_or(line.len());
+  if interpreterLength == 0 {
+    return Err(FsError::NotExecutable);
+  }
+
+  let (interpreter, argument) = line.split_at(interpreterLength);
+  let argument = argument.trim_ascii();
+
+  Ok((
+    interpreter.to_vec(),
+    (!argument.is_empty()).then(|| argument.to_vec()),
+  ))
+}
+
+// Loads the given ELF executable into a new address space (with the given trap frame mapped), and
+// sets up its stack.
+fn loadElf(
+  trapFramePA: usize,
+  file: &dyn File,
+  header: &[u8],
+  path: &[u8],
+  arguments: &[Vec<u8>],
+  environment: &[Vec<u8>],
+) -> Result<(AddressSpace, UserEntry), FsError> {
+  let elfHeader = ElfHeader::parse(header)?;
+
+  let mut programHeadersBytes = vec![0; elfHeader.programHeadersCount * PROGRAM_HEADER_SIZE];
+  if readFully(
+    file,
+    elfHeader.programHeadersOffset,
+    &mut programHeadersBytes,
+  )? != programHeadersBytes.len()
+  {
+    return Err(FsError::NotExecutable);
+  }
+  let programHeaders = elf::parseProgramHeaders(&elfHeader, &programHeadersBytes);
+
+  // Dynamically linked executables aren't supported.
+  if programHeaders
+    .iter()
+    .any(|programHeader| programHeader.r#type == PT_INTERP)
+  {
+    return Err(FsError::NotExecutable);
+  }
+
+  let mut addressSpace = AddressSpace::new(trapFramePA);
+
+  let mut isEntryPointExecutable = false;
+  let mut programHeadersVA = None;
+
+  for programHeader in programHeaders
+    .iter()
+    .filter(|programHeader| programHeader.r#type == PT_LOAD && programHeader.memorySize > 0)
+  {
+    loadSegment(file, &mut addressSpace, programHeader)?;
+
+    let (va, memoryEnd) = (
+      programHeader.va,
+      programHeader.va + programHeader.memorySize,
+    );
+    if programHeader.flags & PF_X != 0 && (va..memoryEnd).contains(&elfHeader.entryPoint) {
+      isEntryPointExecutable = true;
+    }
+
+    // The program headers are loaded along with this segment.
+    let programHeadersEnd = elfHeader.programHeadersOffset + programHeadersBytes.len() as u64;
+    if programHeader.fileOffset <= elfHeader.programHeadersOffset
+      && programHeadersEnd <= programHeader.fileOffset + programHeader.fileSize
+    {
+      programHeadersVA = Some(va + (elfHeader.programHeadersOffset - programHeader.fileOffset));
+    }
+  }
+
+  if !isEntryPointExecutable {
+    return Err(FsError::NotExecutable);
+  }
+
+  addressSpace.mapZeroed(
+    USER_STACK_BOTTOM,
+    USER_STACK_TOP,
+    PTEBitFlags::R | PTEBitFlags::W,
+    "[stack]",
+  )?;
+
+  let mut auxiliaryVector = Vec::new();
+  if let Some(programHeadersVA) = programHeadersVA {
+    auxiliaryVector.push((AT_PHDR, programHeadersVA as usize));
+  }
+  auxiliaryVector.extend([
+    (AT_PHENT, PROGRAM_HEADER_SIZE),
+    (AT_PHNUM, elfHeader.programHeadersCount),
+    (AT_PAGESZ, PAGE_SIZE),
+    (AT_ENTRY, elfHeader.entryPoint as usize),
+  ]);
+
+  let userEntry = setupStack(
+    &mut addressSpace,
+    elfHeader.entryPoint as usize,
+    path,
+    arguments,
+    environment,
+    auxiliaryVector,
+  )?;
+
+  Ok((addressSpace, userEntry))
+}
+
+// Maps the given PT_LOAD segment, and copies its contents from the file. The rest of the segment
+// (the .bss) stays zero filled, since the frames are zero filled when mapped.
+fn loadSegment(
+  file: &dyn File,
+  addressSpace: &mut AddressSpace,
+  programHeader: &ProgramHeader,
+) -> Result<(), FsError> {
+  let memoryEnd = programHeader
+    .va
+    .checked_add(programHeader.memorySize)
+    .ok_or(FsError::NotExecutable)?;
+  if programHeader.fileSize > programHeader.memorySize || memoryEnd > MAX_USER_PROGRAM_VA as u64 {
+    return Err(FsError::NotExecutable);
+  }
+
+  // RISC-V reserves writable pages, which aren't readable.
+  let mut bitFlags = PTEBitFlags::empty();
+  if programHeader.flags & (PF_R | PF_W) != 0 {
+    bitFlags |= PTEBitFlags::R;
+  }
+  if programHeader.flags & PF_W != 0 {
+    bitFlags |= PTEBitFlags::W;
+  }
+  if programHeader.flags & PF_X != 0 {
+    bitFlags |= PTEBitFlags::X;
+  }
+  if bitFlags.is_empty() {
+    return Err(FsError::NotExecutable);
+  }
+
+  addressSpace.mapZeroed(programHeader.va as usize, memoryEnd as usize, bitFlags, "")?;
+
+  let mut buffer = vec![0; PAGE_SIZE];
+  let mut bytesCopied = 0;
+
+  while bytesCopied < programHeader.fileSize {
+    let chunkLength = (programHeader.fileSize - bytesCopied).min(PAGE_SIZE as u64) as usize;
+
+    let chunk = &mut buffer[..chunkLength];
+    if readFully(file, programHeader.fileOffset + bytesCopied, chunk)? != chunkLength {
+      return Err(FsError::NotExecutable);
+    }
+    addressSpace.write((programHeader.va + bytesCopied) as usize, chunk);
+
+    bytesCopied += chunkLength as u64;
+  }
+
+  Ok(())
+}
+
+/*
+  Copies the arguments, the environment and the auxiliary vector onto the stack, as laid out by the
+  RISC-V ELF psABI (same as Linux) :
+
+    [ argc | argv[0] ... argv[argc - 1] | NULL | envp[0] ... | NULL | auxv pairs ... | AT_NULL ]
+    [ padding | random bytes | strings ... ]   (up till the top of the stack)
+
+  The stack pointer points to argc, and is 16 byte aligned.
+*/
+fn setupStack(
+  addressSpace: &mut AddressSpace,
+  entryPoint: usize,
+  path: &[u8],
+  arguments: &[Vec<u8>],
+  environment: &[Vec<u8>],
+  mut auxiliaryVector: Vec<(usize, usize)>,
+) -> Result<UserEntry, FsError> {
+  // Entries : argc, the argv and envp pointers along with their NULL terminators, and the auxiliary
+  // vector (including AT_RANDOM, AT_EXECFN and AT_NULL).
+  let entriesCount =
+    1 + (arguments.len() + 1) + (environment.len() + 1) + 2 * (auxiliaryVector.len() + 3);
+
+  let stringsSize: usize = arguments
+    .iter()
+    .chain(environment)
+    .map(|string| string.len() + 1)
+    .sum::<usize>()
+    + path.len()
+    + 1;
+
+  if stringsSize + RANDOM_BYTES_COUNT + entriesCount * 8 + 16 > MAX_ARGUMENTS_SIZE {
+    return Err(FsError::ArgumentsTooLong);
+  }
+
+  let mut stackPointer = USER_STACK_TOP;
+  let mut push = |bytes: &[u8]| {
+    stackPointer -= bytes.len();
+    addressSpace.write(stackPointer, bytes);
+    stackPointer
+  };
+  let pushString = |push: &mut dyn FnMut(&[u8]) -> usize, string: &[u8]| {
+    push(&[0]);
+    push(string)
+  };
+
+  let execFnVA = pushString(&mut push, path);
+  let envpVAs: Vec<usize> = environment
+    .iter()
+    .map(|string| pushString(&mut push, string))
+    .collect();
+  let argvVAs: Vec<usize> = arguments
+    .iter()
+    .map(|string| pushString(&mut push, string))
+    .collect();
+
+  let mut randomBytes = [0; RANDOM_BYTES_COUNT];
+  mem::getRandomBytes(&mut randomBytes);
+  let randomBytesVA = push(&randomBytes);
+
+  auxiliaryVector.extend([
+    (AT_RANDOM, randomBytesVA),
+    (AT_EXECFN, execFnVA),
+    (AT_NULL, 0),
+  ]);
+
+  let mut entries = Vec::with_capacity(entriesCount);
+  entries.push(arguments.len());
+  entries.extend(&argvVAs);
+  entries.push(0);
+  entries.extend(&envpVAs);
+  entries.push(0);
+  for (r#type, value) in auxiliaryVector {
+    entries.extend([r#type, value]);
+  }
+
+  let entriesBytes: Vec<u8> = entries
+    .iter()
+    .flat_map(|entry| entry.to_le_bytes())
+    .collect();
+
+  let stackPointer = (stackPointer - entriesBytes.len()) & !15;
+  addressSpace.write(stackPointer, &entriesBytes);
+
+  Ok(UserEntry {
+    entryPoint,
+    stackPointer,
+
+    argc: arguments.len(),
+    argvPointer: stackPointer + 8,
+    envpPointer: stackPointer + 8 * (arguments.len() + 2),
+  })
+}
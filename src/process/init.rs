@@ -0,0 +1,90 @@
+use {
+  super::{
+    exec,
+    manager::PROCESS_MANAGER,
+    process::{getCurrentProcess, ProcessState},
+  },
+  crate::{
+    fs::{
+      syscalls::{self, O_RDWR},
+      vfs::FsError,
+    },
+    trap::user::returnToUserMode,
+  },
+};
+
+/*
+  The init process is the first user process. Hart 0 creates it, once the kernel has initialized.
+  When the init process runs for the first time, its kernel thread :
+
+    (1) opens the console as the standard input, output and error.
+
+    (2) loads the init program, using exec( ). This is done by the init process itself (and not by
+        hart 0), since exec( ) acts on the current process and may sleep waiting for disk I/O.
+
+    (3) returns to user mode, starting the init program.
+
+  REFER : userinit( ) and forkret( ) in xv6-riscv, and kernel_init( ) in Linux (init/main.c).
+*/
+
+// Paths the init program is looked for at (in order), same as in Linux.
+const INIT_PATHS: [&[u8]; 4] = [b"/sbin/init", b"/etc/init", b"/bin/init", b"/bin/sh"];
+
+const CONSOLE_PATH: &[u8] = b"/dev/console";
+
+// Environment the init program starts with, same as in Linux.
+const INIT_ENVIRONMENT: [&[u8]; 2] = [b"HOME=/", b"TERM=linux"];
+
+// Creates the init process, and makes it RUNNABLE.
+// NOTE : Must be invoked only once (by hart 0), after the VFS has been initialized.
+pub fn createInitProcess() {
+  let process = PROCESS_MANAGER
+    .allocateProcess(initProcessMain)
+    .expect("Failed allocating the init process");
+  PROCESS_MANAGER.setInitProcess(process);
+
+  process.metadata.acquire().state = ProcessState::RUNNABLE;
+}
+
+// Where the kernel thread of the init process starts executing.
+extern "C" fn initProcessMain() -> ! {
+  let process = getCurrentProcess().expect("Init process isn't the current process");
+
+  // The scheduler acquired our metadata SpinLock, before switching to us.
+  unsafe { process.metadata.forceRelease() };
+
+  openConsole();
+
+  let environment = INIT_ENVIRONMENT.map(<[u8]>::to_vec);
+  let userEntry = INIT_PATHS
+    .iter()
+    .find_map(|path| match exec::exec(path, &[path.to_vec()], &environment) {
+      Ok(userEntry) => Some(userEntry),
+
+      Err(FsError::NotFound) => None,
+      Err(error) => {
+        println!(
+          "WARN : Failed executing {} : {:?}",
+          core::str::from_utf8(path).unwrap(),
+          error
+        );
+        None
+      }
+    })
+    .expect("No working init program found");
+
+  unsafe { (*process.getTrapFrame()).enterProgram(&userEntry) };
+  returnToUserMode(process)
+}
+
+// Opens the console as file descriptors 0, 1 and 2. The init program can still run without them.
+fn openConsole() {
+  let result = syscalls::sysOpen(CONSOLE_PATH, O_RDWR).and_then(|fd| {
+    syscalls::sysDup(fd)?;
+    syscalls::sysDup(fd)
+  });
+
+  if let Err(error) = result {
+    println!("WARN : Unable to open an initial console : {:?}", error);
+  }
+}
@@ -1,19 +1,151 @@
-use {super::process::Process, array_macro::array};
+use {
+  super::{
+    context::Context,
+    process::{getCurrentProcess, Process, ProcessState},
+  },
+  alloc::vec::Vec,
+  array_macro::array,
+  core::{
+    ptr,
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
+  },
+};
 
 const MAX_ALLOWED_PROCESSES: usize = 64;
 
+// PID, the next allocated process gets. The init process gets PID 1.
+static NEXT_PID: AtomicUsize = AtomicUsize::new(1);
+
 pub struct ProcessManager {
-  processes: [Process; MAX_ALLOWED_PROCESSES],
-  initProcess: *mut Process,
+  pub processes: [Process; MAX_ALLOWED_PROCESSES],
+  initProcess: AtomicPtr<Process>,
 }
 
 impl ProcessManager {
   pub const fn new() -> Self {
     Self {
       processes: array![_ => Process::new( ); MAX_ALLOWED_PROCESSES],
-      initProcess: 0 as *mut Process,
+      initProcess: AtomicPtr::new(ptr::null_mut()),
+    }
+  }
+
+  /*
+    Allocates an UNUSED process and assigns it a new PID. The process's kernel thread starts
+    executing the given function (on the process's kernel stack), when the scheduler switches to it
+    for the first time.
+
+    The process is left in USED state, so that the invoker can set it up before marking it
+    RUNNABLE. Returns None if all the processes are in use.
+  */
+  pub fn allocateProcess(&self, kernelThreadEntry: extern "C" fn() -> !) -> Option<&Process> {
+    let process = self.processes.iter().find(|process| {
+      let mut metadata = process.metadata.acquire();
+      if metadata.state != ProcessState::UNUSED {
+        return false;
+      }
+
+      metadata.state = ProcessState::USED;
+      metadata.pid = NEXT_PID.fetch_add(1, Ordering::Relaxed);
+      true
+    })?;
+
+    // Nobody else accesses the process's data, till it becomes RUNNABLE.
+    let context = unsafe { &mut (*process.data.get()).context };
+    *context = Context::new();
+    context.ra = kernelThreadEntry as usize;
+    context.sp = process.getKernelStackTop();
+
+    Some(process)
+  }
+
+  #[inline]
+  pub fn setInitProcess(&self, process: &Process) {
+    self
+      .initProcess
+      .store(process as *const Process as *mut Process, Ordering::Release);
+  }
+
+  // Wakes up all the processes sleeping on the given wait channel.
+  // NOTE : Must be invoked without holding the metadata SpinLock of any process.
+  pub fn wakeup(&self, waitChannel: usize) {
+    let currentProcess = getCurrentProcess().map_or(ptr::null(), |process| process as *const _);
+
+    for process in self.processes.iter() {
+      if ptr::eq(process, currentProcess) {
+        continue;
+      }
+
+      let mut metadata = process.metadata.acquire();
+      if metadata.state == ProcessState::SLEEPING && metadata.waitChannel == waitChannel {
+        metadata.state = ProcessState::RUNNABLE;
+      }
     }
   }
+
+  // Wakes up all the sleeping processes, whose wakeup deadlines have been reached.
+  // Invoked on each tick of the tick counter.
+  pub fn wakeupExpired(&self, ticks: usize) {
+    for process in self.processes.iter() {
+      let mut metadata = process.metadata.acquire();
+      if metadata.state == ProcessState::SLEEPING
+        && metadata
+          .wakeupDeadline
+          .is_some_and(|deadline| ticks >= deadline)
+      {
+        metadata.state = ProcessState::RUNNABLE;
+      }
+    }
+  }
+
+  // Wakes up one of the processes sleeping on the given wait channel (if any).
+  // Returns whether a process was woken up.
+  // NOTE : Must be invoked without holding the metadata SpinLock of any process.
+  pub fn wakeupOne(&self, waitChannel: usize) -> bool {
+    let currentProcess = getCurrentProcess().map_or(ptr::null(), |process| process as *const _);
+
+    for process in self.processes.iter() {
+      if ptr::eq(process, currentProcess) {
+        continue;
+      }
+
+      let mut metadata = process.metadata.acquire();
+      if metadata.state == ProcessState::SLEEPING && metadata.waitChannel == waitChannel {
+        metadata.state = ProcessState::RUNNABLE;
+        return true;
+      }
+    }
+
+    false
+  }
+
+  // Returns the process with the given PID, if there's one.
+  pub fn findProcess(&self, pid: usize) -> Option<&Process> {
+    if pid == 0 {
+      return None;
+    }
+
+    self.processes.iter().find(|process| {
+      let metadata = process.metadata.acquire();
+      metadata.pid == pid && metadata.state != ProcessState::UNUSED
+    })
+  }
+
+  // Returns the PIDs of all the processes, in increasing order.
+  pub fn getPIDs(&self) -> Vec<usize> {
+    let mut pids: Vec<usize> = self
+      .processes
+      .iter()
+      .filter_map(|process| {
+        let metadata = process.metadata.acquire();
+        (metadata.pid != 0 && metadata.state != ProcessState::UNUSED).then_some(metadata.pid)
+      })
+      .collect();
+
+    pids.sort_unstable();
+    pids
+  }
 }
 
-const PROCESS_MANAGER: ProcessManager = ProcessManager::new();
+unsafe impl Sync for ProcessManager {}
+
+pub static PROCESS_MANAGER: ProcessManager = ProcessManager::new();
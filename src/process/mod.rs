@@ -1,4 +1,11 @@
+pub mod context;
 pub mod core;
 pub mod cpu;
+pub mod elf;
+pub mod exec;
+pub mod init;
 pub mod manager;
 pub mod process;
+pub mod scheduler;
+pub mod signal;
+pub mod trapframe;
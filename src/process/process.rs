@@ -1,10 +1,21 @@
+#[cfg(feature = "lockdep")]
+use crate::locks::lockdep::{self, HeldLocks};
 use {
-  crate::locks::spinlock::{SpinLock, SpinLockGuard},
-  core::cell::UnsafeCell,
+  super::{context::Context, core::Core, cpu::CPU, scheduler, trapframe::TrapFrame},
+  crate::{
+    arch::riscv::qemu::PAGE_SIZE,
+    fs::fdtable::{self, FileDescriptorTable},
+    locks::spinlock::{SpinLock, SpinLockGuard},
+    memory::address_space::AddressSpace,
+    timer,
+  },
+  core::{cell::UnsafeCell, ptr::addr_of_mut},
 };
 
 pub struct Process {
   pub metadata: SpinLock<ProcessMetadata>,
+
+  // Private to the process. So, the metadata SpinLock needn't be held while accessing it.
   pub data: UnsafeCell<ProcessData>,
 }
 
@@ -16,9 +27,123 @@ impl Process {
     }
   }
 
-  pub fn sleep<T>(&self, _sleepLockSpinLockGuard: SpinLockGuard<'_, T>) {
-    unimplemented!()
+  /*
+    Puts the process to sleep on the given wait channel, releasing the given SpinLock guard (which
+    protects the condition the process is waiting for). Once the process is woken up, the SpinLock
+    is re-acquired and its guard is returned.
+
+    The process's metadata SpinLock is acquired before the given SpinLock is released. Since
+    wakeup( ) acquires the metadata SpinLock before looking at the process, a wakeup can't get
+    lost in between the process releasing the given SpinLock and going to sleep.
+
+    NOTE : Must only be invoked by the process itself.
+  */
+  pub fn sleep<'a, T>(
+    &self,
+    waitChannel: usize,
+    conditionSpinLockGuard: SpinLockGuard<'a, T>,
+  ) -> SpinLockGuard<'a, T> {
+    self.sleepUntil(waitChannel, conditionSpinLockGuard, None).0
+  }
+
+  /*
+    Same as sleep( ), except that the process also gets woken up once the tick counter reaches the
+    given deadline (if any).
+    Along with the re-acquired SpinLock's guard, returns whether the deadline has been reached.
+  */
+  pub fn sleepUntil<'a, T>(
+    &self,
+    waitChannel: usize,
+    conditionSpinLockGuard: SpinLockGuard<'a, T>,
+    deadline: Option<usize>,
+  ) -> (SpinLockGuard<'a, T>, bool) {
+    let conditionSpinLock = conditionSpinLockGuard.getSpinLock();
+
+    #[cfg(feature = "lockdep")]
+    lockdep::checkMightSleep(Some(conditionSpinLock.getAddress()));
+
+    let mut metadata = self.metadata.acquire();
+    drop(conditionSpinLockGuard);
+
+    metadata.waitChannel = waitChannel;
+    metadata.wakeupDeadline = deadline;
+    metadata.state = ProcessState::SLEEPING;
+
+    let mut metadata = scheduler::sched(self, metadata);
+
+    // We've been woken up.
+    metadata.waitChannel = 0;
+    metadata.wakeupDeadline = None;
+    drop(metadata);
+
+    let hasDeadlineReached = deadline.is_some_and(|deadline| timer::getTicks() >= deadline);
+
+    (conditionSpinLock.acquire(), hasDeadlineReached)
+  }
+
+  #[inline]
+  pub fn getPID(&self) -> usize {
+    self.metadata.acquire().pid
+  }
+
+  // The file descriptor table is the only part of the process's data, which other processes (like
+  // the ones reading procfs) may access.
+  #[inline]
+  pub fn getFileDescriptorTable(&self) -> &SpinLock<FileDescriptorTable> {
+    unsafe { &(*self.data.get()).fileDescriptorTable }
+  }
+
+  // Same as getFileDescriptorTable( ), for the address space.
+  #[inline]
+  pub fn getAddressSpace(&self) -> &SpinLock<Option<AddressSpace>> {
+    unsafe { &(*self.data.get()).addressSpace }
+  }
+
+  // Returns the trap frame. Its address is also its Physical Address, which gets mapped into the
+  // address space.
+  // NOTE : Must only be accessed by the process itself.
+  #[inline]
+  pub fn getTrapFrame(&self) -> *mut TrapFrame {
+    unsafe { addr_of_mut!((*self.data.get()).trapFrame) }
   }
+
+  // Returns the address, where the kernel stack of the process starts (stacks grow downwards).
+  #[inline]
+  pub fn getKernelStackTop(&self) -> usize {
+    unsafe { addr_of_mut!((*self.data.get()).kernelStack) as usize + KERNEL_STACK_SIZE }
+  }
+}
+
+unsafe impl Sync for Process {}
+
+// Returns the process running on the current CPU core, if any.
+pub fn getCurrentProcess() -> Option<&'static Process> {
+  Core::enterInterruptsDisabledSection();
+  let process = unsafe { CPU.getCurrentCore().process };
+  Core::exitInterruptsDisabledSection();
+
+  unsafe { process.as_ref() }
+}
+
+/*
+  Terminates the current process : its files get closed and its address space gets freed. The
+  process then stays a ZOMBIE, and never runs again.
+
+  NOTE : There's no wait( ) yet. So, the exit status is dropped and the process never gets reaped.
+*/
+pub fn exit(_status: usize) -> ! {
+  let process = getCurrentProcess().expect("exit( ) invoked, without a current process");
+
+  fdtable::closeAll(process.getFileDescriptorTable());
+
+  let addressSpace = process.getAddressSpace().acquire().take();
+  drop(addressSpace);
+
+  let mut metadata = process.metadata.acquire();
+  metadata.state = ProcessState::ZOMBIE;
+  scheduler::sched(process, metadata);
+
+  unreachable!("exit : ZOMBIE process was scheduled");
 }
 
 #[derive(PartialEq, Copy, Clone)]
@@ -41,22 +166,91 @@ pub enum ProcessState {
   ALLOCATED,
 }
 
+impl ProcessState {
+  // Returns the name of the state, as shown in /proc/<pid>/status.
+  pub fn getName(&self) -> &'static str {
+    match self {
+      Self::UNUSED => "unused",
+      Self::USED => "used",
+      Self::RUNNABLE => "runnable",
+      Self::SLEEPING => "sleeping",
+      Self::RUNNING => "running",
+      Self::ZOMBIE => "zombie",
+      Self::ALLOCATED => "allocated",
+    }
+  }
+}
+
 pub struct ProcessMetadata {
   pub state: ProcessState,
+
+  // Process ID.
+  // NOTE : 0 means no PID has been assigned.
+  pub pid: usize,
+
+  // If the process is sleeping, then this identifies what it's waiting for (usually the address of
+  // the object it's waiting on).
+  pub waitChannel: usize,
+
+  // If the process is sleeping, then it also gets woken up once the tick counter reaches this
+  // deadline (if any).
+  pub wakeupDeadline: Option<usize>,
+
+  // Bitmask of the signals sent to the process, which haven't been acted upon yet.
+  pub pendingSignals: usize,
 }
 
 impl ProcessMetadata {
   pub const fn new() -> Self {
     Self {
       state: ProcessState::UNUSED,
+      pid: 0,
+      waitChannel: 0,
+      wakeupDeadline: None,
+      pendingSignals: 0,
     }
   }
 }
 
-pub struct ProcessData {}
+pub const KERNEL_STACK_SIZE: usize = 4 * PAGE_SIZE;
+
+#[repr(C, align(16))]
+pub struct KernelStack([u8; KERNEL_STACK_SIZE]);
+
+pub struct ProcessData {
+  // Execution context of the process's kernel thread, saved while it has given up the CPU core.
+  pub context: Context,
+
+  // User registers, saved while the process is executing in the kernel. Access it using
+  // Process::getTrapFrame( ).
+  pub trapFrame: TrapFrame,
+
+  // The stack the process's kernel thread runs on, after trapping from user mode.
+  pub kernelStack: KernelStack,
+
+  // Files opened by the process. Access it using Process::getFileDescriptorTable( ).
+  pub fileDescriptorTable: SpinLock<FileDescriptorTable>,
+
+  // The user address space, set up by exec( ). None for a kernel thread. Access it using
+  // Process::getAddressSpace( ).
+  pub addressSpace: SpinLock<Option<AddressSpace>>,
+
+  // SleepLocks currently held by the process.
+  #[cfg(feature = "lockdep")]
+  pub heldSleepLocks: HeldLocks,
+}
 
 impl ProcessData {
   pub const fn new() -> Self {
-    Self {}
+    Self {
+      context: Context::new(),
+      trapFrame: TrapFrame::new(),
+      kernelStack: KernelStack([0; KERNEL_STACK_SIZE]),
+      fileDescriptorTable: SpinLock::new(FileDescriptorTable::new()),
+      addressSpace: SpinLock::new(None),
+
+      #[cfg(feature = "lockdep")]
+      heldSleepLocks: HeldLocks::new(),
+    }
   }
 }
@@ -0,0 +1,118 @@
+use {
+  super::{
+    context::switchContext,
+    cpu::CPU,
+    manager::PROCESS_MANAGER,
+    process::{Process, ProcessMetadata, ProcessState},
+  },
+  crate::{
+    arch::riscv::registers::sstatus::Sstatus,
+    locks::{rcu, spinlock::SpinLockGuard},
+  },
+  core::{arch::asm, ptr},
+};
+
+/*
+  Each CPU core runs the scheduler, after it has finished initializing. The scheduler never returns.
+  It loops, doing the following :
+
+    (1) choose a RUNNABLE process.
+
+    (2) switch to the process's execution context, to start running it.
+
+    (3) eventually, the process transfers control back to the scheduler via sched( ).
+
+  NOTE : The metadata SpinLock of the chosen process is acquired by the scheduler, and released by
+  the process (and vice versa, while the process is switching back).
+*/
+pub fn scheduler() -> ! {
+  let core = unsafe { CPU.getCurrentCore() };
+  core.process = ptr::null_mut();
+
+  loop {
+    // The most recent process to run may have had interrupts turned off. Enable them to avoid a
+    // deadlock if all processes are waiting (for I/O completion interrupts, say).
+    unsafe { Sstatus.enableInterrupts() };
+
+    let mut hasRunAnyProcess = false;
+
+    for process in PROCESS_MANAGER.processes.iter() {
+      let mut metadata = process.metadata.acquire();
+
+      if metadata.state == ProcessState::RUNNABLE {
+        metadata.state = ProcessState::RUNNING;
+        core.process = process as *const Process as *mut Process;
+
+        // Switch to the chosen process. It's the process's job to release its metadata SpinLock
+        // and then re-acquire it before switching back to us.
+        unsafe { switchContext(&mut core.schedulerContext, &(*process.data.get()).context) };
+
+        // The process is done running for now.
+        core.process = ptr::null_mut();
+        hasRunAnyProcess = true;
+      }
+
+      drop(metadata);
+
+      // Having context switched (or not running anything), this CPU core is outside any RCU
+      // read-side critical section.
+      rcu::noteQuiescentState();
+    }
+
+    // Nothing to run. Stop running on this CPU core, until an interrupt arrives.
+    if !hasRunAnyProcess {
+      unsafe { asm!("wfi") };
+    }
+  }
+}
+
+/*
+  Switches from the given process (which must be the current process) to the scheduler. Returns
+  when the scheduler switches back to the process.
+
+  The process must hold only its metadata SpinLock (whose guard is passed in and handed back), and
+  must have already changed its state from RUNNING.
+
+  NOTE : intena is a property of this kernel thread, not of the CPU core. So it's saved and restored
+  across the switch.
+*/
+pub fn sched<'a>(
+  process: &Process,
+  metadata: SpinLockGuard<'a, ProcessMetadata>,
+) -> SpinLockGuard<'a, ProcessMetadata> {
+  let core = unsafe { CPU.getCurrentCore() };
+
+  assert!(
+    ptr::eq(core.process, process),
+    "sched : process isn't running on the current CPU core"
+  );
+  assert!(
+    core.noff == 1,
+    "sched : holding SpinLocks other than the process's metadata SpinLock"
+  );
+  assert!(
+    metadata.state != ProcessState::RUNNING,
+    "sched : process is still in RUNNING state"
+  );
+  assert!(
+    !unsafe { Sstatus.areInterruptsEnabled() },
+    "sched : interrupts are enabled"
+  );
+
+  let intena = core.intena;
+
+  unsafe { switchContext(&mut (*process.data.get()).context, &core.schedulerContext) };
+
+  // We may be running on a different CPU core now.
+  unsafe { CPU.getCurrentCore().intena = intena };
+
+  metadata
+}
+
+// Gives up the CPU core for one scheduling round.
+pub fn yieldCPU(process: &Process) {
+  let mut metadata = process.metadata.acquire();
+  metadata.state = ProcessState::RUNNABLE;
+
+  drop(sched(process, metadata));
+}
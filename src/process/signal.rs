@@ -0,0 +1,31 @@
+use super::process::{self, Process};
+
+/*
+  Signals notify a process of some event (like writing to a pipe nobody reads from). The signal
+  numbers are the same as in Linux.
+
+  NOTE : Signal handlers aren't supported yet. So, every signal takes its default action, which for
+  the signals we send is terminating the process. Pending signals are acted upon, while the process
+  is returning to user mode.
+*/
+pub const SIGILL: usize = 4;
+pub const SIGSEGV: usize = 11;
+pub const SIGPIPE: usize = 13;
+
+// Marks the given signal as pending on the given process.
+pub fn sendSignal(process: &Process, signal: usize) {
+  process.metadata.acquire().pendingSignals |= 1 << signal;
+}
+
+// Acts upon the signals pending on the given (current) process : it gets terminated, if there are
+// any.
+pub fn handlePendingSignals(process: &Process) {
+  let pendingSignals = process.metadata.acquire().pendingSignals;
+  if pendingSignals == 0 {
+    return;
+  }
+
+  // Same as the exit status the shell reports, for a process killed by a signal.
+  let signal = pendingSignals.trailing_zeros() as usize;
+  process::exit(128 + signal);
+}
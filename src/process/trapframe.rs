@@ -0,0 +1,87 @@
+use super::exec::UserEntry;
+
+// Register numbers (xn), of the registers the kernel looks at.
+const SP: usize = 2;
+const A0: usize = 10;
+const A1: usize = 11;
+const A2: usize = 12;
+const A7: usize = 17;
+
+// Number of arguments a system call can take (in a0 - a5).
+pub const MAX_SYSCALL_ARGUMENTS: usize = 6;
+
+/*
+  The user registers of a process, saved by the trampoline (../asm/trampoline.S) when the process
+  traps into the kernel, and restored when it returns to user mode. Along with them, the trap
+  frame holds what the trampoline needs for switching to the kernel.
+
+  Each process has its own trap frame, mapped at TRAP_FRAME_VA in its address space.
+
+  NOTE : The layout must match the offsets used in ../asm/trampoline.S.
+*/
+#[repr(C, align(4096))]
+pub struct TrapFrame {
+  // Set by the kernel, each time it returns to user mode.
+  pub kernelSatp: usize,
+  pub kernelSP: usize,
+  pub kernelTrap: usize, // Address of userTrap( ).
+  pub kernelHartID: usize,
+  // Whether the TLB must be flushed, while switching between the kernel and user Page Tables.
+  pub isUsingKernelASID: usize,
+
+  // User program counter.
+  pub epc: usize,
+
+  // Indexed by the register number (xn). x0 is hardwired to 0, so its slot is unused.
+  pub registers: [usize; 32],
+}
+
+impl TrapFrame {
+  pub const fn new() -> Self {
+    Self {
+      kernelSatp: 0,
+      kernelSP: 0,
+      kernelTrap: 0,
+      kernelHartID: 0,
+      isUsingKernelASID: 0,
+
+      epc: 0,
+
+      registers: [0; 32],
+    }
+  }
+
+  // The system call number is passed in a7.
+  #[inline]
+  pub fn getSyscallNumber(&self) -> usize {
+    self.registers[A7]
+  }
+
+  // Returns the system call arguments, passed in a0 - a5.
+  #[inline]
+  pub fn getSyscallArguments(&self) -> [usize; MAX_SYSCALL_ARGUMENTS] {
+    self.registers[A0..A0 + MAX_SYSCALL_ARGUMENTS]
+      .try_into()
+      .unwrap()
+  }
+
+  // The system call return value is passed back in a0.
+  #[inline]
+  pub fn setSyscallReturnValue(&mut self, value: usize) {
+    self.registers[A0] = value;
+  }
+
+  // Makes the process start executing the program exec( ) has loaded, once it returns to user
+  // mode. The registers of the previous program are cleared.
+  pub fn enterProgram(&mut self, userEntry: &UserEntry) {
+    self.registers = [0; 32];
+
+    self.epc = userEntry.entryPoint;
+    self.registers[SP] = userEntry.stackPointer;
+
+    // Same as in xv6, main(argc, argv, envp) can be invoked directly.
+    self.registers[A0] = userEntry.argc;
+    self.registers[A1] = userEntry.argvPointer;
+    self.registers[A2] = userEntry.envpPointer;
+  }
+}
@@ -5,7 +5,8 @@
   clippy::module_inception,
   clippy::upper_case_acronyms
 )]
-#![feature(slice_ptr_get, new_zeroed_alloc)]
+#![feature(slice_ptr_get, new_zeroed_alloc, allocator_api)]
+#![cfg_attr(feature = "lockdep", feature(const_type_name))]
 //
 // Rust's standard library depends on libc, which in-turn depends on the underlying Operating
 // System. Since we're building the Operating System itself, we cannot use the standard library.
@@ -27,6 +28,13 @@
 #![no_main]
 
 core::arch::global_asm!(include_str!("asm/entry.S"));
+core::arch::global_asm!(include_str!("asm/machinevec.S"));
+core::arch::global_asm!(include_str!("asm/kernelvec.S"));
+core::arch::global_asm!(include_str!("asm/switch.S"));
+core::arch::global_asm!(
+  include_str!("asm/trampoline.S"),
+  TRAP_FRAME_VA = const memory::address_space::TRAP_FRAME_VA
+);
 
 #[no_mangle] // Disabling name mangling to ensure that the Rust compiler really outputs a function
              // with the name start. Without the attribute, the compiler would generate some
@@ -34,7 +42,8 @@ core::arch::global_asm!(include_str!("asm/entry.S"));
 unsafe fn start() -> ! {
   use {
     arch::riscv::registers::{
-      medeleg::Medeleg, mepc::Mepc, mhartid::Mhartid, mideleg::Mideleg, mstatus::Mstatus, pmp,
+      mcounteren::Mcounteren, medeleg::Medeleg, menvcfg::Menvcfg, mepc::Mepc, mhartid::Mhartid,
+      mideleg::Mideleg, mie::Mie, mscratch::Mscratch, mstatus::Mstatus, mtvec::Mtvec, pmp,
       satp::Satp, sie::Sie, tp::Tp,
     },
     core::arch::asm,
@@ -70,6 +79,24 @@ unsafe fn start() -> ! {
   let hartId = Mhartid.read();
   Tp.write(hartId);
 
+  arch::riscv::hart::recordHartInfo(hartId);
+
+  // Software interrupts raised through the CLINT (by other harts sending us IPIs) can only be taken
+  // in M-mode. machineVector( ) (defined in ./asm/machinevec.S) forwards them to S-mode.
+  extern "C" {
+    fn machineVector();
+  }
+  Mscratch.write(trap::getMachineTrapScratchAreaAddress(hartId));
+  Mtvec.set(machineVector as usize);
+  Mie.enableSoftwareInterrupts();
+
+  // Let S-mode read the time CSR and program its own timer interrupts (using the stimecmp register
+  // provided by the Sstc extension).
+  Mcounteren.enableTimeCounter();
+  Menvcfg.enableSupervisorTimecmp();
+
+  ipi::markHartOnline(hartId);
+
   // An MRET instruction is used to return from a trap in M-mode.
   // We return to S-mode (stored in MPP bits of the mstatus register) and start execution from
   // main( ) (whose address is stored in the mepc register).
@@ -87,10 +114,14 @@ mod drivers;
 
 mod arch;
 pub mod fs;
+mod ipi;
 mod locks;
 mod main;
 mod memory;
 mod process;
+mod syscall;
+mod timer;
+mod trap;
 
 // The panic_handler attribute defines the function that the compiler should invoke when a panic
 // occurs. The standard library provides its own panic handler function, but in a no_std environment
@@ -0,0 +1,71 @@
+use crate::fs::vfs::FsError;
+
+// Error numbers, same as in Linux. System calls return them negated.
+// REFER : https://github.com/torvalds/linux/blob/master/include/uapi/asm-generic/errno-base.h.
+pub const ENOENT: usize = 2;
+pub const EIO: usize = 5;
+pub const ENXIO: usize = 6;
+pub const E2BIG: usize = 7;
+pub const ENOEXEC: usize = 8;
+pub const EBADF: usize = 9;
+pub const EAGAIN: usize = 11;
+pub const ENOMEM: usize = 12;
+pub const EFAULT: usize = 14;
+pub const EBUSY: usize = 16;
+pub const EEXIST: usize = 17;
+pub const EXDEV: usize = 18;
+pub const ENODEV: usize = 19;
+pub const ENOTDIR: usize = 20;
+pub const EISDIR: usize = 21;
+pub const EINVAL: usize = 22;
+pub const EMFILE: usize = 24;
+pub const EFBIG: usize = 27;
+pub const ENOSPC: usize = 28;
+pub const ESPIPE: usize = 29;
+pub const EROFS: usize = 30;
+pub const EPIPE: usize = 32;
+pub const ENAMETOOLONG: usize = 36;
+pub const ENOSYS: usize = 38;
+pub const ENOTEMPTY: usize = 39;
+pub const ELOOP: usize = 40;
+pub const EOPNOTSUPP: usize = 95;
+
+// Returns the error number, reported to the user for the given error.
+pub fn fromFsError(error: FsError) -> usize {
+  match error {
+    FsError::NotFound => ENOENT,
+    FsError::AlreadyExists => EEXIST,
+
+    FsError::NotADirectory => ENOTDIR,
+    FsError::IsADirectory => EISDIR,
+    FsError::DirectoryNotEmpty => ENOTEMPTY,
+
+    FsError::NoSpace | FsError::NoInodes => ENOSPC,
+
+    FsError::FileTooLarge => EFBIG,
+    FsError::NameTooLong => ENAMETOOLONG,
+    FsError::InvalidArgument | FsError::InvalidSuperBlock => EINVAL,
+
+    FsError::NotSupported => EOPNOTSUPP,
+    FsError::ReadOnly => EROFS,
+    FsError::UnknownFileSystemType => ENODEV,
+    FsError::Busy => EBUSY,
+    FsError::CrossDevice => EXDEV,
+    FsError::TooManySymlinks => ELOOP,
+
+    FsError::BadFileDescriptor => EBADF,
+    FsError::TooManyOpenFiles => EMFILE,
+    FsError::IllegalSeek => ESPIPE,
+    FsError::WouldBlock => EAGAIN,
+    FsError::BrokenPipe => EPIPE,
+    FsError::NoReaders => ENXIO,
+
+    FsError::NotExecutable => ENOEXEC,
+    FsError::ArgumentsTooLong => E2BIG,
+
+    FsError::BadAddress => EFAULT,
+    FsError::OutOfMemory => ENOMEM,
+
+    FsError::IOError => EIO,
+  }
+}
@@ -0,0 +1,166 @@
+use {
+  super::{copyIn, copyInString, copyOut},
+  crate::{
+    arch::riscv::qemu::PAGE_SIZE,
+    fs::{syscalls, vfs::FsError},
+    process::trapframe::TrapFrame,
+  },
+  alloc::{vec, vec::Vec},
+};
+
+/*
+  Wrappers around the File System related system calls (REFER : ../fs/syscalls.rs), which copy
+  the paths and the buffers from / to the user memory.
+*/
+
+// Maximum length of a path (same as Linux's PATH_MAX, excluding the NUL).
+const MAX_PATH_LENGTH: usize = 4095;
+
+// Upper limit on the number of bytes read / written by a single system call, so that a huge count
+// can't make us allocate a huge buffer. Fewer bytes than requested may then be read / written,
+// which the user program must be prepared for anyway.
+const MAX_IO_SIZE: usize = 64 * 1024;
+
+// Maximum length of the mount options (Linux copies a single page of them).
+const MAX_MOUNT_OPTIONS_LENGTH: usize = PAGE_SIZE - 1;
+
+// Special value of the directory file descriptor, meaning the working directory.
+const AT_FDCWD: usize = -100isize as usize;
+
+// File type bits of the mode passed to mknodat( ).
+const S_IFMT: usize = 0o170000;
+const S_IFIFO: usize = 0o010000;
+
+// Copies the path at the given Virtual Address (VA) from the user memory. The given directory file
+// descriptor is what relative paths get resolved from.
+// NOTE : Processes don't have working directories yet. So, only AT_FDCWD is supported (relative
+// paths then get resolved from the root directory).
+fn copyInPath(directoryFD: usize, va: usize) -> Result<Vec<u8>, FsError> {
+  let path = copyInString(va, MAX_PATH_LENGTH)?;
+
+  if directoryFD != AT_FDCWD && !path.starts_with(b"/") {
+    return Err(FsError::NotSupported);
+  }
+  Ok(path)
+}
+
+// openat(directoryFD, path, flags, mode)
+// NOTE : The mode is ignored, since the file systems don't have permissions yet.
+pub fn sysOpenat(trapFrame: &mut TrapFrame) -> Result<usize, FsError> {
+  let [directoryFD, pathVA, flags, ..] = trapFrame.getSyscallArguments();
+
+  let path = copyInPath(directoryFD, pathVA)?;
+  syscalls::sysOpen(&path, flags)
+}
+
+// close(fd)
+pub fn sysClose(trapFrame: &mut TrapFrame) -> Result<usize, FsError> {
+  let [fd, ..] = trapFrame.getSyscallArguments();
+  syscalls::sysClose(fd)
+}
+
+// read(fd, buffer, count)
+pub fn sysRead(trapFrame: &mut TrapFrame) -> Result<usize, FsError> {
+  let [fd, bufferVA, count, ..] = trapFrame.getSyscallArguments();
+
+  let mut buffer = vec![0; count.min(MAX_IO_SIZE)];
+  let bytesRead = syscalls::sysRead(fd, &mut buffer)?;
+
+  copyOut(bufferVA, &buffer[..bytesRead])?;
+  Ok(bytesRead)
+}
+
+// write(fd, buffer, count)
+pub fn sysWrite(trapFrame: &mut TrapFrame) -> Result<usize, FsError> {
+  let [fd, bufferVA, count, ..] = trapFrame.getSyscallArguments();
+
+  let mut buffer = vec![0; count.min(MAX_IO_SIZE)];
+  copyIn(bufferVA, &mut buffer)?;
+
+  syscalls::sysWrite(fd, &buffer)
+}
+
+// lseek(fd, offset, whence)
+pub fn sysLseek(trapFrame: &mut TrapFrame) -> Result<usize, FsError> {
+  let [fd, offset, whence, ..] = trapFrame.getSyscallArguments();
+  syscalls::sysLseek(fd, offset as i64, whence)
+}
+
+// dup(fd)
+pub fn sysDup(trapFrame: &mut TrapFrame) -> Result<usize, FsError> {
+  let [fd, ..] = trapFrame.getSyscallArguments();
+  syscalls::sysDup(fd)
+}
+
+// dup3(oldFD, newFD, flags)
+pub fn sysDup3(trapFrame: &mut TrapFrame) -> Result<usize, FsError> {
+  let [oldFD, newFD, flags, ..] = trapFrame.getSyscallArguments();
+  syscalls::sysDup3(oldFD, newFD, flags)
+}
+
+// fcntl(fd, command, argument)
+pub fn sysFcntl(trapFrame: &mut TrapFrame) -> Result<usize, FsError> {
+  let [fd, command, argument, ..] = trapFrame.getSyscallArguments();
+  syscalls::sysFcntl(fd, command, argument)
+}
+
+// pipe2(fds, flags) : the read end and write end file descriptors get stored into fds (an int[2]).
+pub fn sysPipe2(trapFrame: &mut TrapFrame) -> Result<usize, FsError> {
+  let [fdsVA, flags, ..] = trapFrame.getSyscallArguments();
+
+  let (readFD, writeFD) = syscalls::sysPipe2(flags)?;
+
+  let mut fds = [0; 8];
+  fds[..4].copy_from_slice(&(readFD as i32).to_le_bytes());
+  fds[4..].copy_from_slice(&(writeFD as i32).to_le_bytes());
+
+  if let Err(error) = copyOut(fdsVA, &fds) {
+    let _ = syscalls::sysClose(readFD);
+    let _ = syscalls::sysClose(writeFD);
+    return Err(error);
+  }
+  Ok(0)
+}
+
+// mknodat(directoryFD, path, mode, device)
+// NOTE : Only FIFOs can be created (which is what mkfifo( ) uses it for).
+pub fn sysMknodat(trapFrame: &mut TrapFrame) -> Result<usize, FsError> {
+  let [directoryFD, pathVA, mode, ..] = trapFrame.getSyscallArguments();
+
+  if mode & S_IFMT != S_IFIFO {
+    return Err(FsError::NotSupported);
+  }
+
+  let path = copyInPath(directoryFD, pathVA)?;
+  syscalls::sysMkfifo(&path)
+}
+
+// mount(source, target, fileSystemType, flags, data) : data points to the mount options string. The
+// source and data can be NULL (the source is ignored for the File Systems which aren't disk based).
+pub fn sysMount(trapFrame: &mut TrapFrame) -> Result<usize, FsError> {
+  let [sourceVA, targetVA, fileSystemTypeVA, flags, dataVA, ..] = trapFrame.getSyscallArguments();
+
+  let source = copyInOptionalString(sourceVA, MAX_PATH_LENGTH)?;
+  let target = copyInPath(AT_FDCWD, targetVA)?;
+  let fileSystemType = copyInString(fileSystemTypeVA, MAX_PATH_LENGTH)?;
+  let options = copyInOptionalString(dataVA, MAX_MOUNT_OPTIONS_LENGTH)?;
+
+  syscalls::sysMount(&source, &target, &fileSystemType, flags, &options)
+}
+
+// umount2(target, flags)
+pub fn sysUmount2(trapFrame: &mut TrapFrame) -> Result<usize, FsError> {
+  let [targetVA, flags, ..] = trapFrame.getSyscallArguments();
+
+  let target = copyInPath(AT_FDCWD, targetVA)?;
+  syscalls::sysUmount(&target, flags)
+}
+
+// Copies the string at the given Virtual Address (VA) from the user memory. A NULL string is
+// treated as an empty one.
+fn copyInOptionalString(va: usize, maxLength: usize) -> Result<Vec<u8>, FsError> {
+  match va {
+    0 => Ok(Vec::new()),
+    _ => copyInString(va, maxLength),
+  }
+}
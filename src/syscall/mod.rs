@@ -0,0 +1,113 @@
+mod errno;
+mod fs;
+mod process;
+
+use {
+  crate::{
+    fs::vfs::FsError,
+    memory::address_space::AddressSpace,
+    process::{process::getCurrentProcess, trapframe::TrapFrame},
+  },
+  alloc::vec::Vec,
+};
+
+/*
+  System calls, made by user processes using the ecall instruction. The calling convention is the
+  same as Linux's on RISC-V : the system call number is passed in a7 and the arguments in a0 - a5.
+  The return value is passed back in a0. On failure, that's the negated error number.
+
+  The system call numbers are the same as Linux's too (from its generic system call table).
+
+  REFER : https://github.com/torvalds/linux/blob/master/include/uapi/asm-generic/unistd.h.
+*/
+
+pub const SYS_DUP: usize = 23;
+pub const SYS_DUP3: usize = 24;
+pub const SYS_FCNTL: usize = 25;
+pub const SYS_MKNODAT: usize = 33;
+pub const SYS_UMOUNT2: usize = 39;
+pub const SYS_MOUNT: usize = 40;
+pub const SYS_OPENAT: usize = 56;
+pub const SYS_CLOSE: usize = 57;
+pub const SYS_PIPE2: usize = 59;
+pub const SYS_LSEEK: usize = 62;
+pub const SYS_READ: usize = 63;
+pub const SYS_WRITE: usize = 64;
+pub const SYS_EXIT: usize = 93;
+pub const SYS_EXIT_GROUP: usize = 94;
+pub const SYS_EXECVE: usize = 221;
+
+const SYSCALLS_COUNT: usize = SYS_EXECVE + 1;
+
+// Handles a system call, given the trap frame of the process making it (which holds the
+// arguments). Returns the return value.
+type SyscallHandler = fn(&mut TrapFrame) -> Result<usize, FsError>;
+
+// The system call handlers, indexed by the system call number.
+static SYSCALL_TABLE: [Option<SyscallHandler>; SYSCALLS_COUNT] = {
+  let mut table: [Option<SyscallHandler>; SYSCALLS_COUNT] = [None; SYSCALLS_COUNT];
+
+  table[SYS_DUP] = Some(fs::sysDup);
+  table[SYS_DUP3] = Some(fs::sysDup3);
+  table[SYS_FCNTL] = Some(fs::sysFcntl);
+  table[SYS_MKNODAT] = Some(fs::sysMknodat);
+  table[SYS_UMOUNT2] = Some(fs::sysUmount2);
+  table[SYS_MOUNT] = Some(fs::sysMount);
+  table[SYS_OPENAT] = Some(fs::sysOpenat);
+  table[SYS_CLOSE] = Some(fs::sysClose);
+  table[SYS_PIPE2] = Some(fs::sysPipe2);
+  table[SYS_LSEEK] = Some(fs::sysLseek);
+  table[SYS_READ] = Some(fs::sysRead);
+  table[SYS_WRITE] = Some(fs::sysWrite);
+  table[SYS_EXIT] = Some(process::sysExit);
+  table[SYS_EXIT_GROUP] = Some(process::sysExit);
+  table[SYS_EXECVE] = Some(process::sysExecve);
+
+  table
+};
+
+// Dispatches the system call, the current process has made (using the given trap frame). Its
+// return value is stored back into the trap frame.
+pub fn handleSyscall(trapFrame: &mut TrapFrame) {
+  let syscallNumber = trapFrame.getSyscallNumber();
+
+  let result = match SYSCALL_TABLE.get(syscallNumber).copied().flatten() {
+    Some(handler) => handler(trapFrame).map_err(errno::fromFsError),
+    None => Err(errno::ENOSYS),
+  };
+
+  let returnValue = match result {
+    Ok(returnValue) => returnValue,
+    Err(errno) => errno.wrapping_neg(),
+  };
+  trapFrame.setSyscallReturnValue(returnValue);
+}
+
+// Invokes the given function on the address space of the current process.
+fn withAddressSpace<T>(
+  function: impl FnOnce(&mut AddressSpace) -> Result<T, FsError>,
+) -> Result<T, FsError> {
+  let process = getCurrentProcess().expect("System call invoked, without a current process");
+
+  let mut addressSpace = process.getAddressSpace().acquire();
+  function(
+    addressSpace
+      .as_mut()
+      .expect("System call invoked, without an address space"),
+  )
+}
+
+// Copies bytes from the user memory at the given Virtual Address (VA), into the given buffer.
+fn copyIn(va: usize, buffer: &mut [u8]) -> Result<(), FsError> {
+  withAddressSpace(|addressSpace| addressSpace.copyIn(va, buffer))
+}
+
+// Copies the given bytes to the user memory at the given Virtual Address (VA).
+fn copyOut(va: usize, bytes: &[u8]) -> Result<(), FsError> {
+  withAddressSpace(|addressSpace| addressSpace.copyOut(va, bytes))
+}
+
+// Copies the NUL terminated string at the given Virtual Address (VA) from the user memory.
+fn copyInString(va: usize, maxLength: usize) -> Result<Vec<u8>, FsError> {
+  withAddressSpace(|addressSpace| addressSpace.copyInString(va, maxLength))
+}
@@ -0,0 +1,71 @@
+use {
+  super::{copyIn, copyInString},
+  crate::{
+    arch::riscv::qemu::PAGE_SIZE,
+    fs::vfs::FsError,
+    process::{exec, process, trapframe::TrapFrame},
+  },
+  alloc::vec::Vec,
+};
+
+// Maximum length of a path (same as Linux's PATH_MAX, excluding the NUL).
+const MAX_PATH_LENGTH: usize = 4095;
+
+// Upper limits on the number of arguments (and environment variables) passed to execve( ), and on
+// the length of each of them. exec( ) further limits their total size.
+const MAX_ARGUMENTS_COUNT: usize = 256;
+const MAX_ARGUMENT_LENGTH: usize = PAGE_SIZE;
+
+// exit(status)
+pub fn sysExit(trapFrame: &mut TrapFrame) -> Result<usize, FsError> {
+  let [status, ..] = trapFrame.getSyscallArguments();
+  process::exit(status)
+}
+
+// execve(path, argv, envp) : argv and envp are NULL terminated arrays of string pointers.
+// On success, the new program starts executing with argc in a0 (the return value), argv in a1 and
+// envp in a2.
+pub fn sysExecve(trapFrame: &mut TrapFrame) -> Result<usize, FsError> {
+  let [pathVA, argvVA, envpVA, ..] = trapFrame.getSyscallArguments();
+
+  let path = copyInString(pathVA, MAX_PATH_LENGTH)?;
+  let arguments = copyInStrings(argvVA)?;
+  let environment = copyInStrings(envpVA)?;
+
+  let userEntry = exec::exec(&path, &arguments, &environment)?;
+  trapFrame.enterProgram(&userEntry);
+
+  Ok(userEntry.argc)
+}
+
+// Copies the strings, the NULL terminated array (of string pointers) at the given Virtual Address
+// (VA) points to, from the user memory. A NULL array is treated as an empty one (same as Linux).
+fn copyInStrings(va: usize) -> Result<Vec<Vec<u8>>, FsError> {
+  let mut strings = Vec::new();
+  if va == 0 {
+    return Ok(strings);
+  }
+
+  loop {
+    let mut pointer = [0; 8];
+    let pointerVA = va
+      .checked_add(8 * strings.len())
+      .ok_or(FsError::BadAddress)?;
+    copyIn(pointerVA, &mut pointer)?;
+
+    let stringVA = usize::from_le_bytes(pointer);
+    if stringVA == 0 {
+      return Ok(strings);
+    }
+
+    if strings.len() == MAX_ARGUMENTS_COUNT {
+      return Err(FsError::ArgumentsTooLong);
+    }
+
+    let string = copyInString(stringVA, MAX_ARGUMENT_LENGTH).map_err(|error| match error {
+      FsError::NameTooLong => FsError::ArgumentsTooLong,
+      error => error,
+    })?;
+    strings.push(string);
+  }
+}
@@ -0,0 +1,74 @@
+use {
+  crate::{
+    arch::riscv::registers::{
+      stimecmp::Stimecmp,
+      time::{Time, TIME_FREQUENCY},
+      tp::Tp,
+    },
+    locks::rcu,
+    process::manager::PROCESS_MANAGER,
+  },
+  core::sync::atomic::{AtomicUsize, Ordering},
+};
+
+// Number of ticks of the tick counter, per second.
+pub const TICKS_PER_SECOND: usize = 100;
+
+// Number of time CSR units between 2 consecutive timer interrupts (10 ms).
+const TIMER_INTERVAL: usize = TIME_FREQUENCY / TICKS_PER_SECOND;
+
+/*
+  The tick counter, incremented on each timer interrupt taken by hart 0. It's used to measure
+  timeouts (in ticks) while blocking.
+
+  Every hart gets its own timer interrupts (using the stimecmp register provided by the Sstc
+  extension), and treats them as scheduler ticks.
+*/
+static TICKS: AtomicUsize = AtomicUsize::new(0);
+
+// Returns the current value of the tick counter.
+#[inline]
+pub fn getTicks() -> usize {
+  TICKS.load(Ordering::Acquire)
+}
+
+// Returns the tick counter value, at which a timeout of the given number of ticks (starting now)
+// expires.
+#[inline]
+pub fn getDeadline(timeout: usize) -> usize {
+  getTicks().saturating_add(timeout)
+}
+
+// Converts the given number of milliseconds to ticks (rounding up).
+#[inline]
+pub const fn millisecondsToTicks(milliseconds: usize) -> usize {
+  (milliseconds * TICKS_PER_SECOND).div_ceil(1000)
+}
+
+// Schedules the next timer interrupt for the current hart.
+#[inline]
+fn scheduleNextTimerInterrupt() {
+  unsafe { Stimecmp.write(Time.read() + TIMER_INTERVAL) };
+}
+
+// Starts the timer interrupts on the current hart.
+// NOTE : Should be invoked once on every hart, while the Kernel is initializing.
+pub fn initHart() {
+  scheduleNextTimerInterrupt();
+}
+
+// Handles a supervisor timer interrupt. Invoked by the kernel trap handler.
+pub fn handleTimerInterrupt() {
+  // Writing to stimecmp also acknowledges the timer interrupt.
+  scheduleNextTimerInterrupt();
+
+  if unsafe { Tp.read() } == 0 {
+    let ticks = TICKS.fetch_add(1, Ordering::AcqRel) + 1;
+
+    PROCESS_MANAGER.wakeupExpired(ticks);
+  }
+
+  // RCU read-side critical sections run with interrupts disabled. So the timer interrupt couldn't
+  // have arrived while this hart was inside one.
+  rcu::noteQuiescentState();
+}
@@ -0,0 +1,218 @@
+pub mod user;
+
+#[cfg(feature = "lockdep")]
+use crate::locks::lockdep;
+use {
+  crate::{
+    arch::riscv::{
+      qemu::MAX_CORES,
+      registers::{
+        scause::{Scause, TrapCause},
+        sepc::Sepc,
+        sstatus::Sstatus,
+        stval::Stval,
+        stvec::Stvec,
+        tp::Tp,
+      },
+    },
+    drivers::{
+      plic::{PLICDriver, UART_IRQ, VIRTIO_IRQS},
+      uart, virtio,
+    },
+    ipi, timer,
+  },
+  array_macro::array,
+  core::{
+    ptr::addr_of_mut,
+    sync::atomic::{AtomicUsize, Ordering},
+  },
+};
+
+// Scratch areas used by the M-mode trap handler (../asm/machinevec.S) to save the registers it
+// clobbers. Each hart gets its own scratch area, whose address is stored in its mscratch register.
+static mut MACHINE_TRAP_SCRATCH_AREAS: [[usize; 2]; MAX_CORES] = [[0; 2]; MAX_CORES];
+
+// Returns the address of the scratch area, which the M-mode trap handler running on the given hart
+// can use.
+pub fn getMachineTrapScratchAreaAddress(hartID: usize) -> usize {
+  unsafe { addr_of_mut!(MACHINE_TRAP_SCRATCH_AREAS[hartID]) as usize }
+}
+
+// The highest IRQ number, the PLIC forwards to the harts.
+const MAX_IRQ: u32 = UART_IRQ;
+
+// Number of interrupts taken by a hart, by their source.
+struct InterruptCounters {
+  software: AtomicUsize,
+  timer: AtomicUsize,
+
+  // Indexed by the IRQ number.
+  external: [AtomicUsize; MAX_IRQ as usize + 1],
+}
+
+static INTERRUPT_COUNTERS: [InterruptCounters; MAX_CORES] = array![_ => InterruptCounters {
+  software: AtomicUsize::new(0),
+  timer: AtomicUsize::new(0),
+  external: array![_ => AtomicUsize::new(0); MAX_IRQ as usize + 1],
+}; MAX_CORES];
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum InterruptSource {
+  // IPIs sent by other harts.
+  Software,
+  Timer,
+  // Raised by the device with the given IRQ number, through the PLIC.
+  External(u32),
+}
+
+impl InterruptSource {
+  // Returns all the interrupt sources, the harts can take interrupts from.
+  pub fn getAll() -> impl Iterator<Item = Self> {
+    [Self::Software, Self::Timer]
+      .into_iter()
+      .chain(VIRTIO_IRQS.map(Self::External))
+      .chain(core::iter::once(Self::External(UART_IRQ)))
+  }
+}
+
+// Returns the number of interrupts the given hart has taken from the given source.
+pub fn getInterruptsCount(hartID: usize, source: InterruptSource) -> usize {
+  let counters = &INTERRUPT_COUNTERS[hartID];
+
+  let counter = match source {
+    InterruptSource::Software => &counters.software,
+    InterruptSource::Timer => &counters.timer,
+    InterruptSource::External(irq) => match counters.external.get(irq as usize) {
+      Some(counter) => counter,
+      None => return 0,
+    },
+  };
+  counter.load(Ordering::Relaxed)
+}
+
+// Makes the current hart jump to kernelVector (defined in ../asm/kernelvec.S), whenever a trap is
+// taken into S-mode. Also starts the external (device) and timer interrupts.
+// NOTE : Should be invoked once on every hart, while the Kernel is initializing.
+pub fn initHart() {
+  setKernelTrapVector();
+
+  // Let the PLIC forward the interrupts raised by the virtio devices and the UART, to the S-mode of
+  // this hart.
+  let hartID = unsafe { Tp.read() };
+  for irq in VIRTIO_IRQS.chain([UART_IRQ]) {
+    unsafe {
+      PLICDriver.setPriority(irq, 1);
+      PLICDriver.enableForHart(hartID, irq);
+    }
+  }
+  unsafe { PLICDriver.setThreshold(hartID, 0) };
+
+  timer::initHart();
+}
+
+// Makes the current hart jump to kernelVector, whenever a trap is taken into S-mode.
+fn setKernelTrapVector() {
+  extern "C" {
+    fn kernelVector();
+  }
+
+  unsafe { Stvec.set(kernelVector as usize) };
+}
+
+// Handles traps (interrupts and exceptions) taken while the kernel is executing.
+// Invoked by kernelVector, on whatever kernel stack the hart was using.
+#[no_mangle]
+extern "C" fn kernelTrap() {
+  // Another trap may occur while we're handling this one (say, in case the handler enables
+  // interrupts), which would overwrite the sepc and sstatus registers. So we save them here and
+  // restore them before returning.
+  let (sepc, sstatus) = unsafe { (Sepc.read(), Sstatus.read()) };
+
+  assert!(
+    unsafe { Sstatus.wasPreviousModeSMode() },
+    "Kernel trap didn't originate from S-mode"
+  );
+  assert!(
+    !unsafe { Sstatus.areInterruptsEnabled() },
+    "Interrupts are enabled while handling a kernel trap"
+  );
+
+  let trapCause = unsafe { Scause.readTrapCause() };
+
+  if !handleInterrupt(trapCause) {
+    panic!(
+      "Unexpected kernel trap : cause = {:?}, sepc = {:#x}, stval = {:#x}",
+      trapCause,
+      sepc,
+      unsafe { Stval.read() }
+    );
+  }
+
+  unsafe {
+    Sepc.write(sepc);
+    Sstatus.write(sstatus);
+  }
+}
+
+// Handles the interrupt, which caused the current trap. Returns false, if the trap wasn't caused by
+// an interrupt.
+fn handleInterrupt(trapCause: TrapCause) -> bool {
+  let counters = &INTERRUPT_COUNTERS[unsafe { Tp.read() }];
+
+  #[cfg(feature = "lockdep")]
+  lockdep::enterInterruptContext();
+
+  let isInterrupt = match trapCause {
+    TrapCause::SupervisorSoftwareInterrupt => {
+      counters.software.fetch_add(1, Ordering::Relaxed);
+      ipi::handleSoftwareInterrupt();
+      true
+    }
+
+    TrapCause::SupervisorTimerInterrupt => {
+      counters.timer.fetch_add(1, Ordering::Relaxed);
+      timer::handleTimerInterrupt();
+      true
+    }
+
+    TrapCause::SupervisorExternalInterrupt => {
+      handleExternalInterrupt();
+      true
+    }
+
+    _ => false,
+  };
+
+  #[cfg(feature = "lockdep")]
+  lockdep::exitInterruptContext();
+
+  isInterrupt
+}
+
+// Handles a supervisor external interrupt, raised by some device through the PLIC.
+fn handleExternalInterrupt() {
+  let hartID = unsafe { Tp.read() };
+
+  // Another hart may have already claimed the interrupt, in which case we get 0.
+  let irq = unsafe { PLICDriver.claim(hartID) };
+
+  if irq == 0 {
+    return;
+  }
+  if let Some(counter) = INTERRUPT_COUNTERS[hartID].external.get(irq as usize) {
+    counter.fetch_add(1, Ordering::Relaxed);
+  }
+
+  match irq {
+    irq if VIRTIO_IRQS.contains(&irq) => virtio::handleInterrupt(irq),
+
+    UART_IRQ => uart::handleInterrupt(),
+
+    _ => {
+      println!("WARN : Unexpected external interrupt (IRQ = {})", irq);
+    }
+  }
+
+  // Let the PLIC forward interrupts from this device again.
+  unsafe { PLICDriver.complete(hartID, irq) };
+}
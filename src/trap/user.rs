@@ -0,0 +1,157 @@
+use {
+  super::{handleInterrupt, setKernelTrapVector},
+  crate::{
+    arch::riscv::registers::{
+      satp::Satp,
+      scause::{Scause, TrapCause},
+      sepc::Sepc,
+      sstatus::Sstatus,
+      stval::Stval,
+      stvec::Stvec,
+      tp::Tp,
+    },
+    memory::address_space::TRAMPOLINE_VA,
+    process::{
+      process::{getCurrentProcess, Process},
+      scheduler,
+      signal::{self, SIGILL, SIGSEGV},
+    },
+    syscall,
+  },
+};
+
+/*
+  The user mode trap path :
+
+    (1) A trap taken while a process is executing in U-mode lands in userVector (in the trampoline),
+        which saves the user registers into the trap frame of the process, switches to the kernel
+        Page Table and the kernel stack of the process, and jumps to userTrap( ).
+
+    (2) userTrap( ) handles the trap : a system call (ecall), an interrupt or an exception.
+
+    (3) returnToUserMode( ) then jumps to userReturn (in the trampoline), which switches to the user
+        Page Table, restores the user registers and executes SRET.
+
+  REFER : ../asm/trampoline.S and chapter 4 (Traps and system calls) of the xv6-riscv book.
+*/
+
+extern "C" {
+  // Defined in ../asm/trampoline.S. Only their offsets (from the start of the trampoline) are
+  // used, since they're executed through the mapping at TRAMPOLINE_VA.
+  fn userVector();
+  fn userReturn();
+
+  fn _trampoline(); // Points to the _trampoline linker symbol.
+}
+
+// Returns the address, the given trampoline symbol is mapped at (in both the kernel and the user
+// Page Tables).
+#[inline]
+fn getTrampolineMapping(symbol: unsafe extern "C" fn()) -> usize {
+  TRAMPOLINE_VA + (symbol as usize - _trampoline as usize)
+}
+
+// Handles traps taken while a process is executing in U-mode. Invoked by userVector, on the kernel
+// stack of the process.
+#[no_mangle]
+extern "C" fn userTrap() -> ! {
+  assert!(
+    !unsafe { Sstatus.wasPreviousModeSMode() },
+    "User trap didn't originate from U-mode"
+  );
+
+  // We're in the kernel now. So, traps must be handled by kernelVector.
+  setKernelTrapVector();
+
+  let process = getCurrentProcess().expect("User trap taken, without a current process");
+  let trapFrame = unsafe { &mut *process.getTrapFrame() };
+
+  trapFrame.epc = unsafe { Sepc.read() };
+
+  let trapCause = unsafe { Scause.readTrapCause() };
+  match trapCause {
+    TrapCause::EnvironmentCallFromUMode => {
+      // Return to the instruction after the ecall.
+      trapFrame.epc += 4;
+
+      // sepc, scause and sstatus have been read. So, the system call can be interrupted (it may
+      // block as well).
+      unsafe { Sstatus.enableInterrupts() };
+
+      syscall::handleSyscall(trapFrame);
+    }
+
+    // The process has used up its time slice. Give up the CPU core.
+    TrapCause::SupervisorTimerInterrupt => {
+      handleInterrupt(trapCause);
+      scheduler::yieldCPU(process);
+    }
+
+    _ if handleInterrupt(trapCause) => {}
+
+    // An exception caused by the user program. It gets killed, by the corresponding signal.
+    _ => {
+      println!(
+        "WARN : Process {} killed : cause = {:?}, sepc = {:#x}, stval = {:#x}",
+        process.getPID(),
+        trapCause,
+        trapFrame.epc,
+        unsafe { Stval.read() }
+      );
+
+      let signal = match trapCause {
+        TrapCause::InstructionPageFault | TrapCause::LoadPageFault | TrapCause::StorePageFault => {
+          SIGSEGV
+        }
+        _ => SIGILL,
+      };
+      signal::sendSignal(process, signal);
+    }
+  }
+
+  returnToUserMode(process)
+}
+
+/*
+  Returns to U-mode, continuing the user program of the given (current) process from where its
+  trap frame says. Pending signals are acted upon first. The init process enters U-mode for the
+  first time through this as well (REFER : ../process/init.rs).
+
+  NOTE : Must be invoked on the kernel stack of the process, without holding any SpinLock. That
+  stack gets discarded.
+*/
+pub fn returnToUserMode(process: &Process) -> ! {
+  signal::handlePendingSignals(process);
+
+  // stvec is about to point to userVector. Traps taken in the kernel till we're back in U-mode,
+  // would then be mistaken for user traps.
+  unsafe { Sstatus.disableInterrupts() };
+
+  let activation = process
+    .getAddressSpace()
+    .acquire()
+    .as_ref()
+    .expect("Returning to user mode, without an address space")
+    .getPageTable()
+    .activate();
+
+  let trapFrame = unsafe { &mut *process.getTrapFrame() };
+  unsafe {
+    trapFrame.kernelSatp = Satp.read();
+    trapFrame.kernelSP = process.getKernelStackTop();
+    trapFrame.kernelTrap = userTrap as usize;
+    trapFrame.kernelHartID = Tp.read();
+  }
+  trapFrame.isUsingKernelASID = activation.isUsingKernelASID as usize;
+
+  unsafe {
+    Stvec.set(getTrampolineMapping(userVector));
+
+    Sstatus.prepareReturnToUMode();
+    Sepc.write(trapFrame.epc);
+
+    let userReturn: extern "C" fn(usize) -> ! =
+      core::mem::transmute(getTrampolineMapping(userReturn));
+    userReturn(activation.satp)
+  }
+}